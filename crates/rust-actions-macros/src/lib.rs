@@ -1,21 +1,106 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
 use syn::{parse_macro_input, DeriveInput, ItemFn, FnArg, Type, LitStr, Token};
 
+/// `#[step("name")]`, optionally followed by `version = "1.2.0"`, `pure`,
+/// `deprecated = "use user/create@2"` and any number of `ensures = "outputs.id != ''"`
+struct StepAttr {
+    name: LitStr,
+    version: Option<LitStr>,
+    pure: bool,
+    deprecated: Option<LitStr>,
+    ensures: Vec<LitStr>,
+}
+
+impl Parse for StepAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: LitStr = input.parse()?;
+        let mut version = None;
+        let mut pure = false;
+        let mut deprecated = None;
+        let mut ensures = Vec::new();
+
+        while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            if key == "pure" {
+                pure = true;
+                continue;
+            }
+            if key != "version" && key != "ensures" && key != "deprecated" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "expected `version = \"x.y.z\"`, `pure`, `deprecated = \"...\"` or `ensures = \"...\"`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            if key == "ensures" {
+                ensures.push(input.parse()?);
+                continue;
+            }
+            if key == "deprecated" {
+                deprecated = Some(input.parse()?);
+                continue;
+            }
+            let lit: LitStr = input.parse()?;
+            if !is_semver(&lit.value()) {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    "step version must be a semver version like \"1.2.0\"",
+                ));
+            }
+            version = Some(lit);
+        }
+        if !input.is_empty() {
+            return Err(input.error("expected `,`"));
+        }
+
+        Ok(StepAttr {
+            name,
+            version,
+            pure,
+            deprecated,
+            ensures,
+        })
+    }
+}
+
+fn is_semver(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or("");
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
 #[proc_macro_attribute]
 pub fn step(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let step_name = parse_macro_input!(attr as LitStr);
+    let StepAttr {
+        name: step_name,
+        version,
+        pure,
+        deprecated,
+        ensures,
+    } = parse_macro_input!(attr as StepAttr);
     let input = parse_macro_input!(item as ItemFn);
 
     let fn_name = &input.sig.ident;
 
+    if let Some(param) = input.sig.generics.type_params().next() {
+        return syn::Error::new_spanned(
+            param,
+            "Step functions can't be generic; take `&mut dyn Trait` to share a step across worlds"
+        ).to_compile_error().into();
+    }
+
     let mut params = input.sig.inputs.iter();
 
-    let world_type = match params.next() {
+    let world_param = match params.next() {
         Some(FnArg::Typed(pat_type)) => {
             extract_world_type(&pat_type.ty)
         }
@@ -27,29 +112,73 @@ pub fn step(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
-    let has_args = params.next().is_some();
+    // After the world: an optional args struct (by value), then an optional `&StepContext`
+    let mut args_type = None;
+    let mut has_context = false;
+    for param in params {
+        let is_context = matches!(param, FnArg::Typed(pt) if matches!(*pt.ty, syn::Type::Reference(_)));
+        if has_context || (!is_context && args_type.is_some()) {
+            return syn::Error::new_spanned(
+                param,
+                "Step function takes at most an args struct followed by `&StepContext`"
+            ).to_compile_error().into();
+        }
+        match param {
+            _ if is_context => has_context = true,
+            FnArg::Typed(pt) => args_type = Some(&pt.ty),
+            FnArg::Receiver(_) => {}
+        }
+    }
+
+    let context_arg = has_context.then(|| quote! { , ctx });
+    let step_name_str = step_name.value();
 
-    let step_call = if has_args {
+    let step_call = if args_type.is_some() {
         quote! {
             let parsed_args = match ::rust_actions::args::FromArgs::from_args(&args) {
                 Ok(a) => a,
+                Err(::rust_actions::Error::Args(reason)) => {
+                    let step = #step_name_str.to_string();
+                    return Box::pin(async move { Err(::rust_actions::Error::InvalidArgs { step, reason }) });
+                }
                 Err(e) => return Box::pin(async move { Err(e) }),
             };
             Box::pin(async move {
-                let result = #fn_name(world, parsed_args).await?;
+                let result = #fn_name(world, parsed_args #context_arg).await?;
                 Ok(::rust_actions::outputs::IntoOutputs::into_outputs(result))
             })
         }
     } else {
         quote! {
             Box::pin(async move {
-                let result = #fn_name(world).await?;
+                let result = #fn_name(world #context_arg).await?;
                 Ok(::rust_actions::outputs::IntoOutputs::into_outputs(result))
             })
         }
     };
 
-    let step_name_str = step_name.value();
+    // `&mut dyn Trait` steps are registered under the trait object's TypeId and run on any
+    // world declaring `#[world(implements(Trait))]`
+    let (world_type, downcast) = match world_param {
+        WorldParam::Concrete(ty) => {
+            let downcast = quote! { world_any.downcast_mut::<#ty>() };
+            (ty, downcast)
+        }
+        WorldParam::Dyn(ty) => {
+            let downcast = quote! { ::rust_actions::registry::cast_world::<#ty>(world_any) };
+            (ty, downcast)
+        }
+    };
+
+    let with_version = version.map(|v| quote! { .with_version(#v) });
+    let with_pure = pure.then(|| quote! { .pure() });
+    let with_ensures = (!ensures.is_empty()).then(|| quote! { .with_ensures(&[#(#ensures),*]) });
+    let with_deprecation = deprecated.map(|note| quote! { .deprecated(#note) });
+    let without_outputs = returns_unit(&input.sig.output).then(|| quote! { .without_outputs() });
+    let with_doc = doc_comment(&input.attrs).map(|doc| quote! { .with_doc(#doc) });
+    let with_args = args_type.map(|ty| quote! {
+        .with_args(<#ty as ::rust_actions::args::FromArgs>::FIELDS)
+    });
     let erased_fn_name = syn::Ident::new(
         &format!("__erased_{}", fn_name),
         fn_name.span()
@@ -63,12 +192,16 @@ pub fn step(attr: TokenStream, item: TokenStream) -> TokenStream {
         fn #erased_fn_name<'a>(
             world_any: &'a mut dyn ::std::any::Any,
             args: ::rust_actions::args::RawArgs,
+            ctx: &'a ::rust_actions::context::StepContext,
         ) -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output = ::rust_actions::Result<::rust_actions::outputs::StepOutputs>> + Send + 'a>> {
-            let world = match world_any.downcast_mut::<#world_type>() {
+            let world = match #downcast {
                 Some(w) => w,
                 None => {
                     let msg = format!(
-                        "World type mismatch: expected {}",
+                        "World type mismatch in step {} ({}:{}): expected {}",
+                        #step_name_str,
+                        ::std::file!(),
+                        ::std::line!(),
                         ::std::any::type_name::<#world_type>()
                     );
                     return Box::pin(async move {
@@ -89,25 +222,72 @@ pub fn step(attr: TokenStream, item: TokenStream) -> TokenStream {
                 },
                 #erased_fn_name,
             )
+            #with_version
+            #with_args
+            #with_pure
+            #with_ensures
+            #with_deprecation
+            #without_outputs
+            .defined_at(::std::module_path!(), ::std::file!(), ::std::line!())
+            #with_doc
         }
     };
 
     TokenStream::from(expanded)
 }
 
-fn extract_world_type(ty: &Type) -> proc_macro2::TokenStream {
+/// The `///` comment on a step function, with the leading space of each line removed.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }),
+                ..
+            }) => Some(doc.value()),
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').unwrap_or(&line).to_string())
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// Whether a step returns `Result<()>`, i.e. has no outputs to assert on.
+fn returns_unit(output: &syn::ReturnType) -> bool {
+    let syn::ReturnType::Type(_, ty) = output else {
+        return false;
+    };
+    let Type::Path(path) = &**ty else {
+        return false;
+    };
+    let Some(syn::PathArguments::AngleBracketed(args)) = path.path.segments.last().map(|s| &s.arguments) else {
+        return false;
+    };
+    matches!(args.args.first(), Some(syn::GenericArgument::Type(Type::Tuple(t))) if t.elems.is_empty())
+}
+
+enum WorldParam {
+    Concrete(proc_macro2::TokenStream),
+    Dyn(proc_macro2::TokenStream),
+}
+
+fn extract_world_type(ty: &Type) -> WorldParam {
     match ty {
-        Type::Reference(type_ref) => {
-            if let Type::Path(type_path) = &*type_ref.elem {
+        Type::Reference(type_ref) => match &*type_ref.elem {
+            Type::Path(type_path) => {
                 let path = &type_path.path;
-                quote! { #path }
-            } else {
-                quote! { compile_error!("Expected a type path for world parameter") }
+                WorldParam::Concrete(quote! { #path })
             }
-        }
-        _ => {
-            quote! { compile_error!("World parameter must be a mutable reference") }
-        }
+            Type::TraitObject(object) => WorldParam::Dyn(quote! { #object }),
+            _ => WorldParam::Concrete(
+                quote! { compile_error!("Expected a type path or `dyn Trait` for world parameter") },
+            ),
+        },
+        _ => WorldParam::Concrete(
+            quote! { compile_error!("World parameter must be a mutable reference") },
+        ),
     }
 }
 
@@ -116,32 +296,185 @@ pub fn derive_world(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
 
+    let attrs = match WorldAttrs::parse(&input) {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    // Spanned at the attribute (or the struct name for the default `Self::setup`) so a
+    // missing or mistyped constructor is reported there
+    let init = match &attrs.init {
+        Some(path) => quote_spanned! {path.span()=>
+            ::rust_actions::world::WorldInit::<Self>::init(#path)
+        },
+        None => quote_spanned! {name.span()=>
+            ::rust_actions::world::WorldInit::<Self>::init(Self::setup)
+        },
+    };
+
+    let reset = attrs.reset.as_ref().map(|path| {
+        quote_spanned! {path.span()=>
+            const RESETTABLE: bool = true;
+
+            fn reset(&mut self) -> impl ::std::future::Future<Output = ::rust_actions::Result<()>> + Send {
+                #path(self)
+            }
+        }
+    });
+
+    let teardown = attrs.teardown.as_ref().map(|path| {
+        quote_spanned! {path.span()=>
+            fn teardown(self) -> impl ::std::future::Future<Output = ::rust_actions::Result<()>> + Send {
+                #path(self)
+            }
+        }
+    });
+
+    let assume_identity = attrs.assume_identity.as_ref().map(|path| {
+        quote_spanned! {path.span()=>
+            fn assume_identity(
+                &mut self,
+                name: &str,
+            ) -> impl ::std::future::Future<Output = ::rust_actions::Result<()>> + Send {
+                #path(self, name)
+            }
+        }
+    });
+
+    // Only the trait call is spanned at the flag, so a missing `WorldSnapshot` impl is
+    // reported there without lints firing on the generated fn
+    let snapshot = attrs.snapshot.map(|span| {
+        let call = quote_spanned! {span=> ::rust_actions::world::WorldSnapshot::snapshot(self) };
+        quote! {
+            async fn snapshot(
+                &self,
+            ) -> ::rust_actions::Result<::std::option::Option<::rust_actions::serde_json::Value>> {
+                #call.await.map(Some)
+            }
+        }
+    });
+
+    let casts = attrs.implements.iter().map(|bound| {
+        let target = quote! { dyn #bound };
+        quote_spanned! {bound.span()=>
+            const _: () = {
+                fn cast(world: &mut dyn ::std::any::Any) -> ::std::option::Option<&mut (#target + 'static)> {
+                    world.downcast_mut::<#name>().map(|w| w as &mut (#target + 'static))
+                }
+                static CAST: ::rust_actions::registry::WorldCastFn<#target> = cast;
+
+                ::rust_actions::inventory::submit! {
+                    ::rust_actions::registry::WorldCast::new(
+                        ::std::any::TypeId::of::<#name>(),
+                        ::std::any::TypeId::of::<#target>(),
+                        &CAST,
+                    )
+                }
+            };
+        }
+    });
+
     let expanded = quote! {
         impl ::rust_actions::world::World for #name {
             fn new() -> impl ::std::future::Future<Output = ::rust_actions::Result<Self>> + Send {
-                Self::setup()
+                #init
             }
+
+            #reset
+
+            #teardown
+
+            #assume_identity
+
+            #snapshot
         }
+
+        #(#casts)*
     };
 
     TokenStream::from(expanded)
 }
 
+/// `#[world(init = path, reset = path, teardown = path, assume_identity = path, snapshot,
+/// implements(Trait, ...))]`
+#[derive(Default)]
+struct WorldAttrs {
+    init: Option<syn::ExprPath>,
+    reset: Option<syn::ExprPath>,
+    teardown: Option<syn::ExprPath>,
+    assume_identity: Option<syn::ExprPath>,
+    /// Span of the `snapshot` flag
+    snapshot: Option<proc_macro2::Span>,
+    /// Step traits whose `&mut dyn Trait` steps run on this world
+    implements: Vec<syn::Path>,
+}
+
+impl WorldAttrs {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut attrs = WorldAttrs::default();
+        for attr in input.attrs.iter().filter(|a| a.path().is_ident("world")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("init") {
+                    attrs.init = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("reset") {
+                    attrs.reset = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("teardown") {
+                    attrs.teardown = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("assume_identity") {
+                    attrs.assume_identity = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("snapshot") {
+                    attrs.snapshot = Some(meta.path.span());
+                } else if meta.path.is_ident("implements") {
+                    meta.parse_nested_meta(|bound| {
+                        attrs.implements.push(bound.path);
+                        Ok(())
+                    })?;
+                } else {
+                    return Err(meta.error(
+                        "unsupported world attribute, expected `init`, `reset`, `teardown`, `assume_identity`, `snapshot` or `implements`",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attrs)
+    }
+}
+
 #[proc_macro_derive(Args, attributes(arg))]
 pub fn derive_args(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
 
+    let rows_field = match rows_field(&input) {
+        Ok(field) => field,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    // `#[arg(rows)]` moves the `rows:` table into that field as a list of objects
+    let rows = rows_field.as_ref().map(|field| {
+        let field = field.to_string();
+        quote! {
+            let mut args = args.clone();
+            if let Some(table) = args.remove(::rust_actions::args::ROWS_KEY) {
+                args.insert(#field.to_string(), ::rust_actions::args::table_rows(&table)?);
+            }
+            let args = &args;
+        }
+    });
+
+    let fields = match arg_fields(&input, rows_field.as_ref()) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     let expanded = quote! {
         impl ::rust_actions::args::FromArgs for #name {
+            const FIELDS: &'static [::rust_actions::args::ArgField] = &[#(#fields),*];
+
             fn from_args(args: &::rust_actions::args::RawArgs) -> ::rust_actions::Result<Self> {
-                let value = ::rust_actions::serde_json::Value::Object(
-                    args.iter()
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect()
-                );
-                ::rust_actions::serde_json::from_value(value)
-                    .map_err(|e| ::rust_actions::Error::Args(e.to_string()))
+                #rows
+                ::rust_actions::args::deserialize(args)
             }
         }
     };
@@ -149,6 +482,149 @@ pub fn derive_args(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+fn rows_field(input: &DeriveInput) -> syn::Result<Option<syn::Ident>> {
+    let syn::Data::Struct(data) = &input.data else {
+        return Ok(None);
+    };
+
+    let mut found = None;
+    for field in &data.fields {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("arg")) {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("rows") {
+                    return Err(meta.error("unsupported arg attribute, expected `rows`"));
+                }
+                if found.is_some() {
+                    return Err(meta.error("only one field can be #[arg(rows)]"));
+                }
+                found = field.ident.clone();
+                Ok(())
+            })?;
+        }
+    }
+    Ok(found)
+}
+
+/// `ArgField`s for the struct's named fields under their `with:` keys, honouring serde's
+/// `rename`, `default` and `skip`. The `#[arg(rows)]` field is listed as `rows`.
+///
+/// For an enum the keys of all its variants are listed, plus its `#[serde(tag)]` key; a key
+/// that only some variants take is optional.
+fn arg_fields(
+    input: &DeriveInput,
+    rows_field: Option<&syn::Ident>,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let container = serde_attrs(&input.attrs)?;
+    let fields = match &input.data {
+        syn::Data::Struct(data) => named_fields(&data.fields, container.default, rows_field)?,
+        syn::Data::Enum(data) => {
+            let mut fields: Vec<(String, String, bool)> = container
+                .tag
+                .map(|tag| (tag, "String".to_string(), false))
+                .into_iter()
+                .collect();
+            let variants = data
+                .variants
+                .iter()
+                .map(|v| named_fields(&v.fields, container.default, None))
+                .collect::<syn::Result<Vec<_>>>()?;
+            for variant in &variants {
+                for (key, ty, optional) in variant {
+                    if fields.iter().any(|(k, _, _)| k == key) {
+                        continue;
+                    }
+                    let everywhere = variants.iter().all(|v| v.iter().any(|(k, _, _)| k == key));
+                    fields.push((key.clone(), ty.clone(), *optional || !everywhere));
+                }
+            }
+            fields
+        }
+        syn::Data::Union(_) => Vec::new(),
+    };
+
+    Ok(fields
+        .into_iter()
+        .map(|(key, ty_name, optional)| {
+            quote! {
+                ::rust_actions::args::ArgField {
+                    name: #key,
+                    ty: #ty_name,
+                    optional: #optional,
+                }
+            }
+        })
+        .collect())
+}
+
+/// `(key, type, optional)` for each named field.
+fn named_fields(
+    fields: &syn::Fields,
+    all_default: bool,
+    rows_field: Option<&syn::Ident>,
+) -> syn::Result<Vec<(String, String, bool)>> {
+    let mut found = Vec::new();
+    for field in fields {
+        let Some(ident) = &field.ident else {
+            continue;
+        };
+        let attrs = serde_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let key = if rows_field == Some(ident) {
+            "rows".to_string()
+        } else {
+            attrs
+                .rename
+                .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string())
+        };
+        let ty = &field.ty;
+        let ty_name = quote!(#ty).to_string().replace(' ', "").replace(',', ", ");
+        let optional = all_default || attrs.default || is_option(ty);
+        found.push((key, ty_name, optional));
+    }
+    Ok(found)
+}
+
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    tag: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+fn serde_attrs(attrs: &[syn::Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut found = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                found.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("tag") {
+                found.tag = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("default") {
+                found.default = true;
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                found.skip = true;
+            }
+            // Consume whatever else serde accepts, e.g. `with = ".."` or `rename(..)`
+            if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                content.parse::<proc_macro2::TokenStream>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(found)
+}
+
+fn is_option(ty: &Type) -> bool {
+    matches!(ty, Type::Path(p) if p.path.segments.last().is_some_and(|s| s.ident == "Option"))
+}
+
 #[proc_macro_derive(Outputs)]
 pub fn derive_outputs(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -203,9 +679,14 @@ pub fn after_step(_attr: TokenStream, item: TokenStream) -> TokenStream {
     TokenStream::from(quote! { #input })
 }
 
+/// `generate_tests!("tests/workflows", TestWorld, include = "**/*.test.yaml", exclude = "k8s/**")`;
+/// `include` and `exclude` may repeat. `modules = true` nests tests in a module per directory.
 struct GenerateTestsArgs {
     path: LitStr,
     world_type: syn::Path,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    modules: bool,
 }
 
 impl Parse for GenerateTestsArgs {
@@ -213,7 +694,29 @@ impl Parse for GenerateTestsArgs {
         let path: LitStr = input.parse()?;
         input.parse::<Token![,]>()?;
         let world_type: syn::Path = input.parse()?;
-        Ok(GenerateTestsArgs { path, world_type })
+        let mut args = GenerateTestsArgs {
+            path,
+            world_type,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            modules: false,
+        };
+        while input.parse::<Token![,]>().is_ok() && !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "include" => args.include.push(input.parse::<LitStr>()?.value()),
+                "exclude" => args.exclude.push(input.parse::<LitStr>()?.value()),
+                "modules" => args.modules = input.parse::<syn::LitBool>()?.value,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        key,
+                        "expected `include`, `exclude` or `modules`",
+                    ))
+                }
+            }
+        }
+        Ok(args)
     }
 }
 
@@ -262,7 +765,11 @@ struct WorkflowTrigger {
 
 fn parse_workflow_header(path: &Path) -> Option<WorkflowHeader> {
     let content = std::fs::read_to_string(path).ok()?;
-    serde_yaml::from_str(&content).ok()
+    // JSON is valid YAML
+    match path.extension()?.to_str()? {
+        "toml" => toml::from_str(&content).ok(),
+        _ => serde_yaml::from_str(&content).ok(),
+    }
 }
 
 fn is_reusable_workflow(path: &Path) -> bool {
@@ -278,21 +785,66 @@ fn get_ignore_message(path: &Path) -> Option<String> {
         .and_then(|h| h.ignore.message().map(String::from))
 }
 
-fn discover_yaml_files(dir: &Path) -> Vec<PathBuf> {
+/// YAML, JSON and TOML workflow files passing the `include`/`exclude` patterns, as
+/// `parse_workflows_filtered` discovers them.
+fn discover_workflow_files(dir: &Path, include: &[String], exclude: &[String]) -> Vec<PathBuf> {
     walkdir::WalkDir::new(dir)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| {
+            let rel_path = e.path().strip_prefix(dir).unwrap_or(e.path());
             e.path().is_file()
                 && e.path()
                     .extension()
-                    .map(|ext| ext == "yaml" || ext == "yml")
-                    .unwrap_or(false)
+                    .is_some_and(|ext| ["yaml", "yml", "json", "toml"].iter().any(|x| ext == *x))
+                && (include.is_empty() || include.iter().any(|p| path_glob_match(p, rel_path)))
+                && !exclude.iter().any(|p| path_glob_match(p, rel_path))
         })
         .map(|e| e.path().to_path_buf())
         .collect()
 }
 
+/// Same rules as `WorkflowFilter`: patterns without a `/` match the file name, `**` spans
+/// directories.
+fn path_glob_match(pattern: &str, path: &Path) -> bool {
+    let segments: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if !pattern.contains('/') {
+        return segments.last().is_some_and(|name| glob_match(pattern, name));
+    }
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    segments_match(&pattern, &segments)
+}
+
+fn segments_match(pattern: &[&str], segments: &[String]) -> bool {
+    match pattern.split_first() {
+        None => segments.is_empty(),
+        Some((&"**", rest)) => (0..=segments.len()).any(|i| segments_match(rest, &segments[i..])),
+        Some((head, rest)) => segments
+            .split_first()
+            .is_some_and(|(segment, tail)| glob_match(head, segment) && segments_match(rest, tail)),
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            rest.is_empty()
+                || text
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain(std::iter::once(text.len()))
+                    .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
+
 fn path_to_test_name(path: &Path, base: &Path) -> proc_macro2::Ident {
     let rel_path = path.strip_prefix(base).unwrap_or(path);
 
@@ -308,6 +860,62 @@ fn path_to_test_name(path: &Path, base: &Path) -> proc_macro2::Ident {
     proc_macro2::Ident::new(&name, proc_macro2::Span::call_site())
 }
 
+/// A directory's tests and subdirectories, for `generate_tests!(.., modules = true)`.
+#[derive(Default)]
+struct TestModule {
+    tests: Vec<proc_macro2::TokenStream>,
+    children: BTreeMap<String, TestModule>,
+}
+
+impl TestModule {
+    fn insert(&mut self, dirs: &[String], test: proc_macro2::TokenStream) {
+        match dirs.split_first() {
+            None => self.tests.push(test),
+            Some((dir, rest)) => self.children.entry(dir.clone()).or_default().insert(rest, test),
+        }
+    }
+
+    fn expand(&self) -> proc_macro2::TokenStream {
+        let tests = &self.tests;
+        let children = self.children.iter().map(|(name, module)| {
+            let name = module_name(name);
+            let body = module.expand();
+            quote! {
+                mod #name {
+                    #[allow(unused_imports)]
+                    use super::*;
+                    #body
+                }
+            }
+        });
+        quote! {
+            #(#tests)*
+            #(#children)*
+        }
+    }
+}
+
+/// A directory name as a module name: `-` and `.` become `_`, and a keyword or leading digit
+/// gets a `_` added.
+fn module_name(dir: &str) -> proc_macro2::Ident {
+    let mut name = dir.replace(['-', '.', ' '], "_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if syn::parse_str::<syn::Ident>(&name).is_err() {
+        name.push('_');
+    }
+    proc_macro2::Ident::new(&name, proc_macro2::Span::call_site())
+}
+
+/// Makes the generated code depend on the file so cargo recompiles when it changes.
+fn track_file(path: &Path) -> proc_macro2::TokenStream {
+    let path_str = path.to_string_lossy();
+    quote! {
+        const _: &[u8] = include_bytes!(#path_str);
+    }
+}
+
 #[proc_macro]
 pub fn generate_tests(input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(input as GenerateTestsArgs);
@@ -325,34 +933,62 @@ pub fn generate_tests(input: TokenStream) -> TokenStream {
             .into();
     }
 
-    let yaml_files = discover_yaml_files(&full_path);
+    let workflow_files = discover_workflow_files(&full_path, &args.include, &args.exclude);
 
-    let tests = yaml_files
+    let (include, exclude) = (&args.include, &args.exclude);
+    let tests = workflow_files
         .iter()
         .filter(|f| !is_reusable_workflow(f))
         .map(|file| {
             let rel_path = file.strip_prefix(&manifest_dir).unwrap_or(file);
-            let test_name = path_to_test_name(file, &full_path);
+            // With modules, the directories are in the module path instead of the name
+            let test_name = match file.parent().filter(|_| args.modules) {
+                Some(dir) => path_to_test_name(file, dir),
+                None => path_to_test_name(file, &full_path),
+            };
             let path_str = rel_path.to_string_lossy();
 
             let ignore_attr = get_ignore_message(file)
                 .map(|msg| quote! { #[ignore = #msg] })
                 .unwrap_or_default();
 
-            quote! {
+            let test = quote! {
                 #ignore_attr
                 #[::tokio::test]
                 async fn #test_name() {
                     ::rust_actions::prelude::RustActions::<#world_type>::new()
+                        #(.include_workflows(#include))*
+                        #(.exclude_workflows(#exclude))*
                         .workflow(#path_str)
                         .run()
                         .await;
                 }
-            }
+            };
+            let dirs: Vec<String> = file
+                .strip_prefix(&full_path)
+                .ok()
+                .and_then(Path::parent)
+                .map(|dir| dir.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect())
+                .unwrap_or_default();
+            (dirs, test)
         });
 
+    let tests = if args.modules {
+        let mut root = TestModule::default();
+        for (dirs, test) in tests {
+            root.insert(&dirs, test);
+        }
+        root.expand()
+    } else {
+        let tests = tests.map(|(_, test)| test);
+        quote! { #(#tests)* }
+    };
+
+    let tracked = workflow_files.iter().map(|file| track_file(file));
+
     let expanded = quote! {
-        #(#tests)*
+        #(#tracked)*
+        #tests
     };
 
     TokenStream::from(expanded)
@@ -399,7 +1035,11 @@ pub fn workflow_test(input: TokenStream) -> TokenStream {
         .map(|msg| quote! { #[ignore = #msg] })
         .unwrap_or_default();
 
+    let tracked = track_file(&full_path);
+
     let expanded = quote! {
+        #tracked
+
         #ignore_attr
         #[::tokio::test]
         async fn #test_name() {
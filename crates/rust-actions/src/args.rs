@@ -1,10 +1,26 @@
-use crate::Result;
-use serde_json::Value;
+use crate::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 pub type RawArgs = HashMap<String, Value>;
 
+/// Key under `with:` holding a data table for a `#[arg(rows)]` field.
+pub const ROWS_KEY: &str = "rows";
+
+/// A key a step accepts under `with:`, as listed by `#[derive(Args)]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgField {
+    pub name: &'static str,
+    /// The Rust type as written, e.g. `Option<String>`
+    pub ty: &'static str,
+    pub optional: bool,
+}
+
 pub trait FromArgs: Sized {
+    /// The accepted `with:` keys; empty when they aren't known, as for `RawArgs`.
+    const FIELDS: &'static [ArgField] = &[];
+
     fn from_args(args: &RawArgs) -> Result<Self>;
 }
 
@@ -19,3 +35,186 @@ impl FromArgs for RawArgs {
         Ok(args.clone())
     }
 }
+
+/// Deserializes `with:` args into `T`, as `#[derive(Args)]` does. Errors name the offending
+/// key by its path, e.g. `'payment.card.expiry': invalid type: integer `1`, expected a string`,
+/// and an untagged enum that matches no variant lists the keys it was given. Serde buffers the
+/// value of an internally tagged or untagged enum, so within one the path stops at its key.
+pub fn deserialize<T: DeserializeOwned>(args: &RawArgs) -> Result<T> {
+    let value = Value::Object(args.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+    serde_path_to_error::deserialize(&value).map_err(|e| {
+        let path = e.path().to_string();
+        let mut reason = e.inner().to_string();
+        if let Some(name) = reason.strip_prefix("data did not match any variant of untagged enum ") {
+            reason = format!("matches no variant of {}", name);
+            let given = path_value(&value, &path).and_then(Value::as_object);
+            if let Some(given) = given {
+                let keys: Vec<&str> = given.keys().map(String::as_str).collect();
+                reason.push_str(&format!(" (got keys: {})", keys.join(", ")));
+            }
+        }
+        match path.as_str() {
+            "." => Error::Args(reason),
+            _ => Error::Args(format!("'{}': {}", path, reason)),
+        }
+    })
+}
+
+/// The value at a `serde_path_to_error` path such as `payment.cards[0]`.
+fn path_value<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path == "." {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, segment| {
+        let mut parts = segment.split('[');
+        let key = parts.next()?;
+        let value = if key.is_empty() { value } else { value.get(key)? };
+        parts.try_fold(value, |value, index| value.get(index.strip_suffix(']')?.parse::<usize>().ok()?))
+    })
+}
+
+/// Converts a data table into an array of objects. A table whose first row is a list is read
+/// as a header row followed by value rows:
+///
+/// ```yaml
+/// rows:
+///   - [username, email]
+///   - [alice, alice@example.com]
+/// ```
+///
+/// Rows that are already objects are passed through unchanged.
+pub fn table_rows(table: &Value) -> Result<Value> {
+    let rows = table
+        .as_array()
+        .ok_or_else(|| Error::Args(format!("'{}' must be a list", ROWS_KEY)))?;
+
+    let Some(Value::Array(header)) = rows.first() else {
+        return Ok(table.clone());
+    };
+
+    let columns = header
+        .iter()
+        .map(|c| {
+            c.as_str().map(str::to_string).ok_or_else(|| {
+                Error::Args(format!("'{}' header cells must be strings, got {}", ROWS_KEY, c))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    rows[1..]
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let cells = row.as_array().filter(|cells| cells.len() == columns.len());
+            let cells = cells.ok_or_else(|| {
+                Error::Args(format!(
+                    "'{}' row {} must be a list of {} values",
+                    ROWS_KEY,
+                    i + 1,
+                    columns.len()
+                ))
+            })?;
+            let object: Map<String, Value> = columns.iter().cloned().zip(cells.iter().cloned()).collect();
+            Ok(Value::Object(object))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_table_rows_with_header() {
+        let table = json!([["username", "age"], ["alice", 30], ["bob", 41]]);
+        assert_eq!(
+            table_rows(&table).unwrap(),
+            json!([{ "username": "alice", "age": 30 }, { "username": "bob", "age": 41 }])
+        );
+    }
+
+    #[test]
+    fn test_table_rows_passthrough_and_errors() {
+        let objects = json!([{ "username": "alice" }]);
+        assert_eq!(table_rows(&objects).unwrap(), objects);
+
+        assert!(table_rows(&json!([["username", "age"], ["alice"]])).is_err());
+        assert!(table_rows(&json!([[1, 2]])).is_err());
+        assert!(table_rows(&json!("alice")).is_err());
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct PaymentArgs {
+        amount: u32,
+        payer: Payer,
+        method: PaymentMethod,
+        fallback: Option<Funding>,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Payer {
+        name: String,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    #[allow(dead_code)]
+    enum PaymentMethod {
+        Card { number: String, expiry: String },
+        BankTransfer { iban: String },
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[serde(untagged)]
+    #[allow(dead_code)]
+    enum Funding {
+        Card { number: String },
+        Account { iban: String },
+    }
+
+    fn raw(value: Value) -> RawArgs {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn error(value: Value) -> String {
+        deserialize::<PaymentArgs>(&raw(value)).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_deserialize_enum_args() {
+        let args = raw(json!({
+            "amount": 10,
+            "payer": { "name": "alice" },
+            "method": { "type": "bank_transfer", "iban": "DE89" },
+            "fallback": { "iban": "GB33" },
+        }));
+        let parsed: PaymentArgs = deserialize(&args).unwrap();
+        assert!(matches!(parsed.method, PaymentMethod::BankTransfer { .. }));
+        assert!(matches!(parsed.fallback, Some(Funding::Account { .. })));
+    }
+
+    #[test]
+    fn test_deserialize_errors_name_the_key() {
+        let transfer = json!({ "type": "bank_transfer", "iban": "DE89" });
+        let payer = json!({ "name": "alice" });
+        assert_eq!(
+            error(json!({ "amount": 10, "payer": { "name": 7 }, "method": transfer })),
+            "Args error: 'payer.name': invalid type: integer `7`, expected a string"
+        );
+        assert_eq!(
+            error(json!({ "amount": 10, "payer": payer, "method": { "type": "card", "number": "4242" } })),
+            "Args error: 'method': missing field `expiry`"
+        );
+        let cheque = error(json!({ "amount": 10, "payer": payer, "method": { "type": "cheque" } }));
+        assert!(cheque.starts_with("Args error: 'method.type': unknown variant `cheque`"), "{}", cheque);
+        assert_eq!(
+            error(json!({ "amount": 10, "payer": payer, "method": transfer, "fallback": { "sort_code": "12" } })),
+            "Args error: 'fallback': matches no variant of Funding (got keys: sort_code)"
+        );
+        assert_eq!(error(json!({})), "Args error: missing field `amount`");
+    }
+}
@@ -0,0 +1,155 @@
+//! Outputs of `memoize: true` reusable workflow calls, kept on disk so other test processes
+//! (each `cargo test` binary, or the per-workflow tests of `generate_tests!`) reuse a setup that
+//! already ran instead of running it against the environment again. Opt in with
+//! `RustActions::call_cache` or `call-cache` in the config file.
+//!
+//! An entry is keyed by the content of the called workflow and every workflow it calls through
+//! `@file:`, its inputs and matrix, and the config profile, so editing any of them or switching
+//! profile runs it again. Nothing expires: delete the directory when the environment is reset.
+
+use crate::config::PROFILE_ENV;
+use crate::expr::JobOutputs;
+use crate::parser::Workflow;
+use crate::workflow_registry::{is_file_ref, WorkflowRegistry};
+use crate::Result;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(Debug, Clone)]
+pub struct CallCache {
+    dir: PathBuf,
+}
+
+impl CallCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Identifies a call of `workflow` across processes; `call` is its in-process memo key.
+    /// Workflows are hashed as `canonical_json` with FNV-1a, so the same workflows hash the
+    /// same in every process and toolchain.
+    pub fn key(registry: &WorkflowRegistry, workflow: &Workflow, call: &str) -> String {
+        let profile = std::env::var(PROFILE_ENV).unwrap_or_default();
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut pending = vec![workflow];
+        let mut seen = HashSet::new();
+        while let Some(workflow) = pending.pop() {
+            let content = serde_json::to_value(workflow).map(|v| canonical_json(&v)).unwrap_or_default();
+            hash = fnv1a(hash, content.as_bytes());
+            for uses in workflow.jobs.values().filter_map(|job| job.uses.as_deref()) {
+                if is_file_ref(uses) && seen.insert(uses) {
+                    pending.extend(registry.resolve_file_ref(uses).ok());
+                }
+            }
+        }
+        for part in [call, &profile] {
+            hash = fnv1a(hash, part.as_bytes());
+        }
+        format!("{:016x}", hash)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The outputs stored under `key`; unreadable entries count as missing.
+    pub fn get(&self, key: &str) -> Option<JobOutputs> {
+        let content = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Writes via a temporary file unique to this process, so concurrent test processes never
+    /// read a half-written entry.
+    pub fn put(&self, key: &str, outputs: &JobOutputs) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_string_pretty(outputs)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// `value` as compact JSON with object keys sorted. `Value::to_string` keeps a map's insertion
+/// order once any crate in the build enables serde_json's `preserve_order`, and maps filled
+/// from a `HashMap` are then ordered differently in every process.
+pub(crate) fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| format!("{}:{}", Value::from(key.as_str()), canonical_json(value)))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Feeds `bytes` and a terminating zero byte, which serialized JSON never contains, so
+/// adjacent parts can't run into each other.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .chain([&0])
+        .fold(hash, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entries_round_trip_by_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CallCache::new(dir.path());
+        let seed = Workflow::from_yaml("name: Seed\njobs:\n  users: { uses: \"@file:users.yaml\" }\n").unwrap();
+        let edited = Workflow::from_yaml("name: Seed\njobs:\n  users: { steps: [{ uses: db/seed2 }] }\n").unwrap();
+        let users = |step: &str| {
+            let mut registry = WorkflowRegistry::empty(dir.path());
+            let yaml = format!("name: Users\njobs:\n  users: {{ steps: [{{ uses: {} }}] }}\n", step);
+            registry.insert("users.yaml", Workflow::from_yaml(&yaml).unwrap());
+            registry
+        };
+        let registry = users("db/seed");
+
+        let key = CallCache::key(&registry, &seed, "seed.yaml:{}");
+        assert_eq!(key, CallCache::key(&registry, &seed.clone(), "seed.yaml:{}"));
+        assert_ne!(key, CallCache::key(&registry, &edited, "seed.yaml:{}"));
+        assert_ne!(key, CallCache::key(&registry, &seed, r#"seed.yaml:{"count":2}"#));
+        assert_ne!(key, CallCache::key(&users("db/seed2"), &seed, "seed.yaml:{}"));
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0x089b_e207_b544_f1e4);
+
+        assert!(cache.get(&key).is_none());
+        let mut outputs = JobOutputs::new();
+        outputs.insert("user_id", json!("u-1"));
+        cache.put(&key, &outputs).unwrap();
+        assert_eq!(cache.get(&key).unwrap().get("user_id"), Some(&json!("u-1")));
+
+        std::fs::write(cache.path(&key), "{").unwrap();
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_canonical_json_sorts_keys_at_every_depth() {
+        let mut map = serde_json::Map::new();
+        map.insert("b".to_string(), json!(1));
+        map.insert("a".to_string(), json!({ "z": [true, { "y": "\"", "x": null }] }));
+        assert_eq!(canonical_json(&Value::Object(map)), r#"{"a":{"z":[true,{"x":null,"y":"\""}]},"b":1}"#);
+    }
+}
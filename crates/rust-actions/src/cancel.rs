@@ -0,0 +1,122 @@
+//! Cooperative cancellation. Ctrl-C cancels every run in progress: no further steps, jobs or
+//! workflows start, while `after_scenario`/`after_all` hooks and deferred cleanups still run.
+//! A second Ctrl-C, or one with no run in progress, exits the process.
+
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
+use tokio::sync::Notify;
+
+/// Passed to steps through `StepContext::cancellation`. Long-running steps can poll
+/// `is_cancelled` or race their work against `cancelled()` to stop early.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Tokens of the runs in progress, cancelled by the next Ctrl-C
+static ACTIVE: Lazy<Mutex<Vec<Weak<Inner>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static LISTENER: Once = Once::new();
+
+/// Cancels `token` on Ctrl-C until the guard is dropped.
+pub(crate) fn on_ctrl_c(token: &CancellationToken) -> CtrlCGuard {
+    // A thread of its own, since the signal handler outlives any one test's runtime
+    LISTENER.call_once(|| {
+        let listener = std::thread::Builder::new()
+            .name("rust-actions-ctrl-c".to_string())
+            .spawn(listen);
+        if let Err(e) = listener {
+            tracing::warn!(target: "rust_actions", "Failed to listen for Ctrl-C: {}", e);
+        }
+    });
+    if let Ok(mut active) = ACTIVE.lock() {
+        active.push(Arc::downgrade(&token.inner));
+    }
+    CtrlCGuard {
+        token: token.clone(),
+    }
+}
+
+pub(crate) struct CtrlCGuard {
+    token: CancellationToken,
+}
+
+impl Drop for CtrlCGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE.lock() {
+            active.retain(|t| t.upgrade().is_some_and(|t| !Arc::ptr_eq(&t, &self.token.inner)));
+        }
+    }
+}
+
+fn listen() {
+    let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+        return;
+    };
+    runtime.block_on(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            let running: Vec<CancellationToken> = ACTIVE
+                .lock()
+                .map(|active| active.iter().filter_map(Weak::upgrade).map(|inner| CancellationToken { inner }).collect())
+                .unwrap_or_default();
+            if running.is_empty() || running.iter().all(CancellationToken::is_cancelled) {
+                std::process::exit(130);
+            }
+            eprintln!("\nCancelling: running cleanups and teardown hooks (Ctrl-C again to abort)");
+            running.iter().for_each(CancellationToken::cancel);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancelled_completes_after_cancel() {
+        let token = CancellationToken::new();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!token.is_cancelled());
+
+        token.cancel();
+        waiter.await.unwrap();
+        assert!(token.is_cancelled());
+        token.cancelled().await;
+    }
+}
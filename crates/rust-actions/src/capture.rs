@@ -0,0 +1,179 @@
+//! Log capture for `RustActions::capture_logs`: while a step runs, `tracing` events from the
+//! code under test are recorded as lines, and shown under the step if it fails. Events still
+//! reach the embedder's own subscriber.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::instrument::WithSubscriber;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Dispatch, Event, Level, Metadata, Subscriber};
+
+/// Lines kept per step; older ones are dropped, since the last lines before a failure are the
+/// ones that explain it.
+const MAX_LINES: usize = 200;
+
+/// Runs `future` with events at `level` or above recorded, returning them with its output.
+pub(crate) async fn capture<F: Future>(level: Level, future: F) -> (F::Output, Vec<String>) {
+    let lines = Arc::new(Mutex::new(Lines::default()));
+    let capture = LogCapture {
+        level,
+        lines: lines.clone(),
+        inner: tracing::dispatcher::get_default(Dispatch::clone),
+    };
+    let output = future.with_subscriber(Dispatch::new(capture)).await;
+    let lines = std::mem::take(&mut *lines.lock().unwrap_or_else(|e| e.into_inner()));
+    (output, lines.into_vec())
+}
+
+#[derive(Default)]
+struct Lines {
+    lines: VecDeque<String>,
+    dropped: usize,
+}
+
+impl Lines {
+    fn push(&mut self, line: String) {
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+
+    fn into_vec(self) -> Vec<String> {
+        let dropped = (self.dropped > 0).then(|| format!("... {} earlier lines", self.dropped));
+        dropped.into_iter().chain(self.lines).collect()
+    }
+}
+
+/// Records events, then forwards everything to the subscriber that was the default when the
+/// step started.
+struct LogCapture {
+    level: Level,
+    lines: Arc<Mutex<Lines>>,
+    inner: Dispatch,
+}
+
+impl LogCapture {
+    /// The runner's own events are left out; they describe the run, not the code under test.
+    fn captures(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_event()
+            && *metadata.level() <= self.level
+            && !metadata.target().starts_with("rust_actions")
+    }
+}
+
+impl Subscriber for LogCapture {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        self.inner.register_callsite(metadata);
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.captures(metadata) || self.inner.enabled(metadata)
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.inner.new_span(span)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        self.inner.record(span, values)
+    }
+
+    fn record_follows_from(&self, span: &Id, follows: &Id) {
+        self.inner.record_follows_from(span, follows)
+    }
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        if self.captures(metadata) {
+            let mut line = format!("{} {}:", metadata.level(), metadata.target());
+            event.record(&mut LineVisitor(&mut line));
+            if let Ok(mut lines) = self.lines.lock() {
+                lines.push(line);
+            }
+        }
+        if self.inner.enabled(metadata) {
+            self.inner.event(event);
+        }
+    }
+
+    fn enter(&self, span: &Id) {
+        self.inner.enter(span)
+    }
+
+    fn exit(&self, span: &Id) {
+        self.inner.exit(span)
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        self.inner.clone_span(id)
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        self.inner.try_close(id)
+    }
+}
+
+/// Appends ` message key=value ...` to a line.
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.0, " {}", value);
+            }
+            name => {
+                let _ = write!(self.0, " {}={:?}", name, value);
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.0, " {:?}", value);
+            }
+            name => {
+                let _ = write!(self.0, " {}={:?}", name, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_capture_records_events_at_level() {
+        let (output, lines) = capture(Level::INFO, async {
+            tracing::info!(target: "payments::client", status = 503, "retrying charge");
+            tracing::debug!(target: "payments::client", "connection reused");
+            tracing::error!(target: "rust_actions", "step finished");
+            7
+        })
+        .await;
+
+        assert_eq!(output, 7);
+        assert_eq!(lines, vec!["INFO payments::client: retrying charge status=503"]);
+    }
+
+    #[test]
+    fn test_lines_keep_the_latest() {
+        let mut lines = Lines::default();
+        for i in 0..MAX_LINES + 2 {
+            lines.push(i.to_string());
+        }
+        let lines = lines.into_vec();
+        assert_eq!(lines.len(), MAX_LINES + 1);
+        assert_eq!(lines[0], "... 2 earlier lines");
+        assert_eq!(lines[1], "2");
+    }
+}
@@ -0,0 +1,115 @@
+use crate::expr::JobOutputs;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CHECKPOINT_DIR: &str = "target/rust-actions/checkpoints";
+
+/// Outputs of every job that passed in a session, keyed by workflow path and job name.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub session_id: String,
+    /// How many runs the session has had, counting the current one
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
+    pub jobs: HashMap<String, JobOutputs>,
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+impl Default for Checkpoint {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Checkpoint {
+    pub fn new(session_id: impl Into<String>) -> Self {
+        Self {
+            session_id: session_id.into(),
+            attempt: first_attempt(),
+            jobs: HashMap::new(),
+        }
+    }
+
+    pub fn path(dir: &Path, session_id: &str) -> PathBuf {
+        dir.join(format!("{}.json", session_id))
+    }
+
+    pub fn load(dir: &Path, session_id: &str) -> Result<Self> {
+        let path = Self::path(dir, session_id);
+        if !path.exists() {
+            return Err(Error::CheckpointNotFound {
+                session_id: session_id.to_string(),
+                path: path.display().to_string(),
+            });
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Writes via a temporary file so an interrupted save never leaves a truncated checkpoint.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let path = Self::path(dir, &self.session_id);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    pub fn remove(dir: &Path, session_id: &str) -> Result<()> {
+        let path = Self::path(dir, session_id);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, workflow: &Path, job: &str) -> Option<&JobOutputs> {
+        self.jobs.get(&Self::key(workflow, job))
+    }
+
+    pub fn record(&mut self, workflow: &Path, job: &str, outputs: JobOutputs) {
+        self.jobs.insert(Self::key(workflow, job), outputs);
+    }
+
+    fn key(workflow: &Path, job: &str) -> String {
+        format!("{}#{}", workflow.display(), job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let workflow = Path::new("orders/checkout.yaml");
+
+        let mut checkpoint = Checkpoint::new("abc123");
+        let mut outputs = JobOutputs::new();
+        outputs.insert("order_id", json!("o-1"));
+        checkpoint.record(workflow, "create", outputs);
+        checkpoint.save(dir.path()).unwrap();
+
+        let loaded = Checkpoint::load(dir.path(), "abc123").unwrap();
+        assert_eq!(
+            loaded.get(workflow, "create").unwrap().get("order_id"),
+            Some(&json!("o-1"))
+        );
+        assert!(loaded.get(workflow, "pay").is_none());
+        assert_eq!(loaded.attempt, 1);
+
+        Checkpoint::remove(dir.path(), "abc123").unwrap();
+        assert!(matches!(
+            Checkpoint::load(dir.path(), "abc123"),
+            Err(Error::CheckpointNotFound { .. })
+        ));
+    }
+}
@@ -43,6 +43,38 @@ impl VirtualClock {
     pub fn current(&self) -> Duration {
         Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
     }
+
+    /// Starts measuring simulated and wall-clock time from now.
+    pub fn stopwatch(&self) -> Stopwatch {
+        Stopwatch {
+            clock: self.clone(),
+            start: self.now(),
+            tokio_start: tokio::time::Instant::now(),
+            wall_start: std::time::Instant::now(),
+        }
+    }
+}
+
+/// Measures simulated time, which is how far tokio's clock moved (it jumps ahead while paused,
+/// e.g. with `start_paused = true`) plus any `VirtualClock::advance`, alongside wall-clock time.
+/// Without paused time the two agree, give or take manual advances.
+#[derive(Clone)]
+pub struct Stopwatch {
+    clock: VirtualClock,
+    start: Instant,
+    tokio_start: tokio::time::Instant,
+    wall_start: std::time::Instant,
+}
+
+impl Stopwatch {
+    /// Simulated time since the stopwatch started.
+    pub fn elapsed(&self) -> Duration {
+        self.tokio_start.elapsed() + self.clock.elapsed_since(self.start)
+    }
+
+    pub fn wall(&self) -> Duration {
+        self.wall_start.elapsed()
+    }
 }
 
 impl Default for VirtualClock {
@@ -68,6 +100,18 @@ mod tests {
         assert_eq!(clock.elapsed_since(start), Duration::from_millis(1500));
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_stopwatch_follows_paused_time() {
+        let clock = VirtualClock::new();
+        let watch = clock.stopwatch();
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!(watch.elapsed(), Duration::from_secs(35));
+        assert!(watch.wall() < Duration::from_secs(5));
+    }
+
     #[test]
     fn test_set() {
         let clock = VirtualClock::new();
@@ -0,0 +1,431 @@
+use crate::exit::{ExitPolicy, FailureKind};
+use crate::expr::MissingKeyPolicy;
+use crate::outputs::{OutputLimit, OverflowPolicy};
+use crate::policy::StepPolicy;
+use crate::retry::RetryPolicy;
+use crate::services::{DockerServices, ExternalServices, ServiceProvider};
+use crate::{Error, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Looked up in the current directory and its ancestors by `RustActions::new()`.
+pub const CONFIG_FILE: &str = "rust-actions.toml";
+/// Points at a config file to use instead of searching for `CONFIG_FILE`.
+pub const CONFIG_ENV: &str = "RUST_ACTIONS_CONFIG";
+/// Selects a `[profile.<name>]` section, applied on top of the top-level settings.
+pub const PROFILE_ENV: &str = "RUST_ACTIONS_PROFILE";
+
+/// Runner settings shared by every test binary of a workspace. Each one is the file form of a
+/// `RustActions` builder method, and builder calls made after `new()` override it.
+///
+/// ```toml
+/// workflows = "tests/workflows"
+/// exclude-workflows = ["fixtures/**"]
+/// progress-bar = true
+///
+/// [reports]
+/// json = "target/rust-actions/results.json"
+/// html = "target/rust-actions/report.html"
+///
+/// [retry]
+/// max-attempts = 3
+///
+/// [exit]
+/// max-failed-jobs = 2
+///
+/// [steps]
+/// deny = ["admin/*"]
+///
+/// [rate-limits]
+/// "payments/*" = 5.0
+///
+/// [services.endpoints]
+/// postgres = "postgres://ci-db.internal:5432"
+///
+/// [profile.pre-merge]
+/// fail-fast = true
+/// allow-failure = ["Nightly import"]
+///
+/// [profile.locked-down.services]
+/// provider = "external"
+///
+/// [profile.nightly.reports]
+/// baseline = "target/rust-actions/main.json"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub workflows: Option<PathBuf>,
+    /// Path patterns; see `RustActions::include_workflows`
+    pub include_workflows: Vec<String>,
+    pub exclude_workflows: Vec<String>,
+    pub checkpoint_dir: Option<PathBuf>,
+    /// See `RustActions::call_cache`
+    pub call_cache: Option<PathBuf>,
+    pub progress_bar: Option<bool>,
+    pub fail_fast: Option<bool>,
+    /// Seed for `RustActions::shuffle`
+    pub shuffle: Option<u64>,
+    /// See `RustActions::streaming`
+    pub streaming: Option<bool>,
+    /// Workflow name or `<workflow> / <job>` patterns; see `RustActions::allow_failure`
+    pub allow_failure: Vec<String>,
+    /// Names jobs and steps may act `as:`; see `RustActions::identities`
+    pub identities: Vec<String>,
+    /// A `tracing` level, e.g. `debug`; see `RustActions::capture_logs`
+    pub capture_logs: Option<String>,
+    /// `strict` or `lenient`; see `RustActions::missing_keys`
+    pub missing_keys: Option<MissingKeyPolicy>,
+    pub allow_unknown_fields: Option<bool>,
+    pub reports: ReportsConfig,
+    pub retry: Option<RetryConfig>,
+    pub exit: Option<ExitConfig>,
+    pub output_limit: Option<OutputLimitConfig>,
+    pub services: ServicesConfig,
+    pub steps: StepsConfig,
+    /// Step name pattern → executions per second, checked in file order
+    pub rate_limits: toml::Table,
+    pub profile: HashMap<String, Config>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ReportsConfig {
+    /// `save_results`
+    pub json: Option<PathBuf>,
+    /// `html_report`
+    pub html: Option<PathBuf>,
+    /// `coverage_report`
+    pub coverage: Option<PathBuf>,
+    pub baseline: Option<PathBuf>,
+    /// Defaults to 1.5 when `baseline` is set
+    pub regression_ratio: Option<f64>,
+    /// `failure_manifest`
+    pub failures: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub delay_ms: Option<u64>,
+    pub backoff: Option<f64>,
+}
+
+/// Unset codes keep `ExitPolicy`'s defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ExitConfig {
+    pub max_failed_jobs: Option<usize>,
+    pub test_failures: Option<i32>,
+    pub validation: Option<i32>,
+    pub infrastructure: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OutputLimitConfig {
+    pub max_bytes: usize,
+    /// `truncate` (default) or `fail`
+    #[serde(default)]
+    pub policy: OverflowPolicyConfig,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicyConfig {
+    #[default]
+    Truncate,
+    Fail,
+}
+
+/// See `RustActions::service_provider`. A profile's endpoints are added to the top-level ones.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServicesConfig {
+    /// `docker` (the default) or `external`
+    pub provider: Option<ServiceProviderConfig>,
+    /// Service name → endpoint, for the `external` provider
+    pub endpoints: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServiceProviderConfig {
+    Docker,
+    External,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StepsConfig {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl Config {
+    /// Reads the file named by `RUST_ACTIONS_CONFIG`, else the nearest `rust-actions.toml`,
+    /// with the profile named by `RUST_ACTIONS_PROFILE` applied. No file means no settings.
+    pub fn discover() -> Result<Self> {
+        let path = match std::env::var_os(CONFIG_ENV) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => std::env::current_dir()?
+                .ancestors()
+                .map(|dir| dir.join(CONFIG_FILE))
+                .find(|path| path.is_file()),
+        };
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let config = Self::load(&path)?;
+        match std::env::var(PROFILE_ENV) {
+            Ok(profile) if !profile.is_empty() => config.with_profile(&profile),
+            _ => Ok(config),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+        Self::from_toml(&content).map_err(|e| match e {
+            Error::Config(msg) => Error::Config(format!("{}: {}", path.display(), msg)),
+            other => other,
+        })
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content).map_err(|e| Error::Config(e.to_string()))?;
+        config.rate_limits()?;
+        for profile in config.profile.values() {
+            profile.rate_limits()?;
+        }
+        Ok(config)
+    }
+
+    /// These settings with `[profile.<name>]` applied on top. Lists and rate limits are
+    /// extended; everything else set in the profile replaces the top-level value.
+    pub fn with_profile(mut self, name: &str) -> Result<Self> {
+        let profile = self.profile.remove(name).ok_or_else(|| {
+            let mut known: Vec<&String> = self.profile.keys().collect();
+            known.sort();
+            Error::Config(format!("Unknown profile '{}' (defined: {:?})", name, known))
+        })?;
+
+        self.workflows = profile.workflows.or(self.workflows);
+        self.include_workflows.extend(profile.include_workflows);
+        self.exclude_workflows.extend(profile.exclude_workflows);
+        self.checkpoint_dir = profile.checkpoint_dir.or(self.checkpoint_dir);
+        self.call_cache = profile.call_cache.or(self.call_cache);
+        self.progress_bar = profile.progress_bar.or(self.progress_bar);
+        self.fail_fast = profile.fail_fast.or(self.fail_fast);
+        self.shuffle = profile.shuffle.or(self.shuffle);
+        self.streaming = profile.streaming.or(self.streaming);
+        self.allow_failure.extend(profile.allow_failure);
+        self.identities.extend(profile.identities);
+        self.capture_logs = profile.capture_logs.or(self.capture_logs);
+        self.missing_keys = profile.missing_keys.or(self.missing_keys);
+        self.allow_unknown_fields = profile.allow_unknown_fields.or(self.allow_unknown_fields);
+        self.reports.json = profile.reports.json.or(self.reports.json);
+        self.reports.html = profile.reports.html.or(self.reports.html);
+        self.reports.coverage = profile.reports.coverage.or(self.reports.coverage);
+        self.reports.baseline = profile.reports.baseline.or(self.reports.baseline);
+        self.reports.regression_ratio = profile
+            .reports
+            .regression_ratio
+            .or(self.reports.regression_ratio);
+        self.reports.failures = profile.reports.failures.or(self.reports.failures);
+        self.retry = profile.retry.or(self.retry);
+        self.exit = profile.exit.or(self.exit);
+        self.output_limit = profile.output_limit.or(self.output_limit);
+        self.services.provider = profile.services.provider.or(self.services.provider);
+        self.services.endpoints.extend(profile.services.endpoints);
+        self.steps.allow.extend(profile.steps.allow);
+        self.steps.deny.extend(profile.steps.deny);
+        self.rate_limits.extend(profile.rate_limits);
+        Ok(self)
+    }
+
+    pub fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry.as_ref().map(|retry| {
+            let mut policy = RetryPolicy::new(retry.max_attempts);
+            if let Some(ms) = retry.delay_ms {
+                policy = policy.delay(Duration::from_millis(ms));
+            }
+            if let Some(backoff) = retry.backoff {
+                policy = policy.backoff(backoff);
+            }
+            policy
+        })
+    }
+
+    pub fn service_provider(&self) -> Option<Arc<dyn ServiceProvider>> {
+        match self.services.provider? {
+            ServiceProviderConfig::Docker => Some(Arc::new(DockerServices::new())),
+            ServiceProviderConfig::External => {
+                let endpoints = self.services.endpoints.clone();
+                Some(Arc::new(ExternalServices::new().endpoints(endpoints)))
+            }
+        }
+    }
+
+    pub fn exit_policy(&self) -> Option<ExitPolicy> {
+        self.exit.as_ref().map(|exit| {
+            let mut policy = ExitPolicy::new();
+            if let Some(n) = exit.max_failed_jobs {
+                policy = policy.max_failed_jobs(n);
+            }
+            let codes = [
+                (FailureKind::Test, exit.test_failures),
+                (FailureKind::Validation, exit.validation),
+                (FailureKind::Infrastructure, exit.infrastructure),
+            ];
+            for (kind, code) in codes {
+                if let Some(code) = code {
+                    policy = policy.code(kind, code);
+                }
+            }
+            policy
+        })
+    }
+
+    pub fn output_limit(&self) -> Option<OutputLimit> {
+        self.output_limit.as_ref().map(|limit| OutputLimit {
+            max_bytes: limit.max_bytes,
+            policy: match limit.policy {
+                OverflowPolicyConfig::Truncate => OverflowPolicy::Truncate,
+                OverflowPolicyConfig::Fail => OverflowPolicy::Fail,
+            },
+        })
+    }
+
+    /// `None` when the file sets neither `allow` nor `deny`.
+    pub fn step_policy(&self) -> Option<StepPolicy> {
+        let policy = self.steps.allow.iter().fold(StepPolicy::new(), |p, s| p.allow(s));
+        let policy = self.steps.deny.iter().fold(policy, |p, s| p.deny(s));
+        (!policy.is_empty()).then_some(policy)
+    }
+
+    /// `[rate-limits]` as (pattern, executions per second), in file order.
+    pub fn rate_limits(&self) -> Result<Vec<(String, f64)>> {
+        self.rate_limits
+            .iter()
+            .map(|(pattern, value)| {
+                let per_second = value
+                    .as_float()
+                    .or_else(|| value.as_integer().map(|n| n as f64))
+                    .ok_or_else(|| {
+                        Error::Config(format!(
+                            "rate-limits.\"{}\" must be a number, got {}",
+                            pattern, value
+                        ))
+                    })?;
+                Ok((pattern.clone(), per_second))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_overrides_and_extends() {
+        let config = Config::from_toml(
+            r#"
+workflows = "tests/workflows"
+allow-failure = ["Nightly import"]
+identities = ["admin"]
+
+[reports]
+json = "target/results.json"
+
+[retry]
+max-attempts = 3
+delay-ms = 50
+
+[steps]
+deny = ["admin/*"]
+
+[rate-limits]
+"payments/*" = 5
+"*" = 100.0
+
+[services.endpoints]
+postgres = "ci-db:5432"
+
+[profile.nightly]
+progress-bar = true
+allow-failure = ["Users / login"]
+
+[profile.nightly.reports]
+baseline = "target/main.json"
+
+[profile.nightly.steps]
+deny = ["*/delete"]
+
+[profile.legacy]
+missing-keys = "lenient"
+identities = ["auditor"]
+
+[profile.legacy.exit]
+max-failed-jobs = 2
+infrastructure = 70
+
+[profile.locked-down.services]
+provider = "external"
+
+[profile.locked-down.services.endpoints]
+redis = "ci-cache"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.rate_limits().unwrap(),
+            vec![("payments/*".to_string(), 5.0), ("*".to_string(), 100.0)]
+        );
+        assert_eq!(
+            config.retry_policy(),
+            Some(RetryPolicy::new(3).delay(Duration::from_millis(50)))
+        );
+
+        let nightly = config.clone().with_profile("nightly").unwrap();
+        assert_eq!(nightly.workflows, Some(PathBuf::from("tests/workflows")));
+        assert_eq!(nightly.progress_bar, Some(true));
+        assert_eq!(nightly.reports.json, Some(PathBuf::from("target/results.json")));
+        assert_eq!(nightly.reports.baseline, Some(PathBuf::from("target/main.json")));
+        assert_eq!(nightly.allow_failure, vec!["Nightly import", "Users / login"]);
+        let policy = nightly.step_policy().unwrap();
+        assert!(!policy.is_allowed("admin/reset"));
+        assert!(!policy.is_allowed("user/delete"));
+        assert!(policy.is_allowed("user/create"));
+
+        assert_eq!(nightly.missing_keys, None);
+        let legacy = config.clone().with_profile("legacy").unwrap();
+        assert_eq!(legacy.missing_keys, Some(MissingKeyPolicy::Lenient));
+        assert_eq!(legacy.identities, vec!["admin", "auditor"]);
+        assert_eq!(nightly.exit_policy(), None);
+        assert_eq!(
+            legacy.exit_policy(),
+            Some(ExitPolicy::new().max_failed_jobs(2).code(FailureKind::Infrastructure, 70))
+        );
+
+        assert!(config.service_provider().is_none());
+        let locked_down = config.clone().with_profile("locked-down").unwrap();
+        assert_eq!(locked_down.services.provider, Some(ServiceProviderConfig::External));
+        assert_eq!(locked_down.services.endpoints.len(), 2);
+        assert!(locked_down.service_provider().is_some());
+
+        assert!(matches!(config.with_profile("weekly"), Err(Error::Config(_))));
+        assert!(matches!(
+            Config::from_toml("workflow = \"tests\""),
+            Err(Error::Config(msg)) if msg.contains("unknown field `workflow`")
+        ));
+    }
+}
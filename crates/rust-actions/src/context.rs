@@ -0,0 +1,226 @@
+use crate::args::RawArgs;
+use crate::cancel::CancellationToken;
+use crate::clock::VirtualClock;
+use crate::matrix::MatrixCombination;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Where a step is running, plus `defer` for registering cleanups. Steps receive it by
+/// taking `&StepContext` as their last parameter:
+///
+/// ```ignore
+/// #[step("deploy/check")]
+/// async fn check(world: &mut TestWorld, ctx: &StepContext) -> Result<()> {
+///     let region = ctx.matrix("region");
+///     ...
+/// }
+/// ```
+#[derive(Clone)]
+pub struct StepContext {
+    workflow: String,
+    job: String,
+    session_id: String,
+    attempt: u32,
+    env: HashMap<String, String>,
+    matrix: MatrixCombination,
+    clock: VirtualClock,
+    cancellation: CancellationToken,
+    temp_dir: Option<PathBuf>,
+    /// The job's `as:`
+    job_identity: Option<String>,
+    deferred: Arc<Mutex<Vec<DeferredStep>>>,
+    compensations: Arc<Mutex<Vec<DeferredStep>>>,
+    vars: Arc<Mutex<Vec<(String, Value)>>>,
+    links: Arc<Mutex<Vec<StepLink>>>,
+}
+
+/// A URL or file path a step attached to its result with `StepContext::link`, e.g. the trace
+/// of a failed request or a dumped payload. Reports show them with the step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepLink {
+    pub label: String,
+    pub target: String,
+}
+
+/// A cleanup step registered with `StepContext::defer` or a step's `cleanup:` list, or a
+/// compensating one registered with `StepContext::compensate` or `compensate:`.
+#[derive(Debug, Clone)]
+pub struct DeferredStep {
+    pub uses: String,
+    pub args: RawArgs,
+}
+
+impl StepContext {
+    pub fn new(
+        workflow: impl Into<String>,
+        job: impl Into<String>,
+        session_id: impl Into<String>,
+        clock: VirtualClock,
+    ) -> Self {
+        Self {
+            workflow: workflow.into(),
+            job: job.into(),
+            session_id: session_id.into(),
+            attempt: 1,
+            env: HashMap::new(),
+            matrix: MatrixCombination::new(),
+            clock,
+            cancellation: CancellationToken::new(),
+            temp_dir: None,
+            job_identity: None,
+            deferred: Arc::new(Mutex::new(Vec::new())),
+            compensations: Arc::new(Mutex::new(Vec::new())),
+            vars: Arc::new(Mutex::new(Vec::new())),
+            links: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn with_attempt(mut self, attempt: u32) -> Self {
+        self.attempt = attempt;
+        self
+    }
+
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    pub fn with_matrix(mut self, matrix: MatrixCombination) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn with_temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    pub(crate) fn with_job_identity(mut self, identity: Option<String>) -> Self {
+        self.job_identity = identity;
+        self
+    }
+
+    pub(crate) fn job_identity(&self) -> Option<&str> {
+        self.job_identity.as_deref()
+    }
+
+    pub fn workflow(&self) -> &str {
+        &self.workflow
+    }
+
+    pub fn job(&self) -> &str {
+        &self.job
+    }
+
+    /// The run's session id, the same for every step of the run and `${{ run.session }}` in
+    /// expressions. Use it rather than the process environment, which the runner never sets.
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// 1 for a fresh session, one more each time it's resumed, as `${{ run.attempt }}`.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Workflow and job `env:` merged, as seen by `${{ env.* }}`.
+    pub fn env(&self, key: &str) -> Option<&str> {
+        self.env.get(key).map(String::as_str)
+    }
+
+    pub fn env_vars(&self) -> &HashMap<String, String> {
+        &self.env
+    }
+
+    pub fn matrix(&self, key: &str) -> Option<&Value> {
+        self.matrix.get(key)
+    }
+
+    /// The job's matrix combination; empty for jobs without a matrix.
+    pub fn matrix_values(&self) -> &MatrixCombination {
+        &self.matrix
+    }
+
+    /// The job's temp dir, as `${{ runner.temp }}`; removed when the job ends.
+    pub fn temp_dir(&self) -> Option<&Path> {
+        self.temp_dir.as_deref()
+    }
+
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// Cancelled on Ctrl-C. The runner starts no further steps either way; a long step can
+    /// check it to stop early, leaving its cleanup to `defer`.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Registers a cleanup step to run when the job ends, whether or not it passed. Cleanups
+    /// run in reverse registration order. `args` is the step's `with:` as a JSON object.
+    pub fn defer(&self, uses: impl Into<String>, args: Value) {
+        push(&self.deferred, uses.into(), args);
+    }
+
+    /// Registers a step that undoes this one, run only if a later step of the job fails.
+    /// Compensations run before cleanups, most recent first.
+    pub fn compensate(&self, uses: impl Into<String>, args: Value) {
+        push(&self.compensations, uses.into(), args);
+    }
+
+    /// Sets `${{ vars.<name> }}` for the rest of the job, once this step passes.
+    pub fn set_var(&self, name: impl Into<String>, value: impl Into<Value>) {
+        if let Ok(mut vars) = self.vars.lock() {
+            vars.push((name.into(), value.into()));
+        }
+    }
+
+    /// Takes the vars set since the last call, in the order they were set.
+    pub(crate) fn take_vars(&self) -> Vec<(String, Value)> {
+        self.vars.lock().map(|mut vars| std::mem::take(&mut *vars)).unwrap_or_default()
+    }
+
+    /// Attaches a URL or file path to this step's result, such as a trace or dashboard link,
+    /// so a failure report points straight at it.
+    pub fn link(&self, label: impl Into<String>, target: impl Into<String>) {
+        if let Ok(mut links) = self.links.lock() {
+            links.push(StepLink {
+                label: label.into(),
+                target: target.into(),
+            });
+        }
+    }
+
+    /// Takes the links attached since the last call, in the order they were attached.
+    pub(crate) fn take_links(&self) -> Vec<StepLink> {
+        self.links.lock().map(|mut links| std::mem::take(&mut *links)).unwrap_or_default()
+    }
+
+    /// Takes the most recently deferred cleanup step.
+    pub(crate) fn pop_deferred(&self) -> Option<DeferredStep> {
+        self.deferred.lock().ok()?.pop()
+    }
+
+    /// Takes the most recently registered compensating step.
+    pub(crate) fn pop_compensation(&self) -> Option<DeferredStep> {
+        self.compensations.lock().ok()?.pop()
+    }
+}
+
+fn push(steps: &Mutex<Vec<DeferredStep>>, uses: String, args: Value) {
+    let args = match args {
+        Value::Object(map) => map.into_iter().collect(),
+        _ => RawArgs::new(),
+    };
+    if let Ok(mut steps) = steps.lock() {
+        steps.push(DeferredStep { uses, args });
+    }
+}
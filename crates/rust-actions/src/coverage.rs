@@ -0,0 +1,156 @@
+//! Which registered steps a run exercised, and from which workflows, for finding dead or
+//! untested step code. Built-in steps (`fs/*`, `vars/set`, ...) are left out.
+
+use crate::registry::{StepRegistry, StepSource};
+use crate::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StepCoverage {
+    /// Every registered step with the workflows that ran it, as `name@x.y.z` for versioned
+    /// steps; an empty set means no workflow exercised it
+    pub steps: BTreeMap<String, BTreeSet<String>>,
+    /// Deprecated steps that workflows still ran
+    pub deprecated: BTreeMap<String, DeprecatedUse>,
+    /// Where each step whose definition is known lives, with its doc comment
+    pub sources: BTreeMap<String, StepSource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeprecatedUse {
+    /// From `#[step(.., deprecated = "...")]`
+    pub note: String,
+    pub workflows: BTreeSet<String>,
+}
+
+impl StepCoverage {
+    /// `exercised` maps registered step names to the workflows that ran them.
+    pub(crate) fn new(
+        registry: &StepRegistry,
+        builtin: &HashSet<String>,
+        exercised: &HashMap<String, BTreeSet<String>>,
+    ) -> Self {
+        let mut coverage = Self::default();
+        for name in registry.names().into_iter().filter(|n| !builtin.contains(n)) {
+            let workflows = exercised.get(&name).cloned().unwrap_or_default();
+            if let Some(note) = registry.deprecation(&name).filter(|_| !workflows.is_empty()) {
+                coverage.deprecated.insert(
+                    name.clone(),
+                    DeprecatedUse {
+                        note: note.to_string(),
+                        workflows: workflows.clone(),
+                    },
+                );
+            }
+            if let Some(source) = registry.source(&name) {
+                coverage.sources.insert(name.clone(), *source);
+            }
+            coverage.steps.insert(name, workflows);
+        }
+        coverage
+    }
+
+    /// Registered steps no workflow ran, sorted.
+    pub fn unexercised(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .filter(|(_, workflows)| workflows.is_empty())
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn exercised_count(&self) -> usize {
+        self.steps.len() - self.unexercised().len()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for StepCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Step coverage: {}/{} steps exercised",
+            self.exercised_count(),
+            self.steps.len()
+        )?;
+        let unexercised = self.unexercised();
+        if !unexercised.is_empty() {
+            write!(f, "\n  Never run:")?;
+            for name in unexercised {
+                write!(f, "\n    - {}", name)?;
+                if let Some(source) = self.sources.get(name) {
+                    write!(f, " at {}", source)?;
+                }
+            }
+        }
+        if !self.deprecated.is_empty() {
+            write!(f, "\n  Deprecated but still run:")?;
+            for (name, usage) in &self.deprecated {
+                let workflows: Vec<&str> = usage.workflows.iter().map(String::as_str).collect();
+                write!(f, "\n    - {} ({}) by {}", name, usage.note, workflows.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::StepContext;
+    use crate::outputs::StepOutputs;
+    use std::any::Any;
+    use std::future::Future;
+    use std::pin::Pin;
+
+    fn noop<'a>(
+        _: &'a mut dyn Any,
+        _: crate::args::RawArgs,
+        _: &'a StepContext,
+    ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>> {
+        Box::pin(async { Ok(StepOutputs::new()) })
+    }
+
+    #[test]
+    fn test_step_coverage() {
+        let mut registry = StepRegistry::new();
+        registry.register("fs/read", noop);
+        for name in ["user/create@1.0.0", "user/create@2.0.0", "user/delete"] {
+            registry.register(name, noop);
+        }
+        registry.deprecate("user/create@1.0.0", "use user/create@2");
+        registry.set_source(
+            "user/delete",
+            StepSource {
+                module: "app::steps",
+                file: "src/steps.rs",
+                line: 41,
+                doc: None,
+            },
+        );
+        let builtin = HashSet::from(["fs/read".to_string()]);
+        let exercised = HashMap::from([
+            ("user/create@1.0.0".to_string(), BTreeSet::from(["Legacy".to_string()])),
+            ("user/create@2.0.0".to_string(), BTreeSet::from(["Users".to_string()])),
+        ]);
+
+        let coverage = StepCoverage::new(&registry, &builtin, &exercised);
+        assert_eq!(coverage.unexercised(), vec!["user/delete"]);
+        assert_eq!(
+            coverage.to_string(),
+            "Step coverage: 2/3 steps exercised\n  Never run:\n    - user/delete at src/steps.rs:41\n  \
+             Deprecated but still run:\n    - user/create@1.0.0 (use user/create@2) by Legacy"
+        );
+    }
+}
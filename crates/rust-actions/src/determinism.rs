@@ -2,6 +2,7 @@ use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -86,6 +87,45 @@ impl SeededRng {
         self.rng.gen_range(min..max)
     }
 
+    pub fn next_range_i64(&mut self, min: i64, max: i64) -> i64 {
+        self.rng.gen_range(min..max)
+    }
+
+    /// Normally distributed, via the Box-Muller transform.
+    pub fn next_gaussian(&mut self, mean: f64, std_dev: f64) -> f64 {
+        let u1: f64 = 1.0 - self.rng.gen::<f64>();
+        let u2: f64 = self.rng.gen();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        mean + std_dev * z
+    }
+
+    /// `e^x` for a gaussian `x`, e.g. latencies or order sizes: always positive, long tail.
+    pub fn next_log_normal(&mut self, mu: f64, sigma: f64) -> f64 {
+        self.next_gaussian(mu, sigma).exp()
+    }
+
+    /// A time in `start..end`, to the nanosecond.
+    pub fn next_time(&mut self, start: SystemTime, end: SystemTime) -> SystemTime {
+        let span = end.duration_since(start).unwrap_or_default().as_nanos();
+        if span == 0 {
+            return start;
+        }
+        let offset = self.rng.gen_range(0..span);
+        start + Duration::new((offset / 1_000_000_000) as u64, (offset % 1_000_000_000) as u32)
+    }
+
+    /// A UTC date in `start..end`, as `YYYY-MM-DD`.
+    pub fn next_date(&mut self, start: SystemTime, end: SystemTime) -> String {
+        let (date, _) = format_utc(self.next_time(start, end));
+        date
+    }
+
+    /// A UTC time in `start..end`, as RFC 3339 to the second (`2024-03-05T14:07:59Z`).
+    pub fn next_datetime(&mut self, start: SystemTime, end: SystemTime) -> String {
+        let (date, time) = format_utc(self.next_time(start, end));
+        format!("{}T{}Z", date, time)
+    }
+
     pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
         if items.is_empty() {
             None
@@ -95,10 +135,50 @@ impl SeededRng {
         }
     }
 
+    /// Picks an item with probability proportional to `weight(item)`. `None` if `items` is
+    /// empty or no weight is positive.
+    pub fn choose_weighted<'a, T>(&mut self, items: &'a [T], weight: impl Fn(&T) -> f64) -> Option<&'a T> {
+        use rand::seq::SliceRandom;
+        items.choose_weighted(&mut self.rng, weight).ok()
+    }
+
     pub fn shuffle<T>(&mut self, items: &mut [T]) {
         use rand::seq::SliceRandom;
         items.shuffle(&mut self.rng);
     }
+
+    /// Endless values from `f`, e.g. `rng.sample_iter(|r| r.next_gaussian(100.0, 15.0)).take(50)`.
+    pub fn sample_iter<'a, T>(&'a mut self, mut f: impl FnMut(&mut Self) -> T + 'a) -> impl Iterator<Item = T> + 'a {
+        std::iter::repeat_with(move || f(self))
+    }
+
+    pub fn uuids(&mut self) -> impl Iterator<Item = Uuid> + '_ {
+        self.sample_iter(Self::next_uuid)
+    }
+}
+
+/// `(YYYY-MM-DD, HH:MM:SS)` in UTC, with Howard Hinnant's `civil_from_days`.
+fn format_utc(time: SystemTime) -> (String, String) {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+    let (days, secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}:{:02}:{:02}", secs / 3_600, secs % 3_600 / 60, secs % 60),
+    )
 }
 
 impl Default for SeededRng {
@@ -149,6 +229,44 @@ mod tests {
         assert_ne!(rng1.seed(), rng3.seed());
     }
 
+    #[test]
+    fn test_distributions() {
+        let mut rng = SeededRng::with_seed(7);
+        let samples: Vec<f64> = rng.sample_iter(|r| r.next_gaussian(100.0, 15.0)).take(10_000).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 100.0).abs() < 1.0, "mean {}", mean);
+        assert!(rng.sample_iter(|r| r.next_log_normal(0.0, 1.0)).take(100).all(|x| x > 0.0));
+        assert!(rng.sample_iter(|r| r.next_range_i64(-5, 5)).take(100).all(|x| (-5..5).contains(&x)));
+
+        let items = [("never", 0.0), ("rare", 1.0), ("common", 9.0)];
+        let picks: Vec<&str> = (0..1_000)
+            .map(|_| rng.choose_weighted(&items, |(_, w)| *w).unwrap().0)
+            .collect();
+        assert!(!picks.contains(&"never"));
+        assert!(picks.iter().filter(|p| **p == "common").count() > 800);
+        assert_eq!(rng.choose_weighted(&items[..1], |(_, w)| *w), None);
+
+        let mut again = SeededRng::with_seed(7);
+        let first: Vec<f64> = again.sample_iter(|r| r.next_gaussian(100.0, 15.0)).take(10_000).collect();
+        assert_eq!(first, samples);
+    }
+
+    #[test]
+    fn test_next_date() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_709_596_800); // 2024-03-05
+        let end = start + Duration::from_secs(86_400);
+        let mut rng = SeededRng::with_seed(1);
+        assert_eq!(rng.next_date(start, end), "2024-03-05");
+        assert!(rng.next_datetime(start, end).starts_with("2024-03-05T"));
+        assert_eq!(rng.next_time(start, start), start);
+
+        assert_eq!(format_utc(UNIX_EPOCH), ("1970-01-01".to_string(), "00:00:00".to_string()));
+        let leap = UNIX_EPOCH + Duration::from_secs(951_782_400 + 86_399); // 2000-02-29
+        assert_eq!(format_utc(leap), ("2000-02-29".to_string(), "23:59:59".to_string()));
+        let before = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(format_utc(before), ("1969-12-31".to_string(), "23:59:59".to_string()));
+    }
+
     #[test]
     fn test_sequence_determinism() {
         let mut rng1 = SeededRng::with_seed(999);
@@ -0,0 +1,260 @@
+use crate::runner::{job_key, WorkflowResult};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Regressions smaller than this are treated as noise regardless of ratio.
+const MIN_REGRESSION_DELTA: Duration = Duration::from_millis(50);
+
+/// A serialized run, as written by `RustActions::save_results`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub session_id: String,
+    pub workflows: Vec<WorkflowResult>,
+}
+
+impl RunRecord {
+    pub fn new(session_id: impl Into<String>, workflows: Vec<WorkflowResult>) -> Self {
+        Self {
+            session_id: session_id.into(),
+            workflows,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Wall time of each job keyed as `<workflow> / <job>`, summed over matrix combinations.
+    /// Records from before wall time was kept fall back to the recorded duration.
+    pub fn job_wall_durations(&self) -> HashMap<String, Duration> {
+        let mut durations = HashMap::new();
+        for workflow in &self.workflows {
+            for job in &workflow.jobs {
+                let wall = if job.wall_duration.is_zero() {
+                    job.duration
+                } else {
+                    job.wall_duration
+                };
+                *durations.entry(job_key(&workflow.name, &job.name)).or_default() += wall;
+            }
+        }
+        durations
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DurationRegression {
+    pub job: String,
+    pub baseline: Duration,
+    pub current: Duration,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunDiff {
+    pub newly_failing: Vec<String>,
+    pub newly_passing: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub regressions: Vec<DurationRegression>,
+}
+
+struct JobSummary {
+    passed: bool,
+    duration: Duration,
+}
+
+fn job_summaries(workflows: &[WorkflowResult]) -> BTreeMap<String, JobSummary> {
+    workflows
+        .iter()
+        .flat_map(|w| {
+            w.jobs.iter().map(move |j| {
+                (
+                    format!("{} / {}{}", w.name, j.name, j.matrix_suffix),
+                    JobSummary {
+                        passed: j.passed(),
+                        duration: j.duration,
+                    },
+                )
+            })
+        })
+        .collect()
+}
+
+impl RunDiff {
+    /// Compares two runs job by job. A job counts as a duration regression when it took more
+    /// than `ratio` times its baseline duration.
+    pub fn compare(baseline: &[WorkflowResult], current: &[WorkflowResult], ratio: f64) -> Self {
+        let before = job_summaries(baseline);
+        let after = job_summaries(current);
+        let mut diff = RunDiff::default();
+
+        for (key, now) in &after {
+            match before.get(key) {
+                None => {
+                    diff.added.push(key.clone());
+                    if !now.passed {
+                        diff.newly_failing.push(key.clone());
+                    }
+                }
+                Some(then) => {
+                    if then.passed && !now.passed {
+                        diff.newly_failing.push(key.clone());
+                    } else if !then.passed && now.passed {
+                        diff.newly_passing.push(key.clone());
+                    }
+
+                    let limit = then.duration.mul_f64(ratio);
+                    if now.duration > limit
+                        && now.duration.saturating_sub(then.duration) >= MIN_REGRESSION_DELTA
+                    {
+                        diff.regressions.push(DurationRegression {
+                            job: key.clone(),
+                            baseline: then.duration,
+                            current: now.duration,
+                        });
+                    }
+                }
+            }
+        }
+
+        diff.removed = before
+            .keys()
+            .filter(|k| !after.contains_key(*k))
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    pub fn has_regressions(&self) -> bool {
+        !self.newly_failing.is_empty() || !self.regressions.is_empty()
+    }
+}
+
+impl fmt::Display for RunDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Compared to baseline:")?;
+        if !self.has_regressions()
+            && self.newly_passing.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+        {
+            return write!(f, "  no changes");
+        }
+
+        let sections = [
+            ("newly failing", &self.newly_failing),
+            ("newly passing", &self.newly_passing),
+            ("added", &self.added),
+            ("removed", &self.removed),
+        ];
+        for (label, jobs) in sections {
+            if jobs.is_empty() {
+                continue;
+            }
+            writeln!(f, "  {} ({}):", label, jobs.len())?;
+            for job in jobs {
+                writeln!(f, "    {}", job)?;
+            }
+        }
+
+        if !self.regressions.is_empty() {
+            writeln!(f, "  slower ({}):", self.regressions.len())?;
+            for r in &self.regressions {
+                writeln!(f, "    {} ({:?} -> {:?})", r.job, r.baseline, r.current)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{JobResult, StepRecord, StepResult};
+
+    fn job(name: &str, passed: bool, millis: u64) -> JobResult {
+        let duration = Duration::from_millis(millis);
+        let result = if passed {
+            StepResult::Passed(duration)
+        } else {
+            StepResult::Failed(duration, "boom".to_string())
+        };
+        JobResult {
+            name: name.to_string(),
+            steps: vec![StepRecord {
+                wall_duration: duration,
+                ..StepRecord::new("step", result)
+            }],
+            duration,
+            wall_duration: duration,
+            ..Default::default()
+        }
+    }
+
+    fn workflow(jobs: Vec<JobResult>) -> WorkflowResult {
+        WorkflowResult {
+            name: "wf".to_string(),
+            jobs,
+            duration: Duration::ZERO,
+            ignored: None,
+            failed_assertions: vec![],
+            wall_duration: Duration::ZERO,
+            quarantined: false,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_runs() {
+        let baseline = vec![workflow(vec![
+            job("a", true, 100),
+            job("b", false, 100),
+            job("c", true, 100),
+            job("gone", true, 10),
+        ])];
+        let current = vec![workflow(vec![
+            job("a", false, 100),
+            job("b", true, 100),
+            job("c", true, 400),
+            job("new", true, 10),
+        ])];
+
+        let diff = RunDiff::compare(&baseline, &current, 1.5);
+        assert_eq!(diff.newly_failing, vec!["wf / a"]);
+        assert_eq!(diff.newly_passing, vec!["wf / b"]);
+        assert_eq!(diff.added, vec!["wf / new"]);
+        assert_eq!(diff.removed, vec!["wf / gone"]);
+        assert_eq!(diff.regressions.len(), 1);
+        assert_eq!(diff.regressions[0].job, "wf / c");
+        assert!(diff.has_regressions());
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("runs/main.json");
+        RunRecord::new("abc", vec![workflow(vec![job("a", true, 5)])])
+            .save(&path)
+            .unwrap();
+
+        let loaded = RunRecord::load(&path).unwrap();
+        assert_eq!(loaded.session_id, "abc");
+        assert!(loaded.workflows[0].jobs[0].passed());
+    }
+}
@@ -0,0 +1,98 @@
+//! `env-file:` support: dotenv-style `KEY=VALUE` files loaded into a workflow's or job's `env`.
+
+use crate::{Error, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub(crate) fn load(path: &Path) -> Result<HashMap<String, String>> {
+    let error = |reason: String| Error::EnvFile {
+        path: path.display().to_string(),
+        reason,
+    };
+    let content = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    parse(&content).map_err(error)
+}
+
+/// Blank lines and `#` comments are skipped, and `export ` prefixes are allowed. Single-quoted
+/// values are literal, double-quoted ones understand `\n`, `\t`, `\"` and `\\`, and unquoted
+/// ones end at ` #`.
+fn parse(content: &str) -> std::result::Result<HashMap<String, String>, String> {
+    let mut vars = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let at = |reason: &str| format!("line {}: {}", index + 1, reason);
+
+        let (key, value) = line.split_once('=').ok_or_else(|| at("expected KEY=VALUE"))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(at(&format!("invalid key '{}'", key)));
+        }
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let inner = value[1..]
+                    .strip_suffix(quote)
+                    .ok_or_else(|| at("unterminated quote"))?;
+                match quote {
+                    '"' => unescape(inner),
+                    _ => inner.to_string(),
+                }
+            }
+            _ => match value.find(" #") {
+                Some(comment) => value[..comment].trim_end().to_string(),
+                None => value.to_string(),
+            },
+        };
+        vars.insert(key.to_string(), value);
+    }
+    Ok(vars)
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let vars = parse(
+            r#"
+# Staging endpoints
+API_URL=https://staging.example.com # not part of the value
+export API_TOKEN = 'abc#123'
+GREETING="hello\n\"world\""
+EMPTY=
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(vars["API_URL"], "https://staging.example.com");
+        assert_eq!(vars["API_TOKEN"], "abc#123");
+        assert_eq!(vars["GREETING"], "hello\n\"world\"");
+        assert_eq!(vars["EMPTY"], "");
+        assert_eq!(parse("API_URL").unwrap_err(), "line 1: expected KEY=VALUE");
+        assert_eq!(parse("\nTOKEN='abc").unwrap_err(), "line 2: unterminated quote");
+        assert_eq!(parse("API-URL=x").unwrap_err(), "line 1: invalid key 'API-URL'");
+    }
+}
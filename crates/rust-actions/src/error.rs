@@ -5,12 +5,22 @@ pub enum Error {
     #[error("Step not found: {0}")]
     StepNotFound(String),
 
+    #[error("Ambiguous step '{uses}': {reason}")]
+    AmbiguousStep { uses: String, reason: String },
+
     #[error("Args error: {0}")]
     Args(String),
 
+    #[error("Invalid args for step '{step}': {reason}")]
+    InvalidArgs { step: String, reason: String },
+
     #[error("Expression error: {0}")]
     Expression(String),
 
+    /// A reference to a key the context doesn't have; see `MissingKeyPolicy`
+    #[error("Expression error: {0}")]
+    MissingKey(String),
+
     #[error("Assertion failed: {0}")]
     Assertion(String),
 
@@ -23,6 +33,9 @@ pub enum Error {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("TOML parse error: {0}")]
+    Toml(#[from] toml::de::Error),
+
     #[error("Step error: {0}")]
     Step(#[from] StepError),
 
@@ -32,6 +45,9 @@ pub enum Error {
     #[error("Environment variable not found: {0}")]
     EnvVar(String),
 
+    #[error("Invalid env file {path}: {reason}")]
+    EnvFile { path: String, reason: String },
+
     #[error("Workflow not found: {path}")]
     WorkflowNotFound { path: String },
 
@@ -44,9 +60,39 @@ pub enum Error {
     #[error("Circular dependency detected: {chain}")]
     CircularDependency { chain: String },
 
+    #[error("Workflow calls nested deeper than {limit}: {chain}")]
+    CallDepthExceeded { limit: usize, chain: String },
+
     #[error("Job dependency not found: {job} requires {dependency}")]
     JobDependencyNotFound { job: String, dependency: String },
 
+    #[error("Stage error: {0}")]
+    Stage(String),
+
+    #[error("Step dependency not found: {step} depends on {dependency}")]
+    StepDependencyNotFound { step: String, dependency: String },
+
+    #[error("No checkpoint for session {session_id} at {path}")]
+    CheckpointNotFound { session_id: String, path: String },
+
+    #[error("Unknown fields in {file} (see `RustActions::allow_unknown_fields`):\n{fields}")]
+    UnknownFields { file: String, fields: String },
+
+    #[error("Validation failed:\n{0}")]
+    Validation(String),
+
+    #[error("Step policy violation:\n{0}")]
+    StepPolicy(String),
+
+    #[error("Config error: {0}")]
+    Config(String),
+
+    #[error("Metrics error: {0}")]
+    Metrics(String),
+
+    #[error("Step panicked: {0}")]
+    StepPanicked(String),
+
     #[error("{0}")]
     Custom(String),
 }
@@ -56,6 +102,10 @@ pub enum StepError {
     #[error("Assertion failed: {0}")]
     Assertion(String),
 
+    /// A transient failure (timeout, connection reset) worth retrying under `RetryPolicy`
+    #[error("{0}")]
+    Retryable(String),
+
     #[error("{0}")]
     Custom(String),
 }
@@ -68,6 +118,21 @@ impl StepError {
     pub fn custom(msg: impl Into<String>) -> Self {
         StepError::Custom(msg.into())
     }
+
+    pub fn retryable(msg: impl Into<String>) -> Self {
+        StepError::Retryable(msg.into())
+    }
+}
+
+impl Error {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::Step(StepError::Retryable(_)))
+    }
+
+    /// A `${{ }}` reference to an env var, output, input, etc. that isn't set.
+    pub fn is_missing_key(&self) -> bool {
+        matches!(self, Error::MissingKey(_) | Error::EnvVar(_))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,74 @@
+//! `tracing` events for a run, under the `rust_actions` target: one per finished step, job
+//! and workflow, with the names, result and duration as fields. They're emitted whether or
+//! not the console output is on, so an embedder's subscriber sees the same run.
+
+use crate::mask::SecretMasker;
+use crate::runner::{JobResult, StepResult, WorkflowResult};
+use std::time::Duration;
+
+pub(crate) fn job_finished(workflow: &str, job: &JobResult, secrets: &SecretMasker) {
+    let name = format!("{}{}", job.name, job.matrix_suffix);
+    for step in &job.steps {
+        step_finished(workflow, &name, &step.name, &step.result, step.continue_on_error || job.quarantined, secrets);
+    }
+    for (step, result) in job.compensations.iter().chain(&job.cleanup) {
+        step_finished(workflow, &name, step, result, true, secrets);
+    }
+
+    for warning in &job.warnings {
+        tracing::warn!(target: "rust_actions", workflow, job = %name, warning = %warning, "missing key");
+    }
+
+    let duration_ms = millis(job.duration);
+    if job.passed() {
+        tracing::info!(target: "rust_actions", workflow, job = %name, result = "passed", duration_ms, "job finished");
+    } else if job.quarantined {
+        tracing::warn!(target: "rust_actions", workflow, job = %name, result = "quarantined", duration_ms, "job finished");
+    } else {
+        tracing::error!(target: "rust_actions", workflow, job = %name, result = "failed", duration_ms, "job finished");
+    }
+}
+
+pub(crate) fn workflow_finished(workflow: &WorkflowResult) {
+    let name = workflow.name.as_str();
+    let duration_ms = millis(workflow.duration);
+    let (jobs_passed, jobs_failed) = (workflow.jobs_passed(), workflow.jobs_failed());
+    if workflow.passed() {
+        tracing::info!(target: "rust_actions", workflow = name, result = "passed", jobs_passed, jobs_failed, duration_ms, "workflow finished");
+    } else if !workflow.fails_run() {
+        tracing::warn!(target: "rust_actions", workflow = name, result = "quarantined", jobs_passed, jobs_failed, duration_ms, "workflow finished");
+    } else {
+        tracing::error!(target: "rust_actions", workflow = name, result = "failed", jobs_passed, jobs_failed, duration_ms, "workflow finished");
+    }
+}
+
+/// Failures that don't fail the run (`continue-on-error`, compensation, cleanup, quarantine) are warnings.
+fn step_finished(
+    workflow: &str,
+    job: &str,
+    step: &str,
+    result: &StepResult,
+    tolerated: bool,
+    secrets: &SecretMasker,
+) {
+    match result {
+        StepResult::Passed(d) => {
+            tracing::debug!(target: "rust_actions", workflow, job, step, result = "passed", duration_ms = millis(*d), "step finished");
+        }
+        StepResult::Skipped => {
+            tracing::debug!(target: "rust_actions", workflow, job, step, result = "skipped", "step finished");
+        }
+        StepResult::Failed(d, msg) => {
+            let error = secrets.mask(msg);
+            if tolerated {
+                tracing::warn!(target: "rust_actions", workflow, job, step, result = "failed", duration_ms = millis(*d), error, "step finished");
+            } else {
+                tracing::error!(target: "rust_actions", workflow, job, step, result = "failed", duration_ms = millis(*d), error, "step finished");
+            }
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
@@ -0,0 +1,60 @@
+//! Remote step execution. By default steps run in-process against the job's world; a
+//! `StepExecutor` set with `RustActions::executor` sends them elsewhere instead, e.g. to
+//! workers colocated with the environment under test, which answer with
+//! `RustActions::serve_call`.
+
+use crate::args::RawArgs;
+use crate::context::StepContext;
+use crate::matrix::MatrixCombination;
+use crate::outputs::StepOutputs;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Runs steps somewhere other than the controller's process. The transport (HTTP, JSON-RPC
+/// over a socket, a child process) is up to the implementation.
+pub trait StepExecutor: Send + Sync {
+    /// Whether this executor runs `uses`; steps it doesn't handle run in-process.
+    fn handles(&self, uses: &str) -> bool {
+        let _ = uses;
+        true
+    }
+
+    /// Runs the step and returns its outputs. `StepError::Retryable` errors are retried under
+    /// `default_retry`, as for local steps.
+    fn execute<'a>(
+        &'a self,
+        call: StepCall,
+    ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>;
+}
+
+/// One step invocation: the step, its evaluated `with:` args, and the `StepContext` values a
+/// worker needs to rebuild the context it runs in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepCall {
+    pub uses: String,
+    pub args: RawArgs,
+    pub workflow: String,
+    pub job: String,
+    pub session_id: String,
+    pub attempt: u32,
+    pub env: HashMap<String, String>,
+    pub matrix: MatrixCombination,
+}
+
+impl StepCall {
+    pub(crate) fn new(uses: &str, args: RawArgs, ctx: &StepContext) -> Self {
+        Self {
+            uses: uses.to_string(),
+            args,
+            workflow: ctx.workflow().to_string(),
+            job: ctx.job().to_string(),
+            session_id: ctx.session_id().to_string(),
+            attempt: ctx.attempt(),
+            env: ctx.env_vars().clone(),
+            matrix: ctx.matrix_values().clone(),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use crate::runner::{StepResult, WorkflowResult};
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Why a run failed. `RustActions::run` exits with the code `ExitPolicy` gives it, so CI can
+/// tell a broken suite or environment from a failing test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    /// A step or workflow assertion failed
+    Test,
+    /// Workflows or config failed to load, parse or validate; nothing ran
+    Validation,
+    /// A world or a workflow's `services:` couldn't be started, or the runner hit an IO error
+    Infrastructure,
+    Cancelled,
+}
+
+impl FailureKind {
+    /// Classifies an error that stopped the run before any workflow ran.
+    pub fn of(error: &Error) -> Self {
+        match error {
+            Error::Io(_) | Error::Container(_) | Error::Metrics(_) => FailureKind::Infrastructure,
+            _ => FailureKind::Validation,
+        }
+    }
+}
+
+/// Exit codes of `RustActions::run`, and how many failed jobs it tolerates. Infrastructure
+/// failures are never tolerated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitPolicy {
+    test_failures: i32,
+    validation: i32,
+    infrastructure: i32,
+    cancelled: i32,
+    max_failed_jobs: usize,
+}
+
+impl Default for ExitPolicy {
+    /// 1 for test failures, 2 for validation failures, 3 for infrastructure failures and 130
+    /// when cancelled, failing on the first failed job.
+    fn default() -> Self {
+        Self {
+            test_failures: 1,
+            validation: 2,
+            infrastructure: 3,
+            cancelled: 130,
+            max_failed_jobs: 0,
+        }
+    }
+}
+
+impl ExitPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Passes the run unless more than `n` jobs fail outside quarantine.
+    pub fn max_failed_jobs(mut self, n: usize) -> Self {
+        self.max_failed_jobs = n;
+        self
+    }
+
+    pub fn code(mut self, kind: FailureKind, code: i32) -> Self {
+        match kind {
+            FailureKind::Test => self.test_failures = code,
+            FailureKind::Validation => self.validation = code,
+            FailureKind::Infrastructure => self.infrastructure = code,
+            FailureKind::Cancelled => self.cancelled = code,
+        }
+        self
+    }
+
+    pub fn exit_code(&self, kind: Option<FailureKind>) -> i32 {
+        match kind {
+            None => 0,
+            Some(FailureKind::Test) => self.test_failures,
+            Some(FailureKind::Validation) => self.validation,
+            Some(FailureKind::Infrastructure) => self.infrastructure,
+            Some(FailureKind::Cancelled) => self.cancelled,
+        }
+    }
+
+    pub fn failed_jobs_allowed(&self) -> usize {
+        self.max_failed_jobs
+    }
+}
+
+/// One failed job or workflow assertion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub workflow: String,
+    /// With the matrix suffix; `None` for a failed workflow assertion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+    /// The first failed step; `None` when the job couldn't run at all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+    pub message: String,
+    /// Under `quarantined:` or `allow_failure`; doesn't count towards the exit code
+    #[serde(default)]
+    pub quarantined: bool,
+}
+
+/// How a run ended, written as JSON by `RustActions::failure_manifest` for CI to parse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureManifest {
+    pub session_id: String,
+    pub exit_code: i32,
+    /// `None` when the run passed
+    pub kind: Option<FailureKind>,
+    /// The error that stopped the run before any workflow ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Jobs that failed outside quarantine
+    pub failed_jobs: usize,
+    pub max_failed_jobs: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl FailureManifest {
+    /// For a run that stopped before any workflow ran.
+    pub fn from_error(session_id: &str, error: &Error, policy: &ExitPolicy) -> Self {
+        let kind = FailureKind::of(error);
+        Self {
+            session_id: session_id.to_string(),
+            exit_code: policy.exit_code(Some(kind)),
+            kind: Some(kind),
+            error: Some(error.to_string()),
+            failed_jobs: 0,
+            max_failed_jobs: policy.max_failed_jobs,
+            failures: vec![],
+        }
+    }
+
+    /// Cancellation wins over infrastructure failures, which win over test failures. Test
+    /// failures only count once more than `max_failed_jobs` jobs failed.
+    pub fn from_results(
+        session_id: &str,
+        results: &[WorkflowResult],
+        policy: &ExitPolicy,
+        cancelled: bool,
+    ) -> Self {
+        let mut failures = Vec::new();
+        let mut failed_jobs = 0;
+        let mut failed_assertions = false;
+
+        for workflow in results.iter().filter(|w| !w.is_ignored()) {
+            for assertion in &workflow.failed_assertions {
+                failed_assertions |= !workflow.quarantined;
+                failures.push(Failure {
+                    kind: FailureKind::Test,
+                    workflow: workflow.name.clone(),
+                    job: None,
+                    step: None,
+                    message: assertion.clone(),
+                    quarantined: workflow.quarantined,
+                });
+            }
+
+            for job in workflow.jobs.iter().filter(|j| !j.passed()) {
+                if !job.quarantined {
+                    failed_jobs += 1;
+                }
+                let failed_step = job.steps.iter().find_map(|step| match &step.result {
+                    StepResult::Failed(_, msg) if !step.continue_on_error => Some((&step.name, msg)),
+                    _ => None,
+                });
+                let (kind, step, message) = match (&job.infra_error, failed_step) {
+                    (Some(error), _) => (FailureKind::Infrastructure, None, error.clone()),
+                    (None, Some((name, msg))) => (FailureKind::Test, Some(name.clone()), msg.clone()),
+                    (None, None) => continue,
+                };
+                failures.push(Failure {
+                    kind,
+                    workflow: workflow.name.clone(),
+                    job: Some(format!("{}{}", job.name, job.matrix_suffix)),
+                    step,
+                    message,
+                    quarantined: job.quarantined,
+                });
+            }
+        }
+
+        let counted = |kind| failures.iter().any(|f: &Failure| f.kind == kind && !f.quarantined);
+        let kind = if cancelled {
+            Some(FailureKind::Cancelled)
+        } else if counted(FailureKind::Infrastructure) {
+            Some(FailureKind::Infrastructure)
+        } else if failed_assertions || failed_jobs > policy.max_failed_jobs {
+            Some(FailureKind::Test)
+        } else {
+            None
+        };
+
+        Self {
+            session_id: session_id.to_string(),
+            exit_code: policy.exit_code(kind),
+            kind,
+            error: None,
+            failed_jobs,
+            max_failed_jobs: policy.max_failed_jobs,
+            failures,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.kind.is_none()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{JobResult, StepRecord};
+    use std::time::Duration;
+
+    fn job(name: &str, steps: Vec<StepResult>, infra_error: Option<&str>) -> JobResult {
+        JobResult {
+            name: name.to_string(),
+            steps: steps.into_iter().map(|r| StepRecord::new("user/create", r)).collect(),
+            infra_error: infra_error.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn workflow(jobs: Vec<JobResult>) -> WorkflowResult {
+        WorkflowResult {
+            name: "Users".to_string(),
+            jobs,
+            duration: Duration::ZERO,
+            ignored: None,
+            failed_assertions: vec![],
+            wall_duration: Duration::ZERO,
+            quarantined: false,
+            description: None,
+        }
+    }
+
+    fn failed(msg: &str) -> StepResult {
+        StepResult::Failed(Duration::ZERO, msg.to_string())
+    }
+
+    #[test]
+    fn test_failed_jobs_within_the_threshold_pass() {
+        let results = vec![workflow(vec![
+            job("signup", vec![failed("expected 201, got 500")], None),
+            job("login", vec![StepResult::Passed(Duration::ZERO)], None),
+        ])];
+
+        let strict = FailureManifest::from_results("s", &results, &ExitPolicy::new(), false);
+        assert_eq!(strict.kind, Some(FailureKind::Test));
+        assert_eq!(strict.exit_code, 1);
+        assert_eq!(strict.failures[0].step.as_deref(), Some("user/create"));
+
+        let lenient = FailureManifest::from_results("s", &results, &ExitPolicy::new().max_failed_jobs(1), false);
+        assert!(lenient.passed());
+        assert_eq!(lenient.exit_code, 0);
+        assert_eq!(lenient.failed_jobs, 1);
+        assert_eq!(lenient.failures.len(), 1);
+    }
+
+    #[test]
+    fn test_infrastructure_failures_are_never_tolerated() {
+        let results = vec![workflow(vec![
+            job("signup", vec![failed("expected 201, got 500")], None),
+            job("login", vec![], Some("Container error: postgres didn't start")),
+        ])];
+        let policy = ExitPolicy::new().max_failed_jobs(5).code(FailureKind::Infrastructure, 70);
+
+        let manifest = FailureManifest::from_results("s", &results, &policy, false);
+
+        assert_eq!(manifest.kind, Some(FailureKind::Infrastructure));
+        assert_eq!(manifest.exit_code, 70);
+        assert_eq!(manifest.failures[1].step, None);
+        assert_eq!(manifest.failures[1].job.as_deref(), Some("login"));
+
+        let cancelled = FailureManifest::from_results("s", &results, &policy, true);
+        assert_eq!(cancelled.exit_code, 130);
+    }
+
+    #[test]
+    fn test_load_errors_are_classified() {
+        let policy = ExitPolicy::new();
+        let invalid = FailureManifest::from_error("s", &Error::Validation("unknown step".into()), &policy);
+        assert_eq!((invalid.kind, invalid.exit_code), (Some(FailureKind::Validation), 2));
+
+        let io = Error::Io(std::io::Error::other("disk full"));
+        assert_eq!(FailureManifest::from_error("s", &io, &policy).exit_code, 3);
+    }
+}
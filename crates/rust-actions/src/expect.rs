@@ -0,0 +1,91 @@
+use serde_json::Value;
+
+/// Wildcard in an `expect:` block: matches any value that is present and not null.
+pub const ANY: &str = "*";
+
+/// Checks a step's outputs against its `expect:` block, returning one message per mismatch.
+///
+/// Objects match when every expected key matches (extra keys are fine, and keys fall back to
+/// a case-insensitive lookup so HTTP headers match regardless of casing). Arrays must have
+/// the same length and match element-wise. Numbers compare by value; everything else must be
+/// equal.
+pub fn check_expect(expected: &Value, outputs: &Value) -> Vec<String> {
+    let mut failures = Vec::new();
+    check_at("outputs", expected, Some(outputs), &mut failures);
+    failures
+}
+
+fn check_at(path: &str, expected: &Value, actual: Option<&Value>, failures: &mut Vec<String>) {
+    let actual = match actual {
+        Some(Value::Null) | None if expected.as_str() == Some(ANY) => {
+            failures.push(format!("expected {} to be set, but it is missing", path));
+            return;
+        }
+        Some(actual) => actual,
+        None => {
+            failures.push(format!("expected {} to be {}, but it is missing", path, expected));
+            return;
+        }
+    };
+
+    match (expected, actual) {
+        (Value::String(s), _) if s == ANY => {}
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let field = actual.get(key).or_else(|| {
+                    actual
+                        .iter()
+                        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                        .map(|(_, v)| v)
+                });
+                check_at(&format!("{}.{}", path, key), value, field, failures);
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            for (i, (e, a)) in expected.iter().zip(actual).enumerate() {
+                check_at(&format!("{}[{}]", path, i), e, Some(a), failures);
+            }
+        }
+        (Value::Number(e), Value::Number(a)) if e.as_f64() == a.as_f64() => {}
+        (expected, actual) if expected == actual => {}
+        (expected, actual) => {
+            failures.push(format!("expected {} to be {}, got {}", path, expected, actual));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_expect_matches() {
+        let outputs = json!({
+            "status": 200,
+            "body": { "id": "u-1", "name": "alice", "tags": ["a", "b"] },
+            "headers": { "Content-Type": "application/json" }
+        });
+        let expected = json!({
+            "status": 200.0,
+            "body": { "id": "*", "tags": ["a", "*"] },
+            "headers": { "content-type": "application/json" }
+        });
+        assert!(check_expect(&expected, &outputs).is_empty());
+    }
+
+    #[test]
+    fn test_expect_reports_each_mismatch() {
+        let outputs = json!({ "status": 404, "body": { "id": null } });
+        let expected = json!({ "status": 200, "body": { "id": "*", "name": "alice" } });
+
+        assert_eq!(
+            check_expect(&expected, &outputs),
+            vec![
+                "expected outputs.body.id to be set, but it is missing",
+                "expected outputs.body.name to be \"alice\", but it is missing",
+                "expected outputs.status to be 200, got 404",
+            ]
+        );
+    }
+}
@@ -1,24 +1,116 @@
+use crate::generators::RandomContext;
+use crate::mask::SecretMasker;
 use crate::outputs::StepOutputs;
+use crate::parser::Workflow;
 use crate::{Error, Result};
+use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-
+use std::sync::{Arc, Mutex, RwLock};
+
+static EXPR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$\{\{\s*(.+?)\s*\}\}").unwrap());
+
+/// Checked in order, so `>=` is found before `>`
+const OPERATORS: [&str; 11] = [
+    " icontains ",
+    " contains ",
+    " matches-regex ",
+    " in ",
+    "=~",
+    "==",
+    "!=",
+    ">=",
+    "<=",
+    ">",
+    "<",
+];
+
+/// Patterns of `=~` assertions, compiled once per process
+static REGEX_CACHE: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Everything `${{ }}` expressions can see. Each part is behind an `Arc`, so the derived
+/// contexts for assertions (`with_outputs` etc.) share them instead of copying; mutate a part
+/// with `Arc::make_mut`, which only copies it while a derived context is still alive.
+#[derive(Clone)]
 pub struct ExprContext {
-    pub env: HashMap<String, String>,
-    pub steps: HashMap<String, StepOutputs>,
-    pub background: HashMap<String, StepOutputs>,
-    pub containers: HashMap<String, ContainerInfo>,
+    pub env: Arc<HashMap<String, String>>,
+    pub steps: Arc<HashMap<String, StepOutputs>>,
+    pub background: Arc<HashMap<String, StepOutputs>>,
+    pub containers: Arc<HashMap<String, ContainerInfo>>,
     pub outputs: Option<StepOutputs>,
-    pub needs: HashMap<String, JobOutputs>,
-    pub matrix: HashMap<String, Value>,
-    pub jobs: HashMap<String, JobOutputs>,
-    pub inputs: HashMap<String, Value>,
+    pub needs: Arc<HashMap<String, JobOutputs>>,
+    pub matrix: Arc<HashMap<String, Value>>,
+    pub jobs: Arc<HashMap<String, JobOutputs>>,
+    /// Job outputs of workflows that already ran this session, keyed by file stem
+    pub workflows: Arc<HashMap<String, HashMap<String, JobOutputs>>>,
+    pub inputs: Arc<HashMap<String, Value>>,
+    /// Values the test harness passed to `RustActions::global`
+    pub globals: Arc<HashMap<String, Value>>,
+    /// The current step's evaluated `with:` args
+    pub with: Arc<HashMap<String, Value>>,
+    /// Values earlier steps of the job stored with `capture:`
+    pub vars: Arc<HashMap<String, Value>>,
+    /// Outputs of the workflow's `setup:` steps, as `setup.outputs.*`
+    pub setup: Arc<HashMap<String, Value>>,
+    /// What the job changed, from `snapshot::diff_snapshots`; null without a snapshot
+    pub diff: Arc<Value>,
+    pub runner: Option<RunnerInfo>,
+    pub run: Option<RunInfo>,
+    pub random: Option<Arc<RandomContext>>,
+    /// Compiled expressions shared by the run; without it, each evaluation parses its input
+    pub cache: Option<Arc<ExprCache>>,
+    /// Set under `MissingKeyPolicy::Lenient`: missing keys resolve to an empty string (null
+    /// for a whole-value `${{ }}` or an assertion operand) and are recorded here
+    pub missing_keys: Option<Arc<MissingKeyLog>>,
+}
+
+/// What a `${{ }}` reference to a missing env var, output, input, etc. does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingKeyPolicy {
+    /// Fails the step, workflow output or assertion
+    #[default]
+    Strict,
+    /// Resolves to empty/null, with a warning on the job
+    Lenient,
+}
+
+/// Missing keys resolved under `MissingKeyPolicy::Lenient`, once each, in the order seen.
+#[derive(Debug, Default)]
+pub struct MissingKeyLog {
+    warnings: Mutex<Vec<String>>,
+}
+
+impl MissingKeyLog {
+    fn record(&self, expr: &str, error: &Error) {
+        let warning = format!("${{{{ {} }}}} is missing: {}", expr, missing_reason(error));
+        if let Ok(mut warnings) = self.warnings.lock() {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    pub fn take(&self) -> Vec<String> {
+        self.warnings.lock().map(|mut w| std::mem::take(&mut *w)).unwrap_or_default()
+    }
+}
+
+fn missing_reason(error: &Error) -> String {
+    match error {
+        Error::MissingKey(reason) => reason.clone(),
+        other => other.to_string(),
+    }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct JobOutputs {
     pub outputs: HashMap<String, Value>,
+    /// Outputs of the steps listed in the job's `export-steps`, by step id
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub steps: HashMap<String, Value>,
 }
 
 impl JobOutputs {
@@ -43,6 +135,27 @@ impl JobOutputs {
         self.outputs.insert(key.into(), value);
     }
 
+    pub fn export_step(&mut self, id: impl Into<String>, outputs: Value) {
+        self.steps.insert(id.into(), outputs);
+    }
+
+    /// Adds another matrix combination's outputs; same-named outputs take the newer value.
+    pub fn extend(&mut self, other: JobOutputs) {
+        self.outputs.extend(other.outputs);
+        self.steps.extend(other.steps);
+    }
+
+    /// With every secret masked, for outputs written to disk.
+    pub fn masked(self, secrets: &SecretMasker) -> Self {
+        let mask = |values: HashMap<String, Value>| {
+            values.into_iter().map(|(key, value)| (key, secrets.mask_value(&value))).collect()
+        };
+        Self {
+            outputs: mask(self.outputs),
+            steps: mask(self.steps),
+        }
+    }
+
     pub fn to_value(&self) -> Value {
         Value::Object(
             self.outputs
@@ -60,60 +173,104 @@ pub struct ContainerInfo {
     pub port: u16,
 }
 
+/// Per-job runner metadata exposed as `${{ runner.* }}`.
+#[derive(Debug, Clone)]
+pub struct RunnerInfo {
+    pub temp: String,
+    pub session_id: String,
+    pub os: String,
+    pub started_at: u64,
+}
+
+impl RunnerInfo {
+    pub fn get(&self, prop: &str) -> Option<Value> {
+        match prop {
+            "temp" => Some(Value::String(self.temp.clone())),
+            "session_id" => Some(Value::String(self.session_id.clone())),
+            "os" => Some(Value::String(self.os.clone())),
+            "started_at" => Some(Value::Number(self.started_at.into())),
+            _ => None,
+        }
+    }
+}
+
+/// Where the current step runs, exposed as `${{ run.* }}` so steps can tag what they create.
+#[derive(Debug, Clone)]
+pub struct RunInfo {
+    pub workflow: String,
+    pub job: String,
+    pub session: String,
+    /// 1 for a fresh session, one more for each resume of it
+    pub attempt: u32,
+}
+
+impl RunInfo {
+    pub fn get(&self, prop: &str) -> Option<Value> {
+        match prop {
+            "workflow" => Some(Value::String(self.workflow.clone())),
+            "job" => Some(Value::String(self.job.clone())),
+            "session" => Some(Value::String(self.session.clone())),
+            "attempt" => Some(Value::Number(self.attempt.into())),
+            _ => None,
+        }
+    }
+}
+
 impl ExprContext {
     pub fn new() -> Self {
         Self {
-            env: HashMap::new(),
-            steps: HashMap::new(),
-            background: HashMap::new(),
-            containers: HashMap::new(),
+            env: Arc::default(),
+            steps: Arc::default(),
+            background: Arc::default(),
+            containers: Arc::default(),
             outputs: None,
-            needs: HashMap::new(),
-            matrix: HashMap::new(),
-            jobs: HashMap::new(),
-            inputs: HashMap::new(),
+            needs: Arc::default(),
+            matrix: Arc::default(),
+            jobs: Arc::default(),
+            workflows: Arc::default(),
+            inputs: Arc::default(),
+            globals: Arc::default(),
+            with: Arc::default(),
+            vars: Arc::default(),
+            setup: Arc::default(),
+            diff: Arc::new(Value::Null),
+            runner: None,
+            run: None,
+            random: None,
+            cache: None,
+            missing_keys: None,
+        }
+    }
+
+    /// `result` of evaluating `expr`, with a missing key resolved to `fallback` when lenient.
+    fn resolve_missing<T>(&self, expr: &str, result: Result<T>, fallback: impl FnOnce() -> T) -> Result<T> {
+        match (result, &self.missing_keys) {
+            (Err(e), Some(log)) if e.is_missing_key() => {
+                log.record(expr, &e);
+                Ok(fallback())
+            }
+            (result, _) => result,
         }
     }
 
     pub fn with_outputs(&self, outputs: StepOutputs) -> Self {
         Self {
-            env: self.env.clone(),
-            steps: self.steps.clone(),
-            background: self.background.clone(),
-            containers: self.containers.clone(),
             outputs: Some(outputs),
-            needs: self.needs.clone(),
-            matrix: self.matrix.clone(),
-            jobs: self.jobs.clone(),
-            inputs: self.inputs.clone(),
+            ..self.clone()
         }
     }
 
     pub fn with_matrix(&self, matrix: HashMap<String, Value>) -> Self {
         Self {
-            env: self.env.clone(),
-            steps: self.steps.clone(),
-            background: self.background.clone(),
-            containers: self.containers.clone(),
-            outputs: self.outputs.clone(),
-            needs: self.needs.clone(),
-            matrix,
-            jobs: self.jobs.clone(),
-            inputs: self.inputs.clone(),
+            matrix: Arc::new(matrix),
+            ..self.clone()
         }
     }
 
     pub fn with_inputs(&self, inputs: HashMap<String, Value>) -> Self {
         Self {
-            env: self.env.clone(),
-            steps: self.steps.clone(),
-            background: self.background.clone(),
-            containers: self.containers.clone(),
-            outputs: self.outputs.clone(),
-            needs: self.needs.clone(),
-            matrix: self.matrix.clone(),
-            jobs: self.jobs.clone(),
-            inputs,
+            inputs: Arc::new(inputs),
+            ..self.clone()
         }
     }
 }
@@ -124,18 +281,289 @@ impl Default for ExprContext {
     }
 }
 
-pub fn evaluate(input: &str, ctx: &ExprContext) -> Result<String> {
-    let re = Regex::new(r"\$\{\{\s*(.+?)\s*\}\}").unwrap();
+/// A string with `${{ }}` placeholders, split once into text and expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledExpr {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Expr(String),
+}
+
+impl CompiledExpr {
+    pub fn parse(input: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut last = 0;
+        for cap in EXPR_RE.captures_iter(input) {
+            let full_match = cap.get(0).unwrap();
+            if full_match.start() > last {
+                segments.push(Segment::Text(input[last..full_match.start()].to_string()));
+            }
+            segments.push(Segment::Expr(cap[1].to_string()));
+            last = full_match.end();
+        }
+        if last < input.len() {
+            segments.push(Segment::Text(input[last..].to_string()));
+        }
+        Self { segments }
+    }
+
+    /// Like `evaluate`, but a string that is a single `${{ }}` keeps its value's JSON type.
+    pub fn evaluate_value(&self, ctx: &ExprContext) -> Result<Value> {
+        match self.segments.as_slice() {
+            [Segment::Expr(expr)] => {
+                ctx.resolve_missing(expr, evaluate_expr_value(expr, ctx), || Value::Null)
+            }
+            _ => self.evaluate(ctx).map(Value::String),
+        }
+    }
+
+    pub fn evaluate(&self, ctx: &ExprContext) -> Result<String> {
+        let mut result = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => result.push_str(text),
+                Segment::Expr(expr) => {
+                    let value = ctx.resolve_missing(expr, evaluate_expr(expr, ctx), String::new)?;
+                    result.push_str(&value);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
 
-    let mut result = input.to_string();
-    for cap in re.captures_iter(input) {
-        let full_match = &cap[0];
+/// A `${{ left op right }}` assertion with its operator found and literal operands parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledAssertion {
+    left: Operand,
+    op: &'static str,
+    right: Operand,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Literal(Value),
+    Path(String),
+}
+
+impl CompiledAssertion {
+    pub fn parse(assertion: &str) -> Result<Self> {
+        let Some(cap) = EXPR_RE.captures(assertion) else {
+            return Err(Error::Expression(format!(
+                "Invalid assertion format: {}",
+                assertion
+            )));
+        };
         let expr = &cap[1];
-        let value = evaluate_expr(expr, ctx)?;
-        result = result.replace(full_match, &value);
+
+        for op in OPERATORS {
+            if let Some(pos) = find_operator(expr, op) {
+                let compiled = Self {
+                    left: Operand::parse(&expr[..pos])?,
+                    op: match op.trim() {
+                        "matches-regex" => "=~",
+                        op => op,
+                    },
+                    right: Operand::parse(&expr[pos + op.len()..])?,
+                };
+                // A literal pattern is checked, and compiled for later, up front
+                if let ("=~", Operand::Literal(pattern)) = (compiled.op, &compiled.right) {
+                    cached_regex(&value_to_string(pattern))?;
+                }
+                return Ok(compiled);
+            }
+        }
+
+        Err(Error::Expression(format!(
+            "No comparison operator found in expression: {}",
+            expr
+        )))
     }
 
-    Ok(result)
+    pub fn evaluate(&self, ctx: &ExprContext) -> Result<bool> {
+        let left = self.left.evaluate(ctx)?;
+        let right = self.right.evaluate(ctx)?;
+        if self.op == "=~" {
+            let regex = cached_regex(&value_to_string(&right))?;
+            return Ok(!left.is_null() && regex.is_match(&value_to_string(&left)));
+        }
+        Ok(compare_values(&left, &right, self.op))
+    }
+}
+
+impl Operand {
+    fn parse(operand: &str) -> Result<Self> {
+        let operand = operand.trim();
+
+        // Evaluated whole, so the head of a pipe can itself be a literal
+        if split_pipes(operand).is_some() {
+            return Ok(Operand::Path(operand.to_string()));
+        }
+        let literal = if operand.starts_with('{') || operand.starts_with('[') {
+            serde_json::from_str(operand)
+                .map_err(|e| Error::Expression(format!("Invalid JSON: {}", e)))?
+        } else if operand.starts_with('"') || operand.starts_with('\'') {
+            Value::String(operand[1..operand.len() - 1].to_string())
+        } else if operand == "true" {
+            Value::Bool(true)
+        } else if operand == "false" {
+            Value::Bool(false)
+        } else if operand == "null" {
+            Value::Null
+        } else if let Ok(num) = operand.parse::<i64>() {
+            Value::Number(num.into())
+        } else if let Ok(num) = operand.parse::<f64>() {
+            serde_json::Number::from_f64(num)
+                .map(Value::Number)
+                .unwrap_or(Value::Null)
+        } else {
+            return Ok(Operand::Path(operand.to_string()));
+        };
+        Ok(Operand::Literal(literal))
+    }
+
+    fn evaluate(&self, ctx: &ExprContext) -> Result<Value> {
+        match self {
+            Operand::Literal(value) => Ok(value.clone()),
+            Operand::Path(expr) => {
+                ctx.resolve_missing(expr, evaluate_expr_value(expr, ctx), || Value::Null)
+            }
+        }
+    }
+}
+
+/// Compiled templates and assertions keyed by their source text, so steps that run many times
+/// (e.g. across a matrix) are parsed once.
+#[derive(Debug, Default)]
+pub struct ExprCache {
+    templates: RwLock<HashMap<String, Arc<CompiledExpr>>>,
+    assertions: RwLock<HashMap<String, Arc<CompiledAssertion>>>,
+}
+
+impl ExprCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn template(&self, input: &str) -> Arc<CompiledExpr> {
+        if let Some(compiled) = self.templates.read().ok().and_then(|t| t.get(input).cloned()) {
+            return compiled;
+        }
+        let compiled = Arc::new(CompiledExpr::parse(input));
+        if let Ok(mut templates) = self.templates.write() {
+            templates.insert(input.to_string(), compiled.clone());
+        }
+        compiled
+    }
+
+    /// Assertions that fail to parse aren't cached; they error again when evaluated.
+    pub fn assertion(&self, assertion: &str) -> Result<Arc<CompiledAssertion>> {
+        if let Some(compiled) = self.assertions.read().ok().and_then(|a| a.get(assertion).cloned()) {
+            return Ok(compiled);
+        }
+        let compiled = Arc::new(CompiledAssertion::parse(assertion)?);
+        if let Ok(mut assertions) = self.assertions.write() {
+            assertions.insert(assertion.to_string(), compiled.clone());
+        }
+        Ok(compiled)
+    }
+
+    /// Drops every compiled expression, e.g. once a streamed workflow has run.
+    pub fn clear(&self) {
+        if let Ok(mut templates) = self.templates.write() {
+            templates.clear();
+        }
+        if let Ok(mut assertions) = self.assertions.write() {
+            assertions.clear();
+        }
+    }
+
+    /// Compiles every expression in `workflow` ahead of its run.
+    pub fn compile_workflow(&self, workflow: &Workflow) {
+        let mut assertions: Vec<&String> = workflow.post_assert.iter().collect();
+        let mut templates: Vec<&Value> = Vec::new();
+
+        for job in workflow.jobs.values() {
+            assertions.extend(&job.post_assert);
+            templates.extend(job.with.values());
+        }
+        for (_, steps) in workflow.step_lists() {
+            for step in steps {
+                assertions.extend(step.pre_assert.iter().chain(&step.post_assert));
+                templates.extend(step.with.values());
+                templates.extend(&step.expect);
+                for cleanup in &step.cleanup {
+                    templates.extend(cleanup.with.values());
+                }
+                for expr in step.capture.values() {
+                    self.compile_template(&capture_template(expr));
+                }
+            }
+        }
+
+        for assertion in assertions {
+            let _ = self.assertion(assertion);
+        }
+        for value in templates {
+            self.compile_value(value);
+        }
+        let outputs = workflow.jobs.values().flat_map(|job| job.outputs.values());
+        let call_outputs = workflow
+            .on
+            .iter()
+            .filter_map(|on| on.workflow_call.as_ref())
+            .flat_map(|call| call.outputs.values().map(|o| &o.value));
+        for output in outputs.chain(call_outputs) {
+            self.compile_template(output);
+        }
+    }
+
+    fn compile_value(&self, value: &Value) {
+        match value {
+            Value::String(s) => self.compile_template(s),
+            Value::Object(map) => map.values().for_each(|v| self.compile_value(v)),
+            Value::Array(arr) => arr.iter().for_each(|v| self.compile_value(v)),
+            _ => {}
+        }
+    }
+
+    fn compile_template(&self, input: &str) {
+        if input.contains("${{") {
+            self.template(input);
+        }
+    }
+}
+
+pub fn evaluate(input: &str, ctx: &ExprContext) -> Result<String> {
+    if !input.contains("${{") {
+        return Ok(input.to_string());
+    }
+    match &ctx.cache {
+        Some(cache) => cache.template(input).evaluate(ctx),
+        None => CompiledExpr::parse(input).evaluate(ctx),
+    }
+}
+
+/// A `capture:` expression as a template: either bare (`outputs.body.id`) or a full
+/// `${{ }}` string.
+pub fn capture_template(expr: &str) -> String {
+    if expr.contains("${{") {
+        expr.to_string()
+    } else {
+        format!("${{{{ {} }}}}", expr.trim())
+    }
+}
+
+/// Like `evaluate`, but a string that is a single `${{ }}` keeps its value's JSON type.
+pub fn evaluate_typed(input: &str, ctx: &ExprContext) -> Result<Value> {
+    match &ctx.cache {
+        Some(cache) => cache.template(input).evaluate_value(ctx),
+        None => CompiledExpr::parse(input).evaluate_value(ctx),
+    }
 }
 
 pub fn evaluate_value(value: &Value, ctx: &ExprContext) -> Result<Value> {
@@ -160,40 +588,12 @@ pub fn evaluate_value(value: &Value, ctx: &ExprContext) -> Result<Value> {
 }
 
 pub fn evaluate_assertion(assertion: &str, ctx: &ExprContext) -> Result<bool> {
-    let re = Regex::new(r"\$\{\{\s*(.+?)\s*\}\}").unwrap();
-
-    if let Some(cap) = re.captures(assertion) {
-        let expr = &cap[1];
-        evaluate_bool_expr(expr, ctx)
-    } else {
-        Err(Error::Expression(format!(
-            "Invalid assertion format: {}",
-            assertion
-        )))
+    match &ctx.cache {
+        Some(cache) => cache.assertion(assertion)?.evaluate(ctx),
+        None => CompiledAssertion::parse(assertion)?.evaluate(ctx),
     }
 }
 
-fn evaluate_bool_expr(expr: &str, ctx: &ExprContext) -> Result<bool> {
-    let ops = [" contains ", "==", "!=", ">=", "<=", ">", "<"];
-
-    for op in ops {
-        if let Some(pos) = find_operator(expr, op) {
-            let left = expr[..pos].trim();
-            let right = expr[pos + op.len()..].trim();
-
-            let left_val = evaluate_operand(left, ctx)?;
-            let right_val = evaluate_operand(right, ctx)?;
-
-            return Ok(compare_values(&left_val, &right_val, op.trim()));
-        }
-    }
-
-    Err(Error::Expression(format!(
-        "No comparison operator found in expression: {}",
-        expr
-    )))
-}
-
 fn find_operator(expr: &str, op: &str) -> Option<usize> {
     let mut depth = 0;
     let mut in_string = false;
@@ -231,34 +631,17 @@ fn find_operator(expr: &str, op: &str) -> Option<usize> {
     None
 }
 
-fn evaluate_operand(operand: &str, ctx: &ExprContext) -> Result<Value> {
-    let operand = operand.trim();
-
-    if operand.starts_with('{') || operand.starts_with('[') {
-        serde_json::from_str(operand)
-            .map_err(|e| Error::Expression(format!("Invalid JSON: {}", e)))
-    } else if operand.starts_with('"') {
-        Ok(Value::String(operand[1..operand.len() - 1].to_string()))
-    } else if operand.starts_with('\'') {
-        Ok(Value::String(operand[1..operand.len() - 1].to_string()))
-    } else if operand == "true" {
-        Ok(Value::Bool(true))
-    } else if operand == "false" {
-        Ok(Value::Bool(false))
-    } else if operand == "null" {
-        Ok(Value::Null)
-    } else if let Ok(num) = operand.parse::<i64>() {
-        Ok(Value::Number(num.into()))
-    } else if let Ok(num) = operand.parse::<f64>() {
-        Ok(serde_json::Number::from_f64(num)
-            .map(Value::Number)
-            .unwrap_or(Value::Null))
-    } else {
-        evaluate_expr_value(operand, ctx)
+fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
+    if let Some((head, filters)) = split_pipes(expr) {
+        return piped(head, &filters, ctx);
+    }
+    if let Some(spec) = expr.strip_prefix("random.") {
+        return random_value(spec, ctx);
+    }
+    if let Some(inner) = expr.strip_prefix("fromJSON(").and_then(|e| e.strip_suffix(')')) {
+        return from_json(inner, ctx);
     }
-}
 
-fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
     let parts: Vec<&str> = expr.split('.').collect();
 
     match parts.as_slice() {
@@ -272,7 +655,7 @@ fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
             .outputs
             .as_ref()
             .and_then(|o| o.get(field).cloned())
-            .ok_or_else(|| Error::Expression(format!("Output not found: {}", field))),
+            .ok_or_else(|| Error::MissingKey(format!("Output not found: {}", field))),
 
         ["outputs", rest @ ..] => {
             let field = rest[0];
@@ -281,7 +664,7 @@ fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
                 .outputs
                 .as_ref()
                 .and_then(|o| o.get(field).cloned())
-                .ok_or_else(|| Error::Expression(format!("Output not found: {}", field)))?;
+                .ok_or_else(|| Error::MissingKey(format!("Output not found: {}", field)))?;
             navigate_value(&base, &remaining)
         }
 
@@ -295,21 +678,21 @@ fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
             .steps
             .get(*step_id)
             .map(|o| o.to_value())
-            .ok_or_else(|| Error::Expression(format!("Step not found: {}", step_id))),
+            .ok_or_else(|| Error::MissingKey(format!("Step not found: {}", step_id))),
 
         ["steps", step_id, "outputs", field] => ctx
             .steps
             .get(*step_id)
             .and_then(|o| o.get(field).cloned())
             .ok_or_else(|| {
-                Error::Expression(format!("Step output not found: {}.{}", step_id, field))
+                Error::MissingKey(format!("Step output not found: {}.{}", step_id, field))
             }),
 
         ["containers", name, prop] => {
             let container = ctx
                 .containers
                 .get(*name)
-                .ok_or_else(|| Error::Expression(format!("Container not found: {}", name)))?;
+                .ok_or_else(|| Error::MissingKey(format!("Container not found: {}", name)))?;
             match *prop {
                 "url" => Ok(Value::String(container.url.clone())),
                 "host" => Ok(Value::String(container.host.clone())),
@@ -321,19 +704,25 @@ fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
             }
         }
 
+        // runner.temp, runner.session_id, runner.os, runner.started_at
+        ["runner", prop] => runner_property(ctx, prop),
+
+        // run.workflow, run.job, run.session, run.attempt
+        ["run", prop] => run_property(ctx, prop),
+
         // needs.job_name.outputs.field
         ["needs", job_name, "outputs"] => ctx
             .needs
             .get(*job_name)
             .map(|o| o.to_value())
-            .ok_or_else(|| Error::Expression(format!("Job not found in needs: {}", job_name))),
+            .ok_or_else(|| Error::MissingKey(format!("Job not found in needs: {}", job_name))),
 
         ["needs", job_name, "outputs", field] => ctx
             .needs
             .get(*job_name)
             .and_then(|o| o.get(field).cloned())
             .ok_or_else(|| {
-                Error::Expression(format!("Job output not found: {}.{}", job_name, field))
+                Error::MissingKey(format!("Job output not found: {}.{}", job_name, field))
             }),
 
         ["needs", job_name, "outputs", field, rest @ ..] => {
@@ -342,9 +731,32 @@ fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
                 .get(*job_name)
                 .and_then(|o| o.get(field).cloned())
                 .ok_or_else(|| {
-                    Error::Expression(format!("Job output not found: {}.{}", job_name, field))
+                    Error::MissingKey(format!("Job output not found: {}.{}", job_name, field))
                 })?;
-            navigate_value(&base, &rest.to_vec())
+            navigate_value(&base, rest)
+        }
+
+        // needs.job_name.steps.step_id.outputs[.field...] (from the job's `export-steps`)
+        ["needs", job_name, "steps", step_id, "outputs", rest @ ..] => {
+            navigate_value(&exported_step(ctx, job_name, step_id)?, rest)
+        }
+
+        // globals.key[.nested...]
+        ["globals", key, rest @ ..] => {
+            let global = ctx
+                .globals
+                .get(*key)
+                .ok_or_else(|| Error::MissingKey(format!("Global not found: {}", key)))?;
+            navigate_value(global, rest)
+        }
+
+        // setup.outputs.key[.nested...] (from the workflow's `setup:` steps)
+        ["setup", "outputs", key, rest @ ..] => {
+            let output = ctx
+                .setup
+                .get(*key)
+                .ok_or_else(|| Error::MissingKey(format!("Setup output not found: {}", key)))?;
+            navigate_value(output, rest)
         }
 
         // matrix.key
@@ -352,34 +764,101 @@ fn evaluate_expr_value(expr: &str, ctx: &ExprContext) -> Result<Value> {
             .matrix
             .get(*key)
             .cloned()
-            .ok_or_else(|| Error::Expression(format!("Matrix key not found: {}", key))),
+            .ok_or_else(|| Error::MissingKey(format!("Matrix key not found: {}", key))),
+
+        // inputs.field[.nested...] (for reusable workflow inputs)
+        ["inputs", field, rest @ ..] => {
+            let input = ctx
+                .inputs
+                .get(*field)
+                .ok_or_else(|| Error::MissingKey(format!("Input not found: {}", field)))?;
+            navigate_value(input, rest)
+        }
 
-        // inputs.field (for reusable workflow inputs)
-        ["inputs", field] => ctx
-            .inputs
-            .get(*field)
-            .cloned()
-            .ok_or_else(|| Error::Expression(format!("Input not found: {}", field))),
+        // diff.created.users, diff.count.created.users, diff.changed, ...
+        ["diff", rest @ ..] if ctx.diff.is_null() => Err(Error::Expression(format!(
+            "diff.{} is unavailable: the world has no #[world(snapshot)]",
+            rest.join(".")
+        ))),
+        ["diff", rest @ ..] => navigate_value(&ctx.diff, rest),
+
+        // vars.name[.nested...] (from earlier steps' `capture:`)
+        ["vars", name, rest @ ..] => {
+            let var = ctx
+                .vars
+                .get(*name)
+                .ok_or_else(|| Error::MissingKey(format!("Captured var not found: {}", name)))?;
+            navigate_value(var, rest)
+        }
+
+        // with.field (the current step's evaluated args)
+        ["with", field, rest @ ..] => {
+            let base = ctx
+                .with
+                .get(*field)
+                .ok_or_else(|| Error::MissingKey(format!("Step arg not found: {}", field)))?;
+            navigate_value(base, rest)
+        }
 
         // jobs.job_name.outputs.field (for workflow-level references)
         ["jobs", job_name, "outputs"] => ctx
             .jobs
             .get(*job_name)
             .map(|o| o.to_value())
-            .ok_or_else(|| Error::Expression(format!("Job not found: {}", job_name))),
+            .ok_or_else(|| Error::MissingKey(format!("Job not found: {}", job_name))),
 
         ["jobs", job_name, "outputs", field] => ctx
             .jobs
             .get(*job_name)
             .and_then(|o| o.get(field).cloned())
             .ok_or_else(|| {
-                Error::Expression(format!("Job output not found: {}.{}", job_name, field))
+                Error::MissingKey(format!("Job output not found: {}.{}", job_name, field))
             }),
 
+        // workflows.name.jobs.job_name.outputs.field
+        ["workflows", workflow, "jobs", job_name, "outputs", field, rest @ ..] => {
+            let base = workflow_job_output(ctx, workflow, job_name, field)?;
+            navigate_value(&base, rest)
+        }
+
         _ => Err(Error::Expression(format!("Unknown expression: {}", expr))),
     }
 }
 
+fn workflow_job_output(
+    ctx: &ExprContext,
+    workflow: &str,
+    job_name: &str,
+    field: &str,
+) -> Result<Value> {
+    ctx.workflows
+        .get(workflow)
+        .ok_or_else(|| Error::Expression(format!("Workflow has not run: {}", workflow)))?
+        .get(job_name)
+        .and_then(|o| o.get(field).cloned())
+        .ok_or_else(|| {
+            Error::MissingKey(format!(
+                "Job output not found: {}.{}.{}",
+                workflow, job_name, field
+            ))
+        })
+}
+
+fn exported_step(ctx: &ExprContext, job_name: &str, step_id: &str) -> Result<Value> {
+    ctx.needs
+        .get(job_name)
+        .ok_or_else(|| Error::MissingKey(format!("Job not found in needs: {}", job_name)))?
+        .steps
+        .get(step_id)
+        .cloned()
+        .ok_or_else(|| {
+            Error::Expression(format!(
+                "Step {}.{} was not exported (list it in the job's `export-steps`)",
+                job_name, step_id
+            ))
+        })
+}
+
 fn navigate_value(value: &Value, path: &[&str]) -> Result<Value> {
     if path.is_empty() {
         return Ok(value.clone());
@@ -390,7 +869,7 @@ fn navigate_value(value: &Value, path: &[&str]) -> Result<Value> {
             let field = path[0];
             let next = map
                 .get(field)
-                .ok_or_else(|| Error::Expression(format!("Field not found: {}", field)))?;
+                .ok_or_else(|| Error::MissingKey(format!("Field not found: {}", field)))?;
             navigate_value(next, &path[1..])
         }
         Value::Array(arr) => {
@@ -402,9 +881,7 @@ fn navigate_value(value: &Value, path: &[&str]) -> Result<Value> {
                 .ok_or_else(|| Error::Expression(format!("Array index out of bounds: {}", index)))?;
             navigate_value(next, &path[1..])
         }
-        _ => Err(Error::Expression(format!(
-            "Cannot navigate into non-object/array value"
-        ))),
+        _ => Err(Error::Expression("Cannot navigate into non-object/array value".to_string())),
     }
 }
 
@@ -413,6 +890,8 @@ fn compare_values(left: &Value, right: &Value, op: &str) -> bool {
         "==" => left == right,
         "!=" => left != right,
         "contains" => value_contains(left, right),
+        "icontains" => value_contains(&lowercase(left), &lowercase(right)),
+        "in" => value_in(left, right),
         ">" => compare_numeric(left, right, |a, b| a > b),
         "<" => compare_numeric(left, right, |a, b| a < b),
         ">=" => compare_numeric(left, right, |a, b| a >= b),
@@ -442,7 +921,7 @@ fn value_to_f64(value: &Value) -> Option<f64> {
 fn value_contains(haystack: &Value, needle: &Value) -> bool {
     match (haystack, needle) {
         (Value::Object(h), Value::Object(n)) => n.iter().all(|(k, v)| {
-            h.get(k).map_or(false, |hv| {
+            h.get(k).is_some_and(|hv| {
                 if v.is_object() || v.is_array() {
                     value_contains(hv, v)
                 } else {
@@ -475,10 +954,58 @@ fn value_contains(haystack: &Value, needle: &Value) -> bool {
     }
 }
 
+/// `value` with every string in it lowercased, for `icontains`.
+fn lowercase(value: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.to_lowercase()),
+        Value::Array(items) => Value::Array(items.iter().map(lowercase).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), lowercase(v))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// `needle in haystack`: an element of an array, a substring of a string or a key of an object.
+fn value_in(needle: &Value, haystack: &Value) -> bool {
+    match (needle, haystack) {
+        (needle, Value::Array(items)) => items.contains(needle),
+        (Value::String(n), Value::String(h)) => h.contains(n.as_str()),
+        (Value::String(n), Value::Object(map)) => map.contains_key(n),
+        _ => false,
+    }
+}
+
+fn cached_regex(pattern: &str) -> Result<Regex> {
+    let mut cache = REGEX_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(regex) = cache.get(pattern) {
+        return Ok(regex.clone());
+    }
+    let regex = Regex::new(pattern)
+        .map_err(|e| Error::Expression(format!("Invalid regex '{}': {}", pattern, e)))?;
+    cache.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
 fn evaluate_expr(expr: &str, ctx: &ExprContext) -> Result<String> {
+    if let Some((head, filters)) = split_pipes(expr) {
+        return piped(head, &filters, ctx).map(|v| value_to_string(&v));
+    }
+    if let Some(spec) = expr.strip_prefix("random.") {
+        return random_value(spec, ctx).map(|v| value_to_string(&v));
+    }
+    if let Some(inner) = expr.strip_prefix("fromJSON(").and_then(|e| e.strip_suffix(')')) {
+        return from_json(inner, ctx).map(|v| value_to_string(&v));
+    }
+
     let parts: Vec<&str> = expr.split('.').collect();
 
     match parts.as_slice() {
+        // outputs.field (the current step's outputs, e.g. in `cleanup:` args)
+        ["outputs", field] => ctx
+            .outputs
+            .as_ref()
+            .and_then(|o| o.get_string(field))
+            .ok_or_else(|| Error::MissingKey(format!("Output not found: {}", field))),
+
         ["env", var_name] => ctx
             .env
             .get(*var_name)
@@ -490,7 +1017,7 @@ fn evaluate_expr(expr: &str, ctx: &ExprContext) -> Result<String> {
             .get(*step_id)
             .and_then(|outputs| outputs.get_string(field))
             .ok_or_else(|| {
-                Error::Expression(format!("Step output not found: {}.{}", step_id, field))
+                Error::MissingKey(format!("Step output not found: {}.{}", step_id, field))
             }),
 
         ["background", step_id, "outputs", field] => ctx
@@ -498,7 +1025,7 @@ fn evaluate_expr(expr: &str, ctx: &ExprContext) -> Result<String> {
             .get(*step_id)
             .and_then(|outputs| outputs.get_string(field))
             .ok_or_else(|| {
-                Error::Expression(format!(
+                Error::MissingKey(format!(
                     "Background output not found: {}.{}",
                     step_id, field
                 ))
@@ -508,19 +1035,23 @@ fn evaluate_expr(expr: &str, ctx: &ExprContext) -> Result<String> {
             .containers
             .get(*name)
             .map(|c| c.url.clone())
-            .ok_or_else(|| Error::Expression(format!("Container not found: {}", name))),
+            .ok_or_else(|| Error::MissingKey(format!("Container not found: {}", name))),
 
         ["containers", name, "host"] => ctx
             .containers
             .get(*name)
             .map(|c| c.host.clone())
-            .ok_or_else(|| Error::Expression(format!("Container not found: {}", name))),
+            .ok_or_else(|| Error::MissingKey(format!("Container not found: {}", name))),
 
         ["containers", name, "port"] => ctx
             .containers
             .get(*name)
             .map(|c| c.port.to_string())
-            .ok_or_else(|| Error::Expression(format!("Container not found: {}", name))),
+            .ok_or_else(|| Error::MissingKey(format!("Container not found: {}", name))),
+
+        ["runner", prop] => runner_property(ctx, prop).map(|v| value_to_string(&v)),
+
+        ["run", prop] => run_property(ctx, prop).map(|v| value_to_string(&v)),
 
         // needs.job_name.outputs.field
         ["needs", job_name, "outputs", field] => ctx
@@ -528,22 +1059,63 @@ fn evaluate_expr(expr: &str, ctx: &ExprContext) -> Result<String> {
             .get(*job_name)
             .and_then(|outputs| outputs.get_string(field))
             .ok_or_else(|| {
-                Error::Expression(format!("Job output not found: {}.{}", job_name, field))
+                Error::MissingKey(format!("Job output not found: {}.{}", job_name, field))
             }),
 
+        ["needs", job_name, "steps", step_id, "outputs", rest @ ..] => {
+            let base = exported_step(ctx, job_name, step_id)?;
+            navigate_value(&base, rest).map(|v| value_to_string(&v))
+        }
+
+        // globals.key[.nested...]
+        ["globals", key, rest @ ..] => {
+            let global = ctx
+                .globals
+                .get(*key)
+                .ok_or_else(|| Error::MissingKey(format!("Global not found: {}", key)))?;
+            navigate_value(global, rest).map(|v| value_to_string(&v))
+        }
+
+        // setup.outputs.key[.nested...]
+        ["setup", "outputs", key, rest @ ..] => {
+            let output = ctx
+                .setup
+                .get(*key)
+                .ok_or_else(|| Error::MissingKey(format!("Setup output not found: {}", key)))?;
+            navigate_value(output, rest).map(|v| value_to_string(&v))
+        }
+
         // matrix.key
         ["matrix", key] => ctx
             .matrix
             .get(*key)
-            .map(|v| value_to_string(v))
-            .ok_or_else(|| Error::Expression(format!("Matrix key not found: {}", key))),
+            .map(value_to_string)
+            .ok_or_else(|| Error::MissingKey(format!("Matrix key not found: {}", key))),
+
+        // inputs.field[.nested...] (for reusable workflow inputs)
+        ["inputs", field, rest @ ..] => {
+            let input = ctx
+                .inputs
+                .get(*field)
+                .ok_or_else(|| Error::MissingKey(format!("Input not found: {}", field)))?;
+            navigate_value(input, rest).map(|v| value_to_string(&v))
+        }
+
+        // vars.name[.nested...]
+        ["vars", name, rest @ ..] => {
+            let var = ctx
+                .vars
+                .get(*name)
+                .ok_or_else(|| Error::MissingKey(format!("Captured var not found: {}", name)))?;
+            navigate_value(var, rest).map(|v| value_to_string(&v))
+        }
 
-        // inputs.field (for reusable workflow inputs)
-        ["inputs", field] => ctx
-            .inputs
+        // with.field
+        ["with", field] => ctx
+            .with
             .get(*field)
-            .map(|v| value_to_string(v))
-            .ok_or_else(|| Error::Expression(format!("Input not found: {}", field))),
+            .map(value_to_string)
+            .ok_or_else(|| Error::MissingKey(format!("Step arg not found: {}", field))),
 
         // jobs.job_name.outputs.field
         ["jobs", job_name, "outputs", field] => ctx
@@ -551,13 +1123,206 @@ fn evaluate_expr(expr: &str, ctx: &ExprContext) -> Result<String> {
             .get(*job_name)
             .and_then(|outputs| outputs.get_string(field))
             .ok_or_else(|| {
-                Error::Expression(format!("Job output not found: {}.{}", job_name, field))
+                Error::MissingKey(format!("Job output not found: {}.{}", job_name, field))
             }),
 
+        // workflows.name.jobs.job_name.outputs.field
+        ["workflows", workflow, "jobs", job_name, "outputs", field] => {
+            workflow_job_output(ctx, workflow, job_name, field).map(|v| value_to_string(&v))
+        }
+
         _ => Err(Error::Expression(format!("Unknown expression: {}", expr))),
     }
 }
 
+// fromJSON(env.REGIONS): parses a string operand as JSON; other values pass through
+fn from_json(operand: &str, ctx: &ExprContext) -> Result<Value> {
+    match Operand::parse(operand)?.evaluate(ctx)? {
+        Value::String(s) => serde_json::from_str(&s)
+            .map_err(|e| Error::Expression(format!("fromJSON({}): {}", operand.trim(), e))),
+        other => Ok(other),
+    }
+}
+
+/// `head | filter | filter(arg)` split at its top-level pipes; `None` without any.
+fn split_pipes(expr: &str) -> Option<(&str, Vec<&str>)> {
+    let pos = find_operator(expr, "|")?;
+    let mut filters = Vec::new();
+    let mut rest = &expr[pos + 1..];
+    while let Some(next) = find_operator(rest, "|") {
+        filters.push(rest[..next].trim());
+        rest = &rest[next + 1..];
+    }
+    filters.push(rest.trim());
+    Some((expr[..pos].trim(), filters))
+}
+
+/// Call arguments split at their top-level commas, so quoted strings and JSON literals keep theirs.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut rest = args;
+    while let Some(next) = find_operator(rest, ",") {
+        split.push(rest[..next].trim());
+        rest = &rest[next + 1..];
+    }
+    split.push(rest.trim());
+    split
+}
+
+fn piped(head: &str, filters: &[&str], ctx: &ExprContext) -> Result<Value> {
+    let value = Operand::parse(head)?.evaluate(ctx)?;
+    filters
+        .iter()
+        .try_fold(value, |value, filter| apply_filter(value, filter, ctx))
+}
+
+const FILTERS: [&str; 14] = [
+    "upper", "lower", "trim", "length", "keys", "values", "sort", "reverse", "unique", "first",
+    "last", "join", "split", "json",
+];
+
+// upper, lower, trim, length, keys, values, sort, reverse, unique, first, last, join(sep),
+// split(sep), json
+fn apply_filter(value: Value, filter: &str, ctx: &ExprContext) -> Result<Value> {
+    let (name, arg) = match filter.find('(') {
+        Some(open) if filter.ends_with(')') => {
+            let inner = filter[open + 1..filter.len() - 1].trim();
+            let arg = if inner.is_empty() {
+                None
+            } else {
+                Some(value_to_string(&Operand::parse(inner)?.evaluate(ctx)?))
+            };
+            (filter[..open].trim(), arg)
+        }
+        _ => (filter, None),
+    };
+    let result = match (name, value) {
+        ("upper", Value::String(s)) => Value::String(s.to_uppercase()),
+        ("lower", Value::String(s)) => Value::String(s.to_lowercase()),
+        ("trim", Value::String(s)) => Value::String(s.trim().to_string()),
+        ("length", Value::String(s)) => Value::from(s.chars().count()),
+        ("length", Value::Array(items)) => Value::from(items.len()),
+        ("length", Value::Object(map)) => Value::from(map.len()),
+        ("keys", Value::Object(map)) => Value::Array(map.keys().cloned().map(Value::String).collect()),
+        ("values", Value::Object(map)) => Value::Array(map.into_iter().map(|(_, v)| v).collect()),
+        ("sort", Value::Array(mut items)) => {
+            items.sort_by(compare_sortable);
+            Value::Array(items)
+        }
+        ("reverse", Value::Array(mut items)) => {
+            items.reverse();
+            Value::Array(items)
+        }
+        ("reverse", Value::String(s)) => Value::String(s.chars().rev().collect()),
+        ("unique", Value::Array(items)) => {
+            let mut unique: Vec<Value> = Vec::new();
+            for item in items {
+                if !unique.contains(&item) {
+                    unique.push(item);
+                }
+            }
+            Value::Array(unique)
+        }
+        ("first", Value::Array(items)) => items.into_iter().next().unwrap_or(Value::Null),
+        ("last", Value::Array(items)) => items.into_iter().next_back().unwrap_or(Value::Null),
+        ("join", Value::Array(items)) => Value::String(
+            items
+                .iter()
+                .map(value_to_string)
+                .collect::<Vec<_>>()
+                .join(arg.as_deref().unwrap_or(",")),
+        ),
+        ("split", Value::String(s)) => Value::Array(
+            s.split(arg.as_deref().unwrap_or(","))
+                .map(|part| Value::String(part.to_string()))
+                .collect(),
+        ),
+        ("json", value) => Value::String(value.to_string()),
+        (name, value) if FILTERS.contains(&name) => {
+            return Err(Error::Expression(format!(
+                "| {} can't be applied to {}",
+                name,
+                type_name(&value)
+            )))
+        }
+        (name, _) => {
+            return Err(Error::Expression(format!(
+                "Unknown filter '{}' (available: {})",
+                name,
+                FILTERS.join(", ")
+            )))
+        }
+    };
+    Ok(result)
+}
+
+/// Numbers by value, anything else by its string form.
+fn compare_sortable(a: &Value, b: &Value) -> std::cmp::Ordering {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(std::cmp::Ordering::Equal),
+        _ => value_to_string(a).cmp(&value_to_string(b)),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+// random.name or random.name(arg, ...)
+fn random_value(spec: &str, ctx: &ExprContext) -> Result<Value> {
+    let random = ctx
+        .random
+        .as_ref()
+        .ok_or_else(|| Error::Expression("No random context available".to_string()))?;
+
+    let (name, args) = match spec.find('(') {
+        Some(open) if spec.ends_with(')') => {
+            let inner = spec[open + 1..spec.len() - 1].trim();
+            let args = if inner.is_empty() {
+                Vec::new()
+            } else {
+                split_args(inner)
+                    .into_iter()
+                    .map(|arg| Operand::parse(arg)?.evaluate(ctx))
+                    .collect::<Result<Vec<_>>>()?
+            };
+            (spec[..open].trim(), args)
+        }
+        _ => (spec.trim(), Vec::new()),
+    };
+
+    random.generate(name, &args)
+}
+
+fn runner_property(ctx: &ExprContext, prop: &str) -> Result<Value> {
+    let runner = ctx
+        .runner
+        .as_ref()
+        .ok_or_else(|| Error::Expression("No runner context available".to_string()))?;
+    runner
+        .get(prop)
+        .ok_or_else(|| Error::Expression(format!("Unknown runner property: {}", prop)))
+}
+
+fn run_property(ctx: &ExprContext, prop: &str) -> Result<Value> {
+    let run = ctx
+        .run
+        .as_ref()
+        .ok_or_else(|| Error::Expression("No run context available".to_string()))?;
+    run.get(prop)
+        .ok_or_else(|| Error::Expression(format!("Unknown run property: {}", prop)))
+}
+
 fn value_to_string(value: &Value) -> String {
     match value {
         Value::String(s) => s.clone(),
@@ -575,7 +1340,7 @@ mod tests {
     #[test]
     fn test_evaluate_env() {
         let mut ctx = ExprContext::new();
-        ctx.env.insert("DB_URL".to_string(), "postgres://localhost".to_string());
+        Arc::make_mut(&mut ctx.env).insert("DB_URL".to_string(), "postgres://localhost".to_string());
 
         let result = evaluate("${{ env.DB_URL }}", &ctx).unwrap();
         assert_eq!(result, "postgres://localhost");
@@ -586,16 +1351,112 @@ mod tests {
         let mut ctx = ExprContext::new();
         let mut outputs = StepOutputs::new();
         outputs.insert("id", "user-123");
-        ctx.steps.insert("user".to_string(), outputs);
+        Arc::make_mut(&mut ctx.steps).insert("user".to_string(), outputs);
 
         let result = evaluate("User ID: ${{ steps.user.outputs.id }}", &ctx).unwrap();
         assert_eq!(result, "User ID: user-123");
     }
 
+    #[test]
+    fn test_derived_context_shares_parts() {
+        let mut ctx = ExprContext::new();
+        let mut outputs = StepOutputs::new();
+        outputs.insert("id", "user-123");
+        Arc::make_mut(&mut ctx.steps).insert("user".to_string(), outputs.clone());
+
+        let assert_ctx = ctx.with_outputs(outputs);
+        assert!(Arc::ptr_eq(&ctx.steps, &assert_ctx.steps));
+        assert!(evaluate_assertion("${{ outputs.id == steps.user.outputs.id }}", &assert_ctx).unwrap());
+
+        // Writing while a derived context is alive leaves that context unchanged
+        Arc::make_mut(&mut ctx.steps).remove("user");
+        assert!(evaluate("${{ steps.user.outputs.id }}", &assert_ctx).is_ok());
+        assert!(evaluate("${{ steps.user.outputs.id }}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_compiled_expressions() {
+        let mut ctx = ExprContext::new();
+        Arc::make_mut(&mut ctx.env).insert("NAME".to_string(), "alice".to_string());
+        ctx.cache = Some(Arc::new(ExprCache::new()));
+        let cache = ctx.cache.clone().unwrap();
+
+        let template = "Hi ${{ env.NAME }}, bye ${{env.NAME}}!";
+        assert_eq!(evaluate(template, &ctx).unwrap(), "Hi alice, bye alice!");
+        assert!(Arc::ptr_eq(&cache.template(template), &cache.template(template)));
+
+        let assertion = "${{ env.NAME == 'alice' }}";
+        assert!(evaluate_assertion(assertion, &ctx).unwrap());
+        assert!(Arc::ptr_eq(
+            &cache.assertion(assertion).unwrap(),
+            &cache.assertion(assertion).unwrap()
+        ));
+
+        assert!(CompiledAssertion::parse("${{ env.NAME }}").is_err());
+        assert!(CompiledAssertion::parse("env.NAME == 'alice'").is_err());
+        assert!(CompiledAssertion::parse("${{ env.NAME == [1, }}").is_err());
+    }
+
+    #[test]
+    fn test_matching_operators() {
+        let mut ctx = ExprContext::new();
+        let mut outputs = StepOutputs::new();
+        outputs.insert("message", "User ALICE was Created");
+        outputs.insert("status", "active");
+        outputs.insert("code", 404);
+        outputs.insert("tags", serde_json::json!(["Admin", "beta"]));
+        Arc::make_mut(&mut ctx.steps).insert("user".to_string(), outputs);
+        let holds = |assertion: &str| evaluate_assertion(assertion, &ctx).unwrap();
+
+        assert!(holds("${{ steps.user.outputs.message icontains 'alice was created' }}"));
+        assert!(!holds("${{ steps.user.outputs.message contains 'alice was created' }}"));
+        assert!(holds("${{ steps.user.outputs.tags icontains 'admin' }}"));
+
+        assert!(holds(r"${{ steps.user.outputs.message =~ '^User \w+ was' }}"));
+        assert!(holds("${{ steps.user.outputs.code matches-regex '^4\\d\\d$' }}"));
+        assert!(!holds("${{ steps.user.outputs.status =~ 'inactive' }}"));
+
+        assert!(holds(r#"${{ steps.user.outputs.status in ["active", "pending"] }}"#));
+        assert!(holds("${{ 'beta' in steps.user.outputs.tags }}"));
+        assert!(holds("${{ 'ALICE' in steps.user.outputs.message }}"));
+        assert!(!holds("${{ steps.user.outputs.code in [200, 201] }}"));
+
+        assert!(CompiledAssertion::parse("${{ steps.user.outputs.status =~ '(' }}").is_err());
+    }
+
+    #[test]
+    fn test_pipe_filters() {
+        let mut outputs = StepOutputs::new();
+        outputs.insert("name", "  Alice ");
+        outputs.insert("items", serde_json::json!([3, 1, 2, 1]));
+        outputs.insert("roles", serde_json::json!({"viewer": true, "admin": true}));
+        let ctx = ExprContext::new().with_outputs(outputs);
+        let value = |expr: &str| CompiledExpr::parse(expr).evaluate_value(&ctx).unwrap();
+
+        assert_eq!(value("${{ outputs.name | trim | upper }}"), "ALICE");
+        assert_eq!(value("${{ outputs.items | length }}"), 4);
+        assert_eq!(value("${{ outputs.items | unique | sort }}"), serde_json::json!([1, 2, 3]));
+        assert_eq!(value("${{ outputs.roles | keys | sort | join(', ') }}"), "admin, viewer");
+        assert_eq!(value("${{ 'a|b' | split('|') | last }}"), "b");
+        assert_eq!(value("${{ [2, 10, 1] | sort | first }}"), 1);
+        assert_eq!(
+            evaluate("Roles: ${{ outputs.roles | keys | sort | json }}", &ctx).unwrap(),
+            r#"Roles: ["admin","viewer"]"#
+        );
+
+        assert!(evaluate_assertion("${{ outputs.items | length == 4 }}", &ctx).unwrap());
+        assert!(evaluate_assertion("${{ outputs.name | lower contains 'alice' }}", &ctx).unwrap());
+
+        let error = evaluate("${{ outputs.items | upper }}", &ctx).unwrap_err().to_string();
+        assert!(error.contains("| upper can't be applied to an array"), "{}", error);
+        let error = evaluate("${{ outputs.name | capitalize }}", &ctx).unwrap_err().to_string();
+        assert!(error.contains("Unknown filter 'capitalize'"), "{}", error);
+    }
+
     #[test]
     fn test_evaluate_container() {
         let mut ctx = ExprContext::new();
-        ctx.containers.insert(
+        Arc::make_mut(&mut ctx.containers).insert(
             "postgres".to_string(),
             ContainerInfo {
                 url: "postgres://localhost:5432".to_string(),
@@ -607,4 +1468,151 @@ mod tests {
         let result = evaluate("${{ containers.postgres.url }}", &ctx).unwrap();
         assert_eq!(result, "postgres://localhost:5432");
     }
+
+    #[test]
+    fn test_evaluate_runner() {
+        let mut ctx = ExprContext::new();
+        ctx.runner = Some(RunnerInfo {
+            temp: "/tmp/rust-actions-abc".to_string(),
+            session_id: "abc".to_string(),
+            os: "linux".to_string(),
+            started_at: 1_700_000_000,
+        });
+
+        let result = evaluate("${{ runner.temp }}/out.json", &ctx).unwrap();
+        assert_eq!(result, "/tmp/rust-actions-abc/out.json");
+        assert!(evaluate_assertion("${{ runner.started_at > 0 }}", &ctx).unwrap());
+        assert!(evaluate("${{ runner.arch }}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_run() {
+        let mut ctx = ExprContext::new();
+        assert!(evaluate("${{ run.job }}", &ctx).is_err());
+
+        ctx.run = Some(RunInfo {
+            workflow: "Orders".to_string(),
+            job: "checkout".to_string(),
+            session: "abc".to_string(),
+            attempt: 2,
+        });
+        let result = evaluate("${{ run.workflow }}/${{ run.job }}-${{ run.session }}", &ctx).unwrap();
+        assert_eq!(result, "Orders/checkout-abc");
+        assert!(evaluate_assertion("${{ run.attempt == 2 }}", &ctx).unwrap());
+        assert!(evaluate("${{ run.id }}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_vars() {
+        let mut ctx = ExprContext::new();
+        Arc::make_mut(&mut ctx.vars).insert("order".to_string(), serde_json::json!({ "id": 42, "sku": "A-1" }));
+
+        assert_eq!(evaluate("${{ vars.order.sku }}/${{ vars.order.id }}", &ctx).unwrap(), "A-1/42");
+        assert_eq!(evaluate_typed("${{ vars.order.id }}", &ctx).unwrap(), serde_json::json!(42));
+        assert!(evaluate_assertion("${{ vars.order.id == 42 }}", &ctx).unwrap());
+        assert!(evaluate("${{ vars.missing }}", &ctx).is_err());
+        assert_eq!(capture_template("outputs.body.id"), "${{ outputs.body.id }}");
+    }
+
+    #[test]
+    fn test_evaluate_workflow_outputs() {
+        let mut provision = JobOutputs::new();
+        provision.insert("tenant_id", Value::String("t-1".to_string()));
+        let mut ctx = ExprContext::new();
+        Arc::make_mut(&mut ctx.workflows)
+            .insert("setup".to_string(), HashMap::from([("provision".to_string(), provision)]));
+
+        let result = evaluate("${{ workflows.setup.jobs.provision.outputs.tenant_id }}", &ctx);
+        assert_eq!(result.unwrap(), "t-1");
+        assert!(evaluate_assertion(
+            "${{ workflows.setup.jobs.provision.outputs.tenant_id == \"t-1\" }}",
+            &ctx
+        )
+        .unwrap());
+        assert!(evaluate("${{ workflows.other.jobs.provision.outputs.tenant_id }}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_with_args() {
+        let mut ctx = ExprContext::new();
+        let with = Arc::make_mut(&mut ctx.with);
+        with.insert("amount".to_string(), Value::from(25));
+        with.insert(
+            "customer".to_string(),
+            serde_json::from_str(r#"{"tier": "gold"}"#).unwrap(),
+        );
+
+        assert!(evaluate_assertion("${{ with.amount > 0 }}", &ctx).unwrap());
+        assert!(evaluate_assertion("${{ with.customer.tier == \"gold\" }}", &ctx).unwrap());
+        assert_eq!(evaluate("${{ with.amount }}", &ctx).unwrap(), "25");
+        assert!(evaluate("${{ with.missing }}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_random() {
+        use crate::determinism::SeededRng;
+        use crate::generators::GeneratorRegistry;
+
+        let make_ctx = || {
+            let mut ctx = ExprContext::new();
+            ctx.random = Some(Arc::new(RandomContext::new(
+                SeededRng::with_seed(42),
+                Arc::new(GeneratorRegistry::with_builtins()),
+            )));
+            ctx
+        };
+
+        let ctx = make_ctx();
+        let first = evaluate("${{ random.uuid }}", &ctx).unwrap();
+        let second = evaluate("${{ random.uuid }}", &ctx).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first, evaluate("${{ random.uuid }}", &make_ctx()).unwrap());
+
+        assert_eq!(evaluate("${{ random.string(12) }}", &ctx).unwrap().len(), 12);
+        assert!(evaluate_assertion("${{ random.int(1, 100) <= 100 }}", &ctx).unwrap());
+    }
+
+    #[test]
+    fn test_random_args_keep_quoted_commas() {
+        use crate::determinism::SeededRng;
+        use crate::generators::GeneratorRegistry;
+
+        let mut generators = GeneratorRegistry::with_builtins();
+        generators.register("echo", |_, args| Ok(Value::Array(args.to_vec())));
+        let mut ctx = ExprContext::new();
+        ctx.random = Some(Arc::new(RandomContext::new(
+            SeededRng::with_seed(42),
+            Arc::new(generators),
+        )));
+
+        assert_eq!(
+            evaluate_typed("${{ random.echo('a,b', [1, 2], 3) }}", &ctx).unwrap(),
+            serde_json::json!(["a,b", [1, 2], 3])
+        );
+    }
+
+    #[test]
+    fn test_lenient_missing_keys() {
+        let mut ctx = ExprContext::new();
+        Arc::make_mut(&mut ctx.env).insert("REGION".to_string(), "eu".to_string());
+        assert!(evaluate("${{ env.API_KEY }}", &ctx).is_err());
+
+        let log = Arc::new(MissingKeyLog::default());
+        ctx.missing_keys = Some(log.clone());
+        assert_eq!(evaluate("key=${{ env.API_KEY }}&region=${{ env.REGION }}", &ctx).unwrap(), "key=&region=eu");
+        let whole = evaluate_value(&Value::String("${{ inputs.tier }}".to_string()), &ctx).unwrap();
+        assert_eq!(whole, Value::String(String::new()));
+        assert!(evaluate_assertion("${{ steps.create.outputs.id == null }}", &ctx).unwrap());
+        assert_eq!(evaluate("${{ env.API_KEY }}", &ctx).unwrap(), "");
+        assert!(evaluate("${{ runner.temp }}", &ctx).is_err());
+
+        assert_eq!(
+            log.take(),
+            vec![
+                "${{ env.API_KEY }} is missing: Environment variable not found: API_KEY",
+                "${{ inputs.tier }} is missing: Input not found: tier",
+                "${{ steps.create.outputs.id }} is missing: Step output not found: create.id",
+            ]
+        );
+    }
 }
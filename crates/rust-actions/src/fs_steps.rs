@@ -0,0 +1,231 @@
+//! Built-in `fs/*` steps for services that produce files or exports. Paths are relative to the
+//! job's temp dir (`${{ runner.temp }}`), which is removed when the job ends.
+
+use crate::args::{self, ArgField, FromArgs, RawArgs};
+use crate::context::StepContext;
+use crate::error::StepError;
+use crate::outputs::StepOutputs;
+use crate::registry::StepRegistry;
+use crate::{Error, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::any::Any;
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+
+type StepFuture<'a> = Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>;
+
+pub(crate) fn register(registry: &mut StepRegistry) {
+    registry.register_with_args("fs/write", write, WriteArgs::FIELDS);
+    registry.register_with_args("fs/read", read, PathArgs::FIELDS);
+    registry.register_with_args("fs/assert-exists", assert_exists, ExistsArgs::FIELDS);
+    registry.register_with_args("fs/assert-contains", assert_contains, ContainsArgs::FIELDS);
+}
+
+#[derive(Deserialize)]
+struct PathArgs {
+    path: String,
+}
+
+impl FromArgs for PathArgs {
+    const FIELDS: &'static [ArgField] = &[PATH];
+
+    fn from_args(args: &RawArgs) -> Result<Self> {
+        args::deserialize(args)
+    }
+}
+
+/// `content` is written as is when it's a string, else as pretty-printed JSON.
+#[derive(Deserialize)]
+struct WriteArgs {
+    path: String,
+    content: Value,
+}
+
+impl FromArgs for WriteArgs {
+    const FIELDS: &'static [ArgField] = &[
+        PATH,
+        ArgField { name: "content", ty: "Value", optional: false },
+    ];
+
+    fn from_args(args: &RawArgs) -> Result<Self> {
+        args::deserialize(args)
+    }
+}
+
+#[derive(Deserialize)]
+struct ExistsArgs {
+    path: String,
+    /// `false` asserts that the file is absent
+    #[serde(default = "default_true")]
+    exists: bool,
+}
+
+impl FromArgs for ExistsArgs {
+    const FIELDS: &'static [ArgField] = &[
+        PATH,
+        ArgField { name: "exists", ty: "bool", optional: true },
+    ];
+
+    fn from_args(args: &RawArgs) -> Result<Self> {
+        args::deserialize(args)
+    }
+}
+
+#[derive(Deserialize)]
+struct ContainsArgs {
+    path: String,
+    text: String,
+}
+
+impl FromArgs for ContainsArgs {
+    const FIELDS: &'static [ArgField] = &[
+        PATH,
+        ArgField { name: "text", ty: "String", optional: false },
+    ];
+
+    fn from_args(args: &RawArgs) -> Result<Self> {
+        args::deserialize(args)
+    }
+}
+
+const PATH: ArgField = ArgField { name: "path", ty: "String", optional: false };
+
+fn default_true() -> bool {
+    true
+}
+
+fn write<'a>(_world: &'a mut dyn Any, args: RawArgs, ctx: &'a StepContext) -> StepFuture<'a> {
+    Box::pin(async move {
+        let args: WriteArgs = parse("fs/write", &args)?;
+        let path = resolve(ctx, &args.path)?;
+        let content = match args.content {
+            Value::String(s) => s,
+            other => serde_json::to_string_pretty(&other)?,
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &content).await?;
+
+        let mut outputs = StepOutputs::new();
+        outputs.insert("path", path.to_string_lossy().into_owned());
+        outputs.insert("size", content.len());
+        Ok(outputs)
+    })
+}
+
+fn read<'a>(_world: &'a mut dyn Any, args: RawArgs, ctx: &'a StepContext) -> StepFuture<'a> {
+    Box::pin(async move {
+        let args: PathArgs = parse("fs/read", &args)?;
+        let path = resolve(ctx, &args.path)?;
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| StepError::custom(format!("Failed to read {}: {}", args.path, e)))?;
+
+        let mut outputs = StepOutputs::new();
+        outputs.insert("size", content.len());
+        outputs.insert("content", content);
+        Ok(outputs)
+    })
+}
+
+fn assert_exists<'a>(_world: &'a mut dyn Any, args: RawArgs, ctx: &'a StepContext) -> StepFuture<'a> {
+    Box::pin(async move {
+        let args: ExistsArgs = parse("fs/assert-exists", &args)?;
+        let exists = tokio::fs::try_exists(resolve(ctx, &args.path)?).await?;
+        match (args.exists, exists) {
+            (true, false) => Err(StepError::assertion(format!("{} does not exist", args.path)).into()),
+            (false, true) => Err(StepError::assertion(format!("{} exists", args.path)).into()),
+            _ => Ok(StepOutputs::new()),
+        }
+    })
+}
+
+fn assert_contains<'a>(_world: &'a mut dyn Any, args: RawArgs, ctx: &'a StepContext) -> StepFuture<'a> {
+    Box::pin(async move {
+        let args: ContainsArgs = parse("fs/assert-contains", &args)?;
+        let content = tokio::fs::read_to_string(resolve(ctx, &args.path)?)
+            .await
+            .map_err(|e| StepError::assertion(format!("Failed to read {}: {}", args.path, e)))?;
+        if !content.contains(&args.text) {
+            return Err(StepError::assertion(format!(
+                "{} does not contain {:?}",
+                args.path, args.text
+            ))
+            .into());
+        }
+        Ok(StepOutputs::new())
+    })
+}
+
+/// Names the step in args errors, as `#[step]` functions do.
+fn parse<T: FromArgs>(step: &str, args: &RawArgs) -> Result<T> {
+    T::from_args(args).map_err(|e| match e {
+        Error::Args(reason) => Error::InvalidArgs {
+            step: step.to_string(),
+            reason,
+        },
+        other => other,
+    })
+}
+
+/// `path` under the job's temp dir. Absolute paths and `..` are rejected, so a step can't
+/// touch files outside it.
+fn resolve(ctx: &StepContext, path: &str) -> Result<PathBuf> {
+    let temp = ctx
+        .temp_dir()
+        .ok_or_else(|| StepError::custom("fs steps need the job's temp dir"))?;
+    let relative = Path::new(path);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(StepError::custom(format!(
+            "'{}' must be a relative path inside the job's temp dir",
+            path
+        ))
+        .into());
+    }
+    Ok(temp.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::VirtualClock;
+    use serde_json::json;
+
+    fn raw(value: Value) -> RawArgs {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fs_steps_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let ctx = StepContext::new("wf", "job", "session", VirtualClock::new())
+            .with_temp_dir(temp.path());
+        let mut world = ();
+
+        let written = write(&mut world, raw(json!({ "path": "out/report.json", "content": { "rows": 2 } })), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(written.get("size"), Some(&json!(15)));
+
+        let read_back = read(&mut world, raw(json!({ "path": "out/report.json" })), &ctx).await.unwrap();
+        assert_eq!(read_back.get_string("content").unwrap(), "{\n  \"rows\": 2\n}");
+
+        assert_exists(&mut world, raw(json!({ "path": "out/report.json" })), &ctx).await.unwrap();
+        assert_exists(&mut world, raw(json!({ "path": "missing.txt", "exists": false })), &ctx)
+            .await
+            .unwrap();
+        assert_contains(&mut world, raw(json!({ "path": "out/report.json", "text": "rows" })), &ctx)
+            .await
+            .unwrap();
+
+        let missing = assert_exists(&mut world, raw(json!({ "path": "missing.txt" })), &ctx).await;
+        assert_eq!(missing.unwrap_err().to_string(), "Step error: Assertion failed: missing.txt does not exist");
+        let absent = assert_contains(&mut world, raw(json!({ "path": "out/report.json", "text": "cols" })), &ctx).await;
+        assert!(absent.is_err());
+        let escaped = read(&mut world, raw(json!({ "path": "../secrets" })), &ctx).await;
+        assert!(escaped.unwrap_err().to_string().contains("must be a relative path"));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::determinism::SeededRng;
+use crate::{Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A value generator callable as `${{ random.<name>(args...) }}`.
+pub type GeneratorFn = fn(&mut SeededRng, &[Value]) -> Result<Value>;
+
+#[derive(Clone)]
+pub struct GeneratorRegistry {
+    generators: HashMap<String, GeneratorFn>,
+}
+
+impl GeneratorRegistry {
+    pub fn new() -> Self {
+        Self {
+            generators: HashMap::new(),
+        }
+    }
+
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register("uuid", gen_uuid);
+        registry.register("email", gen_email);
+        registry.register("string", gen_string);
+        registry.register("hex", gen_hex);
+        registry.register("int", gen_int);
+        registry.register("bool", gen_bool);
+        registry
+    }
+
+    pub fn register(&mut self, name: impl Into<String>, func: GeneratorFn) {
+        self.generators.insert(name.into(), func);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&GeneratorFn> {
+        self.generators.get(name)
+    }
+}
+
+impl Default for GeneratorRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// Seeded generator state shared by every expression evaluated within a job.
+pub struct RandomContext {
+    rng: Mutex<SeededRng>,
+    generators: Arc<GeneratorRegistry>,
+}
+
+impl RandomContext {
+    pub fn new(rng: SeededRng, generators: Arc<GeneratorRegistry>) -> Self {
+        Self {
+            rng: Mutex::new(rng),
+            generators,
+        }
+    }
+
+    pub fn generate(&self, name: &str, args: &[Value]) -> Result<Value> {
+        let func = self
+            .generators
+            .get(name)
+            .ok_or_else(|| Error::Expression(format!("Unknown random generator: {}", name)))?;
+        let mut rng = self
+            .rng
+            .lock()
+            .map_err(|_| Error::Expression("Random generator state poisoned".to_string()))?;
+        func(&mut rng, args)
+    }
+}
+
+fn arg_u64(args: &[Value], index: usize, name: &str) -> Result<Option<u64>> {
+    match args.get(index) {
+        None => Ok(None),
+        Some(v) => v.as_u64().map(Some).ok_or_else(|| {
+            Error::Expression(format!(
+                "random.{}: argument {} must be a non-negative integer",
+                name,
+                index + 1
+            ))
+        }),
+    }
+}
+
+fn arg_i64(args: &[Value], index: usize, name: &str) -> Result<i64> {
+    args.get(index).and_then(|v| v.as_i64()).ok_or_else(|| {
+        Error::Expression(format!(
+            "random.{}: argument {} must be an integer",
+            name,
+            index + 1
+        ))
+    })
+}
+
+fn gen_uuid(rng: &mut SeededRng, _args: &[Value]) -> Result<Value> {
+    Ok(Value::String(rng.next_uuid().to_string()))
+}
+
+fn gen_email(rng: &mut SeededRng, args: &[Value]) -> Result<Value> {
+    let domain = args
+        .first()
+        .and_then(|v| v.as_str())
+        .unwrap_or("example.com");
+    Ok(Value::String(format!(
+        "user-{}@{}",
+        rng.next_hex(10),
+        domain
+    )))
+}
+
+fn gen_string(rng: &mut SeededRng, args: &[Value]) -> Result<Value> {
+    let len = arg_u64(args, 0, "string")?.unwrap_or(16) as usize;
+    Ok(Value::String(rng.next_string(len)))
+}
+
+fn gen_hex(rng: &mut SeededRng, args: &[Value]) -> Result<Value> {
+    let len = arg_u64(args, 0, "hex")?.unwrap_or(16) as usize;
+    Ok(Value::String(rng.next_hex(len)))
+}
+
+fn gen_int(rng: &mut SeededRng, args: &[Value]) -> Result<Value> {
+    let (min, max) = match args.len() {
+        0 => (0, i64::from(u32::MAX)),
+        2 => (arg_i64(args, 0, "int")?, arg_i64(args, 1, "int")?),
+        _ => {
+            return Err(Error::Expression(
+                "random.int expects no arguments or (min, max)".to_string(),
+            ))
+        }
+    };
+    if min > max {
+        return Err(Error::Expression(format!(
+            "random.int: min {} is greater than max {}",
+            min, max
+        )));
+    }
+    // Inclusive of both bounds; the full i64 range has more values than a u64 counts. The sum
+    // always lands in [min, max], so wrapping only avoids the intermediate overflow
+    let value = match max.abs_diff(min).checked_add(1) {
+        Some(span) => min.wrapping_add(rng.next_range(0, span) as i64),
+        None => rng.next_i64(),
+    };
+    Ok(Value::Number(value.into()))
+}
+
+fn gen_bool(rng: &mut SeededRng, _args: &[Value]) -> Result<Value> {
+    Ok(Value::Bool(rng.next_bool()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context(seed: u64) -> RandomContext {
+        RandomContext::new(
+            SeededRng::with_seed(seed),
+            Arc::new(GeneratorRegistry::with_builtins()),
+        )
+    }
+
+    #[test]
+    fn test_deterministic_per_seed() {
+        let a = context(7);
+        let b = context(7);
+        assert_eq!(
+            a.generate("uuid", &[]).unwrap(),
+            b.generate("uuid", &[]).unwrap()
+        );
+        assert_eq!(
+            a.generate("email", &[]).unwrap(),
+            b.generate("email", &[]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_int_range_inclusive() {
+        let ctx = context(1);
+        for _ in 0..200 {
+            let v = ctx
+                .generate("int", &[json!(1), json!(3)])
+                .unwrap()
+                .as_i64()
+                .unwrap();
+            assert!((1..=3).contains(&v));
+        }
+        assert!(ctx.generate("int", &[json!(5), json!(1)]).is_err());
+
+        for (min, max) in [(i64::MIN, i64::MAX), (i64::MIN, 0), (-1, i64::MAX), (i64::MAX, i64::MAX)] {
+            let v = ctx.generate("int", &[json!(min), json!(max)]).unwrap().as_i64().unwrap();
+            assert!((min..=max).contains(&v));
+        }
+    }
+
+    #[test]
+    fn test_string_length_and_custom_generator() {
+        let mut registry = GeneratorRegistry::with_builtins();
+        registry.register("constant", |_, _| Ok(json!("fixed")));
+        let ctx = RandomContext::new(SeededRng::with_seed(3), Arc::new(registry));
+
+        let s = ctx.generate("string", &[json!(12)]).unwrap();
+        assert_eq!(s.as_str().unwrap().len(), 12);
+        assert_eq!(ctx.generate("constant", &[]).unwrap(), json!("fixed"));
+        assert!(ctx.generate("missing", &[]).is_err());
+    }
+}
@@ -1,17 +1,58 @@
+use crate::args::RawArgs;
+use crate::context::StepContext;
+use crate::outputs::StepOutputs;
 use crate::parser::Step;
+use crate::registry::{call_guarded, StepRegistry};
 use crate::runner::StepResult;
 use crate::world::World;
+use crate::Result;
+use serde::Serialize;
+use std::any::Any;
 use std::future::Future;
 use std::pin::Pin;
 
 pub type BeforeAllFn = fn() -> Pin<Box<dyn Future<Output = ()> + Send>>;
 pub type AfterAllFn = fn() -> Pin<Box<dyn Future<Output = ()> + Send>>;
-pub type BeforeScenarioFn<W> = for<'a> fn(&'a mut W) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
-pub type AfterScenarioFn<W> = for<'a> fn(&'a mut W) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+pub type BeforeScenarioFn<W> =
+    for<'a> fn(&'a mut W, &'a HookContext<'a>) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+pub type AfterScenarioFn<W> =
+    for<'a> fn(&'a mut W, &'a HookContext<'a>) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
 pub type BeforeStepFn<W> = for<'a> fn(&'a mut W, &'a Step) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
 pub type AfterStepFn<W> =
     for<'a> fn(&'a mut W, &'a Step, &'a StepResult) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
 
+/// Given to scenario hooks with the world, so setup a workflow step already does can be
+/// called from a hook instead of duplicated.
+pub struct HookContext<'a> {
+    steps: &'a StepRegistry,
+    step_ctx: &'a StepContext,
+}
+
+impl<'a> HookContext<'a> {
+    pub(crate) fn new(steps: &'a StepRegistry, step_ctx: &'a StepContext) -> Self {
+        Self { steps, step_ctx }
+    }
+
+    /// The job's context, as its steps see it.
+    pub fn step_context(&self) -> &StepContext {
+        self.step_ctx
+    }
+
+    /// Runs the registered step `uses` (`name` or `name@version`) with `args`, as a workflow
+    /// step would. Cleanups it defers run at the end of the job.
+    pub async fn call_step<W: World>(
+        &self,
+        world: &mut W,
+        uses: &str,
+        args: impl Serialize,
+    ) -> Result<StepOutputs> {
+        let step_fn = self.steps.callable(uses)?;
+        let args: RawArgs = serde_json::from_value(serde_json::to_value(args)?)?;
+        let world: &mut dyn Any = world;
+        call_guarded(&step_fn, world, args, self.step_ctx).await
+    }
+}
+
 pub enum HookDef<W: World> {
     BeforeAll(BeforeAllFn),
     AfterAll(AfterAllFn),
@@ -91,15 +132,15 @@ impl<W: World> HookRegistry<W> {
         }
     }
 
-    pub async fn run_before_scenario(&self, world: &mut W) {
+    pub async fn run_before_scenario(&self, world: &mut W, ctx: &HookContext<'_>) {
         for hook in &self.before_scenario {
-            hook(world).await;
+            hook(world, ctx).await;
         }
     }
 
-    pub async fn run_after_scenario(&self, world: &mut W) {
+    pub async fn run_after_scenario(&self, world: &mut W, ctx: &HookContext<'_>) {
         for hook in &self.after_scenario {
-            hook(world).await;
+            hook(world, ctx).await;
         }
     }
 
@@ -0,0 +1,132 @@
+//! Built-in `assert/json-schema` step: checks a value, usually a step output, against a JSON
+//! Schema file. Schema paths are relative to the working directory, like `workflows`, and
+//! relative `$ref`s resolve against the schema file.
+
+use crate::args::{self, ArgField, FromArgs, RawArgs};
+use crate::context::StepContext;
+use crate::error::StepError;
+use crate::outputs::StepOutputs;
+use crate::registry::StepRegistry;
+use crate::{Error, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::any::Any;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+type StepFuture<'a> = Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>;
+
+pub(crate) fn register(registry: &mut StepRegistry) {
+    registry.register_with_args("assert/json-schema", assert_json_schema, SchemaArgs::FIELDS);
+}
+
+/// `value` is parsed first when it's a string holding JSON, so raw response bodies work too.
+#[derive(Deserialize)]
+struct SchemaArgs {
+    value: Value,
+    schema: String,
+}
+
+impl FromArgs for SchemaArgs {
+    const FIELDS: &'static [ArgField] = &[
+        ArgField { name: "value", ty: "Value", optional: false },
+        ArgField { name: "schema", ty: "String", optional: false },
+    ];
+
+    fn from_args(args: &RawArgs) -> Result<Self> {
+        args::deserialize(args)
+    }
+}
+
+fn assert_json_schema<'a>(_world: &'a mut dyn Any, args: RawArgs, _ctx: &'a StepContext) -> StepFuture<'a> {
+    Box::pin(async move {
+        let args: SchemaArgs = SchemaArgs::from_args(&args).map_err(|e| match e {
+            Error::Args(reason) => Error::InvalidArgs {
+                step: "assert/json-schema".to_string(),
+                reason,
+            },
+            other => other,
+        })?;
+        let value = match args.value {
+            Value::String(s) => serde_json::from_str(&s).unwrap_or(Value::String(s)),
+            other => other,
+        };
+        let violations = validate(Path::new(&args.schema), &value)?;
+        if !violations.is_empty() {
+            return Err(StepError::assertion(format!(
+                "value does not match {}:\n  {}",
+                args.schema,
+                violations.join("\n  ")
+            ))
+            .into());
+        }
+        Ok(StepOutputs::new())
+    })
+}
+
+/// Every violation of the schema at `path`, as `<json pointer>: <reason>`.
+fn validate(path: &Path, value: &Value) -> Result<Vec<String>> {
+    let unreadable = |reason: String| StepError::custom(format!("Invalid schema {}: {}", path.display(), reason));
+    let content = std::fs::read_to_string(path).map_err(|e| unreadable(e.to_string()))?;
+    let schema: Value = serde_json::from_str(&content).map_err(|e| unreadable(e.to_string()))?;
+    let base = std::fs::canonicalize(path).map_err(|e| unreadable(e.to_string()))?;
+    let validator = jsonschema::options()
+        .with_base_uri(format!("file://{}", base.display()))
+        .build(&schema)
+        .map_err(|e| unreadable(e.to_string()))?;
+
+    Ok(validator
+        .iter_errors(value)
+        .map(|e| {
+            let pointer = e.instance_path.to_string();
+            let pointer = if pointer.is_empty() { "/".to_string() } else { pointer };
+            format!("{}: {}", pointer, e)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::VirtualClock;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_assert_json_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("email.json"),
+            r#"{ "type": "string", "pattern": "@" }"#,
+        )
+        .unwrap();
+        let schema = dir.path().join("user.json");
+        std::fs::write(
+            &schema,
+            r#"{
+                "type": "object",
+                "required": ["id", "email"],
+                "properties": {
+                    "id": { "type": "integer" },
+                    "email": { "$ref": "email.json" }
+                }
+            }"#,
+        )
+        .unwrap();
+        let ctx = StepContext::new("wf", "job", "session", VirtualClock::new());
+        let run = |value: Value| {
+            let args = serde_json::from_value(json!({ "value": value, "schema": schema })).unwrap();
+            let ctx = &ctx;
+            async move { assert_json_schema(&mut (), args, ctx).await }
+        };
+
+        run(json!({ "id": 1, "email": "a@example.com" })).await.unwrap();
+        run(json!(r#"{ "id": 2, "email": "b@example.com" }"#)).await.unwrap();
+
+        let message = run(json!({ "id": "1", "email": "nope" })).await.unwrap_err().to_string();
+        assert!(message.contains("/id: \"1\" is not of type \"integer\""), "{}", message);
+        assert!(message.contains("/email: \"nope\" does not match \"@\""), "{}", message);
+        let missing = run(json!({})).await.unwrap_err().to_string();
+        assert!(missing.contains("/: \"id\" is a required property"), "{}", missing);
+    }
+}
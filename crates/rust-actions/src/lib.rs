@@ -1,15 +1,48 @@
 pub mod args;
+pub mod call_cache;
+pub mod cancel;
+mod capture;
+pub mod checkpoint;
 pub mod clock;
+pub mod config;
+pub mod context;
+pub mod coverage;
 pub mod determinism;
+pub mod diff;
+mod envfile;
 pub mod error;
+mod events;
+pub mod executor;
+pub mod exit;
+pub mod expect;
 pub mod expr;
+mod fs_steps;
+pub mod generators;
 pub mod hooks;
+mod json_schema;
+pub mod listing;
+pub mod mask;
 pub mod matrix;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod outputs;
 pub mod parser;
+pub mod policy;
+mod progress;
+mod ratelimit;
 pub mod registry;
+pub mod report;
+pub mod report_filter;
+pub mod retry;
 pub mod runner;
+pub mod scaffold;
+mod schema;
+pub mod services;
+pub mod snapshot;
+pub mod summary;
 pub mod validate;
+mod vars;
+pub mod workflow_builder;
 pub mod workflow_registry;
 pub mod world;
 
@@ -21,18 +54,40 @@ pub use serde_json;
 
 pub mod prelude {
     pub use crate::args::{FromArgs, RawArgs};
-    pub use crate::clock::{Instant, VirtualClock};
+    pub use crate::cancel::CancellationToken;
+    pub use crate::clock::{Instant, Stopwatch, VirtualClock};
+    pub use crate::config::Config;
+    pub use crate::context::{StepContext, StepLink};
+    pub use crate::coverage::StepCoverage;
     pub use crate::determinism::SeededRng;
+    pub use crate::diff::{RunDiff, RunRecord};
     pub use crate::error::{Error, Result, StepError};
-    pub use crate::expr::JobOutputs;
-    pub use crate::hooks::HookDef;
+    pub use crate::executor::{StepCall, StepExecutor};
+    pub use crate::exit::{ExitPolicy, FailureKind, FailureManifest};
+    pub use crate::expr::{ContainerInfo, JobOutputs, MissingKeyPolicy};
+    pub use crate::generators::GeneratorFn;
+    pub use crate::hooks::{HookContext, HookDef};
+    pub use crate::listing::SuiteListing;
     pub use crate::matrix::{expand_matrix, MatrixCombination};
-    pub use crate::outputs::{IntoOutputs, StepOutputs};
-    pub use crate::parser::{Job, Step, Strategy, Workflow};
-    pub use crate::registry::ErasedStepDef;
-    pub use crate::runner::{JobResult, RustActions, StepResult, WorkflowResult};
+    #[cfg(feature = "metrics")]
+    pub use crate::metrics::RunMetrics;
+    pub use crate::outputs::{IntoOutputs, OutputLimit, StepOutputs};
+    pub use crate::parser::{
+        AssertMode, Delay, DurationAssertion, Job, MatrixSample, Need, ServiceSpec, Step, Strategy, ValueType, Workflow,
+        WorkflowFilter, WorkflowFormat,
+    };
+    pub use crate::policy::StepPolicy;
+    pub use crate::registry::{typed_step, DynStepFn, ErasedStepDef, StepRegistry, StepSource};
+    pub use crate::report::HtmlReport;
+    pub use crate::report_filter::{AllowKeys, Redact, ReportField, ReportFilter, ReportLocation, Truncate};
+    pub use crate::retry::RetryPolicy;
+    pub use crate::runner::{JobResult, RustActions, StepRecord, StepResult, WorkflowResult};
+    pub use crate::scaffold::ScaffoldSpec;
+    pub use crate::services::{DockerServices, ExternalServices, ServiceProvider};
+    pub use crate::summary::SuiteSummary;
+    pub use crate::workflow_builder::{JobBuilder, WorkflowBuilder};
     pub use crate::workflow_registry::WorkflowRegistry;
-    pub use crate::world::World;
+    pub use crate::world::{World, WorldSnapshot};
     pub use rust_actions_macros::{
         after_all, after_scenario, after_step, before_all, before_scenario, before_step,
         generate_tests, step, workflow_test, Args, Outputs, World,
@@ -0,0 +1,202 @@
+use crate::matrix::{expand_matrix, format_matrix_suffix, MatrixCombination};
+use crate::parser::{Job, Workflow};
+use crate::runner::order_jobs;
+use crate::workflow_registry::{is_file_ref, WorkflowRegistry};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SuiteListing {
+    pub workflows: Vec<WorkflowListing>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowListing {
+    pub path: PathBuf,
+    pub name: String,
+    pub description: Option<String>,
+    pub ignored: bool,
+    pub jobs: Vec<JobListing>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobListing {
+    pub name: String,
+    pub description: Option<String>,
+    pub needs: Vec<String>,
+    pub uses: Option<String>,
+    pub matrix: Vec<MatrixCombination>,
+    pub step_count: usize,
+}
+
+impl SuiteListing {
+    pub fn build(workflows: &[(PathBuf, Workflow)], registry: Option<&WorkflowRegistry>) -> Self {
+        let workflows = workflows
+            .iter()
+            .map(|(path, workflow)| WorkflowListing::build(path, workflow, registry))
+            .collect();
+        Self { workflows }
+    }
+
+    pub fn workflow_count(&self) -> usize {
+        self.workflows.len()
+    }
+
+    pub fn job_count(&self) -> usize {
+        self.workflows
+            .iter()
+            .flat_map(|w| &w.jobs)
+            .map(|j| j.matrix.len().max(1))
+            .sum()
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.workflows
+            .iter()
+            .flat_map(|w| &w.jobs)
+            .map(|j| j.step_count * j.matrix.len().max(1))
+            .sum()
+    }
+}
+
+impl WorkflowListing {
+    pub(crate) fn build(path: &Path, workflow: &Workflow, registry: Option<&WorkflowRegistry>) -> Self {
+        // Fall back to name order when the dependency graph is broken; validation reports that
+        let order = order_jobs(workflow).unwrap_or_else(|_| {
+            let mut names: Vec<String> = workflow.jobs.keys().cloned().collect();
+            names.sort();
+            names
+        });
+
+        let jobs = order
+            .iter()
+            .map(|name| JobListing::build(name, &workflow.jobs[name], registry))
+            .collect();
+
+        Self {
+            path: path.to_path_buf(),
+            name: workflow.name.clone(),
+            description: workflow.description.clone(),
+            ignored: workflow.ignore.is_ignored(),
+            jobs,
+        }
+    }
+}
+
+impl JobListing {
+    fn build(name: &str, job: &Job, registry: Option<&WorkflowRegistry>) -> Self {
+        let file_ref = job.uses.as_deref().filter(|u| is_file_ref(u));
+
+        let step_count = match (file_ref, registry) {
+            (Some(uses), Some(reg)) => reg
+                .resolve_file_ref(uses)
+                .map(|w| w.jobs.values().map(|j| j.steps.len()).sum())
+                .unwrap_or(0),
+            _ => job.steps.len(),
+        };
+
+        let matrix = match &job.strategy {
+            Some(strategy) => expand_matrix(strategy)
+                .into_iter()
+                .filter(|combo| !combo.is_empty())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Self {
+            name: name.to_string(),
+            description: job.description.clone(),
+            needs: job.needs.as_vec(),
+            uses: job.uses.clone(),
+            matrix,
+            step_count,
+        }
+    }
+}
+
+impl fmt::Display for SuiteListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for workflow in &self.workflows {
+            write!(f, "{} ({})", workflow.name, workflow.path.display())?;
+            if workflow.ignored {
+                write!(f, " [ignored]")?;
+            }
+            writeln!(f, "{}", summary_line(&workflow.description))?;
+
+            for job in &workflow.jobs {
+                let detail = match &job.uses {
+                    Some(uses) => format!("{} steps via {}", job.step_count, uses),
+                    None => format!("{} steps", job.step_count),
+                };
+                let description = summary_line(&job.description);
+                if job.matrix.is_empty() {
+                    writeln!(f, "  {} ({}){}", job.name, detail, description)?;
+                } else {
+                    for combo in &job.matrix {
+                        let suffix = format_matrix_suffix(combo);
+                        writeln!(f, "  {}{} ({}){}", job.name, suffix, detail, description)?;
+                    }
+                }
+            }
+        }
+
+        write!(
+            f,
+            "{} workflows, {} jobs, {} steps",
+            self.workflow_count(),
+            self.job_count(),
+            self.step_count()
+        )
+    }
+}
+
+/// ` - ` and the first line of a `description:`, or nothing.
+fn summary_line(description: &Option<String>) -> String {
+    match description.as_deref().and_then(|d| d.lines().find(|l| !l.trim().is_empty())) {
+        Some(line) => format!(" - {}", line.trim()),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_counts_matrix_jobs() {
+        let yaml = r#"
+name: Flags
+description: |
+  Builds once, then checks every flag.
+  Takes a few minutes.
+jobs:
+  build:
+    description: Compiles the release binary
+    steps:
+      - uses: build/run
+  test:
+    needs: build
+    strategy:
+      matrix:
+        flag: [true, false]
+    steps:
+      - uses: flag/set
+      - uses: flag/check
+"#;
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let listing = SuiteListing::build(&[(PathBuf::from("flags.yaml"), workflow)], None);
+
+        let jobs = &listing.workflows[0].jobs;
+        assert_eq!(jobs[0].name, "build");
+        assert_eq!(jobs[1].matrix.len(), 2);
+        assert_eq!(listing.job_count(), 3);
+        assert_eq!(listing.step_count(), 5);
+
+        let printed = listing.to_string();
+        assert!(printed.contains("test [flag=true] (2 steps)\n"));
+        assert!(printed.starts_with("Flags (flags.yaml) - Builds once, then checks every flag.\n"));
+        assert!(printed.contains("  build (1 steps) - Compiles the release binary\n"));
+        assert!(printed.ends_with("1 workflows, 3 jobs, 5 steps"));
+    }
+}
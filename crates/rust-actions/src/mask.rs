@@ -0,0 +1,131 @@
+use serde_json::Value;
+
+pub const MASK: &str = "***";
+
+/// Secrets shorter than this, or made of digits only, are masked only where they stand alone,
+/// so a secret of `1` doesn't blank every digit 1 in a log line.
+const MIN_SUBSTRING_LEN: usize = 8;
+
+/// Collects sensitive values so they can be redacted from anything printed or reported.
+#[derive(Debug, Clone, Default)]
+pub struct SecretMasker {
+    secrets: Vec<String>,
+}
+
+impl SecretMasker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, secret: impl Into<String>) {
+        let secret = secret.into();
+        if !secret.is_empty() && !self.secrets.contains(&secret) {
+            self.secrets.push(secret);
+            // Longest first so a secret containing another is replaced whole
+            self.secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        }
+    }
+
+    pub fn add_value(&mut self, value: &Value) {
+        match value {
+            Value::String(s) => self.add(s.clone()),
+            Value::Number(n) => self.add(n.to_string()),
+            Value::Array(items) => items.iter().for_each(|v| self.add_value(v)),
+            Value::Object(map) => map.values().for_each(|v| self.add_value(v)),
+            Value::Bool(_) | Value::Null => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    pub fn mask(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |acc, secret| {
+            if secret.len() < MIN_SUBSTRING_LEN || secret.chars().all(|c| c.is_ascii_digit()) {
+                replace_tokens(&acc, secret)
+            } else {
+                acc.replace(secret.as_str(), MASK)
+            }
+        })
+    }
+
+    pub fn mask_value(&self, value: &Value) -> Value {
+        if self.is_empty() {
+            return value.clone();
+        }
+        match value {
+            Value::String(s) => Value::String(self.mask(s)),
+            Value::Number(n) if self.secrets.contains(&n.to_string()) => {
+                Value::String(MASK.to_string())
+            }
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.mask_value(v)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.mask_value(v)))
+                    .collect(),
+            ),
+            _ => value.clone(),
+        }
+    }
+}
+
+/// `text` with each occurrence of `secret` that isn't part of a longer word or number masked.
+fn replace_tokens(text: &str, secret: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = 0;
+    for (start, _) in text.match_indices(secret) {
+        let end = start + secret.len();
+        if is_word(text[..start].chars().next_back()) || is_word(text[end..].chars().next()) {
+            continue;
+        }
+        masked.push_str(&text[rest..start]);
+        masked.push_str(MASK);
+        rest = end;
+    }
+    masked.push_str(&text[rest..]);
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mask_text() {
+        let mut masker = SecretMasker::new();
+        masker.add("tok");
+        masker.add("tok-12345");
+        masker.add("");
+
+        assert_eq!(
+            masker.mask("auth failed for tok-12345 (tok)"),
+            "auth failed for *** (***)"
+        );
+    }
+
+    #[test]
+    fn test_short_and_numeric_secrets_mask_whole_tokens() {
+        let mut masker = SecretMasker::new();
+        masker.add("1");
+        masker.add("4242424242");
+
+        assert_eq!(
+            masker.mask("step 1 of 12 charged 4242424242 (ref 94242424242)"),
+            "step *** of 12 charged *** (ref 94242424242)"
+        );
+    }
+
+    #[test]
+    fn test_mask_value() {
+        let mut masker = SecretMasker::new();
+        masker.add_value(&json!({ "password": "hunter2", "pin": 1234 }));
+
+        assert_eq!(
+            masker.mask_value(&json!({ "body": "pw=hunter2", "pin": 1234, "ok": true })),
+            json!({ "body": "pw=***", "pin": "***", "ok": true })
+        );
+    }
+}
@@ -1,21 +1,36 @@
-use crate::parser::{Matrix, Strategy};
+use crate::determinism::SeededRng;
+use crate::parser::{Matrix, MatrixSample, Strategy};
+use indexmap::IndexMap;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 pub type MatrixCombination = HashMap<String, Value>;
 
 pub fn expand_matrix(strategy: &Strategy) -> Vec<MatrixCombination> {
-    expand_matrix_inner(&strategy.matrix)
+    expand_sampled(&strategy.matrix, strategy.sample)
 }
 
 pub fn expand_matrix_inner(matrix: &Matrix) -> Vec<MatrixCombination> {
+    expand_sampled(matrix, None)
+}
+
+/// The matrix's combinations, reduced by `sample` before `include:` entries are added.
+fn expand_sampled(matrix: &Matrix, sample: Option<MatrixSample>) -> Vec<MatrixCombination> {
     if matrix.dimensions.is_empty() && matrix.include.is_empty() {
         return vec![HashMap::new()];
     }
 
-    let mut combinations = cartesian_product(&matrix.dimensions);
-
-    combinations.retain(|combo| !matches_any_exclude(combo, &matrix.exclude));
+    let mut combinations = match sample {
+        Some(MatrixSample::Pairwise) => pairwise(&matrix.dimensions, &matrix.exclude),
+        _ => {
+            let mut combinations = cartesian_product(&matrix.dimensions);
+            combinations.retain(|combo| !matches_any_exclude(combo, &matrix.exclude));
+            combinations
+        }
+    };
+    if let Some(MatrixSample::Random { count, seed }) = sample {
+        combinations = sample_random(combinations, count, seed);
+    }
 
     for include in &matrix.include {
         let mut new_combo = HashMap::new();
@@ -32,7 +47,8 @@ pub fn expand_matrix_inner(matrix: &Matrix) -> Vec<MatrixCombination> {
     }
 }
 
-fn cartesian_product(matrix: &HashMap<String, Vec<Value>>) -> Vec<MatrixCombination> {
+/// Combinations in declaration order, the first dimension varying slowest.
+fn cartesian_product(matrix: &IndexMap<String, Vec<Value>>) -> Vec<MatrixCombination> {
     if matrix.is_empty() {
         return vec![];
     }
@@ -58,6 +74,115 @@ fn cartesian_product(matrix: &HashMap<String, Vec<Value>>) -> Vec<MatrixCombinat
     result
 }
 
+/// Greedy all-pairs reduction: each combination starts from a pair of values no earlier one
+/// covers, then takes, dimension by dimension, the value covering the most uncovered pairs.
+/// Pairs an `exclude:` entry rules out on its own aren't required.
+fn pairwise(
+    dimensions: &IndexMap<String, Vec<Value>>,
+    excludes: &[HashMap<String, Value>],
+) -> Vec<MatrixCombination> {
+    let keys: Vec<&String> = dimensions.keys().collect();
+    let values: Vec<&Vec<Value>> = dimensions.values().collect();
+    if keys.len() < 2 || values.iter().any(|v| v.is_empty()) {
+        let mut combinations = cartesian_product(dimensions);
+        combinations.retain(|combo| !matches_any_exclude(combo, excludes));
+        return combinations;
+    }
+
+    // Chosen value index per dimension, to the combination it stands for
+    let combo = |chosen: &[Option<usize>]| -> MatrixCombination {
+        chosen
+            .iter()
+            .enumerate()
+            .filter_map(|(dim, v)| v.map(|v| (keys[dim].clone(), values[dim][v].clone())))
+            .collect()
+    };
+
+    // (dimension, value, later dimension, value)
+    let mut uncovered = BTreeSet::new();
+    for a in 0..keys.len() {
+        for b in a + 1..keys.len() {
+            for va in 0..values[a].len() {
+                for vb in 0..values[b].len() {
+                    let mut chosen = vec![None; keys.len()];
+                    chosen[a] = Some(va);
+                    chosen[b] = Some(vb);
+                    if !matches_any_exclude(&combo(&chosen), excludes) {
+                        uncovered.insert((a, va, b, vb));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut combinations = Vec::new();
+    while let Some(&(a, va, b, vb)) = uncovered.iter().next() {
+        let mut chosen = vec![None; keys.len()];
+        chosen[a] = Some(va);
+        chosen[b] = Some(vb);
+
+        for dim in 0..keys.len() {
+            if chosen[dim].is_some() {
+                continue;
+            }
+            let gain = |v: usize| {
+                chosen
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(other, ov)| ov.map(|ov| (other, ov)))
+                    .filter(|&(other, ov)| {
+                        let pair = if other < dim { (other, ov, dim, v) } else { (dim, v, other, ov) };
+                        uncovered.contains(&pair)
+                    })
+                    .count()
+            };
+            let mut best: Option<(usize, usize)> = None;
+            for v in 0..values[dim].len() {
+                let mut candidate = chosen.clone();
+                candidate[dim] = Some(v);
+                if matches_any_exclude(&combo(&candidate), excludes) {
+                    continue;
+                }
+                let gain = gain(v);
+                if !matches!(best, Some((_, best_gain)) if best_gain >= gain) {
+                    best = Some((v, gain));
+                }
+            }
+            chosen[dim] = best.map(|(v, _)| v);
+        }
+
+        if chosen.iter().any(Option::is_none) {
+            // No value of some dimension fits the excludes alongside this pair
+            uncovered.remove(&(a, va, b, vb));
+            continue;
+        }
+        let chosen: Vec<usize> = chosen.into_iter().flatten().collect();
+        for x in 0..keys.len() {
+            for y in x + 1..keys.len() {
+                uncovered.remove(&(x, chosen[x], y, chosen[y]));
+            }
+        }
+        combinations.push(combo(&chosen.into_iter().map(Some).collect::<Vec<_>>()));
+    }
+    combinations
+}
+
+/// `count` of `combinations`, picked with a seeded RNG and kept in their original order.
+fn sample_random(combinations: Vec<MatrixCombination>, count: usize, seed: u64) -> Vec<MatrixCombination> {
+    if combinations.len() <= count {
+        return combinations;
+    }
+    let mut indices: Vec<usize> = (0..combinations.len()).collect();
+    SeededRng::with_seed(seed).shuffle(&mut indices);
+    let keep: BTreeSet<usize> = indices.into_iter().take(count).collect();
+    combinations
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| keep.contains(i))
+        .map(|(_, combo)| combo)
+        .collect()
+}
+
 fn matches_any_exclude(combo: &MatrixCombination, excludes: &[HashMap<String, Value>]) -> bool {
     excludes.iter().any(|exclude| matches_exclude(combo, exclude))
 }
@@ -95,6 +220,16 @@ pub fn format_matrix_suffix(combo: &MatrixCombination) -> String {
     format!(" [{}]", parts.join(", "))
 }
 
+/// Identifier for a combination usable in expressions: values ordered by key, joined with `-`.
+pub fn format_matrix_key(combo: &MatrixCombination) -> String {
+    let mut keys: Vec<&String> = combo.keys().collect();
+    keys.sort();
+    keys.iter()
+        .map(|k| format_value(&combo[*k]))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
 fn format_value(value: &Value) -> String {
     match value {
         Value::String(s) => s.clone(),
@@ -110,10 +245,19 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_format_matrix_key() {
+        let mut combo = HashMap::new();
+        combo.insert("version".to_string(), json!(2));
+        combo.insert("os".to_string(), json!("linux"));
+        assert_eq!(format_matrix_key(&combo), "linux-2");
+        assert_eq!(format_matrix_key(&HashMap::new()), "");
+    }
+
     #[test]
     fn test_empty_matrix() {
         let matrix = Matrix {
-            dimensions: HashMap::new(),
+            dimensions: IndexMap::new(),
             include: vec![],
             exclude: vec![],
         };
@@ -125,7 +269,7 @@ mod tests {
 
     #[test]
     fn test_single_dimension_matrix() {
-        let mut dimensions = HashMap::new();
+        let mut dimensions = IndexMap::new();
         dimensions.insert("version".to_string(), vec![json!("v1"), json!("v2")]);
 
         let matrix = Matrix {
@@ -140,7 +284,7 @@ mod tests {
 
     #[test]
     fn test_cartesian_product() {
-        let mut dimensions = HashMap::new();
+        let mut dimensions = IndexMap::new();
         dimensions.insert("a".to_string(), vec![json!(true), json!(false)]);
         dimensions.insert("b".to_string(), vec![json!(true), json!(false)]);
 
@@ -156,7 +300,7 @@ mod tests {
 
     #[test]
     fn test_exclude() {
-        let mut dimensions = HashMap::new();
+        let mut dimensions = IndexMap::new();
         dimensions.insert("a".to_string(), vec![json!("v1"), json!("v2")]);
         dimensions.insert("b".to_string(), vec![json!("v1"), json!("v2")]);
 
@@ -183,7 +327,7 @@ mod tests {
 
     #[test]
     fn test_include() {
-        let mut dimensions = HashMap::new();
+        let mut dimensions = IndexMap::new();
         dimensions.insert("a".to_string(), vec![json!("v1")]);
 
         let mut include = HashMap::new();
@@ -208,6 +352,62 @@ mod tests {
         assert!(has_experimental);
     }
 
+    fn dimensions(count: usize, values: usize) -> IndexMap<String, Vec<Value>> {
+        (0..count)
+            .map(|d| (format!("d{}", d), (0..values).map(|v| json!(v)).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_pairwise_covers_every_pair() {
+        let strategy = Strategy {
+            matrix: Matrix {
+                dimensions: dimensions(4, 3),
+                include: vec![],
+                exclude: vec![[("d0".to_string(), json!(0)), ("d1".to_string(), json!(0))].into()],
+            },
+            fail_fast: true,
+            max_parallel: None,
+            sample: Some(MatrixSample::Pairwise),
+        };
+
+        let combos = expand_matrix(&strategy);
+        assert!(combos.len() < 81, "{} combinations", combos.len());
+        assert!(!combos.iter().any(|c| c["d0"] == json!(0) && c["d1"] == json!(0)));
+        for (a, b) in [("d0", "d1"), ("d0", "d3"), ("d1", "d2"), ("d2", "d3")] {
+            for va in 0..3 {
+                for vb in 0..3 {
+                    let excluded = (a, b, va, vb) == ("d0", "d1", 0, 0);
+                    let covered = combos.iter().any(|c| c[a] == json!(va) && c[b] == json!(vb));
+                    assert_eq!(covered, !excluded, "{}={} {}={}", a, va, b, vb);
+                }
+            }
+        }
+        assert_eq!(expand_matrix(&strategy), combos);
+    }
+
+    #[test]
+    fn test_random_sample() {
+        let mut strategy = Strategy {
+            matrix: Matrix {
+                dimensions: dimensions(3, 4),
+                include: vec![[("d0".to_string(), json!("extra"))].into()],
+                exclude: vec![],
+            },
+            fail_fast: true,
+            max_parallel: None,
+            sample: Some(MatrixSample::Random { count: 5, seed: 7 }),
+        };
+
+        let combos = expand_matrix(&strategy);
+        assert_eq!(combos.len(), 6);
+        assert_eq!(combos[5]["d0"], json!("extra"));
+        assert_eq!(expand_matrix(&strategy), combos);
+
+        strategy.sample = Some(MatrixSample::Random { count: 5, seed: 8 });
+        assert_ne!(expand_matrix(&strategy), combos);
+    }
+
     #[test]
     fn test_format_matrix_suffix() {
         let combo: MatrixCombination = [
@@ -0,0 +1,223 @@
+//! Run metrics in the Prometheus text format: jobs by outcome, steps by name and outcome, step
+//! durations and retries. `RustActions::push_metrics` pushes them to a Pushgateway when `run()`
+//! finishes, for graphing suite health over time.
+
+use crate::runner::{StepResult, WorkflowResult};
+use crate::summary::SuiteSummary;
+use crate::{Error, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Upper bounds, in seconds, of the step duration histogram's buckets
+const BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Histogram {
+    /// Observations at or below each of `BUCKETS`, not yet cumulative
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; BUCKETS.len()];
+        }
+        if let Some(i) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[i] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// Counters and histograms for one run. Steps are keyed by `uses:`, so renaming a step's
+/// `id:` or `name:` doesn't break a graph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunMetrics {
+    /// Step outcomes by (`uses`, `passed`/`failed`)
+    steps: BTreeMap<(String, &'static str), u64>,
+    durations: BTreeMap<String, Histogram>,
+    retries: BTreeMap<String, u64>,
+    /// Job outcomes by status, filled in by `finish`
+    jobs: BTreeMap<&'static str, u64>,
+    duration: Duration,
+}
+
+impl RunMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a step that ran; skipped steps aren't recorded.
+    pub fn record_step(&mut self, uses: &str, result: &StepResult) {
+        let (status, duration) = match result {
+            StepResult::Passed(duration) => ("passed", duration),
+            StepResult::Failed(duration, _) => ("failed", duration),
+            StepResult::Skipped => return,
+        };
+        *self.steps.entry((uses.to_string(), status)).or_default() += 1;
+        self.durations
+            .entry(uses.to_string())
+            .or_default()
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Records one retry of a step failing with `StepError::Retryable`.
+    pub fn record_retry(&mut self, uses: &str) {
+        *self.retries.entry(uses.to_string()).or_default() += 1;
+    }
+
+    /// Adds the job outcomes and total duration of `results`.
+    pub fn finish(mut self, results: &[WorkflowResult]) -> Self {
+        let summary = SuiteSummary::new(results, 0);
+        self.jobs = BTreeMap::from([
+            ("passed", summary.jobs.passed as u64),
+            ("warning", summary.jobs.warnings as u64),
+            ("failed", summary.jobs.failed as u64),
+            ("quarantined", summary.jobs.quarantined as u64),
+        ]);
+        self.duration = summary.duration;
+        self
+    }
+
+    pub fn retries(&self, uses: &str) -> u64 {
+        self.retries.get(uses).copied().unwrap_or(0)
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "rust_actions_jobs_total", "counter", "Jobs by outcome");
+        for (status, count) in &self.jobs {
+            let _ = writeln!(out, "rust_actions_jobs_total{{status=\"{}\"}} {}", status, count);
+        }
+
+        header(&mut out, "rust_actions_steps_total", "counter", "Steps run, by step and outcome");
+        for ((step, status), count) in &self.steps {
+            let _ = writeln!(
+                out,
+                "rust_actions_steps_total{{step=\"{}\",status=\"{}\"}} {}",
+                escape(step),
+                status,
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "rust_actions_step_duration_seconds",
+            "histogram",
+            "Simulated step duration, by step",
+        );
+        for (step, histogram) in &self.durations {
+            let step = escape(step);
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "rust_actions_step_duration_seconds_bucket{{step=\"{}\",le=\"{}\"}} {}",
+                    step, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "rust_actions_step_duration_seconds_bucket{{step=\"{}\",le=\"+Inf\"}} {}",
+                step, histogram.count
+            );
+            let _ = writeln!(out, "rust_actions_step_duration_seconds_sum{{step=\"{}\"}} {}", step, histogram.sum);
+            let _ = writeln!(out, "rust_actions_step_duration_seconds_count{{step=\"{}\"}} {}", step, histogram.count);
+        }
+
+        header(&mut out, "rust_actions_step_retries_total", "counter", "Retries, by step");
+        for (step, count) in &self.retries {
+            let _ = writeln!(out, "rust_actions_step_retries_total{{step=\"{}\"}} {}", escape(step), count);
+        }
+
+        header(&mut out, "rust_actions_run_duration_seconds", "gauge", "Simulated run duration");
+        let _ = writeln!(out, "rust_actions_run_duration_seconds {}", self.duration.as_secs_f64());
+        out
+    }
+
+    /// Replaces the metrics of the Pushgateway group at `url`, e.g.
+    /// `http://pushgateway:9091/metrics/job/rust_actions`. Only plain `http://` is supported.
+    pub async fn push(&self, url: &str) -> Result<()> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            Error::Metrics(format!("unsupported Pushgateway URL '{}' (expected http://)", url))
+        })?;
+        let (host, path) = match rest.split_once('/') {
+            Some((host, path)) => (host, format!("/{}", path)),
+            None => (rest, "/".to_string()),
+        };
+        let address = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+
+        let body = self.render();
+        let request = format!(
+            "PUT {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            host,
+            body.len(),
+            body
+        );
+        let mut stream = TcpStream::connect(&address).await?;
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+
+        let status_line = response.lines().next().unwrap_or_default();
+        match status_line.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(Error::Metrics(format!("Pushgateway at {} answered '{}'", url, status_line))),
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut metrics = RunMetrics::new();
+        metrics.record_step("user/create", &StepResult::Passed(Duration::from_millis(20)));
+        metrics.record_step("user/create", &StepResult::Failed(Duration::from_secs(2), "boom".into()));
+        metrics.record_step("user/create", &StepResult::Skipped);
+        metrics.record_retry("user/create");
+
+        let text = metrics.render();
+        assert!(text.contains("rust_actions_steps_total{step=\"user/create\",status=\"passed\"} 1"));
+        assert!(text.contains("rust_actions_steps_total{step=\"user/create\",status=\"failed\"} 1"));
+        assert!(text.contains("rust_actions_step_duration_seconds_bucket{step=\"user/create\",le=\"0.025\"} 1"));
+        assert!(text.contains("rust_actions_step_duration_seconds_bucket{step=\"user/create\",le=\"2.5\"} 2"));
+        assert!(text.contains("rust_actions_step_duration_seconds_count{step=\"user/create\"} 2"));
+        assert!(text.contains("rust_actions_step_retries_total{step=\"user/create\"} 1"));
+        assert!(text.contains("# TYPE rust_actions_step_duration_seconds histogram"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\"b\\c"), "a\\\"b\\\\c");
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_https() {
+        let result = RunMetrics::new().push("https://gateway/metrics/job/x").await;
+        assert!(matches!(result, Err(Error::Metrics(_))));
+    }
+}
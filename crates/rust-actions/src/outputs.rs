@@ -1,9 +1,12 @@
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
+/// A step's outputs. Clones share the values, so a large response body isn't copied into
+/// every expression context that sees it.
 #[derive(Debug, Clone, Default)]
 pub struct StepOutputs {
-    values: HashMap<String, Value>,
+    values: Arc<HashMap<String, Value>>,
 }
 
 impl StepOutputs {
@@ -14,7 +17,7 @@ impl StepOutputs {
     pub fn from_value(value: Value) -> Self {
         match value {
             Value::Object(map) => Self {
-                values: map.into_iter().collect(),
+                values: Arc::new(map.into_iter().collect()),
             },
             _ => Self::default(),
         }
@@ -32,7 +35,7 @@ impl StepOutputs {
     }
 
     pub fn insert(&mut self, key: impl Into<String>, value: impl Into<Value>) {
-        self.values.insert(key.into(), value.into());
+        Arc::make_mut(&mut self.values).insert(key.into(), value.into());
     }
 
     pub fn is_empty(&self) -> bool {
@@ -42,6 +45,105 @@ impl StepOutputs {
     pub fn to_value(&self) -> Value {
         Value::Object(self.values.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
     }
+
+    /// Applies `limit` to each output; see `limit_output`.
+    pub fn limited(self, limit: &OutputLimit) -> Result<Self, String> {
+        if !self.values.iter().any(|(_, v)| serialized_len(v) > limit.max_bytes) {
+            return Ok(self);
+        }
+        let values = Arc::unwrap_or_clone(self.values)
+            .into_iter()
+            .map(|(key, value)| {
+                let value = limit_output(&key, value, limit)?;
+                Ok((key, value))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            values: Arc::new(values),
+        })
+    }
+}
+
+/// Caps the size of each step and job output kept for expressions, checkpoints and results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputLimit {
+    /// Size of an output serialized as JSON (a string's length, plus quotes)
+    pub max_bytes: usize,
+    pub policy: OverflowPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    /// Keep the first `max_bytes` and append `TRUNCATED_MARKER` with the bytes dropped
+    Truncate,
+    /// Fail the step (or job, for job outputs)
+    Fail,
+}
+
+impl OutputLimit {
+    pub fn truncate(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            policy: OverflowPolicy::Truncate,
+        }
+    }
+
+    pub fn fail(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            policy: OverflowPolicy::Fail,
+        }
+    }
+}
+
+/// Appended to truncated outputs, followed by the number of bytes dropped and `]`.
+pub const TRUNCATED_MARKER: &str = "...[truncated ";
+
+/// Returns `value` unchanged if it fits in `limit`. Otherwise, under `Truncate`, a string is
+/// cut at `max_bytes` and anything else becomes its JSON text, cut the same way; under
+/// `Fail`, returns an error naming `key`.
+pub fn limit_output(key: &str, value: Value, limit: &OutputLimit) -> Result<Value, String> {
+    let len = serialized_len(&value);
+    if len <= limit.max_bytes {
+        return Ok(value);
+    }
+    if limit.policy == OverflowPolicy::Fail {
+        return Err(format!(
+            "output '{}' is {} bytes, over the {} byte limit",
+            key, len, limit.max_bytes
+        ));
+    }
+
+    let mut text = match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    };
+    let mut end = limit.max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let dropped = text.len() - end;
+    text.truncate(end);
+    text.push_str(&format!("{}{} bytes]", TRUNCATED_MARKER, dropped));
+    Ok(Value::String(text))
+}
+
+/// Length of `value` as JSON, without building the string.
+fn serialized_len(value: &Value) -> usize {
+    struct Counter(usize);
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
 }
 
 pub trait IntoOutputs {
@@ -59,3 +161,27 @@ impl IntoOutputs for StepOutputs {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_limit_outputs() {
+        let mut outputs = StepOutputs::new();
+        outputs.insert("id", "u-1");
+        outputs.insert("body", "héllo world");
+        outputs.insert("items", json!([1, 2, 3, 4]));
+        let shared = outputs.clone();
+
+        let limited = outputs.limited(&OutputLimit::truncate(8)).unwrap();
+        assert_eq!(limited.get("id"), Some(&json!("u-1")));
+        assert_eq!(limited.get("body"), Some(&json!("héllo w...[truncated 4 bytes]")));
+        assert_eq!(limited.get("items"), Some(&json!("[1,2,3,4...[truncated 1 bytes]")));
+        assert_eq!(shared.get("body"), Some(&json!("héllo world")));
+
+        let err = shared.limited(&OutputLimit::fail(8)).unwrap_err();
+        assert!(err.contains("over the 8 byte limit"));
+    }
+}
@@ -1,20 +1,115 @@
-use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::Result;
+use crate::determinism::SeededRng;
+use crate::envfile;
+use crate::policy::glob_match;
+use crate::expr::{evaluate_assertion, CompiledExpr, ExprContext, MissingKeyLog};
+use crate::schema;
+use crate::{Error, Result};
+
+/// Stage order for workflows that use `stage:` without declaring `stages:`.
+pub const DEFAULT_STAGES: &[&str] = &["setup", "test", "teardown"];
+/// Stage of jobs without a `stage:`, once any job in the workflow has one.
+pub const DEFAULT_STAGE: &str = "test";
+
+/// Names the steps of a workflow's `setup:` and `teardown:` go by in results and validation
+/// reports, where a job's name would be
+pub(crate) const SETUP: &str = "setup:";
+pub(crate) const TEARDOWN: &str = "teardown:";
+/// Name of the result that reports a workflow's `services:` failing to start
+pub(crate) const SERVICES: &str = "services:";
+
+/// Workflow file formats, by extension. All three share the YAML schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkflowFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl WorkflowFormat {
+    /// `None` for files that aren't workflows.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Workflow {
     pub name: String,
+    /// What the workflow tests, shown in listings and reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub extends: Option<String>,
     #[serde(default)]
     pub on: Option<WorkflowTrigger>,
     #[serde(default)]
     pub ignore: Ignore,
+    /// Failing jobs are reported, but don't fail the run
+    #[serde(default)]
+    pub quarantined: bool,
+    /// Workflows (paths relative to the workflows directory) that must run first
+    #[serde(default, rename = "needs-workflow")]
+    pub needs_workflow: JobNeeds,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Dotenv file whose variables `env:` starts from, relative to the workflow file
+    #[serde(default, rename = "env-file", skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
+    /// Containers the jobs talk to, as `${{ containers.<name>.* }}`; started before the first
+    /// job by the runner's `ServiceProvider`
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub services: IndexMap<String, ServiceSpec>,
+    /// Order of the job `stage:` names, when not `setup`, `test`, `teardown`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<String>,
+    /// Steps run once before any job, in a world of their own; their outputs are
+    /// `setup.outputs.*` to every job
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setup: Vec<Step>,
+    /// Steps run in the setup world after every job, even when setup or a job failed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teardown: Vec<Step>,
+    /// In declaration order, which breaks ties between jobs that don't need each other
     #[serde(default)]
-    pub jobs: HashMap<String, Job>,
+    pub jobs: IndexMap<String, Job>,
+    /// Checked after all jobs, against `jobs.<name>.outputs`
+    #[serde(default, alias = "post-assert", rename = "assert-after")]
+    pub post_assert: Vec<String>,
+}
+
+/// One entry of a workflow's `services:`. A `DockerServices` provider starts `image`; an
+/// `ExternalServices` provider only uses `port` and `scheme` to fill in what an endpoint leaves
+/// out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ServiceSpec {
+    /// `name` or `name:tag`; the tag defaults to `latest`
+    pub image: String,
+    /// The container port the jobs connect to
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Of `containers.<name>.url`; defaults to `http`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
+}
+
+impl ServiceSpec {
+    pub fn scheme(&self) -> &str {
+        self.scheme.as_deref().unwrap_or("http")
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -55,6 +150,10 @@ pub struct WorkflowCallConfig {
     pub inputs: HashMap<String, InputDef>,
     #[serde(default)]
     pub outputs: HashMap<String, OutputDef>,
+    /// Runs the workflow once per session for each set of inputs; later callers passing the
+    /// same inputs get the first call's outputs without running its jobs again
+    #[serde(default)]
+    pub memoize: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -80,8 +179,24 @@ pub struct OutputDef {
 pub struct Job {
     #[serde(default)]
     pub name: Option<String>,
+    /// What the job tests, shown in listings and reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     #[serde(default)]
     pub needs: JobNeeds,
+    /// Every job of an earlier stage runs before any job of this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<String>,
+    /// Like the workflow's `quarantined:`, for this job
+    #[serde(default)]
+    pub quarantined: bool,
+    /// Of the jobs whose `needs` have run, higher priorities start first
+    #[serde(default)]
+    pub priority: i32,
+    /// Identity its steps act as, unless a step has its own `as:`; see
+    /// `World::assume_identity`
+    #[serde(default, rename = "as", skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
     #[serde(default)]
     pub uses: Option<String>,
     #[serde(default)]
@@ -92,8 +207,84 @@ pub struct Job {
     pub outputs: HashMap<String, String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Like the workflow's `env-file:`, for this job
+    #[serde(default, rename = "env-file", skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
     #[serde(default)]
     pub steps: Vec<Step>,
+    /// Step ids whose outputs dependents can read as `needs.<job>.steps.<id>.outputs.*`
+    #[serde(default, rename = "export-steps")]
+    pub export_steps: Vec<String>,
+    /// Checked after the steps, against `steps.*` and the job's `outputs:` as `outputs.*`
+    #[serde(default, alias = "post-assert", rename = "assert-after")]
+    pub post_assert: Vec<String>,
+    /// Outputs each `needs` job must have produced, checked before this job starts
+    #[serde(default, rename = "verify-needs", skip_serializing_if = "IndexMap::is_empty")]
+    pub verify_needs: IndexMap<String, IndexMap<String, ValueType>>,
+    /// Fails the job when its duration, all steps included, is out of bounds
+    #[serde(default, rename = "assert-duration", skip_serializing_if = "Option::is_none")]
+    pub assert_duration: Option<DurationAssertion>,
+}
+
+/// A step's `assert-mode:`. Either way, a failed assertion fails the step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssertMode {
+    /// Stop at the first failed `assert-after` assertion
+    #[default]
+    First,
+    /// Check every `assert-after` assertion and report all that failed
+    All,
+}
+
+impl AssertMode {
+    fn is_first(&self) -> bool {
+        *self == AssertMode::First
+    }
+}
+
+/// The JSON type a `verify-needs:` output must have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    String,
+    Number,
+    Boolean,
+    Object,
+    Array,
+    /// Only checks that the output exists
+    Any,
+}
+
+impl ValueType {
+    pub fn of(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(_) => Self::String,
+            serde_json::Value::Number(_) => Self::Number,
+            serde_json::Value::Bool(_) => Self::Boolean,
+            serde_json::Value::Object(_) => Self::Object,
+            serde_json::Value::Array(_) => Self::Array,
+            serde_json::Value::Null => Self::Any,
+        }
+    }
+
+    pub fn matches(self, value: &serde_json::Value) -> bool {
+        self == Self::Any || self == Self::of(value)
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+            Self::Object => "object",
+            Self::Array => "array",
+            Self::Any => "any",
+        };
+        f.write_str(name)
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -103,6 +294,35 @@ pub enum JobNeeds {
     None,
     Single(String),
     Multiple(Vec<String>),
+    /// `needs: { setup: { optional: true, alias: base } }`
+    Detailed(IndexMap<String, Need>),
+}
+
+/// How a job depends on one of its `needs`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Need {
+    /// The job may be missing from the workflow or produce no outputs; `needs.*` reads of it
+    /// are then missing keys, which resolve to null under `missing_keys(Lenient)`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// The name expressions use, as `needs.<alias>.outputs.*`, instead of the job's own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl Need {
+    pub fn optional() -> Self {
+        Self {
+            optional: true,
+            alias: None,
+        }
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = Some(alias.into());
+        self
+    }
 }
 
 impl JobNeeds {
@@ -111,6 +331,7 @@ impl JobNeeds {
             JobNeeds::None => vec![],
             JobNeeds::Single(s) => vec![s.clone()],
             JobNeeds::Multiple(v) => v.clone(),
+            JobNeeds::Detailed(m) => m.keys().cloned().collect(),
         }
     }
 
@@ -119,7 +340,39 @@ impl JobNeeds {
             JobNeeds::None => true,
             JobNeeds::Single(_) => false,
             JobNeeds::Multiple(v) => v.is_empty(),
+            JobNeeds::Detailed(m) => m.is_empty(),
+        }
+    }
+
+    pub fn is_optional(&self, job: &str) -> bool {
+        matches!(self, JobNeeds::Detailed(m) if m.get(job).is_some_and(|need| need.optional))
+    }
+
+    /// The name `job` goes by under `needs.*`: its alias, or its own name.
+    pub fn alias<'a>(&'a self, job: &'a str) -> &'a str {
+        match self {
+            JobNeeds::Detailed(m) => m.get(job).and_then(|need| need.alias.as_deref()).unwrap_or(job),
+            _ => job,
+        }
+    }
+
+    /// Adds a dependency on `job`, keeping the plain list form while no need has options.
+    pub fn push(&mut self, job: impl Into<String>, need: Need) {
+        let job = job.into();
+        if need == Need::default() && !matches!(self, JobNeeds::Detailed(_)) {
+            let mut needs = self.as_vec();
+            if !needs.contains(&job) {
+                needs.push(job);
+            }
+            *self = JobNeeds::Multiple(needs);
+            return;
         }
+        let mut detailed: IndexMap<String, Need> = match std::mem::take(self) {
+            JobNeeds::Detailed(m) => m,
+            plain => plain.as_vec().into_iter().map(|dep| (dep, Need::default())).collect(),
+        };
+        detailed.insert(job, need);
+        *self = JobNeeds::Detailed(detailed);
     }
 }
 
@@ -131,6 +384,54 @@ pub struct Strategy {
     pub fail_fast: bool,
     #[serde(default, rename = "max-parallel")]
     pub max_parallel: Option<usize>,
+    /// Runs a subset of the matrix's combinations; `include:` entries always run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<MatrixSample>,
+}
+
+/// `sample: pairwise`, or `sample: { random: 20, seed: 7 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "SampleSource", into = "SampleSource")]
+pub enum MatrixSample {
+    /// Enough combinations that every pair of values of any two dimensions runs together
+    Pairwise,
+    /// `count` combinations, picked with an RNG seeded by `seed`
+    Random { count: usize, seed: u64 },
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum SampleSource {
+    Mode(String),
+    Random {
+        random: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl TryFrom<SampleSource> for MatrixSample {
+    type Error = String;
+
+    fn try_from(source: SampleSource) -> std::result::Result<Self, String> {
+        match source {
+            SampleSource::Mode(mode) if mode == "pairwise" => Ok(MatrixSample::Pairwise),
+            SampleSource::Mode(mode) => Err(format!(
+                "unknown matrix sample '{}' (expected pairwise or {{ random: N, seed: S }})",
+                mode
+            )),
+            SampleSource::Random { random, seed } => Ok(MatrixSample::Random { count: random, seed }),
+        }
+    }
+}
+
+impl From<MatrixSample> for SampleSource {
+    fn from(sample: MatrixSample) -> Self {
+        match sample {
+            MatrixSample::Pairwise => SampleSource::Mode("pairwise".to_string()),
+            MatrixSample::Random { count, seed } => SampleSource::Random { random: count, seed },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -139,8 +440,199 @@ pub struct Matrix {
     pub include: Vec<HashMap<String, serde_json::Value>>,
     #[serde(default)]
     pub exclude: Vec<HashMap<String, serde_json::Value>>,
-    #[serde(flatten)]
-    pub dimensions: HashMap<String, Vec<serde_json::Value>>,
+    /// A dimension may be a `${{ }}` string instead of a list, kept as its only value until
+    /// `Workflow::resolve_matrices` replaces it
+    #[serde(flatten, deserialize_with = "deserialize_dimensions")]
+    pub dimensions: IndexMap<String, Vec<serde_json::Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Dimension {
+    Values(Vec<Value>),
+    Expr(String),
+}
+
+fn deserialize_dimensions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<IndexMap<String, Vec<Value>>, D::Error> {
+    let dimensions = IndexMap::<String, Dimension>::deserialize(deserializer)?;
+    Ok(dimensions
+        .into_iter()
+        .map(|(key, dimension)| match dimension {
+            Dimension::Values(values) => (key, values),
+            Dimension::Expr(expr) => (key, vec![Value::String(expr)]),
+        })
+        .collect())
+}
+
+/// A step's `delay:`: a fixed pause like `500ms`, or a range like `1s..3s` sampled per step
+/// from a seeded RNG. A bare number is milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "DelaySource", into = "String")]
+pub enum Delay {
+    Fixed(Duration),
+    Range(Duration, Duration),
+}
+
+impl Delay {
+    /// The pause to take, drawn from `rng` for a range.
+    pub fn sample(&self, rng: &mut SeededRng) -> Duration {
+        match *self {
+            Delay::Fixed(delay) => delay,
+            Delay::Range(min, max) if max > min => {
+                let nanos = rng.next_range(min.as_nanos() as u64, max.as_nanos() as u64 + 1);
+                Duration::from_nanos(nanos)
+            }
+            Delay::Range(min, _) => min,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DelaySource {
+    Millis(u64),
+    Text(String),
+}
+
+impl TryFrom<DelaySource> for Delay {
+    type Error = String;
+
+    fn try_from(source: DelaySource) -> std::result::Result<Self, String> {
+        let text = match source {
+            DelaySource::Millis(ms) => return Ok(Delay::Fixed(Duration::from_millis(ms))),
+            DelaySource::Text(text) => text,
+        };
+        match text.split_once("..") {
+            Some((min, max)) => {
+                let (min, max) = (parse_duration(min)?, parse_duration(max)?);
+                if min > max {
+                    return Err(format!("delay range '{}' ends before it starts", text));
+                }
+                Ok(Delay::Range(min, max))
+            }
+            None => parse_duration(&text).map(Delay::Fixed),
+        }
+    }
+}
+
+impl From<Delay> for String {
+    fn from(delay: Delay) -> Self {
+        match delay {
+            Delay::Fixed(delay) => format!("{}ms", delay.as_millis()),
+            Delay::Range(min, max) => format!("{}ms..{}ms", min.as_millis(), max.as_millis()),
+        }
+    }
+}
+
+/// An `assert-duration:` like `< 2s` or `>= 100ms`, checked against the simulated duration of
+/// a step or job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DurationAssertion {
+    op: DurationOp,
+    limit: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DurationOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl DurationOp {
+    // Two-character operators first, so `<=` isn't read as `<`
+    const ALL: [DurationOp; 4] = [DurationOp::Le, DurationOp::Ge, DurationOp::Lt, DurationOp::Gt];
+
+    fn symbol(self) -> &'static str {
+        match self {
+            DurationOp::Lt => "<",
+            DurationOp::Le => "<=",
+            DurationOp::Gt => ">",
+            DurationOp::Ge => ">=",
+        }
+    }
+}
+
+impl DurationAssertion {
+    /// `< limit`, the usual performance bound.
+    pub fn less_than(limit: Duration) -> Self {
+        Self { op: DurationOp::Lt, limit }
+    }
+
+    /// `>= limit`, e.g. for a step that must wait out a backoff.
+    pub fn at_least(limit: Duration) -> Self {
+        Self { op: DurationOp::Ge, limit }
+    }
+
+    pub fn holds(&self, duration: Duration) -> bool {
+        match self.op {
+            DurationOp::Lt => duration < self.limit,
+            DurationOp::Le => duration <= self.limit,
+            DurationOp::Gt => duration > self.limit,
+            DurationOp::Ge => duration >= self.limit,
+        }
+    }
+
+    /// `None` when `duration` satisfies the assertion, else why it doesn't.
+    pub fn check(&self, duration: Duration) -> Option<String> {
+        match self.holds(duration) {
+            true => None,
+            false => Some(format!("Duration assertion failed: took {:?}, expected {}", duration, self)),
+        }
+    }
+}
+
+impl std::fmt::Display for DurationAssertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}ms", self.op.symbol(), self.limit.as_millis())
+    }
+}
+
+impl TryFrom<String> for DurationAssertion {
+    type Error = String;
+
+    fn try_from(text: String) -> std::result::Result<Self, String> {
+        let text = text.trim();
+        let op = DurationOp::ALL
+            .into_iter()
+            .find(|op| text.starts_with(op.symbol()))
+            .ok_or_else(|| format!("assert-duration '{}' should start with <, <=, > or >=", text))?;
+        let limit = parse_duration(&text[op.symbol().len()..])?;
+        Ok(Self { op, limit })
+    }
+}
+
+impl std::str::FromStr for DurationAssertion {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, String> {
+        Self::try_from(text.to_string())
+    }
+}
+
+impl From<DurationAssertion> for String {
+    fn from(assertion: DurationAssertion) -> Self {
+        assertion.to_string()
+    }
+}
+
+/// `250ms`, `2s`, `1.5s` or `1m`.
+fn parse_duration(text: &str) -> std::result::Result<Duration, String> {
+    let text = text.trim();
+    let invalid = || format!("invalid duration '{}' (expected e.g. 500ms, 2s or 1m)", text);
+    let split = text.find(|c: char| c.is_ascii_alphabetic()).ok_or_else(invalid)?;
+    let amount: f64 = text[..split].trim().parse().map_err(|_| invalid())?;
+    let seconds = match &text[split..] {
+        "ms" => amount / 1000.0,
+        "s" => amount,
+        "m" => amount * 60.0,
+        _ => return Err(invalid()),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
 }
 
 fn default_true() -> bool {
@@ -151,28 +643,296 @@ fn default_true() -> bool {
 pub struct Step {
     #[serde(default)]
     pub name: Option<String>,
+    /// What the step does, shown in the HTML report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     #[serde(default)]
     pub id: Option<String>,
     pub uses: String,
+    /// Identity the step acts as, instead of the job's `as:`
+    #[serde(default, rename = "as", skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
     #[serde(default)]
     pub with: HashMap<String, serde_json::Value>,
     #[serde(default, rename = "continue-on-error")]
     pub continue_on_error: bool,
+    #[serde(default)]
+    pub setup: bool,
+    #[serde(default, rename = "mask-outputs")]
+    pub mask_outputs: Vec<String>,
     #[serde(default, alias = "pre-assert", rename = "assert-before")]
     pub pre_assert: Vec<String>,
     #[serde(default, alias = "post-assert", rename = "assert-after")]
     pub post_assert: Vec<String>,
+    /// Whether `assert-after` stops at the first failed assertion or reports them all
+    #[serde(default, rename = "assert-mode", skip_serializing_if = "AssertMode::is_first")]
+    pub assert_mode: AssertMode,
+    /// Expected outputs, checked before `assert-after` (see `expect::check_expect`)
+    #[serde(default)]
+    pub expect: Option<serde_json::Value>,
+    /// Steps deferred to the end of the job once this one has run; `with:` sees `outputs.*`
+    #[serde(default)]
+    pub cleanup: Vec<CleanupStep>,
+    /// Undoes this step if a later step of the job fails; `with:` sees `outputs.*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compensate: Option<CleanupStep>,
+    /// Values stored as `vars.<name>` for the rest of the job once the step passes, e.g.
+    /// `order_id: outputs.body.data.id`; see `expr::capture_template`
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub capture: IndexMap<String, String>,
+    /// Pause before the step runs, e.g. to let asynchronous propagation catch up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<Delay>,
+    /// Max executions per second of this step name across the suite; 0 disables a suite limit
+    #[serde(default, rename = "rate-limit")]
+    pub rate_limit: Option<f64>,
+    /// Step ids this step waits on; see `runner::plan_steps`
+    #[serde(default, rename = "depends-on")]
+    pub depends_on: JobNeeds,
+    /// Fails the step when its duration is out of bounds, e.g. `< 2s`
+    #[serde(default, rename = "assert-duration", skip_serializing_if = "Option::is_none")]
+    pub assert_duration: Option<DurationAssertion>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CleanupStep {
+    pub uses: String,
+    #[serde(default)]
+    pub with: HashMap<String, serde_json::Value>,
+}
+
+impl Step {
+    /// The step's `id`, else its `name`, else its `uses`.
+    pub fn label(&self) -> &str {
+        self.id
+            .as_deref()
+            .or(self.name.as_deref())
+            .unwrap_or(&self.uses)
+    }
+
+    /// Step names this step invokes: its own `uses:` and those of its cleanup and compensating
+    /// steps.
+    pub fn all_uses(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.uses.as_str())
+            .chain(self.cleanup.iter().map(|c| c.uses.as_str()))
+            .chain(self.compensate.iter().map(|c| c.uses.as_str()))
+    }
 }
 
 impl Workflow {
+    /// Parses a workflow, rejecting unknown keys (see `from_yaml_with`).
     pub fn from_yaml(yaml: &str) -> Result<Self> {
-        let workflow: Workflow = serde_yaml::from_str(yaml)?;
+        Self::from_yaml_with(yaml, true)
+    }
+
+    /// With `strict`, keys the parser doesn't know (e.g. `post_assert:` for `post-assert:`)
+    /// are an `Error::UnknownFields` instead of being ignored.
+    pub fn from_yaml_with(yaml: &str, strict: bool) -> Result<Self> {
+        Self::from_str_as(yaml, WorkflowFormat::Yaml, strict)
+    }
+
+    /// Like `from_yaml_with`, for a workflow in any of the supported formats.
+    pub fn from_str_as(content: &str, format: WorkflowFormat, strict: bool) -> Result<Self> {
+        let mut workflow = Self::parse(content, format, strict, "<inline>")?;
+        workflow.load_env_files(Path::new("."))?;
+        workflow.resolve_matrices()?;
         Ok(workflow)
     }
 
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_file_with(path, true)
+    }
+
+    pub fn from_file_with(path: impl AsRef<Path>, strict: bool) -> Result<Self> {
+        let mut workflow = Self::from_file_with_chain(path.as_ref(), strict, &mut Vec::new())?;
+        workflow.resolve_matrices()?;
+        Ok(workflow)
+    }
+
+    fn parse(content: &str, format: WorkflowFormat, strict: bool, file: &str) -> Result<Self> {
+        if strict {
+            let document: serde_yaml::Value = match format {
+                WorkflowFormat::Yaml => serde_yaml::from_str(content)?,
+                WorkflowFormat::Json => serde_json::from_str(content)?,
+                WorkflowFormat::Toml => toml::from_str(content)?,
+            };
+            let unknown = schema::unknown_fields(&document);
+            if !unknown.is_empty() {
+                return Err(Error::UnknownFields {
+                    file: file.to_string(),
+                    fields: unknown
+                        .iter()
+                        .map(|f| format!("  - {}", f))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                });
+            }
+        }
+        // Each format's own deserializer, so errors point at the right line
+        Ok(match format {
+            WorkflowFormat::Yaml => serde_yaml::from_str(content)?,
+            WorkflowFormat::Json => serde_json::from_str(content)?,
+            WorkflowFormat::Toml => toml::from_str(content)?,
+        })
+    }
+
+    fn from_file_with_chain(path: &Path, strict: bool, chain: &mut Vec<PathBuf>) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
-        Self::from_yaml(&content)
+        let format = WorkflowFormat::from_path(path).unwrap_or(WorkflowFormat::Yaml);
+        let mut workflow = Self::parse(&content, format, strict, &path.display().to_string())?;
+        workflow.load_env_files(path.parent().unwrap_or(Path::new(".")))?;
+
+        let Some(base_ref) = workflow.extends.clone() else {
+            return Ok(workflow);
+        };
+
+        let canonical = path.canonicalize()?;
+        if chain.contains(&canonical) {
+            chain.push(canonical);
+            return Err(Error::CircularDependency {
+                chain: chain
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> "),
+            });
+        }
+        chain.push(canonical);
+
+        // Relative to the extending file
+        let base_path = path.parent().unwrap_or(Path::new(".")).join(&base_ref);
+        if !base_path.is_file() {
+            return Err(Error::WorkflowNotFound {
+                path: base_path.display().to_string(),
+            });
+        }
+        let base = Self::from_file_with_chain(&base_path, strict, chain)?;
+
+        Ok(workflow.merge_base(base))
+    }
+
+    /// Every list of steps in the workflow with the job it belongs to, `setup:` and
+    /// `teardown:` included.
+    pub fn step_lists(&self) -> impl Iterator<Item = (&str, &[Step])> {
+        std::iter::once((SETUP, self.setup.as_slice()))
+            .chain(self.jobs.iter().map(|(name, job)| (name.as_str(), job.steps.as_slice())))
+            .chain(std::iter::once((TEARDOWN, self.teardown.as_slice())))
+    }
+
+    /// Applies `extends:` semantics: env is merged key by key, `needs-workflow` entries are
+    /// combined, and jobs are inherited unless redefined (a redefined job replaces the base job
+    /// entirely, in the base job's place), and so are services. `stages`, `setup` and `teardown`
+    /// are inherited unless redeclared. `name`, `on`, `ignore` and `quarantined` always come from the extending
+    /// workflow.
+    pub fn merge_base(mut self, base: Workflow) -> Self {
+        let mut needs = base.needs_workflow.as_vec();
+        for dep in self.needs_workflow.as_vec() {
+            if !needs.contains(&dep) {
+                needs.push(dep);
+            }
+        }
+        if !needs.is_empty() {
+            self.needs_workflow = JobNeeds::Multiple(needs);
+        }
+
+        let mut env = base.env;
+        env.extend(std::mem::take(&mut self.env));
+        self.env = env;
+
+        let mut services = base.services;
+        for (name, service) in std::mem::take(&mut self.services) {
+            services.insert(name, service);
+        }
+        self.services = services;
+
+        if self.stages.is_empty() {
+            self.stages = base.stages;
+        }
+        if self.setup.is_empty() {
+            self.setup = base.setup;
+        }
+        if self.teardown.is_empty() {
+            self.teardown = base.teardown;
+        }
+
+        let mut jobs = base.jobs;
+        for (name, job) in std::mem::take(&mut self.jobs) {
+            jobs.insert(name, job);
+        }
+        self.jobs = jobs;
+
+        self
+    }
+
+    /// Fills `env` from `env-file:` at the workflow and job level, resolved against `dir`.
+    /// Keys set in `env:` itself win over the file's.
+    pub fn load_env_files(&mut self, dir: &Path) -> Result<()> {
+        fn load(dir: &Path, file: &Option<String>, env: &mut HashMap<String, String>) -> Result<()> {
+            if let Some(file) = file {
+                let mut vars = envfile::load(&dir.join(file))?;
+                vars.extend(std::mem::take(env));
+                *env = vars;
+            }
+            Ok(())
+        }
+
+        load(dir, &self.env_file, &mut self.env)?;
+        for job in self.jobs.values_mut() {
+            load(dir, &job.env_file, &mut job.env)?;
+        }
+        Ok(())
+    }
+
+    /// Resolves matrix dimensions written as an expression, e.g.
+    /// `region: ${{ fromJSON(env.REGIONS) }}`, against the workflow and job `env:`. Process
+    /// environment variables take precedence, so CI can widen a matrix without editing YAML.
+    /// `include:` and `exclude:` entries with an `if:` are kept only when it holds; a missing
+    /// variable compares as `null`.
+    pub fn resolve_matrices(&mut self) -> Result<()> {
+        for (job_name, job) in &mut self.jobs {
+            let Some(strategy) = &mut job.strategy else {
+                continue;
+            };
+            let mut env = self.env.clone();
+            env.extend(job.env.clone());
+            env.extend(std::env::vars());
+            let mut ctx = ExprContext::new();
+            ctx.env = Arc::new(env);
+
+            let matrix = &mut strategy.matrix;
+            let path = format!("jobs.{}.strategy.matrix", job_name);
+            matrix.include = conditional_entries(
+                std::mem::take(&mut matrix.include),
+                &ctx,
+                &format!("{}.include", path),
+            )?;
+            matrix.exclude = conditional_entries(
+                std::mem::take(&mut matrix.exclude),
+                &ctx,
+                &format!("{}.exclude", path),
+            )?;
+
+            for (key, values) in &mut strategy.matrix.dimensions {
+                let [Value::String(expr)] = values.as_slice() else {
+                    continue;
+                };
+                if !expr.contains("${{") {
+                    continue;
+                }
+                let location = format!("jobs.{}.strategy.matrix.{}", job_name, key);
+                *values = match CompiledExpr::parse(expr).evaluate_value(&ctx) {
+                    Ok(Value::Array(resolved)) => resolved,
+                    Ok(other) => {
+                        return Err(Error::Expression(format!(
+                            "{}: expected a list, got {}",
+                            location, other
+                        )))
+                    }
+                    Err(e) => return Err(Error::Expression(format!("{}: {}", location, e))),
+                };
+            }
+        }
+        Ok(())
     }
 
     pub fn is_reusable(&self) -> bool {
@@ -183,53 +943,226 @@ impl Workflow {
     }
 }
 
-pub fn parse_workflows(path: impl AsRef<Path>) -> Result<Vec<(PathBuf, Workflow)>> {
-    let path = path.as_ref();
-    let mut workflows = Vec::new();
+/// The matrix `include:` or `exclude:` entries whose `if:` holds, without it.
+fn conditional_entries(
+    entries: Vec<HashMap<String, Value>>,
+    ctx: &ExprContext,
+    location: &str,
+) -> Result<Vec<HashMap<String, Value>>> {
+    let mut ctx = ctx.clone();
+    ctx.missing_keys = Some(Arc::new(MissingKeyLog::default()));
+    let mut kept = Vec::new();
+    for (i, mut entry) in entries.into_iter().enumerate() {
+        let holds = match entry.remove("if") {
+            None => true,
+            Some(Value::Bool(holds)) => holds,
+            Some(Value::String(condition)) => evaluate_assertion(&condition, &ctx)
+                .map_err(|e| Error::Expression(format!("{}[{}].if: {}", location, i, e)))?,
+            Some(other) => {
+                return Err(Error::Expression(format!(
+                    "{}[{}].if: expected a condition, got {}",
+                    location, i, other
+                )))
+            }
+        };
+        if holds {
+            kept.push(entry);
+        }
+    }
+    Ok(kept)
+}
+
+/// Which files under a workflows directory are workflows, by path relative to it. Patterns
+/// without a `/` match the file name at any depth; others match the whole path, where `*`
+/// stays within a directory and `**` spans any number of them.
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl WorkflowFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Once any is given, only files matching one of the include patterns are workflows.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Whether the file at `path`, relative to the workflows directory, is a workflow.
+    pub fn matches(&self, path: &Path) -> bool {
+        WorkflowFormat::from_path(path).is_some()
+            && (self.include.is_empty() || self.include.iter().any(|p| path_glob_match(p, path)))
+            && !self.exclude.iter().any(|p| path_glob_match(p, path))
+    }
+}
+
+fn path_glob_match(pattern: &str, path: &Path) -> bool {
+    let segments: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if !pattern.contains('/') {
+        return segments.last().is_some_and(|name| glob_match(pattern, name));
+    }
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    segments_match(&pattern, &segments)
+}
+
+fn segments_match(pattern: &[&str], segments: &[String]) -> bool {
+    match pattern.split_first() {
+        None => segments.is_empty(),
+        Some((&"**", rest)) => (0..=segments.len()).any(|i| segments_match(rest, &segments[i..])),
+        Some((head, rest)) => segments
+            .split_first()
+            .is_some_and(|(segment, tail)| glob_match(head, segment) && segments_match(rest, tail)),
+    }
+}
 
+pub fn parse_workflows(path: impl AsRef<Path>, strict: bool) -> Result<Vec<(PathBuf, Workflow)>> {
+    parse_workflows_filtered(path, strict, &WorkflowFilter::default())
+}
+
+/// Like `parse_workflows`, but a directory's files are only parsed when `filter` matches them.
+pub fn parse_workflows_filtered(
+    path: impl AsRef<Path>,
+    strict: bool,
+    filter: &WorkflowFilter,
+) -> Result<Vec<(PathBuf, Workflow)>> {
+    let path = path.as_ref();
     if path.is_file() {
-        workflows.push((path.to_path_buf(), Workflow::from_file(path)?));
-    } else if path.is_dir() {
-        parse_workflows_recursive(path, path, &mut workflows)?;
+        return Ok(vec![(path.to_path_buf(), Workflow::from_file_with(path, strict)?)]);
     }
+    discover_workflow_files(path, filter)?
+        .into_iter()
+        .map(|rel_path| {
+            let workflow = Workflow::from_file_with(path.join(&rel_path), strict)?;
+            Ok((rel_path, workflow))
+        })
+        .collect()
+}
 
-    Ok(workflows)
+/// Paths of the workflow files under `dir`, relative to it, without parsing them.
+pub fn discover_workflow_files(dir: impl AsRef<Path>, filter: &WorkflowFilter) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let mut files = Vec::new();
+    if dir.is_dir() {
+        discover_recursive(dir, dir, filter, &mut files)?;
+    }
+    Ok(files)
 }
 
-fn parse_workflows_recursive(
+fn discover_recursive(
     base_path: &Path,
     current_path: &Path,
-    workflows: &mut Vec<(PathBuf, Workflow)>,
+    filter: &WorkflowFilter,
+    files: &mut Vec<PathBuf>,
 ) -> Result<()> {
-    for entry in std::fs::read_dir(current_path)? {
-        let entry = entry?;
-        let path = entry.path();
+    // Sorted, so discovery order doesn't depend on the filesystem
+    let mut paths = std::fs::read_dir(current_path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
 
+    for path in paths {
         if path.is_dir() {
-            parse_workflows_recursive(base_path, &path, workflows)?;
+            discover_recursive(base_path, &path, filter, files)?;
         } else if path.is_file() {
-            let ext = path.extension().and_then(|e| e.to_str());
-            if matches!(ext, Some("yaml") | Some("yml")) {
-                let rel_path = path
-                    .strip_prefix(base_path)
-                    .unwrap_or(&path)
-                    .to_path_buf();
-                workflows.push((rel_path, Workflow::from_file(&path)?));
+            let rel_path = path
+                .strip_prefix(base_path)
+                .unwrap_or(&path)
+                .to_path_buf();
+            if filter.matches(&rel_path) {
+                files.push(rel_path);
             }
         }
     }
     Ok(())
 }
 
-pub fn parse_workflow_file(path: impl AsRef<Path>) -> Result<(PathBuf, Workflow)> {
+pub fn parse_workflow_file(path: impl AsRef<Path>, strict: bool) -> Result<(PathBuf, Workflow)> {
     let path = path.as_ref();
-    Ok((path.to_path_buf(), Workflow::from_file(path)?))
+    Ok((path.to_path_buf(), Workflow::from_file_with(path, strict)?))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_matrix_from_env() {
+        let yaml = r#"
+name: Regions
+env:
+  REGIONS: '["eu", "us"]'
+jobs:
+  deploy:
+    env:
+      TIERS: '[1, 2, 3]'
+    strategy:
+      matrix:
+        region: ${{ fromJSON(env.REGIONS) }}
+        tier: ${{ fromJSON(env.TIERS) }}
+        os: [linux]
+    steps:
+      - uses: deploy/check
+"#;
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let dimensions = &workflow.jobs["deploy"].strategy.as_ref().unwrap().matrix.dimensions;
+        assert_eq!(dimensions["region"], vec![Value::from("eu"), Value::from("us")]);
+        assert_eq!(dimensions["tier"].len(), 3);
+        assert_eq!(dimensions["os"], vec![Value::from("linux")]);
+
+        let not_a_list = yaml.replace("fromJSON(env.REGIONS)", "env.REGIONS");
+        assert!(Workflow::from_yaml(&not_a_list).is_err());
+    }
+
+    #[test]
+    fn test_conditional_matrix_entries() {
+        let yaml = r#"
+name: Regions
+env:
+  RUN_EXPERIMENTAL: "true"
+jobs:
+  deploy:
+    strategy:
+      matrix:
+        region: [eu, us]
+        include:
+          - region: ap
+            experimental: true
+            if: ${{ env.RUN_EXPERIMENTAL == 'true' }}
+          - region: sa
+            if: ${{ env.RUST_ACTIONS_TEST_UNSET_FLAG == 'true' }}
+          - region: af
+        exclude:
+          - region: us
+            if: false
+    steps:
+      - uses: deploy/check
+"#;
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let matrix = &workflow.jobs["deploy"].strategy.as_ref().unwrap().matrix;
+        let regions: Vec<&Value> = matrix.include.iter().map(|entry| &entry["region"]).collect();
+        assert_eq!(regions, vec!["ap", "af"]);
+        assert!(matrix.include.iter().all(|entry| !entry.contains_key("if")));
+        assert!(matrix.exclude.is_empty());
+
+        let not_a_condition = yaml.replace("if: false", "if: 3");
+        assert!(matches!(
+            Workflow::from_yaml(&not_a_condition),
+            Err(Error::Expression(msg)) if msg.contains("jobs.deploy.strategy.matrix.exclude[0].if")
+        ));
+    }
+
     #[test]
     fn test_parse_reusable_workflow() {
         let yaml = r#"
@@ -331,6 +1264,99 @@ jobs:
         assert_eq!(strategy.matrix.dimensions["service_a_feature_x"].len(), 2);
     }
 
+    #[test]
+    fn test_extends_merges_base() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("base")).unwrap();
+        std::fs::write(
+            dir.path().join("base/common.yaml"),
+            r#"
+name: Common
+ignore: base workflow
+env:
+  REGION: eu
+  TIER: free
+jobs:
+  setup:
+    steps:
+      - uses: tenant/create
+  check:
+    steps:
+      - uses: check/basic
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("pro.yaml"),
+            r#"
+name: Pro
+extends: base/common.yaml
+env:
+  TIER: pro
+jobs:
+  check:
+    needs: setup
+    steps:
+      - uses: check/pro
+"#,
+        )
+        .unwrap();
+
+        let workflow = Workflow::from_file(dir.path().join("pro.yaml")).unwrap();
+        assert_eq!(workflow.name, "Pro");
+        assert!(!workflow.ignore.is_ignored());
+        assert_eq!(workflow.env["REGION"], "eu");
+        assert_eq!(workflow.env["TIER"], "pro");
+        assert_eq!(workflow.jobs["setup"].steps[0].uses, "tenant/create");
+        assert_eq!(workflow.jobs["check"].steps[0].uses, "check/pro");
+    }
+
+    #[test]
+    fn test_env_file_fills_env() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("env")).unwrap();
+        std::fs::write(dir.path().join("env/staging.env"), "API_URL=https://staging\nREGIONS='[\"eu\", \"us\"]'\n").unwrap();
+        std::fs::write(dir.path().join("env/admin.env"), "API_TOKEN=admin-token\n").unwrap();
+        std::fs::write(
+            dir.path().join("deploy.yaml"),
+            r#"
+name: Deploy
+env-file: env/staging.env
+env:
+  API_URL: https://override
+jobs:
+  deploy:
+    env-file: env/admin.env
+    strategy:
+      matrix:
+        region: ${{ fromJSON(env.REGIONS) }}
+    steps:
+      - uses: deploy/run
+"#,
+        )
+        .unwrap();
+
+        let workflow = Workflow::from_file(dir.path().join("deploy.yaml")).unwrap();
+        assert_eq!(workflow.env["API_URL"], "https://override");
+        let job = &workflow.jobs["deploy"];
+        assert_eq!(job.env["API_TOKEN"], "admin-token");
+        assert_eq!(job.strategy.as_ref().unwrap().matrix.dimensions["region"].len(), 2);
+
+        std::fs::remove_file(dir.path().join("env/admin.env")).unwrap();
+        let missing = Workflow::from_file(dir.path().join("deploy.yaml")).unwrap_err();
+        assert!(matches!(missing, Error::EnvFile { path, .. } if path.ends_with("admin.env")));
+    }
+
+    #[test]
+    fn test_extends_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.yaml"), "name: A\nextends: b.yaml\n").unwrap();
+        std::fs::write(dir.path().join("b.yaml"), "name: B\nextends: a.yaml\n").unwrap();
+
+        let result = Workflow::from_file(dir.path().join("a.yaml"));
+        assert!(matches!(result, Err(Error::CircularDependency { .. })));
+    }
+
     #[test]
     fn test_parse_matrix_with_include_exclude() {
         let yaml = r#"
@@ -364,4 +1390,162 @@ jobs:
             serde_json::Value::Bool(true)
         );
     }
+
+    #[test]
+    fn test_json_and_toml_workflows() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("base.toml"),
+            r#"
+name = "Base"
+
+[jobs.create]
+steps = [{ uses = "user/create", with = { username = "alice" } }]
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("generated.json"),
+            r#"{
+  "name": "Generated",
+  "extends": "base.toml",
+  "jobs": {
+    "check": {
+      "needs": "create",
+      "strategy": { "matrix": { "region": ["eu", "us"] } },
+      "steps": [{ "uses": "user/find", "assert-after": ["${{ outputs.found }}"] }]
+    }
+  }
+}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a workflow").unwrap();
+
+        let workflows = parse_workflows(dir.path(), true).unwrap();
+        let paths: Vec<&Path> = workflows.iter().map(|(p, _)| p.as_path()).collect();
+        assert_eq!(paths, vec![Path::new("base.toml"), Path::new("generated.json")]);
+
+        let generated = &workflows[1].1;
+        let jobs: Vec<&str> = generated.jobs.keys().map(String::as_str).collect();
+        assert_eq!(jobs, vec!["create", "check"]);
+        assert_eq!(generated.jobs["create"].steps[0].with["username"], "alice");
+        assert_eq!(generated.jobs["check"].steps[0].post_assert, vec!["${{ outputs.found }}"]);
+
+        let unknown = Workflow::from_str_as(r#"{"name": "x", "job": {}}"#, WorkflowFormat::Json, true);
+        assert!(matches!(unknown, Err(Error::UnknownFields { .. })));
+    }
+
+    #[test]
+    fn test_parse_delay() {
+        let yaml = r#"
+name: Paced
+jobs:
+  poll:
+    steps:
+      - uses: a
+        delay: 500ms
+      - uses: b
+        delay: 1s..1.5s
+      - uses: c
+        delay: 250
+"#;
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let delays: Vec<Option<Delay>> = workflow.jobs["poll"].steps.iter().map(|s| s.delay).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Delay::Fixed(Duration::from_millis(500))),
+                Some(Delay::Range(Duration::from_secs(1), Duration::from_millis(1500))),
+                Some(Delay::Fixed(Duration::from_millis(250))),
+            ]
+        );
+
+        let mut rng = SeededRng::with_seed(3);
+        let sampled = delays[1].unwrap().sample(&mut rng);
+        assert!((Duration::from_secs(1)..=Duration::from_millis(1500)).contains(&sampled));
+
+        for bad in ["5 seconds", "2s..1s", "fast"] {
+            let yaml = format!("name: X\njobs:\n  j:\n    steps:\n      - uses: a\n        delay: {}\n", bad);
+            assert!(Workflow::from_yaml(&yaml).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_assert_duration() {
+        let yaml = r#"
+name: Latency
+jobs:
+  search:
+    assert-duration: "<= 1m"
+    steps:
+      - uses: search/find
+        assert-duration: "< 2s"
+"#;
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let job = &workflow.jobs["search"];
+        let step = job.steps[0].assert_duration.unwrap();
+        assert!(step.holds(Duration::from_millis(1999)));
+        assert!(!step.holds(Duration::from_secs(2)));
+        assert!(job.assert_duration.unwrap().holds(Duration::from_secs(60)));
+        assert_eq!(
+            step.check(Duration::from_secs(3)).unwrap(),
+            "Duration assertion failed: took 3s, expected < 2000ms"
+        );
+
+        for bad in ["2s", "< fast", "== 1s"] {
+            assert!(bad.parse::<DurationAssertion>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_needs_options() {
+        let yaml = r#"
+name: Checkout
+jobs:
+  setup:
+    steps:
+      - uses: a
+  order:
+    needs:
+      setup: { optional: true, alias: base }
+      login: {}
+    steps:
+      - uses: b
+"#;
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let needs = &workflow.jobs["order"].needs;
+        assert_eq!(needs.as_vec(), vec!["setup", "login"]);
+        assert!(needs.is_optional("setup"));
+        assert!(!needs.is_optional("login"));
+        assert_eq!(needs.alias("setup"), "base");
+        assert_eq!(needs.alias("login"), "login");
+
+        let mut plain = JobNeeds::Single("setup".into());
+        plain.push("login", Need::default());
+        assert!(matches!(&plain, JobNeeds::Multiple(v) if v == &["setup", "login"]));
+        plain.push("seed", Need::optional());
+        assert!(plain.is_optional("seed"));
+        assert_eq!(plain.as_vec(), vec!["setup", "login", "seed"]);
+    }
+
+    #[test]
+    fn test_workflow_filter() {
+        let filter = WorkflowFilter::new()
+            .include("**/*.test.yaml")
+            .include("smoke/*")
+            .exclude("fixtures/**")
+            .exclude("*.wip.test.yaml");
+        assert!(filter.matches(Path::new("checkout.test.yaml")));
+        assert!(filter.matches(Path::new("orders/refund.test.yaml")));
+        assert!(filter.matches(Path::new("smoke/ping.yml")));
+        assert!(!filter.matches(Path::new("smoke/nested/ping.yml")));
+        assert!(!filter.matches(Path::new("k8s/deployment.yaml")));
+        assert!(!filter.matches(Path::new("fixtures/users.test.yaml")));
+        assert!(!filter.matches(Path::new("orders/draft.wip.test.yaml")));
+        assert!(!filter.matches(Path::new("smoke/notes.txt")));
+
+        let everything = WorkflowFilter::new();
+        assert!(everything.matches(Path::new("k8s/deployment.yaml")));
+        assert!(!everything.matches(Path::new("README.md")));
+    }
 }
@@ -0,0 +1,83 @@
+/// Allow/deny rules for step names. Patterns match the whole name, with `*` matching any
+/// sequence of characters (`admin/*`, `*/delete`).
+///
+/// A step is allowed when it matches no deny pattern and, if any allow patterns are set,
+/// at least one of them.
+#[derive(Debug, Clone, Default)]
+pub struct StepPolicy {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl StepPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    pub fn deny(mut self, pattern: impl Into<String>) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    pub fn is_allowed(&self, step: &str) -> bool {
+        if self.deny.iter().any(|p| glob_match(p, step)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|p| glob_match(p, step))
+    }
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            if rest.is_empty() {
+                return true;
+            }
+            text.char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(text.len()))
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("admin/*", "admin/reset"));
+        assert!(!glob_match("admin/*", "user/admin"));
+        assert!(glob_match("*/delete", "user/delete"));
+        assert!(glob_match("db/*/drop", "db/users/drop"));
+        assert!(glob_match("db/execute", "db/execute"));
+        assert!(!glob_match("db/execute", "db/executes"));
+    }
+
+    #[test]
+    fn test_allow_and_deny() {
+        let policy = StepPolicy::new().deny("db/execute").deny("admin/*");
+        assert!(!policy.is_allowed("db/execute"));
+        assert!(!policy.is_allowed("admin/reset"));
+        assert!(policy.is_allowed("user/create"));
+
+        let policy = StepPolicy::new().allow("user/*").deny("user/delete");
+        assert!(policy.is_allowed("user/create"));
+        assert!(!policy.is_allowed("user/delete"));
+        assert!(!policy.is_allowed("order/create"));
+    }
+}
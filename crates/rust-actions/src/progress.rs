@@ -0,0 +1,226 @@
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const TEMPLATE: &str =
+    "{spinner:.green} [{elapsed_precise}] [{bar:30.cyan/blue}] {pos}/{len} jobs (ETA {eta}) {wide_msg}";
+
+/// Console output for a run. Without a bar every line is printed as it happens; with one,
+/// only failures are printed (above the bar) and the rest is summarized by the bar itself.
+/// A quiet one prints nothing, leaving the run to its `tracing` events.
+#[derive(Clone, Default)]
+pub(crate) struct Progress {
+    bar: Option<ProgressBar>,
+    quiet: bool,
+    state: Arc<Mutex<ProgressState>>,
+}
+
+#[derive(Default)]
+struct ProgressState {
+    workflows_total: usize,
+    workflows_done: usize,
+    workflow: String,
+    header_printed: bool,
+    running: Vec<String>,
+    /// Estimated from the previous run's job durations
+    remaining: Option<Duration>,
+}
+
+impl Progress {
+    pub fn bar(workflows: usize, jobs: usize) -> Self {
+        let bar = ProgressBar::new(jobs as u64);
+        if let Ok(style) = ProgressStyle::with_template(TEMPLATE) {
+            bar.set_style(style.progress_chars("=> "));
+        }
+        bar.enable_steady_tick(Duration::from_millis(100));
+
+        let progress = Self {
+            bar: Some(bar),
+            quiet: false,
+            state: Arc::new(Mutex::new(ProgressState {
+                workflows_total: workflows,
+                ..Default::default()
+            })),
+        };
+        progress.refresh();
+        progress
+    }
+
+    pub fn quiet() -> Self {
+        Self {
+            quiet: true,
+            ..Self::default()
+        }
+    }
+
+    /// A line of routine output, dropped while the bar is shown.
+    pub fn line(&self, line: impl AsRef<str>) {
+        if self.bar.is_none() && !self.quiet {
+            println!("{}", line.as_ref());
+        }
+    }
+
+    /// Something that went wrong outside any step, as a `tracing` warning and on stderr.
+    pub fn warn(&self, message: impl std::fmt::Display) {
+        tracing::warn!(target: "rust_actions", "{}", message);
+        if self.quiet {
+            return;
+        }
+        let line = format!("{} {}", "Warning:".yellow().bold(), message);
+        match &self.bar {
+            Some(bar) => print_above(bar, &line),
+            None => eprintln!("{}", line),
+        }
+    }
+
+    /// Output for a finished job or check. Failed reports are always printed, preceded by
+    /// their workflow's header the first time one appears under a bar.
+    pub fn report(&self, passed: bool, lines: &[String]) {
+        if self.quiet {
+            return;
+        }
+        let Some(bar) = self.bar.as_ref().filter(|_| !passed) else {
+            lines.iter().for_each(|l| self.line(l));
+            return;
+        };
+
+        if let Ok(mut state) = self.state.lock() {
+            if !state.header_printed {
+                state.header_printed = true;
+                print_above(bar, &format!("\n{} {}", "Workflow:".bold(), state.workflow));
+            }
+        }
+        for line in lines {
+            print_above(bar, line);
+        }
+    }
+
+    pub fn workflow_started(&self, name: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            state.workflow = name.to_string();
+            state.header_printed = false;
+        }
+        self.line(format!("\n{} {}", "Workflow:".bold(), name));
+    }
+
+    pub fn workflow_finished(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.workflows_done += 1;
+        }
+        self.refresh();
+    }
+
+    pub fn job_started(&self, label: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            state.running.push(label.to_string());
+        }
+        self.refresh();
+    }
+
+    pub fn job_finished(&self, label: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(i) = state.running.iter().position(|r| r == label) {
+                state.running.remove(i);
+            }
+        }
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+        }
+        self.refresh();
+    }
+
+    /// The run's estimated wall time, printed up front and then counted down on the bar.
+    pub fn estimate(&self, total: Duration, jobs_without_history: usize) {
+        let unknown = match jobs_without_history {
+            0 => String::new(),
+            1 => " (1 job without history)".to_string(),
+            n => format!(" ({} jobs without history)", n),
+        };
+        self.line(format!(
+            "{} {} from the previous run{}",
+            "Estimated:".dimmed(),
+            format_estimate(total),
+            unknown
+        ));
+        self.remaining(total);
+    }
+
+    pub fn remaining(&self, remaining: Duration) {
+        if let Ok(mut state) = self.state.lock() {
+            state.remaining = Some(remaining);
+        }
+        self.refresh();
+    }
+
+    /// Counts jobs that won't run (e.g. restored from a checkpoint) as done.
+    pub fn jobs_skipped(&self, count: usize) {
+        if let Some(bar) = &self.bar {
+            bar.inc(count as u64);
+        }
+    }
+
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+
+    fn refresh(&self) {
+        let (Some(bar), Ok(state)) = (&self.bar, self.state.lock()) else {
+            return;
+        };
+        let mut msg = format!("{}/{} workflows", state.workflows_done, state.workflows_total);
+        if let Some(remaining) = state.remaining {
+            msg.push_str(&format!(" | {} left", format_estimate(remaining)));
+        }
+        if !state.running.is_empty() {
+            msg.push_str(&format!(" | running: {}", state.running.join(", ")));
+        }
+        bar.set_message(msg);
+    }
+}
+
+/// `~45s`, `~3m 05s`
+fn format_estimate(duration: Duration) -> String {
+    let secs = duration.as_secs_f64().round() as u64;
+    if secs < 60 {
+        format!("~{}s", secs)
+    } else {
+        format!("~{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// `ProgressBar::println` is a no-op when the bar is hidden (e.g. stderr isn't a terminal),
+/// so failures fall back to plain stdout there.
+fn print_above(bar: &ProgressBar, line: &str) {
+    if bar.is_hidden() {
+        println!("{}", line);
+    } else {
+        bar.println(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_running_jobs() {
+        let progress = Progress::bar(2, 3);
+        let bar = progress.bar.clone().unwrap();
+
+        progress.job_started("create");
+        progress.job_started("deploy (region=eu)");
+        assert!(bar.message().contains("running: create, deploy (region=eu)"));
+
+        progress.job_finished("create");
+        progress.jobs_skipped(1);
+        progress.workflow_finished();
+        assert_eq!(bar.position(), 2);
+        assert_eq!(bar.message(), "1/2 workflows | running: deploy (region=eu)");
+
+        progress.remaining(Duration::from_secs(185));
+        assert_eq!(bar.message(), "1/2 workflows | ~3m 05s left | running: deploy (region=eu)");
+    }
+}
@@ -0,0 +1,82 @@
+use crate::policy::glob_match;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Spaces out step executions so no step name runs more often than its limit.
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    /// Suite-level limits as (step name pattern, executions per second); first match wins
+    limits: Vec<(String, f64)>,
+    /// Earliest start of the next execution, per step name
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl RateLimiter {
+    /// Adds a limit for `pattern`, replacing any earlier limit for the same pattern.
+    pub fn add_limit(&mut self, pattern: impl Into<String>, per_second: f64) {
+        let pattern = pattern.into();
+        match self.limits.iter_mut().find(|(p, _)| *p == pattern) {
+            Some(existing) => existing.1 = per_second,
+            None => self.limits.push((pattern, per_second)),
+        }
+    }
+
+    /// The step's own `rate-limit:` if set, else the first suite-level limit matching `uses`.
+    pub fn limit_for(&self, uses: &str, step_limit: Option<f64>) -> Option<f64> {
+        step_limit
+            .or_else(|| {
+                self.limits
+                    .iter()
+                    .find(|(pattern, _)| glob_match(pattern, uses))
+                    .map(|(_, limit)| *limit)
+            })
+            .filter(|limit| limit.is_finite() && *limit > 0.0)
+    }
+
+    /// Waits for the next free slot for `uses`, at most `per_second` slots per second.
+    pub async fn acquire(&self, uses: &str, per_second: f64) {
+        let interval = Duration::from_secs_f64(1.0 / per_second);
+        let now = Instant::now();
+        let slot = match self.next_slot.lock() {
+            Ok(mut next_slot) => {
+                let slot = next_slot.get(uses).copied().unwrap_or(now).max(now);
+                next_slot.insert(uses.to_string(), slot + interval);
+                slot
+            }
+            Err(_) => now,
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_for() {
+        let mut limiter = RateLimiter::default();
+        limiter.add_limit("payments/*", 2.0);
+        limiter.add_limit("*", 50.0);
+
+        assert_eq!(limiter.limit_for("payments/charge", None), Some(2.0));
+        assert_eq!(limiter.limit_for("payments/charge", Some(1.0)), Some(1.0));
+        assert_eq!(limiter.limit_for("user/create", None), Some(50.0));
+        assert_eq!(limiter.limit_for("user/create", Some(0.0)), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_spaces_out_executions() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            limiter.acquire("payments/charge", 2.0).await;
+        }
+        limiter.acquire("user/create", 2.0).await;
+
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+}
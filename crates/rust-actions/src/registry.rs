@@ -1,24 +1,136 @@
-use crate::args::RawArgs;
+use crate::args::{ArgField, RawArgs};
+use crate::context::StepContext;
 use crate::outputs::StepOutputs;
+use crate::policy::glob_match;
 use crate::world::World;
-use crate::Result;
+use crate::{Error, Result};
+use futures::FutureExt;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use serde_json::Value;
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 
-pub type StepFn<W> =
-    for<'a> fn(&'a mut W, RawArgs) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>;
+pub type StepFn<W> = for<'a> fn(
+    &'a mut W,
+    RawArgs,
+    &'a StepContext,
+) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>;
 
 pub type ErasedStepFn = for<'a> fn(
     &'a mut dyn Any,
     RawArgs,
+    &'a StepContext,
 ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>;
 
+/// A step function built at runtime, like those a `StepFactory` returns.
+pub type DynStepFn = Arc<
+    dyn for<'a> Fn(
+            &'a mut dyn Any,
+            RawArgs,
+            &'a StepContext,
+        ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>
+        + Send
+        + Sync,
+>;
+
+/// Builds one step of a family registered with `register_family`, given the part of `uses:`
+/// the pattern's `*` matched (`orders` for `queue/orders` under `queue/*`) and the config
+/// passed at registration.
+pub type StepFactory = Arc<dyn Fn(&str, &Value) -> DynStepFn + Send + Sync>;
+
+/// Wraps a closure as a `DynStepFn`.
+pub fn dyn_step<F>(step: F) -> DynStepFn
+where
+    F: for<'a> Fn(
+            &'a mut dyn Any,
+            RawArgs,
+            &'a StepContext,
+        ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>
+        + Send
+        + Sync
+        + 'static,
+{
+    Arc::new(step)
+}
+
+/// Wraps a closure over the world type `W` as a `DynStepFn`, failing on any other world
+/// the way `#[step]` functions do.
+pub fn typed_step<W, F>(step: F) -> DynStepFn
+where
+    W: 'static,
+    F: for<'a> Fn(
+            &'a mut W,
+            RawArgs,
+            &'a StepContext,
+        ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>
+        + Send
+        + Sync
+        + 'static,
+{
+    dyn_step(move |world_any, args, ctx| match world_any.downcast_mut::<W>() {
+        Some(world) => step(world, args, ctx),
+        None => {
+            let msg = format!("World type mismatch: expected {}", std::any::type_name::<W>());
+            Box::pin(async move { Err(Error::Custom(msg)) })
+        }
+    })
+}
+
+/// Runs a step, failing with `Error::StepPanicked` if it panics instead of unwinding through
+/// the runner. Only the step's own future is held across awaits, so this stays `Send`.
+pub(crate) fn call_guarded<'a>(
+    step_fn: &DynStepFn,
+    world: &'a mut dyn Any,
+    args: RawArgs,
+    ctx: &'a StepContext,
+) -> impl Future<Output = Result<StepOutputs>> + Send + 'a {
+    let future = std::panic::catch_unwind(AssertUnwindSafe(|| step_fn(world, args, ctx)));
+    async move {
+        let result = match future {
+            Ok(future) => AssertUnwindSafe(future).catch_unwind().await,
+            Err(payload) => Err(payload),
+        };
+        result.unwrap_or_else(|payload| Err(Error::StepPanicked(panic_message(payload.as_ref()))))
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
 pub struct ErasedStepDef {
     pub name: &'static str,
+    pub version: Option<&'static str>,
     pub world_type_id: TypeId,
     pub func: ErasedStepFn,
+    pub args: &'static [ArgField],
+    /// Set by `#[step("name", pure)]`: outputs depend only on the args
+    pub pure: bool,
+    /// `#[step("name", ensures = "...")]` assertions checked after every run of the step
+    pub ensures: &'static [&'static str],
+    /// Set by `#[step]` when the function returns `Result<()>`
+    pub no_outputs: bool,
+    /// `#[step("name", deprecated = "...")]`: what to use instead
+    pub deprecated: Option<&'static str>,
+    /// `module_path!()` where `#[step]` was applied; empty when not set
+    pub module: &'static str,
+    /// `file!()`/`line!()` where `#[step]` was applied; empty when not set
+    pub file: &'static str,
+    pub line: u32,
+    /// The step function's doc comment
+    pub doc: Option<&'static str>,
 }
 
 impl ErasedStepDef {
@@ -29,54 +141,698 @@ impl ErasedStepDef {
     ) -> Self {
         Self {
             name,
+            version: None,
             world_type_id,
             func,
+            args: &[],
+            pure: false,
+            ensures: &[],
+            no_outputs: false,
+            deprecated: None,
+            module: "",
+            file: "",
+            line: 0,
+            doc: None,
         }
     }
+
+    pub const fn with_version(mut self, version: &'static str) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub const fn with_args(mut self, args: &'static [ArgField]) -> Self {
+        self.args = args;
+        self
+    }
+
+    pub const fn pure(mut self) -> Self {
+        self.pure = true;
+        self
+    }
+
+    pub const fn with_ensures(mut self, ensures: &'static [&'static str]) -> Self {
+        self.ensures = ensures;
+        self
+    }
+
+    pub const fn without_outputs(mut self) -> Self {
+        self.no_outputs = true;
+        self
+    }
+
+    pub const fn deprecated(mut self, note: &'static str) -> Self {
+        self.deprecated = Some(note);
+        self
+    }
+
+    pub const fn defined_at(mut self, module: &'static str, file: &'static str, line: u32) -> Self {
+        self.module = module;
+        self.file = file;
+        self.line = line;
+        self
+    }
+
+    pub const fn with_doc(mut self, doc: &'static str) -> Self {
+        self.doc = Some(doc);
+        self
+    }
+
+    /// Where the step is defined, if `#[step]` recorded it.
+    pub fn source(&self) -> Option<StepSource> {
+        (!self.file.is_empty()).then_some(StepSource {
+            module: self.module,
+            file: self.file,
+            line: self.line,
+            doc: self.doc,
+        })
+    }
+}
+
+/// Where a step function is defined and what its doc comment says, for pointing error
+/// messages and reports at the code behind a `uses:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StepSource {
+    pub module: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub doc: Option<&'static str>,
+}
+
+impl fmt::Display for StepSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
 }
 
 inventory::collect!(ErasedStepDef);
 
+/// Converts a type-erased world to a step trait it implements. The `cast` stored in a
+/// `WorldCast` is a `WorldCastFn<dyn Trait>`.
+pub type WorldCastFn<T> = for<'a> fn(&'a mut dyn Any) -> Option<&'a mut T>;
+
+/// Registered by `#[world(implements(Trait))]`, so steps taking `&mut dyn Trait` run against
+/// that world.
+pub struct WorldCast {
+    pub world_type_id: TypeId,
+    pub target_type_id: TypeId,
+    pub cast: &'static (dyn Any + Send + Sync),
+}
+
+impl WorldCast {
+    pub const fn new(
+        world_type_id: TypeId,
+        target_type_id: TypeId,
+        cast: &'static (dyn Any + Send + Sync),
+    ) -> Self {
+        Self {
+            world_type_id,
+            target_type_id,
+            cast,
+        }
+    }
+}
+
+inventory::collect!(WorldCast);
+
+/// Views `world` as `T` (a `dyn Trait`) if its type registered a `WorldCast` to it.
+pub fn cast_world<T: ?Sized + 'static>(world: &mut dyn Any) -> Option<&mut T> {
+    let world_type_id = Any::type_id(&*world);
+    let cast = inventory::iter::<WorldCast>
+        .into_iter()
+        .filter(|c| c.world_type_id == world_type_id && c.target_type_id == TypeId::of::<T>())
+        .find_map(|c| c.cast.downcast_ref::<WorldCastFn<T>>())?;
+    cast(world)
+}
+
+fn implements(world_type_id: TypeId, target_type_id: TypeId) -> bool {
+    inventory::iter::<WorldCast>
+        .into_iter()
+        .any(|c| c.world_type_id == world_type_id && c.target_type_id == target_type_id)
+}
+
+struct StepFamily {
+    pattern: String,
+    config: Value,
+    factory: StepFactory,
+}
+
+impl StepFamily {
+    /// The part of `uses` the pattern's `*` matched, if it matches.
+    fn member<'u>(&self, uses: &'u str) -> Option<&'u str> {
+        if !glob_match(&self.pattern, uses) {
+            return None;
+        }
+        let (prefix, suffix) = match (self.pattern.find('*'), self.pattern.rfind('*')) {
+            (Some(first), Some(last)) => (&self.pattern[..first], &self.pattern[last + 1..]),
+            _ => (self.pattern.as_str(), ""),
+        };
+        let member = uses.strip_prefix(prefix).unwrap_or(uses);
+        Some(member.strip_suffix(suffix).unwrap_or(member))
+    }
+}
+
 pub struct StepRegistry {
     steps: HashMap<String, ErasedStepFn>,
+    versioned: HashMap<String, Vec<(Version, ErasedStepFn)>>,
+    args: HashMap<String, &'static [ArgField]>,
+    pure: HashSet<String>,
+    ensures: HashMap<String, Vec<String>>,
+    no_outputs: HashSet<String>,
+    deprecated: HashMap<String, String>,
+    sources: HashMap<String, StepSource>,
+    /// Tried in registration order, only for names no registered step resolves
+    families: Vec<StepFamily>,
+    /// Steps built by `families`, by `uses`
+    built: Mutex<HashMap<String, DynStepFn>>,
 }
 
 impl StepRegistry {
     pub fn new() -> Self {
         Self {
             steps: HashMap::new(),
+            versioned: HashMap::new(),
+            args: HashMap::new(),
+            pure: HashSet::new(),
+            ensures: HashMap::new(),
+            no_outputs: HashSet::new(),
+            deprecated: HashMap::new(),
+            sources: HashMap::new(),
+            families: Vec::new(),
+            built: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Registers the steps written for `W`, plus those taking `&mut dyn Trait` for any trait
+    /// `W` declares with `#[world(implements(...))]`.
     pub fn collect_for<W: World + 'static>(&mut self) {
+        self.collect_for_filtered::<W>(|_| true);
+    }
+
+    /// `collect_for`, registering only the steps whose name (`name@version` for versioned
+    /// ones) `filter` accepts, e.g. to leave out the steps of a service a suite doesn't deploy.
+    pub fn collect_for_filtered<W: World + 'static>(&mut self, filter: impl Fn(&str) -> bool) {
         let target_type_id = TypeId::of::<W>();
 
         for step in inventory::iter::<ErasedStepDef> {
-            if step.world_type_id == target_type_id {
-                self.steps.insert(step.name.to_string(), step.func);
+            if step.world_type_id == target_type_id
+                || implements(target_type_id, step.world_type_id)
+            {
+                let name = match step.version {
+                    Some(version) => format!("{}@{}", step.name, version),
+                    None => step.name.to_string(),
+                };
+                if !filter(&name) {
+                    continue;
+                }
+                if step.pure {
+                    self.pure.insert(canonical_name(&name));
+                }
+                if step.no_outputs {
+                    self.no_outputs.insert(canonical_name(&name));
+                }
+                if let Some(note) = step.deprecated {
+                    self.deprecate(&name, note);
+                }
+                for assertion in step.ensures {
+                    self.ensure(&name, *assertion);
+                }
+                if let Some(source) = step.source() {
+                    self.set_source(&name, source);
+                }
+                self.register_with_args(name, step.func, step.args);
             }
         }
     }
 
+    /// Registers `name`, or a specific version of it when given as `name@1.2.0`.
     pub fn register(&mut self, name: impl Into<String>, func: ErasedStepFn) {
-        self.steps.insert(name.into(), func);
+        self.register_with_args(name, func, &[]);
+    }
+
+    /// Like `register`, also recording the `with:` keys the step accepts.
+    pub fn register_with_args(
+        &mut self,
+        name: impl Into<String>,
+        func: ErasedStepFn,
+        args: &'static [ArgField],
+    ) {
+        let name = name.into();
+        match name.split_once('@').map(|(n, v)| (n, Version::parse(v))) {
+            Some((base, Ok(version))) => {
+                self.args.insert(canonical_name(&name), args);
+                let versions = self.versioned.entry(base.to_string()).or_default();
+                versions.push((version, func));
+                versions.sort_by(|a, b| b.0.cmp(&a.0));
+            }
+            _ => {
+                self.args.insert(name.clone(), args);
+                self.steps.insert(name, func);
+            }
+        }
+    }
+
+    /// Adds the steps and families of `other`, whose steps replace those of the same name.
+    pub fn extend(&mut self, other: StepRegistry) {
+        for name in other.args.keys() {
+            self.pure.remove(name);
+            self.ensures.remove(name);
+            self.no_outputs.remove(name);
+            self.deprecated.remove(name);
+            self.sources.remove(name);
+        }
+        self.steps.extend(other.steps);
+        for (base, versions) in other.versioned {
+            let existing = self.versioned.entry(base).or_default();
+            existing.retain(|(version, _)| !versions.iter().any(|(v, _)| v == version));
+            existing.extend(versions);
+            existing.sort_by(|a, b| b.0.cmp(&a.0));
+        }
+        self.args.extend(other.args);
+        self.pure.extend(other.pure);
+        self.ensures.extend(other.ensures);
+        self.no_outputs.extend(other.no_outputs);
+        self.deprecated.extend(other.deprecated);
+        self.sources.extend(other.sources);
+        self.families.extend(other.families);
+    }
+
+    /// Registers a family of steps: a `uses:` matching `pattern` (e.g. `queue/*`) that no
+    /// registered step resolves runs the step `factory` builds for it, once per name.
+    pub fn register_family(&mut self, pattern: impl Into<String>, config: Value, factory: StepFactory) {
+        self.families.push(StepFamily {
+            pattern: pattern.into(),
+            config,
+            factory,
+        });
+    }
+
+    /// The function to run for `uses`: the step `resolve` finds, else the one the first
+    /// matching family builds.
+    pub fn callable(&self, uses: &str) -> Result<DynStepFn> {
+        match self.resolve(uses) {
+            Ok(func) => {
+                let func: DynStepFn = Arc::new(*func);
+                Ok(func)
+            }
+            Err(Error::StepNotFound(reason)) => {
+                self.family_step(uses).ok_or(Error::StepNotFound(reason))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn family_step(&self, uses: &str) -> Option<DynStepFn> {
+        let (family, member) = self
+            .families
+            .iter()
+            .find_map(|family| family.member(uses).map(|member| (family, member)))?;
+        let mut built = self.built.lock().ok()?;
+        let step = built
+            .entry(uses.to_string())
+            .or_insert_with(|| (family.factory)(member, &family.config));
+        Some(step.clone())
+    }
+
+    /// Every registered step name, sorted, with versioned steps as `name@x.y.z`.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.args.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// The `with:` keys of the step `uses` resolves to.
+    pub fn arg_fields(&self, uses: &str) -> Result<&'static [ArgField]> {
+        let key = self.resolve_name(uses)?;
+        Ok(self.args.get(&key).copied().unwrap_or(&[]))
+    }
+
+    /// Marks a registered step as pure, so the runner memoizes its outputs per args.
+    pub fn mark_pure(&mut self, name: &str) {
+        self.pure.insert(canonical_name(name));
+    }
+
+    /// Whether the step `uses` resolves to was declared `#[step(.., pure)]` or `mark_pure`d.
+    pub fn is_pure(&self, uses: &str) -> bool {
+        self.resolve_name(uses).is_ok_and(|key| self.pure.contains(&key))
+    }
+
+    /// Marks a registered step as deprecated; `note` says what to use instead.
+    pub fn deprecate(&mut self, name: &str, note: impl Into<String>) {
+        self.deprecated.insert(canonical_name(name), note.into());
+    }
+
+    /// The deprecation note of the step `uses` resolves to, if it's deprecated.
+    pub fn deprecation(&self, uses: &str) -> Option<&str> {
+        let key = self.resolve_name(uses).ok()?;
+        self.deprecated.get(&key).map(String::as_str)
+    }
+
+    /// Records where a registered step is defined; `#[step]` functions have theirs already.
+    pub fn set_source(&mut self, name: &str, source: StepSource) {
+        self.sources.insert(canonical_name(name), source);
+    }
+
+    /// Where the step `uses` resolves to is defined, with its doc comment.
+    pub fn source(&self, uses: &str) -> Option<&StepSource> {
+        let key = self.resolve_name(uses).ok()?;
+        self.sources.get(&key)
+    }
+
+    /// The registered name `uses` resolves to, as listed by `names`.
+    pub fn registered_name(&self, uses: &str) -> Option<String> {
+        self.resolve_name(uses).ok()
+    }
+
+    /// Whether the step `uses` resolves to is a `#[step]` returning `Result<()>`. Steps
+    /// registered by hand are assumed to have outputs.
+    pub fn returns_no_outputs(&self, uses: &str) -> bool {
+        self.resolve_name(uses).is_ok_and(|key| self.no_outputs.contains(&key))
+    }
+
+    /// Adds a post-condition checked after every run of a registered step, whatever the
+    /// workflow says. Either a full `${{ }}` assertion or just the expression inside it.
+    pub fn ensure(&mut self, name: &str, assertion: impl Into<String>) {
+        let assertion = assertion.into();
+        let assertion = if assertion.contains("${{") {
+            assertion
+        } else {
+            format!("${{{{ {} }}}}", assertion.trim())
+        };
+        self.ensures
+            .entry(canonical_name(name))
+            .or_default()
+            .push(assertion);
+    }
+
+    /// The post-conditions of the step `uses` resolves to, as `${{ }}` assertions.
+    pub fn ensures(&self, uses: &str) -> &[String] {
+        self.resolve_name(uses)
+            .ok()
+            .and_then(|key| self.ensures.get(&key))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The registered name `uses` resolves to, as `name@x.y.z` for versioned steps.
+    fn resolve_name(&self, uses: &str) -> Result<String> {
+        Ok(match self.lookup(uses)? {
+            (name, Some(version), _) => format!("{}@{}", name, version),
+            (name, None, _) => name.to_string(),
+        })
     }
 
     pub fn get(&self, name: &str) -> Option<&ErasedStepFn> {
-        self.steps.get(name)
+        self.resolve(name).ok()
+    }
+
+    /// Resolves `uses`. `name@req` picks the highest version matching the requirement
+    /// (`@1` means `^1`). A bare name prefers an unversioned step, then the only registered
+    /// major version. Two registrations of the same version, or a bare name with several
+    /// major versions, are ambiguous.
+    pub fn resolve(&self, uses: &str) -> Result<&ErasedStepFn> {
+        self.lookup(uses).map(|(_, _, func)| func)
+    }
+
+    /// Resolves `uses` to the step's name, its version if versioned, and its function.
+    fn lookup<'a, 'u>(
+        &'a self,
+        uses: &'u str,
+    ) -> Result<(&'u str, Option<&'a Version>, &'a ErasedStepFn)> {
+        let (name, req) = match uses.split_once('@') {
+            Some((name, spec)) => {
+                let req = VersionReq::parse(spec).map_err(|e| {
+                    Error::Args(format!("Invalid version requirement in '{}': {}", uses, e))
+                })?;
+                (name, Some(req))
+            }
+            None => {
+                if let Some(func) = self.steps.get(uses) {
+                    return Ok((uses, None, func));
+                }
+                (uses, None)
+            }
+        };
+
+        let versions = self
+            .versioned
+            .get(name)
+            .ok_or_else(|| self.not_found(uses, name))?;
+
+        let candidates: Vec<&(Version, ErasedStepFn)> = match &req {
+            Some(req) => versions.iter().filter(|(v, _)| req.matches(v)).collect(),
+            None => {
+                let majors: Vec<u64> = versions.iter().map(|(v, _)| v.major).collect();
+                if majors.iter().any(|m| *m != majors[0]) {
+                    return Err(Error::AmbiguousStep {
+                        uses: uses.to_string(),
+                        reason: format!(
+                            "versions {} are registered; pin one with '{}@<major>'",
+                            version_list(versions),
+                            name
+                        ),
+                    });
+                }
+                versions.iter().collect()
+            }
+        };
+
+        let (best, func) = candidates.first().ok_or_else(|| Error::StepNotFound(format!(
+            "{} (registered versions: {})",
+            uses,
+            version_list(versions)
+        )))?;
+        if candidates.iter().filter(|(v, _)| v == best).count() > 1 {
+            return Err(Error::AmbiguousStep {
+                uses: uses.to_string(),
+                reason: format!("version {} is registered more than once", best),
+            });
+        }
+        Ok((name, Some(best), func))
+    }
+
+    /// `StepNotFound` naming the closest registered steps, where they're defined when known,
+    /// and how many steps there are.
+    fn not_found(&self, uses: &str, name: &str) -> Error {
+        let count = self.len();
+        let registered = if count == 1 { "step" } else { "steps" };
+        let similar: Vec<String> = self
+            .similar_names(name)
+            .into_iter()
+            .map(|known| match self.source(&known) {
+                Some(source) => format!("{} at {}", known, source),
+                None => known,
+            })
+            .collect();
+        match similar.as_slice() {
+            [] => Error::StepNotFound(format!("{} ({} {} registered)", uses, count, registered)),
+            similar => Error::StepNotFound(format!(
+                "{} (did you mean {}? {} {} registered)",
+                uses,
+                similar.join(", "),
+                count,
+                registered
+            )),
+        }
+    }
+
+    /// Up to three registered step names (without versions) within a few edits of `name`,
+    /// closest first.
+    pub fn similar_names(&self, name: &str) -> Vec<String> {
+        let max_distance = (name.chars().count() / 4).max(2);
+        let mut names: Vec<(usize, &str)> = self
+            .steps
+            .keys()
+            .chain(self.versioned.keys())
+            .map(|known| (strsim::levenshtein(name, known), known.as_str()))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect();
+        names.sort();
+        names.dedup_by_key(|(_, known)| *known);
+        names.into_iter().take(3).map(|(_, known)| known.to_string()).collect()
     }
 
     pub fn len(&self) -> usize {
-        self.steps.len()
+        self.steps.len() + self.versioned.values().map(Vec::len).sum::<usize>()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.steps.is_empty()
+        self.len() == 0
+    }
+}
+
+/// A registered name with its version formatted the way `resolve_name` formats it.
+fn canonical_name(name: &str) -> String {
+    match name.split_once('@').map(|(n, v)| (n, Version::parse(v))) {
+        Some((base, Ok(version))) => format!("{}@{}", base, version),
+        _ => name.to_string(),
     }
 }
 
+fn version_list(versions: &[(Version, ErasedStepFn)]) -> String {
+    versions
+        .iter()
+        .map(|(v, _)| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl Default for StepRegistry {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step_fn<'a>(
+        _world: &'a mut dyn Any,
+        _args: RawArgs,
+        _ctx: &'a StepContext,
+    ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>> {
+        Box::pin(async { Ok(StepOutputs::new()) })
+    }
+
+    fn other_fn<'a>(
+        _world: &'a mut dyn Any,
+        _args: RawArgs,
+        _ctx: &'a StepContext,
+    ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>> {
+        Box::pin(async { Err(Error::Custom("other".to_string())) })
+    }
+
+    fn same(a: &ErasedStepFn, b: ErasedStepFn) -> bool {
+        std::ptr::fn_addr_eq(*a, b)
+    }
+
+    #[test]
+    fn test_resolve_highest_compatible_version() {
+        let mut registry = StepRegistry::new();
+        registry.register("user/create@1.0.0", other_fn);
+        registry.register("user/create@1.2.0", step_fn);
+        registry.register("user/create@2.0.0", other_fn);
+
+        assert!(same(registry.resolve("user/create@1").unwrap(), step_fn));
+        assert!(same(registry.resolve("user/create@=1.0.0").unwrap(), other_fn));
+        assert!(same(registry.resolve("user/create@2").unwrap(), other_fn));
+        assert!(matches!(
+            registry.resolve("user/create@3"),
+            Err(Error::StepNotFound(_))
+        ));
+        assert!(matches!(
+            registry.resolve("user/create"),
+            Err(Error::AmbiguousStep { .. })
+        ));
+
+        registry.register("user/create", step_fn);
+        assert!(same(registry.resolve("user/create").unwrap(), step_fn));
+    }
+
+    #[test]
+    fn test_extend_replaces_same_names() {
+        let mut registry = StepRegistry::new();
+        registry.register("fs/write", step_fn);
+        registry.register("user/create@1.0.0", step_fn);
+        registry.mark_pure("fs/write");
+
+        let mut other = StepRegistry::new();
+        other.register("fs/write", other_fn);
+        other.register("user/create@1.0.0", other_fn);
+        other.register("user/create@2.0.0", other_fn);
+        registry.extend(other);
+
+        assert!(same(registry.resolve("fs/write").unwrap(), other_fn));
+        assert!(!registry.is_pure("fs/write"));
+        assert!(same(registry.resolve("user/create@=1.0.0").unwrap(), other_fn));
+        assert_eq!(registry.len(), 3);
+    }
+
+    fn message(registry: &StepRegistry, uses: &str) -> String {
+        registry.resolve(uses).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_not_found_suggests_similar_names() {
+        let mut registry = StepRegistry::new();
+        registry.register("user/create", step_fn);
+        registry.register("user/delete", step_fn);
+        registry.register("price/quote@1.0.0", step_fn);
+
+        assert_eq!(
+            message(&registry, "user/craete"),
+            "Step not found: user/craete (did you mean user/create? 3 steps registered)"
+        );
+        assert_eq!(
+            message(&registry, "price/qoute@1"),
+            "Step not found: price/qoute@1 (did you mean price/quote? 3 steps registered)"
+        );
+        assert_eq!(message(&registry, "billing/charge"), "Step not found: billing/charge (3 steps registered)");
+
+        registry.set_source(
+            "user/create",
+            StepSource {
+                module: "app::steps",
+                file: "src/steps.rs",
+                line: 12,
+                doc: Some("Creates a user"),
+            },
+        );
+        assert_eq!(
+            message(&registry, "user/craete"),
+            "Step not found: user/craete (did you mean user/create at src/steps.rs:12? 3 steps registered)"
+        );
+        assert_eq!(registry.source("user/create").unwrap().doc, Some("Creates a user"));
+    }
+
+    #[test]
+    fn test_pure_follows_resolution() {
+        let mut registry = StepRegistry::new();
+        registry.register("price/quote@1.0.0", step_fn);
+        registry.register("price/quote@2.0.0", step_fn);
+        registry.mark_pure("price/quote@2.0.0");
+
+        assert!(registry.is_pure("price/quote@2"));
+        assert!(!registry.is_pure("price/quote@1"));
+        assert!(!registry.is_pure("price/quote"));
+    }
+
+    #[tokio::test]
+    async fn test_family_builds_steps_for_unregistered_names() {
+        let mut registry = StepRegistry::new();
+        registry.register("queue/audit", step_fn);
+        let factory: StepFactory = Arc::new(|queue: &str, config: &Value| {
+            let message = format!("{} on {}", queue, config["broker"].as_str().unwrap_or_default());
+            dyn_step(move |_world, _args, _ctx| {
+                let message = message.clone();
+                Box::pin(async move { Err(Error::Custom(message)) })
+            })
+        });
+        registry.register_family("queue/*", serde_json::json!({ "broker": "local" }), factory);
+
+        let ctx = StepContext::new("w", "j", "s", crate::clock::VirtualClock::new());
+        let mut world = ();
+        for (uses, expected) in [
+            ("queue/orders", Some("orders on local")),
+            ("queue/payments", Some("payments on local")),
+            ("queue/audit", None),
+        ] {
+            let step = registry.callable(uses).unwrap();
+            let result = step(&mut world, RawArgs::new(), &ctx).await;
+            assert_eq!(result.err().map(|e| e.to_string()).as_deref(), expected, "{}", uses);
+        }
+        assert!(matches!(registry.callable("topic/orders"), Err(Error::StepNotFound(_))));
+    }
+
+    #[test]
+    fn test_duplicate_version_is_ambiguous() {
+        let mut registry = StepRegistry::new();
+        registry.register("user/create@1.2.0", step_fn);
+        registry.register("user/create@1.2.0", other_fn);
+
+        assert!(matches!(
+            registry.resolve("user/create@1"),
+            Err(Error::AmbiguousStep { .. })
+        ));
+    }
+}
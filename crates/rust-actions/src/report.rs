@@ -0,0 +1,258 @@
+use crate::args::RawArgs;
+use crate::context::StepLink;
+use crate::mask::SecretMasker;
+use crate::matrix::MatrixCombination;
+use crate::runner::{JobResult, StepResult, WorkflowResult};
+use crate::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+const TEMPLATE: &str = include_str!("report.html");
+const DATA_PLACEHOLDER: &str = "/*REPORT_DATA*/null";
+
+/// A standalone HTML page for a run, as written by `RustActions::html_report`: the results
+/// embedded as JSON, rendered as collapsible workflows → jobs → steps. Failed entries start
+/// expanded.
+#[derive(Debug, Serialize)]
+pub struct HtmlReport {
+    session_id: String,
+    passed: bool,
+    workflows: Vec<ReportWorkflow>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportWorkflow {
+    name: String,
+    passed: bool,
+    ignored: Option<String>,
+    duration_ms: f64,
+    wall_ms: f64,
+    failed_assertions: Vec<String>,
+    quarantined: bool,
+    jobs: Vec<ReportJob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportJob {
+    name: String,
+    matrix: MatrixCombination,
+    passed: bool,
+    duration_ms: f64,
+    wall_ms: f64,
+    steps: Vec<ReportStep>,
+    compensations: Vec<ReportStep>,
+    cleanup: Vec<ReportStep>,
+    warnings: Vec<String>,
+    quarantined: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportStep {
+    name: String,
+    status: &'static str,
+    continue_on_error: bool,
+    duration_ms: f64,
+    wall_ms: f64,
+    error: Option<String>,
+    args: RawArgs,
+    logs: Vec<String>,
+    links: Vec<StepLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+impl HtmlReport {
+    /// Builds the report, masking `secrets` in messages and args once more in case a value
+    /// was only marked secret after an earlier step used it.
+    pub fn new(
+        session_id: impl Into<String>,
+        results: &[WorkflowResult],
+        secrets: &SecretMasker,
+    ) -> Self {
+        let workflows: Vec<ReportWorkflow> = results
+            .iter()
+            .map(|w| ReportWorkflow {
+                name: w.name.clone(),
+                passed: w.passed(),
+                ignored: w.ignored.clone(),
+                duration_ms: millis(w.duration),
+                wall_ms: millis(w.wall_duration),
+                failed_assertions: w.failed_assertions.iter().map(|a| secrets.mask(a)).collect(),
+                quarantined: w.quarantined,
+                jobs: w.jobs.iter().map(|j| ReportJob::new(j, secrets)).collect(),
+                description: w.description.clone(),
+            })
+            .collect();
+
+        Self {
+            session_id: session_id.into(),
+            passed: workflows.iter().all(|w| w.passed),
+            workflows,
+        }
+    }
+
+    pub fn render(&self) -> Result<String> {
+        // `<\/` keeps a `</script>` inside a string from closing the data block
+        let data = serde_json::to_string(self)?.replace("</", "<\\/");
+        Ok(TEMPLATE.replace(DATA_PLACEHOLDER, &data))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.render()?)?;
+        Ok(())
+    }
+}
+
+impl ReportJob {
+    fn new(job: &JobResult, secrets: &SecretMasker) -> Self {
+        let steps = job
+            .steps
+            .iter()
+            .map(|step| {
+                let report = ReportStep::new(
+                    &step.name,
+                    &step.result,
+                    step.continue_on_error,
+                    step.args.clone(),
+                    step.wall_duration,
+                    &step.logs,
+                    secrets,
+                );
+                ReportStep {
+                    links: step.links.clone(),
+                    description: step.description.clone(),
+                    ..report
+                }
+            })
+            .collect();
+        let deferred = |steps: &[(String, StepResult)]| {
+            steps
+                .iter()
+                .map(|(name, result)| {
+                    ReportStep::new(name, result, false, RawArgs::new(), Duration::ZERO, &[], secrets)
+                })
+                .collect()
+        };
+
+        Self {
+            name: format!("{}{}", job.name, job.matrix_suffix),
+            matrix: job.matrix.clone(),
+            passed: job.passed(),
+            duration_ms: millis(job.duration),
+            wall_ms: millis(job.wall_duration),
+            steps,
+            compensations: deferred(&job.compensations),
+            cleanup: deferred(&job.cleanup),
+            warnings: job.warnings.clone(),
+            quarantined: job.quarantined,
+            description: job.description.clone(),
+        }
+    }
+}
+
+impl ReportStep {
+    fn new(
+        name: &str,
+        result: &StepResult,
+        continue_on_error: bool,
+        args: RawArgs,
+        wall: Duration,
+        logs: &[String],
+        secrets: &SecretMasker,
+    ) -> Self {
+        let (status, duration, error) = match result {
+            StepResult::Passed(d) => ("passed", *d, None),
+            StepResult::Failed(d, msg) => ("failed", *d, Some(secrets.mask(msg))),
+            StepResult::Skipped => ("skipped", Duration::ZERO, None),
+        };
+        Self {
+            name: name.to_string(),
+            status,
+            continue_on_error,
+            duration_ms: millis(duration),
+            wall_ms: millis(wall),
+            error,
+            args: args.iter().map(|(k, v)| (k.clone(), secrets.mask_value(v))).collect(),
+            logs: logs.iter().map(|line| secrets.mask(line)).collect(),
+            links: Vec::new(),
+            description: None,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::StepRecord;
+    use serde_json::json;
+
+    #[test]
+    fn test_report_embeds_masked_results() {
+        let job = JobResult {
+            name: "create".to_string(),
+            matrix_suffix: " (region=eu)".to_string(),
+            steps: vec![
+                StepRecord {
+                    args: [("username".to_string(), json!("alice"))].into_iter().collect(),
+                    wall_duration: Duration::from_millis(5),
+                    description: Some("Signs up through the public API".to_string()),
+                    ..StepRecord::new("user/create", StepResult::Passed(Duration::from_millis(5)))
+                },
+                StepRecord {
+                    args: [("password".to_string(), json!("hunter2"))].into_iter().collect(),
+                    logs: vec!["WARN auth: rejected password=\"hunter2\"".to_string()],
+                    links: vec![StepLink {
+                        label: "trace".to_string(),
+                        target: "https://tempo.local/trace/4bf92f".to_string(),
+                    }],
+                    ..StepRecord::new(
+                        "user/login",
+                        StepResult::Failed(Duration::ZERO, "bad password hunter2 </script>".to_string()),
+                    )
+                },
+            ],
+            duration: Duration::from_millis(5),
+            matrix: [("region".to_string(), json!("eu"))].into_iter().collect(),
+            wall_duration: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let workflow = WorkflowResult {
+            name: "Users".to_string(),
+            jobs: vec![job],
+            duration: Duration::from_millis(5),
+            ignored: None,
+            failed_assertions: vec![],
+            wall_duration: Duration::from_millis(5),
+            quarantined: false,
+            description: None,
+        };
+        let mut secrets = SecretMasker::new();
+        secrets.add("hunter2");
+
+        let report = HtmlReport::new("abc123", &[workflow], &secrets);
+        let html = report.render().unwrap();
+
+        assert!(!html.contains(DATA_PLACEHOLDER));
+        assert!(!html.contains("hunter2"));
+        assert!(!html.contains("</script>\""));
+        assert!(html.contains(r#""name":"create (region=eu)","matrix":{"region":"eu"},"passed":false"#));
+        assert!(html.contains(r#""logs":[],"links":[],"description":"Signs up through the public API"}"#));
+        assert!(html.contains(
+            r#""error":"bad password *** <\/script>","args":{"password":"***"},"logs":["WARN auth: rejected password=\"***\""]"#
+        ));
+        assert!(html.contains(r#""links":[{"label":"trace","target":"https://tempo.local/trace/4bf92f"}]"#));
+    }
+}
@@ -0,0 +1,320 @@
+//! Transforms values before `RustActions::run` writes them to the JSON results, the HTML report
+//! and the failure manifest, e.g. to scrub customer-like test data before it reaches shared CI
+//! storage. Filters see values with secrets already masked, and run in the order they were
+//! added with `RustActions::report_filter`. What `execute` returns and the console prints is
+//! left as is.
+
+use crate::mask::MASK;
+use crate::policy::glob_match;
+use crate::runner::{JobResult, StepResult, WorkflowResult};
+use crate::{Error, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// What a value in a report is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportField {
+    /// An evaluated `with:` arg of a step
+    Arg { step: String, key: String },
+    /// A job output; exported step outputs are `steps.<id>`
+    Output { key: String },
+    /// One of the job's matrix values
+    Matrix { key: String },
+    /// A step failure message, failed workflow assertion, warning or infrastructure error
+    Message,
+    /// A line logged while a failed step ran
+    Log { step: String },
+}
+
+impl ReportField {
+    /// The arg, output or matrix key; `None` for messages and log lines.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            ReportField::Arg { key, .. } | ReportField::Output { key } | ReportField::Matrix { key } => {
+                Some(key)
+            }
+            ReportField::Message | ReportField::Log { .. } => None,
+        }
+    }
+}
+
+/// Where a value in a report is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportLocation {
+    pub workflow: String,
+    /// With the matrix suffix; `None` for failed workflow assertions
+    pub job: Option<String>,
+    pub field: ReportField,
+}
+
+/// Transforms or drops a value before it's written to a report. Closures taking
+/// `(&ReportLocation, Value)` are filters too.
+pub trait ReportFilter: Send + Sync {
+    /// The value to write instead of `value`, or `None` to leave it out. Left out args,
+    /// outputs, matrix values and log lines are removed; left out messages are written as
+    /// `***`.
+    fn filter(&self, location: &ReportLocation, value: Value) -> Option<Value>;
+}
+
+impl<F> ReportFilter for F
+where
+    F: Fn(&ReportLocation, Value) -> Option<Value> + Send + Sync,
+{
+    fn filter(&self, location: &ReportLocation, value: Value) -> Option<Value> {
+        self(location, value)
+    }
+}
+
+/// Cuts strings longer than `max_chars` characters, anywhere in a value, and marks the cut
+/// with `…`.
+#[derive(Debug, Clone, Copy)]
+pub struct Truncate {
+    max_chars: usize,
+}
+
+impl Truncate {
+    pub fn new(max_chars: usize) -> Self {
+        Self { max_chars }
+    }
+}
+
+impl ReportFilter for Truncate {
+    fn filter(&self, _: &ReportLocation, value: Value) -> Option<Value> {
+        Some(map_strings(value, &|s| match s.char_indices().nth(self.max_chars) {
+            Some((end, _)) => format!("{}…", &s[..end]),
+            None => s,
+        }))
+    }
+}
+
+/// Keeps only the args, outputs and matrix values whose key matches one of the patterns
+/// (`*` wildcards, as in step policies). Messages and log lines are kept.
+#[derive(Debug, Clone, Default)]
+pub struct AllowKeys {
+    patterns: Vec<String>,
+}
+
+impl AllowKeys {
+    pub fn new(patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl ReportFilter for AllowKeys {
+    fn filter(&self, location: &ReportLocation, value: Value) -> Option<Value> {
+        match location.field.key() {
+            Some(key) if !self.patterns.iter().any(|p| glob_match(p, key)) => None,
+            _ => Some(value),
+        }
+    }
+}
+
+/// Replaces every match of a regex, in any string of any value, with `***`.
+///
+/// ```ignore
+/// Redact::new(r"[\w.+-]+@[\w-]+\.[\w.]+")? // email addresses
+/// ```
+#[derive(Debug, Clone)]
+pub struct Redact {
+    pattern: Regex,
+}
+
+impl Redact {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| Error::Config(format!("report filter: invalid pattern '{}': {}", pattern, e)))?;
+        Ok(Self { pattern })
+    }
+}
+
+impl ReportFilter for Redact {
+    fn filter(&self, _: &ReportLocation, value: Value) -> Option<Value> {
+        Some(map_strings(value, &|s| self.pattern.replace_all(&s, MASK).into_owned()))
+    }
+}
+
+fn map_strings(value: Value, f: &dyn Fn(String) -> String) -> Value {
+    match value {
+        Value::String(s) => Value::String(f(s)),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| map_strings(v, f)).collect()),
+        Value::Object(map) => {
+            Value::Object(map.into_iter().map(|(k, v)| (k, map_strings(v, f))).collect())
+        }
+        other => other,
+    }
+}
+
+/// The results as the reports get them, with every filter applied in order.
+pub(crate) fn apply(
+    filters: &[Arc<dyn ReportFilter>],
+    mut results: Vec<WorkflowResult>,
+) -> Vec<WorkflowResult> {
+    if filters.is_empty() {
+        return results;
+    }
+
+    for workflow in &mut results {
+        let name = workflow.name.clone();
+        let filter = |job: Option<&str>, field: ReportField, value: Value| {
+            let location = ReportLocation {
+                workflow: name.clone(),
+                job: job.map(str::to_string),
+                field,
+            };
+            filters.iter().try_fold(value, |value, f| f.filter(&location, value))
+        };
+
+        for assertion in &mut workflow.failed_assertions {
+            *assertion = message(&|field, value| filter(None, field, value), assertion);
+        }
+        for job in &mut workflow.jobs {
+            let label = format!("{}{}", job.name, job.matrix_suffix);
+            filter_job(job, &|field, value| filter(Some(&label), field, value));
+        }
+    }
+    results
+}
+
+type FilterFn<'a> = dyn Fn(ReportField, Value) -> Option<Value> + 'a;
+
+fn message(filter: &FilterFn<'_>, text: &str) -> String {
+    match filter(ReportField::Message, Value::String(text.to_string())) {
+        Some(Value::String(text)) => text,
+        Some(other) => other.to_string(),
+        None => MASK.to_string(),
+    }
+}
+
+fn filter_job(job: &mut JobResult, filter: &FilterFn<'_>) {
+    let filter_result = |result: &mut StepResult| {
+        if let StepResult::Failed(_, msg) = result {
+            *msg = message(filter, msg);
+        }
+    };
+
+    for step in &mut job.steps {
+        let name = &step.name;
+        step.args = std::mem::take(&mut step.args)
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let field = ReportField::Arg { step: name.clone(), key: key.clone() };
+                filter(field, value).map(|value| (key, value))
+            })
+            .collect();
+        step.logs = std::mem::take(&mut step.logs)
+            .into_iter()
+            .filter_map(|line| {
+                match filter(ReportField::Log { step: name.clone() }, Value::String(line))? {
+                    Value::String(line) => Some(line),
+                    other => Some(other.to_string()),
+                }
+            })
+            .collect();
+        filter_result(&mut step.result);
+    }
+    for (_, result) in job.cleanup.iter_mut().chain(job.compensations.iter_mut()) {
+        filter_result(result);
+    }
+
+    job.outputs.outputs = std::mem::take(&mut job.outputs.outputs)
+        .into_iter()
+        .filter_map(|(key, value)| filter(ReportField::Output { key: key.clone() }, value).map(|v| (key, v)))
+        .collect();
+    job.outputs.steps = std::mem::take(&mut job.outputs.steps)
+        .into_iter()
+        .filter_map(|(id, value)| {
+            let field = ReportField::Output { key: format!("steps.{}", id) };
+            filter(field, value).map(|v| (id, v))
+        })
+        .collect();
+    job.matrix = std::mem::take(&mut job.matrix)
+        .into_iter()
+        .filter_map(|(key, value)| filter(ReportField::Matrix { key: key.clone() }, value).map(|v| (key, v)))
+        .collect();
+    for warning in &mut job.warnings {
+        *warning = message(filter, warning);
+    }
+    if let Some(error) = &mut job.infra_error {
+        *error = message(filter, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::JobOutputs;
+    use crate::runner::StepRecord;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn signup() -> WorkflowResult {
+        let mut outputs = JobOutputs::new();
+        outputs.insert("id", json!("u-1"));
+        outputs.insert("email", json!("alice@example.com"));
+        let job = JobResult {
+            name: "signup".to_string(),
+            steps: vec![StepRecord {
+                args: [
+                    ("email".to_string(), json!("alice@example.com")),
+                    ("bio".to_string(), json!("x".repeat(50))),
+                ]
+                .into(),
+                logs: vec!["sending mail to alice@example.com".to_string()],
+                ..StepRecord::new(
+                    "user/create",
+                    StepResult::Failed(Duration::ZERO, "alice@example.com already exists".to_string()),
+                )
+            }],
+            outputs,
+            ..Default::default()
+        };
+        WorkflowResult {
+            name: "Users".to_string(),
+            jobs: vec![job],
+            duration: Duration::ZERO,
+            ignored: None,
+            failed_assertions: vec![],
+            wall_duration: Duration::ZERO,
+            quarantined: false,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_filters_apply_in_order_to_every_value() {
+        let filters: Vec<Arc<dyn ReportFilter>> = vec![
+            Arc::new(Redact::new(r"[\w.+-]+@[\w-]+\.[\w.]+").unwrap()),
+            Arc::new(Truncate::new(10)),
+        ];
+
+        let results = apply(&filters, vec![signup()]);
+
+        let job = &results[0].jobs[0];
+        assert!(matches!(&job.steps[0].result, StepResult::Failed(_, msg) if msg == "*** alread…"));
+        assert_eq!(job.steps[0].args["email"], json!("***"));
+        assert_eq!(job.steps[0].args["bio"], json!("xxxxxxxxxx…"));
+        assert_eq!(job.steps[0].logs, vec!["sending ma…"]);
+        assert_eq!(job.outputs.get("email"), Some(&json!("***")));
+    }
+
+    #[test]
+    fn test_dropped_values_are_left_out() {
+        let drop_logs = |location: &ReportLocation, value: Value| match location.field {
+            ReportField::Log { .. } | ReportField::Message => None,
+            _ => Some(value),
+        };
+        let filters: Vec<Arc<dyn ReportFilter>> = vec![Arc::new(AllowKeys::new(["id", "b*"])), Arc::new(drop_logs)];
+
+        let results = apply(&filters, vec![signup()]);
+
+        let job = &results[0].jobs[0];
+        assert_eq!(job.outputs.outputs.keys().collect::<Vec<_>>(), vec!["id"]);
+        assert_eq!(job.steps[0].args.keys().collect::<Vec<_>>(), vec!["bio"]);
+        assert!(job.steps[0].logs.is_empty());
+        assert!(matches!(&job.steps[0].result, StepResult::Failed(_, msg) if msg == MASK));
+        assert!(Redact::new("(").is_err());
+    }
+}
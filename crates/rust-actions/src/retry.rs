@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+/// How often a step failing with `StepError::Retryable` is attempted. Retries wait `delay`,
+/// multiplied by `backoff` after each one; other errors fail the step at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    delay: Duration,
+    backoff: f64,
+}
+
+impl RetryPolicy {
+    /// `max_attempts` includes the first call, so `RetryPolicy::new(3)` retries twice.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            delay: Duration::from_millis(100),
+            backoff: 2.0,
+        }
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn backoff(mut self, factor: f64) -> Self {
+        self.backoff = factor.max(1.0);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Wait before attempt `attempt + 1`, given `attempt` (1-based) just failed.
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        self.delay
+            .mul_f64(self.backoff.powi(attempt.saturating_sub(1) as i32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delays() {
+        let policy = RetryPolicy::new(4).delay(Duration::from_millis(50)).backoff(3.0);
+        assert_eq!(policy.max_attempts(), 4);
+        assert_eq!(policy.delay_after(1), Duration::from_millis(50));
+        assert_eq!(policy.delay_after(2), Duration::from_millis(150));
+        assert_eq!(policy.delay_after(3), Duration::from_millis(450));
+
+        assert_eq!(RetryPolicy::new(0).max_attempts(), 1);
+    }
+}
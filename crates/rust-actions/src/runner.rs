@@ -1,21 +1,71 @@
+use crate::args::RawArgs;
+use crate::call_cache::{canonical_json, CallCache};
+use crate::cancel::{self, CancellationToken};
+use crate::capture;
+use crate::checkpoint::{Checkpoint, DEFAULT_CHECKPOINT_DIR};
 use crate::clock::VirtualClock;
-use crate::expr::{evaluate_assertion, evaluate_value, ExprContext, JobOutputs};
-use crate::hooks::HookRegistry;
-use crate::matrix::{expand_matrix, format_matrix_suffix, MatrixCombination};
-use crate::parser::{parse_workflow_file, parse_workflows, Job, Step, Workflow};
-use crate::registry::{ErasedStepFn, StepRegistry};
+use crate::config::Config;
+use crate::context::{DeferredStep, StepContext, StepLink};
+use crate::coverage::StepCoverage;
+use crate::diff::{RunDiff, RunRecord};
+use crate::events;
+use crate::executor::{StepCall, StepExecutor};
+use crate::exit::{ExitPolicy, FailureManifest};
+use crate::determinism::SeededRng;
+use crate::expect::check_expect;
+use crate::expr::{
+    capture_template, evaluate, evaluate_assertion, evaluate_typed, evaluate_value, ExprCache, ExprContext, JobOutputs, MissingKeyLog,
+    ContainerInfo, MissingKeyPolicy, RunInfo, RunnerInfo,
+};
+use crate::fs_steps;
+use crate::json_schema;
+use crate::generators::{GeneratorFn, GeneratorRegistry, RandomContext};
+use crate::hooks::{HookContext, HookDef, HookRegistry};
+use crate::listing::{SuiteListing, WorkflowListing};
+use crate::mask::SecretMasker;
+use crate::outputs::{limit_output, OutputLimit, StepOutputs};
+use crate::matrix::{expand_matrix, format_matrix_key, format_matrix_suffix, MatrixCombination};
+#[cfg(feature = "metrics")]
+use crate::metrics::RunMetrics;
+use crate::parser::{
+    discover_workflow_files, parse_workflow_file, parse_workflows_filtered, AssertMode, CleanupStep, Job, ServiceSpec, Step,
+    Workflow, WorkflowFilter,
+    DEFAULT_STAGE, DEFAULT_STAGES, SERVICES, SETUP, TEARDOWN,
+};
+use crate::policy::{glob_match, StepPolicy};
+use crate::progress::Progress;
+use crate::ratelimit::RateLimiter;
+use crate::registry::{call_guarded, DynStepFn, ErasedStepFn, StepRegistry};
+use crate::report::HtmlReport;
+use crate::report_filter::{self, ReportFilter};
+use crate::retry::RetryPolicy;
+use crate::scaffold::{scaffold, scaffold_interactive, ScaffoldSpec};
+use crate::services::{DockerServices, ServiceProvider};
+use crate::snapshot::diff_snapshots;
+use crate::summary::SuiteSummary;
+use crate::validate::{
+    check_identities, check_step_outputs, check_step_policy, check_step_resolution, lint_workflow,
+    ValidationReport,
+};
+use crate::vars;
 use crate::workflow_registry::{is_file_ref, parse_file_ref, WorkflowRegistry};
 use crate::world::World;
 use crate::{Error, Result};
 use colored::Colorize;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::any::Any;
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::marker::PhantomData;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StepResult {
     Passed(Duration),
     Failed(Duration, String),
@@ -30,57 +80,151 @@ impl StepResult {
     pub fn is_failed(&self) -> bool {
         matches!(self, StepResult::Failed(_, _))
     }
+
+    pub fn masked(self, secrets: &SecretMasker) -> Self {
+        match self {
+            StepResult::Failed(d, msg) => StepResult::Failed(d, secrets.mask(&msg)),
+            other => other,
+        }
+    }
+}
+
+/// One step of a job as it ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub name: String,
+    pub result: StepResult,
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// Evaluated `with:` args, with secrets masked
+    #[serde(default)]
+    pub args: RawArgs,
+    /// Wall-clock time; the duration in `result` is simulated (see `Stopwatch`)
+    #[serde(default)]
+    pub wall_duration: Duration,
+    /// Log lines captured while the step ran; only kept for failed steps, and only with
+    /// `capture_logs`
+    #[serde(default)]
+    pub logs: Vec<String>,
+    /// Links the step attached with `StepContext::link`
+    #[serde(default)]
+    pub links: Vec<StepLink>,
+    /// The step's `description:`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl StepRecord {
+    pub fn new(name: impl Into<String>, result: StepResult) -> Self {
+        Self {
+            name: name.into(),
+            result,
+            continue_on_error: false,
+            args: RawArgs::new(),
+            wall_duration: Duration::ZERO,
+            logs: Vec::new(),
+            links: Vec::new(),
+            description: None,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.result.is_passed() || self.continue_on_error
+    }
+
+    /// Failed without `continue-on-error`
+    pub fn failed(&self) -> bool {
+        self.result.is_failed() && !self.continue_on_error
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct JobResult {
     pub name: String,
     pub matrix_suffix: String,
-    /// Steps: (name, result, continue_on_error)
-    pub steps: Vec<(String, StepResult, bool)>,
+    pub steps: Vec<StepRecord>,
     pub outputs: JobOutputs,
     pub duration: Duration,
+    /// Deferred cleanup steps, in the order they ran. Failures here don't fail the job.
+    #[serde(default)]
+    pub cleanup: Vec<(String, StepResult)>,
+    /// Compensating steps, run before the cleanup steps when a step failed
+    #[serde(default)]
+    pub compensations: Vec<(String, StepResult)>,
+    /// The job's matrix values; empty outside a matrix
+    #[serde(default)]
+    pub matrix: MatrixCombination,
+    /// Wall-clock time; `duration` and step durations are simulated (see `Stopwatch`)
+    #[serde(default)]
+    pub wall_duration: Duration,
+    /// Missing keys resolved under `MissingKeyPolicy::Lenient`
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Under `quarantined:` or `allow_failure`: a failure is reported but doesn't fail the run
+    #[serde(default)]
+    pub quarantined: bool,
+    /// The job's `description:`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Why the job couldn't run at all: its world or temp dir failed to initialize
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub infra_error: Option<String>,
 }
 
 impl JobResult {
     pub fn passed(&self) -> bool {
-        self.steps
-            .iter()
-            .all(|(_, r, continue_on_error)| r.is_passed() || *continue_on_error)
+        self.infra_error.is_none() && self.steps.iter().all(StepRecord::passed)
+    }
+
+    /// Failed outside quarantine
+    pub fn fails_run(&self) -> bool {
+        !self.quarantined && !self.passed()
     }
 
     pub fn steps_passed(&self) -> usize {
-        self.steps
-            .iter()
-            .filter(|(_, r, continue_on_error)| r.is_passed() || *continue_on_error)
-            .count()
+        self.steps.iter().filter(|step| step.passed()).count()
     }
 
     pub fn steps_failed(&self) -> usize {
-        self.steps
-            .iter()
-            .filter(|(_, r, continue_on_error)| r.is_failed() && !*continue_on_error)
-            .count()
+        self.steps.iter().filter(|step| step.failed()).count()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct WorkflowResult {
     pub name: String,
     pub jobs: Vec<JobResult>,
     pub duration: Duration,
     pub ignored: Option<String>,
+    /// Workflow-level `assert-after` failures
+    #[serde(default)]
+    pub failed_assertions: Vec<String>,
+    #[serde(default)]
+    pub wall_duration: Duration,
+    /// Under `quarantined:` or `allow_failure`, as are all of its jobs
+    #[serde(default)]
+    pub quarantined: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 impl WorkflowResult {
     pub fn passed(&self) -> bool {
-        self.ignored.is_some() || self.jobs.iter().all(|j| j.passed())
+        self.ignored.is_some()
+            || (self.failed_assertions.is_empty() && self.jobs.iter().all(|j| j.passed()))
     }
 
     pub fn is_ignored(&self) -> bool {
         self.ignored.is_some()
     }
 
+    /// Has a failure outside quarantine: a job's, or a failed `assert-after` check
+    pub fn fails_run(&self) -> bool {
+        !self.is_ignored()
+            && ((!self.quarantined && !self.failed_assertions.is_empty())
+                || self.jobs.iter().any(|j| j.fails_run()))
+    }
+
     pub fn jobs_passed(&self) -> usize {
         self.jobs.iter().filter(|j| j.passed()).count()
     }
@@ -100,29 +244,291 @@ impl WorkflowResult {
 
 pub struct RustActions<W: World + 'static> {
     workflows_path: PathBuf,
+    workflow_filter: WorkflowFilter,
     single_workflow: Option<PathBuf>,
+    /// Jobs to run, with what they need; empty runs every job
+    selected_jobs: Vec<String>,
+    inline_workflows: Vec<Workflow>,
+    /// `workflow_yaml` workflows as (name, YAML), parsed when the run starts
+    yaml_workflows: Vec<(String, String)>,
+    /// `workflow_files`: YAML by the path `@file:` references use
+    memory_files: BTreeMap<PathBuf, String>,
+    results_path: Option<PathBuf>,
+    html_report_path: Option<PathBuf>,
+    coverage_path: Option<PathBuf>,
+    failure_manifest_path: Option<PathBuf>,
+    exit_policy: ExitPolicy,
+    /// Applied in order to what `run` writes to reports
+    report_filters: Vec<Arc<dyn ReportFilter>>,
+    /// Registered before the world's own steps; left out of `step_coverage`
+    builtin_steps: HashSet<String>,
+    /// Workflows that ran each registered step, for `step_coverage`
+    exercised: Mutex<HashMap<String, BTreeSet<String>>>,
+    /// Pushgateway group URL for `push_metrics`
+    #[cfg(feature = "metrics")]
+    metrics_url: Option<String>,
+    #[cfg(feature = "metrics")]
+    metrics: Mutex<RunMetrics>,
+    baseline: Option<(PathBuf, f64)>,
     steps: StepRegistry,
     hooks: HookRegistry<W>,
+    executor: Option<Arc<dyn StepExecutor>>,
+    service_provider: Arc<dyn ServiceProvider>,
+    /// Services started so far, shared by every later job's `ExprContext` as `containers.*`
+    containers: Mutex<Arc<HashMap<String, ContainerInfo>>>,
+    step_policy: StepPolicy,
+    /// Extra policies keyed by workflow name, applied on top of `step_policy`
+    workflow_policies: HashMap<String, StepPolicy>,
+    /// Names jobs and steps may act `as:`
+    identities: BTreeSet<String>,
+    rate_limiter: RateLimiter,
+    /// Expressions compiled by `check_steps`, shared by every job's `ExprContext`
+    exprs: Arc<ExprCache>,
+    output_limit: Option<OutputLimit>,
+    /// How deep `uses: @file:` jobs may call further reusable workflows
+    max_call_depth: usize,
+    default_retry: Option<RetryPolicy>,
+    generators: Arc<GeneratorRegistry>,
+    /// `${{ globals.* }}`, set by the test harness
+    globals: Arc<HashMap<String, Value>>,
+    /// Outputs of successful `setup: true` steps, keyed by step name + evaluated args
+    setup_cache: Mutex<HashMap<String, StepOutputs>>,
+    /// `setup.outputs.*` of the running workflow, from its `setup:` steps
+    setup_outputs: Mutex<Arc<HashMap<String, Value>>>,
+    /// Outputs of pure steps, keyed like `setup_cache` but kept for the whole session
+    pure_cache: Mutex<HashMap<String, StepOutputs>>,
+    /// Outputs of passed `memoize: true` reusable workflow calls, keyed by `memo_key`
+    memoized_calls: Mutex<HashMap<String, JobOutputs>>,
+    /// Where memoized call outputs are shared with other test processes
+    call_cache: Option<CallCache>,
+    /// Values from `mask-outputs`, redacted from everything printed for the rest of the session
+    secrets: Mutex<SecretMasker>,
+    checkpoint_dir: Option<PathBuf>,
+    resume: bool,
+    checkpoint: Mutex<Checkpoint>,
+    /// Idle worlds kept for reuse when `W::RESETTABLE`
+    world_pool: Mutex<Vec<W>>,
+    /// Job outputs of workflows finished this session, keyed by file stem
+    workflow_outputs: Mutex<HashMap<String, HashMap<String, JobOutputs>>>,
+    progress_bar: bool,
+    fail_fast: bool,
+    /// Seed of `shuffle`
+    shuffle_seed: Option<u64>,
+    /// Start jobs of equal priority slowest first, by their last saved wall time
+    slow_jobs_first: bool,
+    streaming: bool,
+    /// Workflow name or `<workflow> / <job>` patterns whose failures don't fail the run
+    allow_failure: Vec<String>,
+    /// Previous wall time per `job_key`, and what's left of the total
+    estimate: Mutex<Option<(HashMap<String, Duration>, Duration)>>,
+    /// Set once a job fails, so `fail_fast` stops scheduling more
+    failed: AtomicBool,
+    cancellation: CancellationToken,
+    cancel_on_ctrl_c: bool,
+    /// Off for embedders that only want the `tracing` events
+    console: bool,
+    /// Level of the `tracing` events recorded while a step runs
+    capture_logs: Option<tracing::Level>,
+    missing_keys: MissingKeyPolicy,
+    progress: Mutex<Progress>,
+    allow_unknown_fields: bool,
+    /// Why the discovered `rust-actions.toml` couldn't be applied, reported by `execute`
+    config_error: Option<String>,
     session_id: String,
+    started_at: u64,
     clock: VirtualClock,
     _phantom: PhantomData<W>,
 }
 
 impl<W: World + 'static> RustActions<W> {
     pub fn new() -> Self {
-        let mut steps = StepRegistry::new();
+        // Built-in steps first, so a project's own step of the same name wins
+        let mut steps = builtin_registry();
+        let builtin_steps = steps.names().into_iter().collect();
         steps.collect_for::<W>();
 
         let session_id = uuid::Uuid::new_v4().to_string().replace("-", "")[..8].to_string();
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
 
-        Self {
+        let runner = Self {
             workflows_path: PathBuf::from("tests/workflows"),
+            workflow_filter: WorkflowFilter::default(),
             single_workflow: None,
+            selected_jobs: Vec::new(),
+            inline_workflows: Vec::new(),
+            yaml_workflows: Vec::new(),
+            memory_files: BTreeMap::new(),
+            results_path: None,
+            html_report_path: None,
+            coverage_path: None,
+            failure_manifest_path: None,
+            exit_policy: ExitPolicy::default(),
+            report_filters: Vec::new(),
+            builtin_steps,
+            exercised: Mutex::new(HashMap::new()),
+            #[cfg(feature = "metrics")]
+            metrics_url: None,
+            #[cfg(feature = "metrics")]
+            metrics: Mutex::new(RunMetrics::new()),
+            baseline: None,
             steps,
             hooks: HookRegistry::new(),
+            executor: None,
+            service_provider: Arc::new(DockerServices::new()),
+            containers: Mutex::new(Arc::default()),
+            step_policy: StepPolicy::new(),
+            workflow_policies: HashMap::new(),
+            identities: BTreeSet::new(),
+            rate_limiter: RateLimiter::default(),
+            exprs: Arc::new(ExprCache::new()),
+            output_limit: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            default_retry: None,
+            generators: Arc::new(GeneratorRegistry::with_builtins()),
+            globals: Arc::default(),
+            setup_cache: Mutex::new(HashMap::new()),
+            setup_outputs: Mutex::new(Arc::default()),
+            pure_cache: Mutex::new(HashMap::new()),
+            memoized_calls: Mutex::new(HashMap::new()),
+            call_cache: None,
+            secrets: Mutex::new(SecretMasker::new()),
+            checkpoint_dir: None,
+            resume: false,
+            checkpoint: Mutex::new(Checkpoint::default()),
+            world_pool: Mutex::new(Vec::new()),
+            workflow_outputs: Mutex::new(HashMap::new()),
+            progress_bar: false,
+            fail_fast: false,
+            shuffle_seed: None,
+            slow_jobs_first: false,
+            streaming: false,
+            allow_failure: Vec::new(),
+            estimate: Mutex::new(None),
+            failed: AtomicBool::new(false),
+            cancellation: CancellationToken::new(),
+            cancel_on_ctrl_c: true,
+            console: true,
+            capture_logs: None,
+            missing_keys: MissingKeyPolicy::Strict,
+            progress: Mutex::new(Progress::default()),
+            allow_unknown_fields: false,
+            config_error: None,
             session_id,
+            started_at,
             clock: VirtualClock::new(),
             _phantom: PhantomData,
+        };
+
+        match Config::discover() {
+            Ok(config) => runner.config(config),
+            Err(e) => runner.with_config_error(e),
+        }
+    }
+
+    /// Applies a `rust-actions.toml`, as `new()` does with the one it finds. Builder calls
+    /// made afterwards override its values.
+    pub fn config(mut self, config: Config) -> Self {
+        let rate_limits = match config.rate_limits() {
+            Ok(limits) => limits,
+            Err(e) => return self.with_config_error(e),
+        };
+        for (pattern, per_second) in rate_limits {
+            self = self.rate_limit(pattern, per_second);
+        }
+        if let Some(path) = &config.workflows {
+            self = self.workflows(path);
+        }
+        for pattern in &config.include_workflows {
+            self = self.include_workflows(pattern);
+        }
+        for pattern in &config.exclude_workflows {
+            self = self.exclude_workflows(pattern);
+        }
+        if let Some(dir) = &config.checkpoint_dir {
+            self = self.checkpoint_dir(dir);
+        }
+        if let Some(dir) = &config.call_cache {
+            self = self.call_cache(dir);
+        }
+        if let Some(enabled) = config.progress_bar {
+            self.progress_bar = enabled;
+        }
+        if let Some(enabled) = config.fail_fast {
+            self.fail_fast = enabled;
+        }
+        if let Some(seed) = config.shuffle {
+            self.shuffle_seed = Some(seed);
+        }
+        if let Some(enabled) = config.streaming {
+            self.streaming = enabled;
+        }
+        for pattern in &config.allow_failure {
+            self = self.allow_failure(pattern);
+        }
+        self = self.identities(config.identities.iter().cloned());
+        if let Some(level) = &config.capture_logs {
+            match level.parse() {
+                Ok(level) => self.capture_logs = Some(level),
+                Err(_) => {
+                    let e = Error::Config(format!("capture-logs: unknown level '{}'", level));
+                    return self.with_config_error(e);
+                }
+            }
+        }
+        if let Some(policy) = config.missing_keys {
+            self.missing_keys = policy;
+        }
+        if let Some(allowed) = config.allow_unknown_fields {
+            self.allow_unknown_fields = allowed;
+        }
+        if let Some(path) = &config.reports.json {
+            self = self.save_results(path);
+        }
+        if let Some(path) = &config.reports.html {
+            self = self.html_report(path);
+        }
+        if let Some(path) = &config.reports.coverage {
+            self = self.coverage_report(path);
+        }
+        if let Some(path) = &config.reports.baseline {
+            self = self.baseline(path, config.reports.regression_ratio.unwrap_or(1.5));
+        }
+        if let Some(path) = &config.reports.failures {
+            self = self.failure_manifest(path);
+        }
+        if let Some(policy) = config.retry_policy() {
+            self = self.default_retry(policy);
+        }
+        if let Some(policy) = config.exit_policy() {
+            self = self.exit_policy(policy);
+        }
+        if let Some(limit) = config.output_limit() {
+            self = self.output_limit(limit);
+        }
+        if let Some(policy) = config.step_policy() {
+            self = self.step_policy(policy);
+        }
+        if let Some(provider) = config.service_provider() {
+            self.service_provider = provider;
+        }
+        self
+    }
+
+    fn with_config_error(mut self, error: Error) -> Self {
+        self.config_error = Some(match error {
+            Error::Config(msg) => msg,
+            other => other.to_string(),
+        });
+        self
+    }
+
+    fn check_config(&self) -> Result<()> {
+        match &self.config_error {
+            Some(msg) => Err(Error::Config(msg.clone())),
+            None => Ok(()),
         }
     }
 
@@ -130,11 +536,31 @@ impl<W: World + 'static> RustActions<W> {
         &self.clock
     }
 
+    /// The id of this run, as steps see it through `StepContext::session_id` and
+    /// `${{ run.session }}`. Nothing is written to the process environment, so runners in
+    /// parallel tests don't see each other's ids.
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
     pub fn workflows(mut self, path: impl Into<PathBuf>) -> Self {
         self.workflows_path = path.into();
         self
     }
 
+    /// Only files matching one of these patterns, relative to the `workflows` directory, are
+    /// workflows; see `WorkflowFilter`. Without any, every YAML, JSON and TOML file is.
+    pub fn include_workflows(mut self, pattern: impl Into<String>) -> Self {
+        self.workflow_filter = self.workflow_filter.include(pattern);
+        self
+    }
+
+    /// Skips files matching `pattern`, e.g. `k8s/**` for manifests kept next to workflows.
+    pub fn exclude_workflows(mut self, pattern: impl Into<String>) -> Self {
+        self.workflow_filter = self.workflow_filter.exclude(pattern);
+        self
+    }
+
     pub fn features(self, path: impl Into<PathBuf>) -> Self {
         self.workflows(path)
     }
@@ -144,565 +570,2845 @@ impl<W: World + 'static> RustActions<W> {
         self
     }
 
-    pub fn register_step(mut self, name: impl Into<String>, func: ErasedStepFn) -> Self {
-        self.steps.register(name, func);
+    /// Runs only the job `name` and the jobs it transitively `needs`, in each selected workflow
+    /// that has it; call it again to add more jobs. Workflows without any of the jobs, and the
+    /// `assert-after` of workflows that lose jobs, are left out. A name no workflow has fails
+    /// the run with `Error::JobNotFound`.
+    pub fn job(mut self, name: impl Into<String>) -> Self {
+        self.selected_jobs.push(name.into());
         self
     }
 
-    pub async fn run(self) {
-        std::env::set_var("RUST_ACTIONS_SESSION_ID", &self.session_id);
+    /// Exposes `value` to every workflow of this runner as `${{ globals.<key> }}`, e.g. a
+    /// tenant id the test created, without going through process env vars.
+    pub fn global(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        Arc::make_mut(&mut self.globals).insert(key.into(), value.into());
+        self
+    }
 
-        // Always build registry to support @file: references in all workflows
-        let registry = match WorkflowRegistry::build(&self.workflows_path) {
-            Ok(r) => Some(r),
-            Err(e) => {
-                eprintln!(
-                    "{} Failed to build workflow registry: {}",
-                    "Error:".red().bold(),
-                    e
-                );
-                std::process::exit(1);
-            }
-        };
+    /// Adds several values as `global` does.
+    pub fn globals<K: Into<String>, V: Into<Value>>(
+        mut self,
+        globals: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        let all = Arc::make_mut(&mut self.globals);
+        all.extend(globals.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
 
-        let workflows: Vec<(PathBuf, Workflow)> = if let Some(ref path) = self.single_workflow {
-            match parse_workflow_file(path) {
-                Ok(w) => vec![w],
-                Err(e) => {
-                    eprintln!("{} Failed to parse workflow: {}", "Error:".red().bold(), e);
-                    std::process::exit(1);
-                }
-            }
-        } else {
-            match parse_workflows(&self.workflows_path) {
-                Ok(w) => w.into_iter().filter(|(_, w)| !w.is_reusable()).collect(),
-                Err(e) => {
-                    eprintln!(
-                        "{} Failed to parse workflows: {}",
-                        "Error:".red().bold(),
-                        e
-                    );
-                    std::process::exit(1);
-                }
-            }
-        };
+    /// Runs a workflow built in code (see `WorkflowBuilder`). Once any are added, only these
+    /// run; the workflows directory is still used to resolve `@file:` references.
+    pub fn add_workflow(mut self, workflow: impl Into<Workflow>) -> Self {
+        self.inline_workflows.push(workflow.into());
+        self
+    }
 
-        self.hooks.run_before_all().await;
+    /// Declares the names jobs and steps may act `as:`. A workflow using any other name fails
+    /// validation before the run starts; see `World::assume_identity`.
+    pub fn identities<I: Into<String>>(mut self, names: impl IntoIterator<Item = I>) -> Self {
+        self.identities.extend(names.into_iter().map(Into::into));
+        self
+    }
 
-        let mut all_results = Vec::new();
-        let mut total_passed = 0;
-        let mut total_failed = 0;
-        let mut total_ignored = 0;
+    /// Runs a workflow from a YAML string, known as `name` in results and checkpoints. Like
+    /// `add_workflow`, once any are added only these run, and the workflows directory isn't
+    /// read at all: `@file:` references resolve against `workflow_files`.
+    pub fn workflow_yaml(mut self, name: impl Into<String>, yaml: impl Into<String>) -> Self {
+        self.yaml_workflows.push((name.into(), yaml.into()));
+        self
+    }
 
-        for (path, workflow) in workflows {
-            if workflow.ignore.is_ignored() {
-                let msg = workflow.ignore.message().unwrap_or("").to_string();
-                let display_msg = if msg.is_empty() {
-                    String::new()
-                } else {
-                    format!(": {}", msg)
-                };
-                println!(
-                    "\n{} {} {}",
-                    "○".yellow(),
-                    workflow.name,
-                    format!("(ignored{})", display_msg).dimmed()
-                );
-                all_results.push(WorkflowResult {
-                    name: workflow.name,
-                    jobs: vec![],
-                    duration: Duration::ZERO,
-                    ignored: Some(msg),
-                });
-                total_ignored += 1;
-                continue;
-            }
+    /// YAML workflows by path, e.g. `reusable/create-team.yaml`, for `@file:` references to
+    /// resolve against without touching the filesystem. Replaces a file of the same path in the
+    /// workflows directory.
+    pub fn workflow_files<P: Into<PathBuf>, Y: Into<String>>(
+        mut self,
+        files: impl IntoIterator<Item = (P, Y)>,
+    ) -> Self {
+        self.memory_files
+            .extend(files.into_iter().map(|(path, yaml)| (path.into(), yaml.into())));
+        self
+    }
 
-            let result = self.run_workflow(&path, workflow, registry.as_ref()).await;
-            total_passed += result.jobs_passed();
-            total_failed += result.jobs_failed();
-            all_results.push(result);
-        }
+    /// Records the outputs of each passed job under `dir` as the run progresses, so an
+    /// interrupted run can be continued with `resume`.
+    pub fn checkpoint_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.checkpoint_dir = Some(dir.into());
+        self
+    }
 
-        self.hooks.run_after_all().await;
+    /// Shares the outputs of passed `memoize: true` reusable workflow calls with other test
+    /// processes through `dir` (see the `call_cache` module), so a setup shared by many test
+    /// binaries runs once. Only use it with environments that outlive a single process.
+    pub fn call_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.call_cache = Some(CallCache::new(dir));
+        self
+    }
 
-        println!();
-        let total_jobs = total_passed + total_failed;
-        let total_steps_passed: usize = all_results.iter().map(|r| r.total_steps_passed()).sum();
-        let total_steps_failed: usize = all_results.iter().map(|r| r.total_steps_failed()).sum();
-        let total_steps = total_steps_passed + total_steps_failed;
-        let _ = total_ignored;
+    /// Continues session `session_id` from its checkpoint: jobs that already passed are skipped
+    /// and their recorded outputs reused. Uses `DEFAULT_CHECKPOINT_DIR` unless
+    /// `checkpoint_dir` is set.
+    pub fn resume(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = session_id.into();
+        self.resume = true;
+        self
+    }
 
-        if total_failed == 0 {
-            println!(
-                "{} {} ({} passed)",
-                format!("{} jobs", total_jobs).green(),
-                "✓".green(),
-                total_passed
-            );
-        } else {
-            println!(
-                "{} ({} passed, {} failed)",
-                format!("{} jobs", total_jobs).yellow(),
-                total_passed,
-                total_failed
-            );
-        }
+    /// Writes the full run result as JSON to `path` when `run()` finishes.
+    pub fn save_results(mut self, path: impl Into<PathBuf>) -> Self {
+        self.results_path = Some(path.into());
+        self
+    }
 
-        println!(
-            "{} ({} passed, {} failed)",
-            format!("{} steps", total_steps),
-            total_steps_passed,
-            total_steps_failed
-        );
+    /// Writes a standalone HTML report of the run to `path` when `run()` finishes.
+    pub fn html_report(mut self, path: impl Into<PathBuf>) -> Self {
+        self.html_report_path = Some(path.into());
+        self
+    }
 
-        if total_failed > 0 {
-            std::process::exit(1);
-        }
+    /// Prints which registered steps no workflow ran, and which deprecated steps workflows
+    /// still run, and writes the full `StepCoverage` as JSON to `path` when `run()` finishes.
+    pub fn coverage_report(mut self, path: impl Into<PathBuf>) -> Self {
+        self.coverage_path = Some(path.into());
+        self
     }
 
-    async fn run_workflow(
-        &self,
-        _path: &PathBuf,
-        workflow: Workflow,
-        registry: Option<&WorkflowRegistry>,
-    ) -> WorkflowResult {
-        let start = self.clock.now();
-        println!("\n{} {}", "Workflow:".bold(), workflow.name);
+    /// Writes a `FailureManifest` as JSON to `path` when `run()` finishes: the exit code, what
+    /// kind of failure caused it, and every failed job with its first failed step.
+    pub fn failure_manifest(mut self, path: impl Into<PathBuf>) -> Self {
+        self.failure_manifest_path = Some(path.into());
+        self
+    }
 
-        let job_order = match toposort_jobs(&workflow.jobs) {
-            Ok(order) => order,
-            Err(e) => {
-                eprintln!("{} {}", "Error:".red().bold(), e);
-                return WorkflowResult {
-                    name: workflow.name,
-                    jobs: vec![],
-                    duration: self.clock.elapsed_since(start),
-                    ignored: None,
-                };
-            }
-        };
+    /// Exit codes for test, validation and infrastructure failures, and how many failed jobs
+    /// `run()` tolerates. Defaults to `ExitPolicy::default()`.
+    pub fn exit_policy(mut self, policy: ExitPolicy) -> Self {
+        self.exit_policy = policy;
+        self
+    }
 
-        let mut job_outputs: HashMap<String, JobOutputs> = HashMap::new();
-        let mut job_results = Vec::new();
+    /// Transforms or drops values before `run()` writes them to the JSON results, the HTML
+    /// report and the failure manifest, after secrets are masked. Filters run in the order
+    /// they're added; see `report_filter` for the built-in ones.
+    pub fn report_filter(mut self, filter: impl ReportFilter + 'static) -> Self {
+        self.report_filters.push(Arc::new(filter));
+        self
+    }
 
-        for job_name in job_order {
-            let job = &workflow.jobs[&job_name];
+    /// Which registered steps the runs so far exercised, and from which workflows.
+    pub fn step_coverage(&self) -> StepCoverage {
+        let exercised = self.exercised.lock().map(|e| e.clone()).unwrap_or_default();
+        StepCoverage::new(&self.steps, &self.builtin_steps, &exercised)
+    }
 
-            if let Some(uses) = &job.uses {
-                if is_file_ref(uses) {
-                    if let Some(reg) = registry {
-                        match self
-                            .run_file_ref_job(&job_name, uses, job, reg, &job_outputs)
-                            .await
-                        {
-                            Ok(result) => {
-                                job_outputs.insert(job_name.clone(), result.outputs.clone());
-                                job_results.push(result);
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "  {} {} ({})",
-                                    "✗".red(),
-                                    job_name,
-                                    e
-                                );
-                            }
-                        }
-                    }
-                    continue;
-                }
-            }
+    /// Pushes the run's `RunMetrics` to the Pushgateway group at `url` when `run()` finishes,
+    /// e.g. `http://pushgateway:9091/metrics/job/rust_actions`.
+    #[cfg(feature = "metrics")]
+    pub fn push_metrics(mut self, url: impl Into<String>) -> Self {
+        self.metrics_url = Some(url.into());
+        self
+    }
 
-            let matrix_combos = job
-                .strategy
-                .as_ref()
-                .map(|s| expand_matrix(s))
-                .unwrap_or_else(|| vec![HashMap::new()]);
+    /// Step outcomes, durations and retries of the runs so far, with the job outcomes of
+    /// `results`.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self, results: &[WorkflowResult]) -> RunMetrics {
+        let metrics = self.metrics.lock().map(|m| m.clone()).unwrap_or_default();
+        metrics.finish(results)
+    }
 
-            for matrix_values in matrix_combos {
-                let result = self
-                    .run_job(&job_name, job, &workflow.env, &job_outputs, &matrix_values)
-                    .await;
-                job_outputs.insert(job_name.clone(), result.outputs.clone());
-                job_results.push(result);
-            }
-        }
+    /// Prints a comparison against a run saved with `save_results`. Jobs taking more than
+    /// `regression_ratio` times their baseline duration are reported as slower.
+    pub fn baseline(mut self, path: impl Into<PathBuf>, regression_ratio: f64) -> Self {
+        self.baseline = Some((path.into(), regression_ratio));
+        self
+    }
 
-        WorkflowResult {
-            name: workflow.name,
-            jobs: job_results,
-            duration: self.clock.elapsed_since(start),
-            ignored: None,
-        }
+    /// Shows a progress bar (jobs done, jobs running, ETA) instead of a line per job and step.
+    /// Failures are still printed as they happen.
+    pub fn progress_bar(mut self) -> Self {
+        self.progress_bar = true;
+        self
     }
 
-    async fn run_file_ref_job(
-        &self,
-        job_name: &str,
-        uses: &str,
-        job: &Job,
-        registry: &WorkflowRegistry,
-        parent_outputs: &HashMap<String, JobOutputs>,
-    ) -> Result<JobResult> {
-        let start = self.clock.now();
-        let file_path = parse_file_ref(uses)?;
-        let ref_workflow = registry.resolve_file_ref(uses)?;
+    /// Stops starting jobs and workflows once a job fails, for quick signal on pre-merge runs.
+    /// The job already running finishes; the rest are reported as not run.
+    pub fn fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
 
-        println!(
-            "  {} {} (via @file:{})",
-            "Job:".dimmed(),
-            job_name,
-            file_path
-        );
+    /// Runs workflows, and the jobs of each workflow free to start, in an order shuffled by
+    /// `seed` instead of file order and `priority:`, to flush out hidden dependencies between
+    /// them. `needs`, `needs-workflow` and stages still hold. The seed is printed at the start
+    /// of the run, and the same seed gives the same order.
+    pub fn shuffle(mut self, seed: u64) -> Self {
+        self.shuffle_seed = Some(seed);
+        self
+    }
 
-        // Build inputs from workflow defaults + caller's with values
-        let mut inputs: HashMap<String, Value> = HashMap::new();
+    /// Among jobs free to start with the same `priority:`, runs the slowest first, by their
+    /// wall time in the `save_results` file, to shorten the suite's critical path.
+    pub fn slow_jobs_first(mut self) -> Self {
+        self.slow_jobs_first = true;
+        self
+    }
 
-        // First, populate with defaults from the workflow's input definitions
-        if let Some(trigger) = &ref_workflow.on {
-            if let Some(call_config) = &trigger.workflow_call {
-                for (input_name, input_def) in &call_config.inputs {
-                    if let Some(default) = &input_def.default {
-                        inputs.insert(input_name.clone(), default.clone());
-                    }
-                }
-            }
-        }
+    /// Parses each workflow file once and drops each runnable workflow as soon as it has run,
+    /// keeping only the reusable ones in the registry, for suites too large to hold twice.
+    pub fn streaming(mut self) -> Self {
+        self.streaming = true;
+        self
+    }
 
-        // Build context for evaluating 'with' expressions (may reference parent outputs)
-        let mut parent_ctx = ExprContext::new();
-        for (dep_name, dep_outputs) in parent_outputs {
-            parent_ctx.needs.insert(dep_name.clone(), dep_outputs.clone());
-        }
+    /// Quarantines the workflows, or `<workflow> / <job>` jobs, whose name `pattern` matches
+    /// (`*` matches anything), as `quarantined: true` does in YAML. They still run and are
+    /// reported, but their failures don't fail the run or stop a `fail_fast` one.
+    pub fn allow_failure(mut self, pattern: impl Into<String>) -> Self {
+        self.allow_failure.push(pattern.into());
+        self
+    }
 
-        // Then, override with values from the caller's 'with' block (evaluate expressions)
-        for (key, value) in &job.with {
-            let evaluated = evaluate_value(value, &parent_ctx).unwrap_or_else(|_| value.clone());
-            inputs.insert(key.clone(), evaluated);
-        }
+    /// Ctrl-C cancels the run by default: steps see `StepContext::cancellation`, no further
+    /// steps, jobs or workflows start, and `after_scenario`/`after_all` hooks and deferred
+    /// cleanups still run before the partial summary. Turn it off to cancel through
+    /// `cancellation_token` from your own handler instead.
+    pub fn cancel_on_ctrl_c(mut self, enabled: bool) -> Self {
+        self.cancel_on_ctrl_c = enabled;
+        self
+    }
 
-        let mut combined_outputs = JobOutputs::new();
+    /// Cancels this runner's runs, as Ctrl-C does. A cancelled runner stays cancelled.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
 
-        let ref_job_order = toposort_jobs(&ref_workflow.jobs)?;
+    /// Turns the built-in console output off. Every finished step, job and workflow is still
+    /// a `tracing` event under the `rust_actions` target, for the embedder's own subscriber.
+    pub fn console(mut self, enabled: bool) -> Self {
+        self.console = enabled;
+        if let Ok(progress) = self.progress.get_mut() {
+            *progress = if enabled { Progress::default() } else { Progress::quiet() };
+        }
+        self
+    }
 
-        let mut ref_job_outputs: HashMap<String, JobOutputs> = HashMap::new();
-        let mut all_step_results = Vec::new();
+    /// Records `tracing` events at `level` or above while each step runs, and shows them under
+    /// the step if it fails, e.g. the HTTP client's logs leading up to an error. Events still
+    /// reach the subscriber that was already installed.
+    pub fn capture_logs(mut self, level: tracing::Level) -> Self {
+        self.capture_logs = Some(level);
+        self
+    }
 
-        for ref_job_name in ref_job_order {
-            let ref_job = &ref_workflow.jobs[&ref_job_name];
+    /// What a `${{ }}` reference to a missing env var, output or input does. `Lenient` resolves
+    /// it to an empty string (null for a whole value or an assertion operand) and lists it as a
+    /// warning on the job, for suites where many env vars are optional.
+    pub fn missing_keys(mut self, policy: MissingKeyPolicy) -> Self {
+        self.missing_keys = policy;
+        self
+    }
+
+    /// Ignores workflow keys the parser doesn't know instead of failing with
+    /// `Error::UnknownFields`, for workflows written against another version of the crate.
+    pub fn allow_unknown_fields(mut self) -> Self {
+        self.allow_unknown_fields = true;
+        self
+    }
+
+    /// Drops the `#[step]` functions collected for `W`, keeping the built-in `fs/*`,
+    /// `assert/json-schema` and `vars/set` steps, so only steps registered explicitly are
+    /// available. Call it before `register_step` and `step_family`, whose steps it drops too.
+    pub fn without_auto_steps(mut self) -> Self {
+        self.steps = builtin_registry();
+        self
+    }
+
+    /// Runs only the steps of `registry`, plus the built-in ones, instead of every `#[step]`
+    /// collected for `W`; e.g. one built with `StepRegistry::collect_for_filtered` to
+    /// simulate a deployment without some services. Its steps replace built-ins of the same
+    /// name.
+    pub fn with_steps(mut self, registry: StepRegistry) -> Self {
+        self.steps = builtin_registry();
+        self.steps.extend(registry);
+        self
+    }
+
+    pub fn register_step(mut self, name: impl Into<String>, func: ErasedStepFn) -> Self {
+        self.steps.register(name, func);
+        self
+    }
+
+    /// Registers a family of steps, e.g. `queue/*` for `queue/orders` and `queue/payments`.
+    /// `factory` gets the part of `uses:` the `*` matched and `config`, and builds that step
+    /// the first time a workflow runs it. Registered steps of the same name win.
+    pub fn step_family<F>(mut self, pattern: impl Into<String>, config: impl Into<Value>, factory: F) -> Self
+    where
+        F: Fn(&str, &Value) -> DynStepFn + Send + Sync + 'static,
+    {
+        self.steps.register_family(pattern, config.into(), Arc::new(factory));
+        self
+    }
+
+    /// Adds a post-condition to a registered step, as `#[step(.., ensures = "...")]` does.
+    pub fn ensure_step(mut self, name: &str, assertion: impl Into<String>) -> Self {
+        self.steps.ensure(name, assertion);
+        self
+    }
+
+    /// Sends the steps `executor` handles to it instead of running them against the job's
+    /// world. Expressions, assertions, retries and cleanup ordering stay on this side.
+    pub fn executor(mut self, executor: impl StepExecutor + 'static) -> Self {
+        self.executor = Some(Arc::new(executor));
+        self
+    }
+
+    /// Provides workflows' `services:`, by default by starting a container for each with
+    /// `DockerServices`. `ExternalServices` resolves them to endpoints that already run, for
+    /// environments where containers can't be started.
+    pub fn service_provider(mut self, provider: impl ServiceProvider + 'static) -> Self {
+        self.service_provider = Arc::new(provider);
+        self
+    }
+
+    /// Runs a step that another runner's `StepExecutor` sent here, against `world`. `defer`
+    /// and `compensate` calls the step makes stay here; workflows that need them remotely
+    /// declare `cleanup:` or `compensate:` instead.
+    pub async fn serve_call(&self, world: &mut W, call: StepCall) -> Result<StepOutputs> {
+        let step_fn = self.steps.callable(&call.uses)?;
+        let ctx = StepContext::new(call.workflow, call.job, call.session_id, self.clock.clone())
+            .with_attempt(call.attempt)
+            .with_env(call.env)
+            .with_matrix(call.matrix)
+            .with_cancellation(self.cancellation.clone());
+        let world_any: &mut dyn Any = world;
+        call_guarded(&step_fn, world_any, call.args, &ctx).await
+    }
+
+    /// Refuses to run if any selected workflow uses a step the policy doesn't allow.
+    pub fn step_policy(mut self, policy: StepPolicy) -> Self {
+        self.step_policy = policy;
+        self
+    }
+
+    /// Like `step_policy`, for the workflow with the given `name:` only.
+    pub fn workflow_step_policy(mut self, workflow: impl Into<String>, policy: StepPolicy) -> Self {
+        self.workflow_policies.insert(workflow.into(), policy);
+        self
+    }
+
+    /// Runs steps whose name matches `pattern` (`*` wildcards, as in `StepPolicy`) at most
+    /// `per_second` times a second each, across the whole suite. A step's own `rate-limit:`
+    /// takes precedence; the first matching pattern applies otherwise.
+    pub fn rate_limit(mut self, pattern: impl Into<String>, per_second: f64) -> Self {
+        self.rate_limiter.add_limit(pattern, per_second);
+        self
+    }
+
+    /// Retries steps that fail with `StepError::Retryable`; other failures are never retried.
+    pub fn default_retry(mut self, policy: RetryPolicy) -> Self {
+        self.default_retry = Some(policy);
+        self
+    }
+
+    /// Caps the size of each step and job output, e.g. `OutputLimit::truncate(1 << 20)` to
+    /// keep at most 1 MiB of a large response body. Unlimited by default.
+    pub fn output_limit(mut self, limit: OutputLimit) -> Self {
+        self.output_limit = Some(limit);
+        self
+    }
+
+    /// How many reusable workflow calls may be nested, counting the outermost one; deeper
+    /// calls fail the job. Defaults to `DEFAULT_MAX_CALL_DEPTH`.
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    /// Registers a hook. Scenario hooks get a `HookContext` to call registered steps with.
+    pub fn hook(mut self, hook: HookDef<W>) -> Self {
+        self.hooks.register(hook);
+        self
+    }
+
+    pub fn generator(mut self, name: impl Into<String>, func: GeneratorFn) -> Self {
+        Arc::make_mut(&mut self.generators).register(name, func);
+        self
+    }
+
+    fn workflow_registry(&self) -> Result<WorkflowRegistry> {
+        let strict = !self.allow_unknown_fields;
+        let mut registry = if self.yaml_workflows.is_empty() {
+            WorkflowRegistry::build_filtered(&self.workflows_path, strict, &self.workflow_filter)?
+        } else {
+            WorkflowRegistry::empty(&self.workflows_path)
+        };
+        for (path, yaml) in &self.memory_files {
+            registry.insert(path.clone(), parse_in_memory(&path.to_string_lossy(), yaml, strict)?);
+        }
+        Ok(registry)
+    }
+
+    /// Runnable workflows selected by `workflows()`/`workflow()`/`add_workflow()`/
+    /// `workflow_yaml()`, narrowed to the jobs selected with `job()`.
+    fn load_workflows(&self) -> Result<Vec<(PathBuf, Workflow)>> {
+        select_jobs(self.load_selected_workflows()?, &self.selected_jobs)
+    }
+
+    fn load_selected_workflows(&self) -> Result<Vec<(PathBuf, Workflow)>> {
+        if !self.inline_workflows.is_empty() || !self.yaml_workflows.is_empty() {
+            let mut workflows: Vec<(PathBuf, Workflow)> = self
+                .inline_workflows
+                .iter()
+                .map(|w| (PathBuf::from(format!("<{}>", w.name)), w.clone()))
+                .collect();
+            for (name, yaml) in &self.yaml_workflows {
+                workflows.push((PathBuf::from(name), parse_in_memory(name, yaml, !self.allow_unknown_fields)?));
+            }
+            Ok(workflows)
+        } else if let Some(ref path) = self.single_workflow {
+            Ok(vec![parse_workflow_file(path, !self.allow_unknown_fields)?])
+        } else {
+            Ok(parse_workflows_filtered(&self.workflows_path, !self.allow_unknown_fields, &self.workflow_filter)?
+                .into_iter()
+                .filter(|(_, w)| !w.is_reusable())
+                .collect())
+        }
+    }
+
+    /// Discovers workflows, jobs and matrix combinations without executing anything.
+    pub fn list(&self) -> Result<SuiteListing> {
+        self.check_config()?;
+        let registry = self.workflow_registry()?;
+        let workflows = self.load_workflows()?;
+        Ok(SuiteListing::build(&workflows, Some(&registry)))
+    }
+
+    /// Renders a new workflow YAML for `spec` using this world's registered steps.
+    pub fn scaffold(&self, spec: &ScaffoldSpec) -> Result<String> {
+        scaffold(spec, &self.steps)
+    }
+
+    /// Like `scaffold`, asking for the workflow's jobs and steps on `output`.
+    pub fn scaffold_interactive(
+        &self,
+        input: impl std::io::BufRead,
+        output: impl std::io::Write,
+    ) -> Result<String> {
+        scaffold_interactive(&self.steps, input, output)
+    }
+
+    pub async fn run(self) {
+        let all_results = match self.execute().await {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::error!(target: "rust_actions", "Failed to load workflows: {}", e);
+                if self.console {
+                    eprintln!("{} Failed to load workflows: {}", "Error:".red().bold(), e);
+                }
+                let manifest = FailureManifest::from_error(&self.session_id, &e, &self.exit_policy);
+                self.save_failure_manifest(&manifest);
+                std::process::exit(manifest.exit_code);
+            }
+        };
+
+        let summary = SuiteSummary::new(&all_results, 0);
+        let jobs_passed = summary.jobs.passed + summary.jobs.warnings;
+        let steps_passed = summary.steps.passed + summary.steps.tolerated;
+        tracing::info!(
+            target: "rust_actions",
+            session_id = %self.session_id,
+            jobs_passed,
+            jobs_failed = summary.jobs.failed,
+            jobs_quarantined = summary.jobs.quarantined,
+            steps_passed,
+            steps_failed = summary.steps.failed,
+            "run finished"
+        );
+
+        if self.console {
+            print_summary(&summary);
+        }
+        let cancelled = self.cancellation.is_cancelled();
+        if cancelled {
+            let not_run = all_results.iter().filter(|r| r.ignored.as_deref() == Some(CANCELLED)).count();
+            tracing::warn!(target: "rust_actions", workflows_not_run = not_run, "run cancelled");
+            if self.console {
+                println!("{} {} workflows not run", "Cancelled:".yellow().bold(), not_run);
+            }
+        }
+
+        if let Some((path, ratio)) = &self.baseline {
+            match RunRecord::load(path) {
+                Ok(base) => {
+                    let diff = RunDiff::compare(&base.workflows, &all_results, *ratio);
+                    tracing::info!(target: "rust_actions", "{}", diff);
+                    if self.console {
+                        println!("\n{}", diff);
+                    }
+                }
+                Err(e) => self.warn(format!("Failed to load baseline {}: {}", path.display(), e)),
+            }
+        }
+
+        let all_results = report_filter::apply(&self.report_filters, all_results);
+        if let Some(path) = &self.html_report_path {
+            let report = HtmlReport::new(&self.session_id, &all_results, &self.secrets());
+            if let Err(e) = report.save(path) {
+                self.warn(format!("Failed to write HTML report to {}: {}", path.display(), e));
+            }
+        }
+
+        if let Some(path) = &self.coverage_path {
+            let coverage = self.step_coverage();
+            tracing::info!(target: "rust_actions", "{}", coverage);
+            if self.console {
+                println!("\n{}", coverage);
+            }
+            if let Err(e) = coverage.save(path) {
+                self.warn(format!("Failed to write step coverage to {}: {}", path.display(), e));
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(url) = &self.metrics_url {
+            if let Err(e) = self.metrics(&all_results).push(url).await {
+                self.warn(format!("Failed to push metrics to {}: {}", url, e));
+            }
+        }
+
+        let manifest = FailureManifest::from_results(&self.session_id, &all_results, &self.exit_policy, cancelled);
+        self.save_failure_manifest(&manifest);
+        if manifest.passed() && manifest.failed_jobs > 0 && self.console {
+            println!(
+                "{} {} failed jobs (max-failed-jobs = {})",
+                "Tolerated:".yellow().bold(),
+                manifest.failed_jobs,
+                manifest.max_failed_jobs
+            );
+        }
+
+        if let Some(path) = &self.results_path {
+            let secrets = self.secrets();
+            let mut all_results = all_results;
+            for job in all_results.iter_mut().flat_map(|workflow| workflow.jobs.iter_mut()) {
+                job.outputs = std::mem::take(&mut job.outputs).masked(&secrets);
+            }
+            if let Err(e) = RunRecord::new(&self.session_id, all_results).save(path) {
+                self.warn(format!("Failed to save results to {}: {}", path.display(), e));
+            }
+        }
+
+        if !manifest.passed() {
+            std::process::exit(manifest.exit_code);
+        }
+    }
+
+    fn save_failure_manifest(&self, manifest: &FailureManifest) {
+        if let Some(path) = &self.failure_manifest_path {
+            if let Err(e) = manifest.save(path) {
+                self.warn(format!("Failed to write failure manifest to {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    /// Runs every selected workflow and returns the results, without printing the suite
+    /// summary or exiting the process.
+    pub async fn execute(&self) -> Result<Vec<WorkflowResult>> {
+        self.check_config()?;
+
+        let streaming = self.streaming
+            && self.inline_workflows.is_empty()
+            && self.yaml_workflows.is_empty()
+            && self.single_workflow.is_none()
+            && self.selected_jobs.is_empty()
+            && self.workflows_path.is_dir();
+        let (registry, workflows) = if streaming {
+            self.plan_streaming()?
+        } else {
+            // Always build registry to support @file: references in all workflows
+            let registry = self.workflow_registry()?;
+            let workflows = order_workflows(self.shuffled(self.load_workflows()?), &registry)?;
+            self.check_steps(&workflows, &registry)?;
+            let workflows = workflows
+                .into_iter()
+                .map(|(path, workflow)| {
+                    let plan = WorkflowPlan::new(&path, &workflow, &registry);
+                    (path, plan, workflow)
+                })
+                .collect();
+            (registry, workflows)
+        };
+
+        if let Some(dir) = self.active_checkpoint_dir() {
+            let checkpoint = if self.resume {
+                let mut checkpoint = Checkpoint::load(&dir, &self.session_id)?;
+                checkpoint.attempt += 1;
+                checkpoint.save(&dir)?;
+                checkpoint
+            } else {
+                Checkpoint::new(&self.session_id)
+            };
+            tracing::info!(target: "rust_actions", session_id = %self.session_id, "checkpointing");
+            self.progress().line(format!(
+                "{} session {} (resume with `.resume(\"{}\")`)",
+                "Checkpointing:".dimmed(),
+                self.session_id,
+                self.session_id
+            ));
+            if let Ok(mut current) = self.checkpoint.lock() {
+                *current = checkpoint;
+            }
+        }
+
+        if let Some(seed) = self.shuffle_seed {
+            self.progress().line(format!(
+                "{} seed {} (reproduce with `.shuffle({})`)",
+                "Shuffling:".dimmed(),
+                seed,
+                seed
+            ));
+        }
+
+        let progress = if !self.console {
+            Progress::quiet()
+        } else if self.progress_bar {
+            let plans = workflows.iter().map(|(_, plan, _)| plan).filter(|p| !p.ignored);
+            let (count, jobs) = plans.fold((0, 0), |(count, jobs), p| (count + 1, jobs + p.job_count));
+            Progress::bar(count, jobs)
+        } else {
+            Progress::default()
+        };
+        if let Ok(mut current) = self.progress.lock() {
+            *current = progress.clone();
+        }
+
+        self.failed.store(false, Ordering::SeqCst);
+        let _ctrl_c = self.cancel_on_ctrl_c.then(|| cancel::on_ctrl_c(&self.cancellation));
+        self.start_estimate(&workflows, &progress);
+        self.hooks.run_before_all().await;
+
+        let mut all_results = Vec::new();
+
+        for (path, plan, workflow) in workflows {
+            if let Some(reason) = self.stopped().filter(|_| !plan.ignored) {
+                progress.line(format!(
+                    "\n{} {} {}",
+                    "↷".dimmed(),
+                    plan.name,
+                    format!("({})", reason).dimmed()
+                ));
+                progress.workflow_finished();
+                all_results.push(WorkflowResult {
+                    name: plan.name,
+                    jobs: vec![],
+                    duration: Duration::ZERO,
+                    ignored: Some(reason.to_string()),
+                    failed_assertions: vec![],
+                    wall_duration: Duration::ZERO,
+                    quarantined: false,
+                    description: None,
+                });
+                continue;
+            }
+            if workflow.ignore.is_ignored() {
+                let msg = workflow.ignore.message().unwrap_or("").to_string();
+                let display_msg = if msg.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", msg)
+                };
+                progress.line(format!(
+                    "\n{} {} {}",
+                    "○".yellow(),
+                    workflow.name,
+                    format!("(ignored{})", display_msg).dimmed()
+                ));
+                all_results.push(WorkflowResult {
+                    name: workflow.name,
+                    jobs: vec![],
+                    duration: Duration::ZERO,
+                    ignored: Some(msg),
+                    failed_assertions: vec![],
+                    wall_duration: Duration::ZERO,
+                    quarantined: false,
+                    description: workflow.description,
+                });
+                continue;
+            }
+
+            let result = self.run_workflow(&path, workflow, Some(&registry)).await;
+            progress.workflow_finished();
+            all_results.push(result);
+            if streaming {
+                self.exprs.clear();
+            }
+        }
+
+        self.hooks.run_after_all().await;
+        self.drain_world_pool().await;
+        self.stop_services().await;
+        progress.finish();
+        if let Ok(mut current) = self.progress.lock() {
+            *current = if self.console { Progress::default() } else { Progress::quiet() };
+        }
+
+        if let Some(dir) = self.active_checkpoint_dir() {
+            if all_results.iter().all(|r| r.passed()) {
+                Checkpoint::remove(&dir, &self.session_id)?;
+            }
+        }
+
+        Ok(all_results)
+    }
+
+    /// The streaming counterpart of loading, checking and ordering every workflow: reusable
+    /// workflows are kept in the registry, runnable ones beside it, each parsed once.
+    fn plan_streaming(&self) -> Result<(WorkflowRegistry, PlannedWorkflows)> {
+        let strict = !self.allow_unknown_fields;
+        let files = discover_workflow_files(&self.workflows_path, &self.workflow_filter)?;
+
+        let mut registry = WorkflowRegistry::empty(&self.workflows_path);
+        let mut runnable = Vec::new();
+        for path in files {
+            let workflow = Workflow::from_file_with(self.workflows_path.join(&path), strict)?;
+            if workflow.is_reusable() {
+                registry.insert(path, workflow);
+            } else {
+                runnable.push((path, workflow));
+            }
+        }
+        self.check_steps(&runnable, &registry)?;
+        // Compiled again as each workflow runs, so they don't all stay resident
+        self.exprs.clear();
+
+        let mut plans = HashMap::new();
+        let mut parsed = HashMap::new();
+        for (path, workflow) in runnable {
+            plans.insert(path.clone(), WorkflowPlan::new(&path, &workflow, &registry));
+            parsed.insert(path, workflow);
+        }
+        let mut selected: Vec<(PathBuf, WorkflowPlan)> =
+            plans.iter().map(|(path, plan)| (path.clone(), plan.clone())).collect();
+        selected.sort_by(|a, b| a.0.cmp(&b.0));
+        let selected = self.shuffled(selected);
+        let ordered = order_by_needs(selected, |plan| plan.needs.clone(), |path| plans.get(path).cloned())?;
+        Ok((
+            registry,
+            ordered
+                .into_iter()
+                .filter_map(|(path, plan)| {
+                    let workflow = parsed.remove(&path)?;
+                    Some((path, plan, workflow))
+                })
+                .collect(),
+        ))
+    }
+
+    /// `items` shuffled by the `shuffle` seed, or as they are without one.
+    fn shuffled<T>(&self, mut items: Vec<T>) -> Vec<T> {
+        if let Some(seed) = self.shuffle_seed {
+            SeededRng::with_seed(seed).shuffle(&mut items);
+        }
+        items
+    }
+
+    /// Checks non-ignored workflows, and the reusable workflows they call, against the
+    /// global and per-workflow step policies, and that every versioned `uses:` resolves
+    /// unambiguously. Unknown steps are printed as warnings.
+    fn check_steps(
+        &self,
+        workflows: &[(PathBuf, Workflow)],
+        registry: &WorkflowRegistry,
+    ) -> Result<()> {
+        let mut report = ValidationReport::new();
+        let mut resolution = ValidationReport::new();
+
+        for (path, workflow) in workflows {
+            if workflow.ignore.is_ignored() {
+                continue;
+            }
+            let mut policies = vec![&self.step_policy];
+            policies.extend(self.workflow_policies.get(&workflow.name));
+
+            let mut targets = vec![(path.clone(), workflow)];
+            for job in workflow.jobs.values() {
+                if let Some(uses) = job.uses.as_deref().filter(|u| is_file_ref(u)) {
+                    if let Ok(reusable) = registry.resolve_file_ref(uses) {
+                        targets.push((PathBuf::from(parse_file_ref(uses)?), reusable));
+                    }
+                }
+            }
+
+            for policy in policies.into_iter().filter(|p| !p.is_empty()) {
+                for (target_path, target) in &targets {
+                    check_step_policy(target_path, target, policy, &mut report);
+                }
+            }
+            for (target_path, target) in &targets {
+                check_identities(target_path, target, &self.identities, &mut resolution);
+                check_step_resolution(target_path, target, &self.steps, &|uses| self.remote(uses).is_some(), &mut resolution);
+                check_step_outputs(target_path, target, &self.steps, &mut resolution);
+                lint_workflow(target_path, target, &mut resolution);
+                self.exprs.compile_workflow(target);
+            }
+        }
+
+        for warning in &resolution.warnings {
+            self.warn(warning);
+        }
+
+        let messages = |report: &ValidationReport| {
+            let errors: Vec<String> = report.errors.iter().map(|e| e.to_string()).collect();
+            errors.join("\n")
+        };
+        if !report.is_valid() {
+            Err(Error::StepPolicy(messages(&report)))
+        } else if !resolution.is_valid() {
+            Err(Error::Validation(messages(&resolution)))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn active_checkpoint_dir(&self) -> Option<PathBuf> {
+        match &self.checkpoint_dir {
+            Some(dir) => Some(dir.clone()),
+            None if self.resume => Some(PathBuf::from(DEFAULT_CHECKPOINT_DIR)),
+            None => None,
+        }
+    }
+
+    fn completed_workflow_outputs(&self) -> HashMap<String, HashMap<String, JobOutputs>> {
+        self.workflow_outputs
+            .lock()
+            .map(|outputs| outputs.clone())
+            .unwrap_or_default()
+    }
+
+    fn checkpointed_outputs(&self, workflow: &Path, job: &str) -> Option<JobOutputs> {
+        self.checkpoint.lock().ok()?.get(workflow, job).cloned()
+    }
+
+    /// Jobs whose outputs hold a secret aren't recorded and run again on resume: the
+    /// checkpoint is a file on disk, and masked outputs would hand `***` to the jobs after it.
+    fn record_checkpoint(&self, workflow: &Path, job: &str, outputs: JobOutputs) {
+        let Some(dir) = self.active_checkpoint_dir() else {
+            return;
+        };
+        if outputs.clone().masked(&self.secrets()) != outputs {
+            return;
+        }
+        if let Ok(mut checkpoint) = self.checkpoint.lock() {
+            checkpoint.record(workflow, job, outputs);
+            if let Err(e) = checkpoint.save(&dir) {
+                self.warn(format!("Failed to save checkpoint: {}", e));
+            }
+        }
+    }
+
+    async fn run_workflow(
+        &self,
+        path: &Path,
+        workflow: Workflow,
+        registry: Option<&WorkflowRegistry>,
+    ) -> WorkflowResult {
+        let start = self.clock.stopwatch();
+        let progress = self.progress();
+        progress.workflow_started(&workflow.name);
+        let quarantined = workflow.quarantined || self.allows_failure(&workflow.name);
+
+        if let Ok(mut cache) = self.setup_cache.lock() {
+            cache.clear();
+        }
+
+        let previous = |job: &str| self.previous_duration(&workflow.name, job);
+        let job_order = match self.shuffle_seed {
+            Some(seed) => order_jobs_shuffled(&workflow, seed),
+            None => order_jobs_by(&workflow, previous),
+        };
+        let job_order = match job_order {
+            Ok(order) => order,
+            Err(e) => {
+                progress.report(false, &[format!("{} {}", "Error:".red().bold(), e)]);
+                return WorkflowResult {
+                    name: workflow.name,
+                    jobs: vec![],
+                    duration: start.elapsed(),
+                    ignored: None,
+                    failed_assertions: vec![],
+                    wall_duration: start.wall(),
+                    quarantined,
+                    description: workflow.description,
+                };
+            }
+        };
+
+        let mut job_outputs: HashMap<String, JobOutputs> = HashMap::new();
+        let mut job_results = Vec::new();
+
+        let mut setup_failed = false;
+        if let Err(e) = self.start_services(&workflow.services).await {
+            setup_failed = true;
+            progress.report(false, &[format!("  {} {} ({})", "✗".red(), SERVICES, e)]);
+            let result = JobResult {
+                name: SERVICES.to_string(),
+                infra_error: Some(e.to_string()),
+                ..Default::default()
+            };
+            job_results.push(self.phase_finished(result, quarantined));
+        }
+
+        let mut setup_phase = None;
+        if !setup_failed && (!workflow.setup.is_empty() || !workflow.teardown.is_empty()) {
+            match self.start_setup_phase().await {
+                Ok(mut phase) => {
+                    if !workflow.setup.is_empty() {
+                        let result = self.run_phase(&workflow, SETUP, &mut phase).await;
+                        setup_failed = !result.passed();
+                        job_results.push(self.phase_finished(result, quarantined));
+                    }
+                    setup_phase = Some(phase);
+                }
+                Err(e) => {
+                    setup_failed = true;
+                    progress.report(false, &[format!("  {} {} ({})", "✗".red(), SETUP, e)]);
+                    let result = JobResult {
+                        name: SETUP.to_string(),
+                        steps: vec![StepRecord::new(SETUP, StepResult::Failed(Duration::ZERO, e.to_string()))],
+                        infra_error: Some(e.to_string()),
+                        ..Default::default()
+                    };
+                    job_results.push(self.phase_finished(result, quarantined));
+                }
+            }
+        }
+
+        for job_name in job_order {
+            let job = &workflow.jobs[&job_name];
+
+            let matrix_combos = job
+                .strategy
+                .as_ref()
+                .map(expand_matrix)
+                .unwrap_or_else(|| vec![HashMap::new()]);
+
+            if let Some(reason) = self.stopped() {
+                progress.line(format!("  {} {} ({})", "↷".dimmed(), job_name, reason));
+                progress.jobs_skipped(matrix_combos.len());
+                continue;
+            }
+
+            if setup_failed {
+                progress.line(format!("  {} {} (setup failed)", "↷".dimmed(), job_name));
+                progress.jobs_skipped(matrix_combos.len());
+                continue;
+            }
+
+            if let Some(outputs) = self.checkpointed_outputs(path, &job_name) {
+                progress.line(format!("  {} {} (passed in a previous run)", "↷".dimmed(), job_name));
+                progress.jobs_skipped(matrix_combos.len());
+                job_outputs.insert(job_name.clone(), outputs);
+                continue;
+            }
+
+            let first_result = job_results.len();
+
+            let unmet = verify_needs(job, &job_outputs);
+            if !unmet.is_empty() {
+                let mut lines = vec![format!("  {} {} (verify-needs)", "✗".red(), job_name)];
+                lines.extend(unmet.iter().map(|msg| format!("    {}: {}", "Error".red(), msg)));
+                progress.report(false, &lines);
+                progress.jobs_skipped(matrix_combos.len());
+                job_results.push(JobResult {
+                    name: job_name.clone(),
+                    steps: vec![StepRecord::new(
+                        "verify-needs",
+                        StepResult::Failed(Duration::ZERO, unmet.join("; ")),
+                    )],
+                    ..Default::default()
+                });
+            } else if let Some(uses) = job.uses.as_deref().filter(|u| is_file_ref(u)) {
+                if let Some(reg) = registry {
+                    // With a matrix, outputs are namespaced per combination:
+                    // needs.<job>.outputs.<matrix key>.<output>
+                    let mut namespaced = JobOutputs::new();
+                    for matrix_values in &matrix_combos {
+                        let label = format!("{}{}", job_name, format_matrix_suffix(matrix_values));
+                        progress.job_started(&label);
+                        let result = self
+                            .run_file_ref_job(
+                                &job_name,
+                                uses,
+                                job,
+                                reg,
+                                &job_outputs,
+                                matrix_values,
+                                &[],
+                            )
+                            .await;
+                        progress.job_finished(&label);
+                        match result {
+                            Ok(result) => {
+                                if matrix_values.is_empty() {
+                                    namespaced = result.outputs.clone();
+                                } else {
+                                    namespaced.insert(
+                                        format_matrix_key(matrix_values),
+                                        result.outputs.to_value(),
+                                    );
+                                }
+                                job_results.push(result);
+                            }
+                            Err(e) => {
+                                progress.report(
+                                    false,
+                                    &[format!("  {} {} ({})", "✗".red(), label, e)],
+                                );
+                                job_results.push(JobResult {
+                                    name: job_name.clone(),
+                                    matrix_suffix: format_matrix_suffix(matrix_values),
+                                    steps: vec![StepRecord::new(
+                                        uses,
+                                        StepResult::Failed(Duration::ZERO, e.to_string()),
+                                    )],
+                                    matrix: matrix_values.clone(),
+                                    ..Default::default()
+                                });
+                            }
+                        }
+                    }
+                    job_outputs.insert(job_name.clone(), namespaced);
+                }
+            } else {
+                for matrix_values in matrix_combos {
+                    let label = format!("{}{}", job_name, format_matrix_suffix(&matrix_values));
+                    progress.job_started(&label);
+                    let result = self
+                        .run_job(
+                            &workflow.name,
+                            &job_name,
+                            job,
+                            &workflow.env,
+                            &job_outputs,
+                            &matrix_values,
+                        )
+                        .await;
+                    progress.job_finished(&label);
+                    // Each combination adds its outputs, so names with `${{ matrix.* }}` in
+                    // them give fan-in jobs one output per variant
+                    job_outputs
+                        .entry(job_name.clone())
+                        .or_default()
+                        .extend(result.outputs.clone());
+                    job_results.push(result);
+                }
+            }
+
+            let job_quarantined = quarantined
+                || job.quarantined
+                || self.allows_failure(&job_key(&workflow.name, &job_name));
+            for result in &mut job_results[first_result..] {
+                result.quarantined = job_quarantined;
+                result.description = job.description.clone();
+            }
+            let ran = &job_results[first_result..];
+            let secrets = self.secrets();
+            for result in ran {
+                events::job_finished(&workflow.name, result, &secrets);
+            }
+            if !job_quarantined && ran.iter().any(|r| !r.passed()) {
+                self.failed.store(true, Ordering::SeqCst);
+            }
+            self.finish_estimate(&workflow.name, &job_name, &progress);
+            if !ran.is_empty() && ran.iter().all(|r| r.passed()) {
+                if let Some(outputs) = job_outputs.get(&job_name) {
+                    self.record_checkpoint(path, &job_name, outputs.clone());
+                }
+            }
+        }
+
+        // Teardown runs whether or not setup and the jobs passed
+        if let Some(mut phase) = setup_phase {
+            if !workflow.teardown.is_empty() {
+                let result = self.run_phase(&workflow, TEARDOWN, &mut phase).await;
+                job_results.push(self.phase_finished(result, quarantined));
+            }
+            self.release_world(phase.world).await;
+        }
+        if let Ok(mut setup) = self.setup_outputs.lock() {
+            *setup = Arc::default();
+        }
+
+        let mut failed_assertions = Vec::new();
+        if !workflow.post_assert.is_empty() {
+            let mut ctx = self.expr_context();
+            ctx.env = Arc::new(workflow.env.clone());
+            ctx.jobs = Arc::new(job_outputs.clone());
+            failed_assertions = check_assertions(&workflow.post_assert, &ctx);
+            if !quarantined && !failed_assertions.is_empty() {
+                self.failed.store(true, Ordering::SeqCst);
+            }
+            for msg in &failed_assertions {
+                progress.report(
+                    false,
+                    &[
+                        format!("  {} assert-after", "✗".red()),
+                        format!("    {}: {}", "Error".red(), msg),
+                    ],
+                );
+            }
+        }
+
+        if let Ok(mut completed) = self.workflow_outputs.lock() {
+            completed.insert(workflow_key(path), job_outputs);
+        }
+
+        let result = WorkflowResult {
+            name: workflow.name,
+            jobs: job_results,
+            duration: start.elapsed(),
+            ignored: None,
+            failed_assertions,
+            wall_duration: start.wall(),
+            quarantined,
+            description: workflow.description,
+        };
+        events::workflow_finished(&result);
+        result
+    }
+
+    /// Runs a job calling a reusable workflow. Each call gets its own inputs, contexts and
+    /// job outputs, so a caller can call the same workflow several times. `calls` are the
+    /// calls this one is nested in, outermost first.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_file_ref_job(
+        &self,
+        job_name: &str,
+        uses: &str,
+        job: &Job,
+        registry: &WorkflowRegistry,
+        parent_outputs: &HashMap<String, JobOutputs>,
+        matrix_values: &MatrixCombination,
+        calls: &[CallFrame],
+    ) -> Result<JobResult> {
+        let start = self.clock.stopwatch();
+        let matrix_suffix = format_matrix_suffix(matrix_values);
+        let file_path = parse_file_ref(uses)?;
+        let ref_workflow = registry.resolve_file_ref(uses)?;
+
+        let mut lines = vec![format!(
+            "  {} {}{} (via @file:{})",
+            "Job:".dimmed(),
+            job_name,
+            matrix_suffix,
+            file_path
+        )];
+
+        // Build inputs from workflow defaults + caller's with values
+        let mut inputs: HashMap<String, Value> = HashMap::new();
+
+        // First, populate with defaults from the workflow's input definitions
+        if let Some(trigger) = &ref_workflow.on {
+            if let Some(call_config) = &trigger.workflow_call {
+                for (input_name, input_def) in &call_config.inputs {
+                    if let Some(default) = &input_def.default {
+                        inputs.insert(input_name.clone(), default.clone());
+                    }
+                }
+            }
+        }
+
+        // Build context for evaluating 'with' expressions (may reference parent outputs)
+        let mut parent_ctx = self.expr_context();
+        if let Some(caller) = calls.last() {
+            parent_ctx.inputs = Arc::new(caller.inputs.clone());
+        }
+        parent_ctx.matrix = Arc::new(matrix_values.clone());
+        parent_ctx.workflows = Arc::new(self.completed_workflow_outputs());
+        let mut parent_needs = parent_outputs.clone();
+        parent_needs.extend(aliased_needs(job, parent_outputs));
+        parent_ctx.needs = Arc::new(parent_needs);
+
+        // Then, override with values from the caller's 'with' block (evaluate expressions)
+        // Object values are merged into an object default key by key, at any depth
+        for (key, value) in &job.with {
+            let evaluated = evaluate_value(value, &parent_ctx).unwrap_or_else(|_| value.clone());
+            match inputs.get_mut(key) {
+                Some(default) => deep_merge(default, evaluated),
+                None => {
+                    inputs.insert(key.clone(), evaluated);
+                }
+            }
+        }
+
+        // A call repeating one it's nested in with the same inputs would never end; one with
+        // other inputs may, so only the depth limit stops it
+        let chain = || {
+            let files: Vec<&str> = calls.iter().map(|c| c.file.as_str()).chain([file_path]).collect();
+            files.join(" -> ")
+        };
+        if calls.iter().any(|c| c.file == file_path && c.inputs == inputs) {
+            return Err(Error::CircularDependency {
+                chain: format!("{} (called again with the same inputs)", chain()),
+            });
+        }
+        if calls.len() >= self.max_call_depth {
+            return Err(Error::CallDepthExceeded {
+                limit: self.max_call_depth,
+                chain: chain(),
+            });
+        }
+
+        let call_config = ref_workflow.on.as_ref().and_then(|on| on.workflow_call.as_ref());
+        let memo = call_config
+            .filter(|config| config.memoize)
+            .map(|_| memo_key(file_path, &inputs, matrix_values));
+        let cache_key = memo
+            .as_ref()
+            .filter(|_| self.call_cache.is_some())
+            .map(|key| CallCache::key(registry, ref_workflow, key));
+        let memoized = memo
+            .as_ref()
+            .and_then(|key| self.memoized_calls.lock().ok()?.get(key).cloned())
+            .map(|outputs| (outputs, "(memoized)"))
+            .or_else(|| {
+                let outputs = self.call_cache.as_ref()?.get(cache_key.as_ref()?)?;
+                if let (Some(key), Ok(mut memoized)) = (&memo, self.memoized_calls.lock()) {
+                    memoized.insert(key.clone(), outputs.clone());
+                }
+                Some((outputs, "(cached)"))
+            });
+        if let Some((outputs, reused)) = memoized {
+            lines[0].push_str(&format!(" {}", reused.dimmed()));
+            self.progress().report(true, &lines);
+            return Ok(JobResult {
+                name: job_name.to_string(),
+                matrix_suffix,
+                outputs,
+                duration: start.elapsed(),
+                matrix: matrix_values.clone(),
+                wall_duration: start.wall(),
+                quarantined: job.quarantined,
+                description: job.description.clone(),
+                ..Default::default()
+            });
+        }
+
+        let mut calls = calls.to_vec();
+        calls.push(CallFrame {
+            file: file_path.to_string(),
+            inputs: inputs.clone(),
+        });
+
+        let mut combined_outputs = JobOutputs::new();
+
+        let ref_job_order = order_jobs(ref_workflow)?;
+
+        let mut ref_job_outputs: HashMap<String, JobOutputs> = HashMap::new();
+        let mut all_step_results = Vec::new();
+        let mut all_warnings = Vec::new();
+        let mut all_cleanup = Vec::new();
+        let mut all_compensations = Vec::new();
+
+        for ref_job_name in ref_job_order {
+            let ref_job = &ref_workflow.jobs[&ref_job_name];
+
+            if let Some(nested_uses) = ref_job.uses.as_deref().filter(|u| is_file_ref(u)) {
+                let mut visible = parent_outputs.clone();
+                visible.extend(ref_job_outputs.clone());
+                let nested = Box::pin(self.run_file_ref_job(
+                    &format!("{}/{}", job_name, ref_job_name),
+                    nested_uses,
+                    ref_job,
+                    registry,
+                    &visible,
+                    matrix_values,
+                    &calls,
+                ))
+                .await;
+                match nested {
+                    Ok(nested) => {
+                        all_step_results.extend(nested.steps);
+                        all_warnings.extend(nested.warnings);
+                        all_cleanup.extend(nested.cleanup);
+                        all_compensations.extend(nested.compensations);
+                        ref_job_outputs.insert(ref_job_name.clone(), nested.outputs);
+                    }
+                    Err(e) => {
+                        let failed = StepResult::Failed(Duration::ZERO, e.to_string());
+                        lines.extend(step_lines(nested_uses, &failed, false, &[], &[]));
+                        all_step_results.push(StepRecord::new(nested_uses, failed));
+                    }
+                }
+                continue;
+            }
+
+            // Before the world, so a failure here has no world to give back
+            let temp_dir = self.create_job_temp_dir()?;
+
+            let mut world = match self.acquire_world().await {
+                Ok(w) => w,
+                Err(e) => {
+                    return Ok(JobResult {
+                        name: job_name.to_string(),
+                        matrix_suffix,
+                        duration: start.elapsed(),
+                        matrix: matrix_values.clone(),
+                        wall_duration: start.wall(),
+                        infra_error: Some(e.to_string()),
+                        ..Default::default()
+                    });
+                }
+            };
+
+            let mut ctx = self.expr_context();
+            ctx.env = Arc::new(ref_workflow.env.clone());
+            ctx.inputs = Arc::new(inputs.clone());
+            ctx.matrix = parent_ctx.matrix.clone();
+            ctx.workflows = parent_ctx.workflows.clone();
+            ctx.runner = Some(self.runner_info(temp_dir.path()));
+            ctx.run = Some(self.run_info(&ref_workflow.name, &ref_job_name));
+            ctx.random = Some(self.random_context(&format!(
+                "{}{}/{}",
+                job_name, matrix_suffix, ref_job_name
+            )));
+
+            // The called workflow's own jobs win over the caller's of the same name
+            let needs = Arc::make_mut(&mut ctx.needs);
+            for (dep_name, dep_outputs) in parent_outputs {
+                needs.insert(dep_name.clone(), dep_outputs.clone());
+            }
+            needs.extend(aliased_needs(job, parent_outputs));
+            for (dep_name, dep_outputs) in &ref_job_outputs {
+                needs.insert(dep_name.clone(), dep_outputs.clone());
+            }
+
+            let step_ctx = StepContext::new(
+                &ref_workflow.name,
+                &ref_job_name,
+                &self.session_id,
+                self.clock.clone(),
+            )
+            .with_attempt(self.attempt())
+            .with_env((*ctx.env).clone())
+            .with_matrix(matrix_values.clone())
+            .with_cancellation(self.cancellation.clone())
+            .with_temp_dir(temp_dir.path())
+            .with_job_identity(ref_job.identity.clone().or_else(|| job.identity.clone()));
+
+            #[allow(unused_variables)]
+            let step_outputs: HashMap<String, Value> = HashMap::new();
+
+            // Only this job's own steps decide whether it compensates
+            let first_step = all_step_results.len();
+            let plan = plan_steps(&ref_job.steps).unwrap_or_else(|e| {
+                let failed = StepResult::Failed(Duration::ZERO, e.to_string());
+                lines.extend(step_lines("depends-on", &failed, false, &[], &[]));
+                all_step_results.push(StepRecord::new("depends-on", failed));
+                Vec::new()
+            });
+            // Steps that failed or were skipped; steps waiting on them are skipped too
+            let mut blocked = HashSet::new();
+            for (index, waits_on) in plan {
+                let step = &ref_job.steps[index];
+                let step_name = step.name.clone().unwrap_or_else(|| step.uses.clone());
+                if self.cancellation.is_cancelled() || waits_on.iter().any(|i| blocked.contains(i)) {
+                    blocked.insert(index);
+                    lines.extend(step_lines(&step_name, &StepResult::Skipped, false, &[], &[]));
+                    all_step_results.push(StepRecord {
+                        description: step.description.clone(),
+                        ..StepRecord::new(step_name, StepResult::Skipped)
+                    });
+                    continue;
+                }
+                let watch = self.clock.stopwatch();
+                let (result, logs, links) = self.run_logged_step(&mut world, step, &mut ctx, &step_ctx).await;
+                if result.is_failed() && !step.continue_on_error {
+                    blocked.insert(index);
+                }
+                lines.extend(step_lines(&step_name, &result, step.continue_on_error, &logs, &links));
+                all_step_results.push(StepRecord {
+                    name: step_name,
+                    result,
+                    continue_on_error: step.continue_on_error,
+                    args: self.masked_args(&ctx),
+                    wall_duration: watch.wall(),
+                    logs,
+                    links,
+                    description: step.description.clone(),
+                });
+            }
+            if all_step_results[first_step..].iter().any(StepRecord::failed) {
+                let compensations = self.run_compensations(&mut world, &step_ctx).await;
+                lines.extend(deferred_lines("compensate:", &compensations));
+                all_compensations.extend(compensations);
+            }
+            let cleanup = self.run_cleanup(&mut world, &step_ctx).await;
+            lines.extend(deferred_lines("cleanup:", &cleanup));
+            all_cleanup.extend(cleanup);
+            self.release_world(world).await;
+
+            let mut ref_job_output = JobOutputs::new();
+            for (key, expr) in &ref_job.outputs {
+                if let Some((key, value)) = evaluate_output(key, expr, &ctx) {
+                    ref_job_output.insert(key, value);
+                }
+            }
+            ref_job_outputs.insert(ref_job_name.clone(), ref_job_output.clone());
+            all_warnings.extend(missing_key_warnings(&ctx));
+        }
+
+        if let Some(call_config) = call_config {
+            for (key, output_def) in &call_config.outputs {
+                let mut eval_ctx = self.expr_context();
+                eval_ctx.jobs = Arc::new(ref_job_outputs.clone());
+                eval_ctx.matrix = parent_ctx.matrix.clone();
+                if let Some((key, value)) = evaluate_output(key, &output_def.value, &eval_ctx) {
+                    combined_outputs.insert(key, value);
+                }
+            }
+        }
+
+        let passed = all_step_results.iter().all(StepRecord::passed);
+        let cleaned_up = all_cleanup.iter().all(|(_, r)| r.is_passed());
+        // Only a call that passed is reused; the next caller runs a failed one again
+        if let Some(key) = memo.filter(|_| passed && !self.cancellation.is_cancelled()) {
+            if let Ok(mut memoized) = self.memoized_calls.lock() {
+                memoized.entry(key).or_insert_with(|| combined_outputs.clone());
+            }
+            if let (Some(cache), Some(key)) = (&self.call_cache, &cache_key) {
+                // The cache only saves time; a run that can't write it still passes
+                if let Err(e) = cache.put(key, &combined_outputs) {
+                    tracing::warn!(target: "rust_actions", dir = %cache.dir().display(), "Failed to cache call outputs: {}", e);
+                }
+            }
+        }
+        lines.extend(warning_lines(&all_warnings));
+        self.progress().report(passed && cleaned_up, &lines);
+
+        Ok(JobResult {
+            name: job_name.to_string(),
+            matrix_suffix,
+            steps: all_step_results,
+            outputs: combined_outputs,
+            duration: start.elapsed(),
+            cleanup: all_cleanup,
+            compensations: all_compensations,
+            matrix: matrix_values.clone(),
+            wall_duration: start.wall(),
+            warnings: all_warnings,
+            ..Default::default()
+        })
+    }
+
+    /// Takes the world and temp dir a workflow's `setup:` and `teardown:` steps share.
+    async fn start_setup_phase(&self) -> Result<SetupPhase<W>> {
+        let world = self.acquire_world().await?;
+        let temp_dir = self.create_job_temp_dir()?;
+        Ok(SetupPhase { world, temp_dir, step_ctx: None })
+    }
+
+    /// Runs the workflow's `setup:` or `teardown:` steps in the phase's world. Setup stops at
+    /// the first failure and publishes the outputs of its steps with an `id:` as
+    /// `setup.outputs.*`; teardown runs every step, then whatever cleanup the setup steps
+    /// deferred.
+    async fn run_phase(&self, workflow: &Workflow, phase_name: &str, phase: &mut SetupPhase<W>) -> JobResult {
+        let start = self.clock.stopwatch();
+        let progress = self.progress();
+        let teardown = phase_name == TEARDOWN;
+        let steps = if teardown { &workflow.teardown } else { &workflow.setup };
+
+        let mut ctx = self.expr_context();
+        ctx.env = Arc::new(workflow.env.clone());
+        ctx.workflows = Arc::new(self.completed_workflow_outputs());
+        ctx.runner = Some(self.runner_info(phase.temp_dir.path()));
+        ctx.run = Some(self.run_info(&workflow.name, phase_name));
+        ctx.random = Some(self.random_context(&format!("{}/{}", workflow.name, phase_name)));
+
+        // Shared by both phases, so teardown runs the cleanup setup steps deferred
+        let step_ctx = phase
+            .step_ctx
+            .get_or_insert_with(|| {
+                StepContext::new(&workflow.name, SETUP, &self.session_id, self.clock.clone())
+                    .with_attempt(self.attempt())
+                    .with_env(workflow.env.clone())
+                    .with_cancellation(self.cancellation.clone())
+                    .with_temp_dir(phase.temp_dir.path())
+            })
+            .clone();
+
+        let mut step_results = Vec::new();
+        let mut failed = false;
+        for step in steps {
+            let step_name = step.name.clone().unwrap_or_else(|| step.uses.clone());
+            if !teardown && (failed || self.cancellation.is_cancelled()) {
+                step_results.push(StepRecord {
+                    description: step.description.clone(),
+                    ..StepRecord::new(step_name, StepResult::Skipped)
+                });
+                continue;
+            }
+
+            self.hooks.run_before_step(&mut phase.world, step).await;
+            let watch = self.clock.stopwatch();
+            let (result, logs, links) = self.run_logged_step(&mut phase.world, step, &mut ctx, &step_ctx).await;
+            self.hooks.run_after_step(&mut phase.world, step, &result).await;
+
+            failed |= result.is_failed() && !step.continue_on_error;
+            step_results.push(StepRecord {
+                name: step_name,
+                result,
+                continue_on_error: step.continue_on_error,
+                args: self.masked_args(&ctx),
+                wall_duration: watch.wall(),
+                logs,
+                links,
+                description: step.description.clone(),
+            });
+        }
+
+        let cleanup = if teardown {
+            self.run_cleanup(&mut phase.world, &step_ctx).await
+        } else {
+            let mut outputs = HashMap::new();
+            for id in steps.iter().filter_map(|step| step.id.as_ref()) {
+                if let Some(Value::Object(step_outputs)) = ctx.steps.get(id).map(StepOutputs::to_value) {
+                    outputs.extend(step_outputs);
+                }
+            }
+            if let Ok(mut setup) = self.setup_outputs.lock() {
+                *setup = Arc::new(outputs);
+            }
+            Vec::new()
+        };
+
+        let warnings = missing_key_warnings(&ctx);
+        let duration = start.elapsed();
+        let mut lines = vec![format!(
+            "  {} {} ({})",
+            if failed { "✗".red() } else { "✓".green() },
+            phase_name,
+            format_durations(duration, start.wall())
+        )];
+        for step in &step_results {
+            lines.extend(step_lines(&step.name, &step.result, step.continue_on_error, &step.logs, &step.links));
+        }
+        lines.extend(deferred_lines("cleanup:", &cleanup));
+        lines.extend(warning_lines(&warnings));
+        progress.report(!failed, &lines);
+
+        JobResult {
+            name: phase_name.to_string(),
+            steps: step_results,
+            duration,
+            cleanup,
+            wall_duration: start.wall(),
+            warnings,
+            ..Default::default()
+        }
+    }
+
+    /// Counts a `setup:` or `teardown:` result like one of the workflow's jobs.
+    fn phase_finished(&self, mut result: JobResult, quarantined: bool) -> JobResult {
+        result.quarantined = quarantined;
+        if result.fails_run() {
+            self.failed.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    async fn run_job(
+        &self,
+        workflow_name: &str,
+        job_name: &str,
+        job: &Job,
+        workflow_env: &HashMap<String, String>,
+        parent_outputs: &HashMap<String, JobOutputs>,
+        matrix_values: &MatrixCombination,
+    ) -> JobResult {
+        let start = self.clock.stopwatch();
+        let matrix_suffix = format_matrix_suffix(matrix_values);
+
+        let progress = self.progress();
+
+        let mut world = match self.acquire_world().await {
+            Ok(w) => w,
+            Err(e) => {
+                progress.report(
+                    false,
+                    &[format!(
+                        "  {} {}{} (world init failed: {})",
+                        "✗".red(),
+                        job_name,
+                        matrix_suffix,
+                        e
+                    )],
+                );
+                return JobResult {
+                    name: job_name.to_string(),
+                    matrix_suffix,
+                    duration: start.elapsed(),
+                    matrix: matrix_values.clone(),
+                    wall_duration: start.wall(),
+                    infra_error: Some(e.to_string()),
+                    ..Default::default()
+                };
+            }
+        };
+
+        // Removed when dropped at the end of the job
+        let temp_dir = match self.create_job_temp_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                progress.report(
+                    false,
+                    &[format!(
+                        "  {} {}{} (temp dir creation failed: {})",
+                        "✗".red(),
+                        job_name,
+                        matrix_suffix,
+                        e
+                    )],
+                );
+                return JobResult {
+                    name: job_name.to_string(),
+                    matrix_suffix,
+                    duration: start.elapsed(),
+                    matrix: matrix_values.clone(),
+                    wall_duration: start.wall(),
+                    infra_error: Some(e.to_string()),
+                    ..Default::default()
+                };
+            }
+        };
+
+        let mut ctx = self.expr_context();
+        let mut env = workflow_env.clone();
+        env.extend(job.env.clone());
+        ctx.env = Arc::new(env);
+        ctx.matrix = Arc::new(matrix_values.clone());
+        ctx.workflows = Arc::new(self.completed_workflow_outputs());
+        ctx.runner = Some(self.runner_info(temp_dir.path()));
+        ctx.run = Some(self.run_info(workflow_name, job_name));
+        ctx.random = Some(self.random_context(&format!(
+            "{}/{}{}",
+            workflow_name, job_name, matrix_suffix
+        )));
+
+        Arc::make_mut(&mut ctx.needs).extend(aliased_needs(job, parent_outputs));
+
+        let step_ctx = StepContext::new(workflow_name, job_name, &self.session_id, self.clock.clone())
+            .with_attempt(self.attempt())
+            .with_env((*ctx.env).clone())
+            .with_matrix(matrix_values.clone())
+            .with_cancellation(self.cancellation.clone())
+            .with_temp_dir(temp_dir.path())
+            .with_job_identity(job.identity.clone());
+        let hook_ctx = HookContext::new(&self.steps, &step_ctx);
+
+        self.hooks.run_before_scenario(&mut world, &hook_ctx).await;
+
+        // Only job assertions can look at `diff.*`, so other jobs skip the snapshots
+        let before = if job.post_assert.is_empty() {
+            Ok(None)
+        } else {
+            world.snapshot().await
+        };
+
+        let mut step_results = Vec::new();
+        let plan = plan_steps(&job.steps).unwrap_or_else(|e| {
+            step_results.push(StepRecord::new("depends-on", StepResult::Failed(Duration::ZERO, e.to_string())));
+            Vec::new()
+        });
+        // Steps that failed or were skipped; steps waiting on them are skipped too
+        let mut blocked = HashSet::new();
+
+        for (index, waits_on) in plan {
+            let step = &job.steps[index];
+            let step_name = step.name.clone().unwrap_or_else(|| step.uses.clone());
+
+            if self.cancellation.is_cancelled() || waits_on.iter().any(|i| blocked.contains(i)) {
+                blocked.insert(index);
+                step_results.push(StepRecord {
+                    description: step.description.clone(),
+                    ..StepRecord::new(step_name, StepResult::Skipped)
+                });
+                continue;
+            }
+
+            self.hooks.run_before_step(&mut world, step).await;
+
+            let watch = self.clock.stopwatch();
+            let (result, logs, links) = self.run_logged_step(&mut world, step, &mut ctx, &step_ctx).await;
+
+            self.hooks.run_after_step(&mut world, step, &result).await;
+
+            if result.is_failed() && !step.continue_on_error {
+                blocked.insert(index);
+            }
+
+            step_results.push(StepRecord {
+                name: step_name,
+                result,
+                continue_on_error: step.continue_on_error,
+                args: self.masked_args(&ctx),
+                wall_duration: watch.wall(),
+                logs,
+                links,
+                description: step.description.clone(),
+            });
+        }
+
+        // Undoes what the finished steps did, most recent first
+        let compensations = match step_results.iter().any(StepRecord::failed) {
+            true => self.run_compensations(&mut world, &step_ctx).await,
+            false => Vec::new(),
+        };
+
+        let diff = match before {
+            Ok(Some(before)) => world
+                .snapshot()
+                .await
+                .map(|after| after.map(|after| diff_snapshots(&before, &after))),
+            other => other,
+        };
+        match diff {
+            Ok(Some(diff)) => ctx.diff = Arc::new(diff),
+            Ok(None) => {}
+            Err(e) => step_results.push(StepRecord::new("snapshot", StepResult::Failed(Duration::ZERO, e.to_string()))),
+        }
+
+        // Runs even when a step failed, so a half-finished job doesn't leak test data
+        let mut cleanup = self.run_cleanup(&mut world, &step_ctx).await;
+
+        self.hooks.run_after_scenario(&mut world, &hook_ctx).await;
+        // Ones deferred by steps the hooks called
+        cleanup.extend(self.run_cleanup(&mut world, &step_ctx).await);
+        self.release_world(world).await;
+
+        let mut outputs = JobOutputs::new();
+        for (key, expr) in &job.outputs {
+            let Some((key, value)) = evaluate_output(key, expr, &ctx) else {
+                continue;
+            };
+            match &self.output_limit {
+                Some(limit) => match limit_output(&key, value, limit) {
+                    Ok(value) => outputs.insert(key, value),
+                    Err(msg) => step_results.push(StepRecord::new("outputs", StepResult::Failed(Duration::ZERO, msg))),
+                },
+                None => outputs.insert(key, value),
+            }
+        }
+        // Steps that failed or were skipped have no outputs to export
+        for id in &job.export_steps {
+            if let Some(step_outputs) = ctx.steps.get(id) {
+                outputs.export_step(id.clone(), step_outputs.to_value());
+            }
+        }
+
+        let steps_failed = step_results
+            .iter()
+            .any(|step| !step.result.is_passed() && !step.continue_on_error);
+        if !steps_failed && !job.post_assert.is_empty() {
+            let assert_ctx = ctx.with_outputs(StepOutputs::from_value(outputs.to_value()));
+            for msg in check_assertions(&job.post_assert, &assert_ctx) {
+                step_results.push(StepRecord::new("assert-after", StepResult::Failed(Duration::ZERO, msg)));
+            }
+        }
 
-            let mut world = match W::new().await {
-                Ok(w) => w,
-                Err(_) => {
-                    return Ok(JobResult {
-                        name: job_name.to_string(),
-                        matrix_suffix: String::new(),
-                        steps: vec![],
-                        outputs: JobOutputs::new(),
-                        duration: self.clock.elapsed_since(start),
-                    });
+        let warnings = missing_key_warnings(&ctx);
+        let duration = start.elapsed();
+        if let Some(msg) = job.assert_duration.and_then(|bound| bound.check(duration)) {
+            step_results.push(StepRecord::new("assert-duration", StepResult::Failed(Duration::ZERO, msg)));
+        }
+        let all_passed = step_results.iter().all(StepRecord::passed);
+
+        let mark = if all_passed { "✓".green() } else { "✗".red() };
+        let mut lines = vec![format!(
+            "  {} {}{} ({})",
+            mark,
+            job_name,
+            matrix_suffix,
+            format_durations(duration, start.wall())
+        )];
+        for step in &step_results {
+            lines.extend(step_lines(&step.name, &step.result, step.continue_on_error, &step.logs, &step.links));
+        }
+        lines.extend(deferred_lines("compensate:", &compensations));
+        lines.extend(deferred_lines("cleanup:", &cleanup));
+        lines.extend(warning_lines(&warnings));
+        let cleaned_up = cleanup.iter().all(|(_, r)| r.is_passed());
+        progress.report(all_passed && cleaned_up, &lines);
+
+        JobResult {
+            name: job_name.to_string(),
+            matrix_suffix,
+            steps: step_results,
+            outputs,
+            duration,
+            cleanup,
+            compensations,
+            matrix: matrix_values.clone(),
+            wall_duration: start.wall(),
+            warnings,
+            ..Default::default()
+        }
+    }
+
+    /// Runs the job's deferred steps, most recent first, including any they defer in turn.
+    async fn run_cleanup(&self, world: &mut W, step_ctx: &StepContext) -> Vec<(String, StepResult)> {
+        self.run_deferred(world, step_ctx, StepContext::pop_deferred).await
+    }
+
+    /// Runs the compensating steps of the job's finished steps, most recent first.
+    async fn run_compensations(&self, world: &mut W, step_ctx: &StepContext) -> Vec<(String, StepResult)> {
+        self.run_deferred(world, step_ctx, StepContext::pop_compensation).await
+    }
+
+    async fn run_deferred(
+        &self,
+        world: &mut W,
+        step_ctx: &StepContext,
+        pop: fn(&StepContext) -> Option<DeferredStep>,
+    ) -> Vec<(String, StepResult)> {
+        let mut results = Vec::new();
+        while let Some(deferred) = pop(step_ctx) {
+            self.record_coverage(&deferred.uses, step_ctx);
+            let start = self.clock.stopwatch();
+            let result = match self.invoke(world, &deferred.uses, deferred.args, step_ctx).await {
+                Ok(_) => StepResult::Passed(start.elapsed()),
+                Err(e) => StepResult::Failed(start.elapsed(), e.to_string()),
+            };
+            results.push((deferred.uses, result.masked(&self.secrets())));
+        }
+        results
+    }
+
+    /// Takes a pooled world (reset first) or builds a new one. A world whose reset fails is
+    /// dropped.
+    async fn acquire_world(&self) -> Result<W> {
+        loop {
+            let pooled = self.world_pool.lock().ok().and_then(|mut pool| pool.pop());
+            let Some(mut world) = pooled else {
+                return W::new().await;
+            };
+            match world.reset().await {
+                Ok(()) => return Ok(world),
+                Err(e) => {
+                    self.warn(format!("World reset failed: {}", e));
+                    self.teardown_world(world).await;
                 }
+            }
+        }
+    }
+
+    /// Pools a resettable world for the next job. Other worlds, and every world once the run
+    /// is cancelled, are torn down.
+    async fn release_world(&self, world: W) {
+        if W::RESETTABLE && !self.cancellation.is_cancelled() {
+            if let Ok(mut pool) = self.world_pool.lock() {
+                pool.push(world);
+                return;
+            }
+        }
+        self.teardown_world(world).await;
+    }
+
+    /// Starts the services that aren't running yet. They stay up for later workflows until
+    /// `stop_services` at the end of the run.
+    async fn start_services(&self, services: &IndexMap<String, ServiceSpec>) -> Result<()> {
+        for (name, spec) in services {
+            let info = match self.service_provider.describe(name) {
+                Some(info) => info,
+                None => self.service_provider.start(name, spec).await?,
             };
+            if let Ok(mut containers) = self.containers.lock() {
+                Arc::make_mut(&mut containers).insert(name.clone(), info);
+            }
+        }
+        Ok(())
+    }
+
+    async fn stop_services(&self) {
+        let started = self
+            .containers
+            .lock()
+            .map(|mut containers| std::mem::take(&mut *containers))
+            .unwrap_or_default();
+        for name in started.keys() {
+            if let Err(e) = self.service_provider.stop(name).await {
+                self.warn(format!("Stopping service '{}' failed: {}", name, e));
+            }
+        }
+    }
+
+    /// Tears down the pooled worlds once the run is over.
+    async fn drain_world_pool(&self) {
+        let pooled = self
+            .world_pool
+            .lock()
+            .map(|mut pool| std::mem::take(&mut *pool))
+            .unwrap_or_default();
+        for world in pooled {
+            self.teardown_world(world).await;
+        }
+    }
 
-            let mut ctx = ExprContext::new();
-            ctx.env = ref_workflow.env.clone();
-            ctx.inputs = inputs.clone();
+    async fn teardown_world(&self, world: W) {
+        if let Err(e) = world.teardown().await {
+            self.warn(format!("World teardown failed: {}", e));
+        }
+    }
 
-            for (dep_name, dep_outputs) in &ref_job_outputs {
-                ctx.needs.insert(dep_name.clone(), dep_outputs.clone());
+    fn create_job_temp_dir(&self) -> Result<TempDir> {
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("rust-actions-{}-", self.session_id))
+            .tempdir()?;
+        Ok(dir)
+    }
+
+    fn runner_info(&self, temp: &Path) -> RunnerInfo {
+        RunnerInfo {
+            temp: temp.to_string_lossy().into_owned(),
+            session_id: self.session_id.clone(),
+            os: std::env::consts::OS.to_string(),
+            started_at: self.started_at,
+        }
+    }
+
+    fn run_info(&self, workflow: &str, job: &str) -> RunInfo {
+        RunInfo {
+            workflow: workflow.to_string(),
+            job: job.to_string(),
+            session: self.session_id.clone(),
+            attempt: self.attempt(),
+        }
+    }
+
+    /// The checkpointed session's attempt; runs without checkpoints are always the first.
+    fn attempt(&self) -> u32 {
+        self.checkpoint.lock().map_or(1, |c| c.attempt)
+    }
+
+    fn allows_failure(&self, name: &str) -> bool {
+        self.allow_failure.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Why further jobs shouldn't start: the run was cancelled, or `fail_fast` saw a failure.
+    fn stopped(&self) -> Option<&'static str> {
+        if self.cancellation.is_cancelled() {
+            Some(CANCELLED)
+        } else if self.fail_fast && self.failed.load(Ordering::SeqCst) {
+            Some(NOT_RUN)
+        } else {
+            None
+        }
+    }
+
+    /// Estimates the run's wall time from the job durations in the previous `save_results`
+    /// file, if there is one.
+    fn start_estimate(&self, workflows: &PlannedWorkflows, progress: &Progress) {
+        let record = self.results_path.as_ref().and_then(|path| RunRecord::load(path).ok());
+        let Some(durations) = record.map(|r| r.job_wall_durations()) else {
+            return;
+        };
+
+        let mut remaining = Duration::ZERO;
+        let mut unknown = 0;
+        for (_, plan, _) in workflows.iter().filter(|(_, p, _)| !p.ignored) {
+            for job in &plan.jobs {
+                match durations.get(&job_key(&plan.name, job)) {
+                    Some(d) => remaining += *d,
+                    None => unknown += 1,
+                }
             }
-            for (dep_name, dep_outputs) in parent_outputs {
-                ctx.needs.insert(dep_name.clone(), dep_outputs.clone());
+        }
+        tracing::info!(
+            target: "rust_actions",
+            estimate_ms = remaining.as_millis() as u64,
+            jobs_without_history = unknown,
+            "estimated duration"
+        );
+        progress.estimate(remaining, unknown);
+        if let Ok(mut estimate) = self.estimate.lock() {
+            *estimate = Some((durations, remaining));
+        }
+    }
+
+    /// A job's wall time in the `save_results` file, under `slow_jobs_first`.
+    fn previous_duration(&self, workflow: &str, job: &str) -> Duration {
+        if !self.slow_jobs_first {
+            return Duration::ZERO;
+        }
+        let Ok(estimate) = self.estimate.lock() else {
+            return Duration::ZERO;
+        };
+        estimate
+            .as_ref()
+            .and_then(|(durations, _)| durations.get(&job_key(workflow, job)).copied())
+            .unwrap_or_default()
+    }
+
+    fn finish_estimate(&self, workflow: &str, job: &str, progress: &Progress) {
+        let Ok(mut estimate) = self.estimate.lock() else {
+            return;
+        };
+        if let Some((durations, remaining)) = estimate.as_mut() {
+            if let Some(d) = durations.get(&job_key(workflow, job)) {
+                *remaining = remaining.saturating_sub(*d);
+                progress.remaining(*remaining);
             }
+        }
+    }
 
-            #[allow(unused_variables)]
-            let step_outputs: HashMap<String, Value> = HashMap::new();
+    fn warn(&self, message: impl std::fmt::Display) {
+        self.progress().warn(message);
+    }
 
-            for step in &ref_job.steps {
-                let result = self.run_step(&mut world, step, &mut ctx).await;
-                let step_name = step.name.clone().unwrap_or_else(|| step.uses.clone());
+    fn progress(&self) -> Progress {
+        self.progress
+            .lock()
+            .map(|p| p.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn secrets(&self) -> SecretMasker {
+        self.secrets
+            .lock()
+            .map(|s| s.clone())
+            .unwrap_or_default()
+    }
+
+    /// The args `run_step` last evaluated into `ctx.with`, masked for the job result.
+    fn masked_args(&self, ctx: &ExprContext) -> RawArgs {
+        let secrets = self.secrets();
+        ctx.with
+            .iter()
+            .map(|(k, v)| (k.clone(), secrets.mask_value(v)))
+            .collect()
+    }
+
+    fn expr_context(&self) -> ExprContext {
+        let mut ctx = ExprContext::new();
+        ctx.cache = Some(self.exprs.clone());
+        ctx.globals = self.globals.clone();
+        ctx.setup = self.setup_outputs.lock().map(|s| s.clone()).unwrap_or_default();
+        ctx.containers = self.containers.lock().map(|c| c.clone()).unwrap_or_default();
+        if self.missing_keys == MissingKeyPolicy::Lenient {
+            ctx.missing_keys = Some(Arc::new(MissingKeyLog::default()));
+        }
+        ctx
+    }
+
+    fn random_context(&self, scope: &str) -> Arc<RandomContext> {
+        Arc::new(RandomContext::new(
+            SeededRng::from_scenario_name(scope),
+            self.generators.clone(),
+        ))
+    }
+
+    /// Runs a step, capturing its log lines with `capture_logs`. The lines are kept only when
+    /// the step failed; they, the result and the step's links are masked.
+    async fn run_logged_step(
+        &self,
+        world: &mut W,
+        step: &Step,
+        ctx: &mut ExprContext,
+        step_ctx: &StepContext,
+    ) -> (StepResult, Vec<String>, Vec<StepLink>) {
+        let run = self.run_step(world, step, ctx, step_ctx);
+        let (result, logs) = match self.capture_logs {
+            Some(level) => capture::capture(level, run).await,
+            None => (run.await, Vec::new()),
+        };
+        let result = match (result, step.assert_duration) {
+            (StepResult::Passed(duration), Some(bound)) => match bound.check(duration) {
+                Some(msg) => StepResult::Failed(duration, msg),
+                None => StepResult::Passed(duration),
+            },
+            (result, _) => result,
+        };
+        #[cfg(feature = "metrics")]
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.record_step(&step.uses, &result);
+        }
+        let vars = step_ctx.take_vars();
+        if result.is_passed() {
+            Arc::make_mut(&mut ctx.vars).extend(vars);
+        }
+        let secrets = self.secrets();
+        let logs = match result.is_failed() {
+            true => logs.iter().map(|line| secrets.mask(line)).collect(),
+            false => Vec::new(),
+        };
+        let links = step_ctx
+            .take_links()
+            .into_iter()
+            .map(|link| StepLink {
+                target: secrets.mask(&link.target),
+                ..link
+            })
+            .collect();
+        (result.masked(&secrets), logs, links)
+    }
 
-                match &result {
-                    StepResult::Passed(_) => {
-                        println!("    {} {}", "✓".green(), step_name);
+    /// Calls a step, retrying `StepError::Retryable` failures under `default_retry`. Each
+    /// attempt waits for the step's rate limit.
+    async fn call_step(
+        &self,
+        world: &mut W,
+        step: &Step,
+        args: RawArgs,
+        step_ctx: &StepContext,
+    ) -> std::result::Result<StepOutputs, String> {
+        let max_attempts = self.default_retry.map_or(1, |p| p.max_attempts());
+        let mut attempt = 1;
+        loop {
+            if let Some(limit) = self.rate_limiter.limit_for(&step.uses, step.rate_limit) {
+                self.rate_limiter.acquire(&step.uses, limit).await;
+            }
+            match self.invoke(world, &step.uses, args.clone(), step_ctx).await {
+                Ok(outputs) => return Ok(outputs),
+                Err(e) if e.is_retryable() && attempt < max_attempts => {
+                    #[cfg(feature = "metrics")]
+                    if let Ok(mut metrics) = self.metrics.lock() {
+                        metrics.record_retry(&step.uses);
                     }
-                    StepResult::Failed(_, msg) => {
-                        if step.continue_on_error {
-                            println!("    {} {} (expected error)", "○".yellow(), step_name);
-                            println!("      {}: {}", "Error".dimmed(), msg);
-                        } else {
-                            println!("    {} {}", "✗".red(), step_name);
-                            println!("      {}: {}", "Error".red(), msg);
-                        }
+                    if let Some(policy) = &self.default_retry {
+                        tokio::time::sleep(policy.delay_after(attempt)).await;
+                    }
+                    attempt += 1;
+                }
+                Err(e) if attempt > 1 => return Err(format!("{} (after {} attempts)", e, attempt)),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+
+    /// Runs `uses` through the executor when it handles the step, else against `world`.
+    async fn invoke(
+        &self,
+        world: &mut W,
+        uses: &str,
+        args: RawArgs,
+        step_ctx: &StepContext,
+    ) -> Result<StepOutputs> {
+        if let Some(executor) = self.remote(uses) {
+            return executor.execute(StepCall::new(uses, args, step_ctx)).await;
+        }
+        let step_fn = self.steps.callable(uses)?;
+        let world_any: &mut dyn Any = world;
+        call_guarded(&step_fn, world_any, args, step_ctx).await
+    }
+
+    fn record_coverage(&self, uses: &str, step_ctx: &StepContext) {
+        let Some(name) = self.steps.registered_name(uses) else {
+            return;
+        };
+        if let Ok(mut exercised) = self.exercised.lock() {
+            exercised.entry(name).or_default().insert(step_ctx.workflow().to_string());
+        }
+    }
+
+    fn remote(&self, uses: &str) -> Option<&dyn StepExecutor> {
+        self.executor.as_deref().filter(|executor| executor.handles(uses))
+    }
+
+    async fn run_step(
+        &self,
+        world: &mut W,
+        step: &Step,
+        ctx: &mut ExprContext,
+        step_ctx: &StepContext,
+    ) -> StepResult {
+        if let Some(delay) = &step.delay {
+            let scope = format!("{}/{}/{}", step_ctx.workflow(), step_ctx.job(), step.label());
+            tokio::time::sleep(delay.sample(&mut SeededRng::from_scenario_name(&scope))).await;
+        }
+        let start = self.clock.stopwatch();
+
+        ctx.with = Arc::default();
+
+        self.record_coverage(&step.uses, step_ctx);
+        if self.remote(&step.uses).is_none() {
+            if let Err(e) = self.steps.callable(&step.uses) {
+                return StepResult::Failed(start.elapsed(), e.to_string());
+            }
+        }
+
+        let evaluated_args = match step
+            .with
+            .iter()
+            .map(|(k, v)| evaluate_value(v, ctx).map(|ev| (k.clone(), ev)))
+            .collect::<Result<HashMap<_, _>>>()
+        {
+            Ok(args) => args,
+            Err(e) => {
+                return StepResult::Failed(
+                    start.elapsed(),
+                    format!("Args evaluation failed: {}", e),
+                );
+            }
+        };
+
+        // Pre-asserts can check the evaluated args as `with.*`
+        ctx.with = Arc::new(evaluated_args.clone());
+        for assertion in &step.pre_assert {
+            match evaluate_assertion(assertion, ctx) {
+                Ok(true) => {}
+                Ok(false) => {
+                    return StepResult::Failed(
+                        start.elapsed(),
+                        format!("Pre-assertion failed: {}", assertion),
+                    );
+                }
+                Err(e) => {
+                    return StepResult::Failed(
+                        start.elapsed(),
+                        format!("Pre-assertion error: {}", e),
+                    );
+                }
+            }
+        }
+
+        let setup_key = step.setup.then(|| setup_cache_key(&step.uses, &evaluated_args));
+        let pure_key = self
+            .steps
+            .is_pure(&step.uses)
+            .then(|| setup_cache_key(&step.uses, &evaluated_args));
+        let lookup = |cache: &Mutex<HashMap<String, StepOutputs>>, key: &Option<String>| {
+            let key = key.as_ref()?;
+            cache.lock().ok().and_then(|cache| cache.get(key).cloned())
+        };
+        let cached = lookup(&self.setup_cache, &setup_key)
+            .or_else(|| lookup(&self.pure_cache, &pure_key));
+
+        let from_cache = cached.is_some();
+        let outputs = match cached {
+            Some(outputs) => outputs,
+            None => {
+                if let Some(identity) = step.identity.as_deref().or(step_ctx.job_identity()) {
+                    if let Err(e) = world.assume_identity(identity).await {
+                        return StepResult::Failed(
+                            start.elapsed(),
+                            format!("Assuming identity '{}' failed: {}", identity, e),
+                        );
+                    }
+                }
+                let outputs = match self.call_step(world, step, evaluated_args, step_ctx).await {
+                    Ok(outputs) => outputs,
+                    Err(e) => return StepResult::Failed(start.elapsed(), e),
+                };
+                match &self.output_limit {
+                    Some(limit) => match outputs.limited(limit) {
+                        Ok(outputs) => outputs,
+                        Err(e) => return StepResult::Failed(start.elapsed(), e),
+                    },
+                    None => outputs,
+                }
+            }
+        };
+
+        if !step.mask_outputs.is_empty() {
+            if let Ok(mut secrets) = self.secrets.lock() {
+                for key in &step.mask_outputs {
+                    if let Some(value) = outputs.get(key) {
+                        secrets.add_value(value);
+                    }
+                }
+            }
+        }
+
+        if let Some(id) = &step.id {
+            Arc::make_mut(&mut ctx.steps).insert(id.clone(), outputs.clone());
+        }
+
+        // Cached outputs were cleaned up by the job that first produced them
+        if !from_cache {
+            let cleanup_ctx = ctx.with_outputs(outputs.clone());
+            let args = |deferred: &CleanupStep| {
+                deferred
+                    .with
+                    .iter()
+                    .map(|(k, v)| evaluate_value(v, &cleanup_ctx).map(|ev| (k.clone(), ev)))
+                    .collect::<Result<serde_json::Map<_, _>>>()
+                    .map(Value::Object)
+            };
+            for cleanup in &step.cleanup {
+                match args(cleanup) {
+                    Ok(args) => step_ctx.defer(&cleanup.uses, args),
+                    Err(e) => {
+                        return StepResult::Failed(
+                            start.elapsed(),
+                            format!("Cleanup args evaluation failed for {}: {}", cleanup.uses, e),
+                        );
                     }
-                    StepResult::Skipped => {
-                        println!("    {} {} (skipped)", "○".dimmed(), step_name);
+                }
+            }
+            if let Some(compensate) = &step.compensate {
+                match args(compensate) {
+                    Ok(args) => step_ctx.compensate(&compensate.uses, args),
+                    Err(e) => {
+                        return StepResult::Failed(
+                            start.elapsed(),
+                            format!("Compensate args evaluation failed for {}: {}", compensate.uses, e),
+                        );
                     }
                 }
+            }
+        }
 
-                all_step_results.push((step_name, result, step.continue_on_error));
+        if let Some(expected) = &step.expect {
+            let failures = match evaluate_value(expected, ctx) {
+                Ok(expected) => check_expect(&expected, &outputs.to_value()),
+                Err(e) => vec![format!("could not evaluate: {}", e)],
+            };
+            if !failures.is_empty() {
+                return StepResult::Failed(
+                    start.elapsed(),
+                    format!("Expectation failed: {}", failures.join("; ")),
+                );
             }
+        }
 
-            let mut ref_job_output = JobOutputs::new();
-            for (key, expr) in &ref_job.outputs {
-                if let Ok(value) = evaluate_value(&Value::String(expr.clone()), &ctx) {
-                    ref_job_output.insert(key.clone(), value);
+        if !step.post_assert.is_empty() {
+            let assert_ctx = ctx.with_outputs(outputs.clone());
+
+            let mut failures = assertion_failures(&step.post_assert, &assert_ctx);
+            let failures: Vec<String> = match step.assert_mode {
+                AssertMode::First => failures.next().into_iter().collect(),
+                AssertMode::All => failures.collect(),
+            };
+            match failures.len() {
+                0 => {}
+                1 => return StepResult::Failed(start.elapsed(), failures.concat()),
+                n => {
+                    return StepResult::Failed(
+                        start.elapsed(),
+                        format!("{} of {} assertions failed: {}", n, step.post_assert.len(), failures.join("; ")),
+                    );
+                }
+            }
+        }
+
+        // Post-conditions declared with the step itself, checked whatever the workflow asserts
+        let ensures = self.steps.ensures(&step.uses);
+        if !ensures.is_empty() {
+            let assert_ctx = ctx.with_outputs(outputs.clone());
+
+            for assertion in ensures {
+                let failure = match evaluate_assertion(assertion, &assert_ctx) {
+                    Ok(true) => continue,
+                    Ok(false) => format!("{} failed: {}", step.uses, assertion),
+                    Err(e) => format!("{} could not be checked: {}", step.uses, e),
+                };
+                return StepResult::Failed(start.elapsed(), format!("Contract of {}", failure));
+            }
+        }
+
+        if !step.capture.is_empty() {
+            let capture_ctx = ctx.with_outputs(outputs.clone());
+            let mut vars = Vec::with_capacity(step.capture.len());
+            for (name, expr) in &step.capture {
+                match evaluate_typed(&capture_template(expr), &capture_ctx) {
+                    Ok(value) => vars.push((name.clone(), value)),
+                    Err(e) => {
+                        return StepResult::Failed(
+                            start.elapsed(),
+                            format!("Capture of vars.{} failed: {}", name, e),
+                        );
+                    }
+                }
+            }
+            Arc::make_mut(&mut ctx.vars).extend(vars);
+        }
+
+        if let Some(key) = setup_key {
+            if let Ok(mut cache) = self.setup_cache.lock() {
+                cache.entry(key).or_insert(outputs.clone());
+            }
+        }
+        if let Some(key) = pure_key {
+            if let Ok(mut cache) = self.pure_cache.lock() {
+                cache.entry(key).or_insert(outputs);
+            }
+        }
+
+        StepResult::Passed(start.elapsed())
+    }
+}
+
+impl<W: World + 'static> Default for RustActions<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses a `workflow_yaml` or `workflow_files` workflow, with unknown fields reported under `name`.
+fn parse_in_memory(name: &str, yaml: &str, strict: bool) -> Result<Workflow> {
+    Workflow::from_yaml_with(yaml, strict).map_err(|e| match e {
+        Error::UnknownFields { fields, .. } => Error::UnknownFields {
+            file: name.to_string(),
+            fields,
+        },
+        other => other,
+    })
+}
+
+/// Evaluates a job or reusable workflow output. Its name is an expression too, so
+/// `token_${{ matrix.region }}` names one output per matrix combination. `None` when either
+/// can't be evaluated.
+fn evaluate_output(key: &str, expr: &str, ctx: &ExprContext) -> Option<(String, Value)> {
+    let key = evaluate(key, ctx).ok()?;
+    let value = evaluate_value(&Value::String(expr.to_string()), ctx).ok()?;
+    Some((key, value))
+}
+
+/// Overlays `overrides` on `base`. Objects merge key by key; anything else replaces.
+fn deep_merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
                 }
             }
-            ref_job_outputs.insert(ref_job_name.clone(), ref_job_output.clone());
         }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// The steps every runner has, whatever the world.
+fn builtin_registry() -> StepRegistry {
+    let mut steps = StepRegistry::new();
+    fs_steps::register(&mut steps);
+    json_schema::register(&mut steps);
+    vars::register(&mut steps);
+    steps
+}
+
+fn setup_cache_key(uses: &str, args: &HashMap<String, Value>) -> String {
+    let args: serde_json::Map<String, Value> =
+        args.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    format!("{}:{}", uses, canonical_json(&Value::Object(args)))
+}
+
+/// Identifies a memoized reusable workflow call. The caller's matrix is part of it, since the
+/// called workflow's jobs see it as `matrix.*`.
+fn memo_key(file: &str, inputs: &HashMap<String, Value>, matrix: &MatrixCombination) -> String {
+    let matrix: serde_json::Map<String, Value> =
+        matrix.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    format!("{}{}", setup_cache_key(file, inputs), canonical_json(&Value::Object(matrix)))
+}
 
-        if let Some(trigger) = &ref_workflow.on {
-            if let Some(call_config) = &trigger.workflow_call {
-                for (key, output_def) in &call_config.outputs {
-                    let mut eval_ctx = ExprContext::new();
-                    for (jn, outputs) in &ref_job_outputs {
-                        eval_ctx.jobs.insert(jn.clone(), outputs.clone());
-                    }
-                    if let Ok(value) =
-                        evaluate_value(&Value::String(output_def.value.clone()), &eval_ctx)
-                    {
-                        combined_outputs.insert(key.clone(), value);
-                    }
-                }
+/// The outputs of `job`'s needs that ran, keyed as `needs.*` reads them: by alias if they have one.
+fn aliased_needs(job: &Job, outputs: &HashMap<String, JobOutputs>) -> HashMap<String, JobOutputs> {
+    job.needs
+        .as_vec()
+        .iter()
+        .filter_map(|need| {
+            let alias = job.needs.alias(need).to_string();
+            outputs.get(need).map(|o| (alias, o.clone()))
+        })
+        .collect()
+}
+
+/// A message for each output that `job`'s `verify-needs:` expects but its needs didn't produce.
+fn verify_needs(job: &Job, outputs: &HashMap<String, JobOutputs>) -> Vec<String> {
+    let needs = job.needs.as_vec();
+    let mut unmet = Vec::new();
+    for (need, expected) in &job.verify_needs {
+        if !needs.contains(need) {
+            unmet.push(format!("'{}' isn't one of the job's needs", need));
+            continue;
+        }
+        if job.needs.is_optional(need) && !outputs.contains_key(need) {
+            continue;
+        }
+        let alias = job.needs.alias(need);
+        for (key, ty) in expected {
+            match outputs.get(need).and_then(|o| o.get(key)) {
+                None => unmet.push(format!("needs.{}.outputs.{} is missing", alias, key)),
+                Some(value) if !ty.matches(value) => unmet.push(format!(
+                    "needs.{}.outputs.{} should be {}, got {}",
+                    alias, key, ty, value
+                )),
+                Some(_) => {}
             }
         }
+    }
+    unmet
+}
 
-        Ok(JobResult {
-            name: job_name.to_string(),
-            matrix_suffix: String::new(),
-            steps: all_step_results,
-            outputs: combined_outputs,
-            duration: self.clock.elapsed_since(start),
+/// Evaluates `assert-after` expressions, returning a message for each one that doesn't hold.
+fn check_assertions(assertions: &[String], ctx: &ExprContext) -> Vec<String> {
+    assertion_failures(assertions, ctx).collect()
+}
+
+/// Like `check_assertions`, evaluating each expression only as the iterator gets to it.
+fn assertion_failures<'a>(
+    assertions: &'a [String],
+    ctx: &'a ExprContext,
+) -> impl Iterator<Item = String> + 'a {
+    assertions
+        .iter()
+        .filter_map(|assertion| match evaluate_assertion(assertion, ctx) {
+            Ok(true) => None,
+            Ok(false) => Some(format!("Post-assertion failed: {}", assertion)),
+            Err(e) => Some(format!("Post-assertion error: {}", e)),
         })
+}
+
+fn step_lines(
+    name: &str,
+    result: &StepResult,
+    continue_on_error: bool,
+    logs: &[String],
+    links: &[StepLink],
+) -> Vec<String> {
+    let mut lines = match result {
+        StepResult::Passed(_) => vec![format!("    {} {}", "✓".green(), name)],
+        StepResult::Failed(_, msg) if continue_on_error => vec![
+            format!("    {} {} (expected error)", "○".yellow(), name),
+            format!("      {}: {}", "Error".dimmed(), msg),
+        ],
+        StepResult::Failed(_, msg) => vec![
+            format!("    {} {}", "✗".red(), name),
+            format!("      {}: {}", "Error".red(), msg),
+        ],
+        StepResult::Skipped => vec![format!("    {} {} (skipped)", "○".dimmed(), name)],
+    };
+    if !logs.is_empty() {
+        lines.push(format!("      {}:", "Logs".dimmed()));
+        lines.extend(logs.iter().map(|line| format!("        {}", line.dimmed())));
+    }
+    if result.is_failed() && !links.is_empty() {
+        lines.push(format!("      {}:", "Links".dimmed()));
+        lines.extend(links.iter().map(|link| format!("        {}: {}", link.label, link.target)));
     }
+    lines
+}
 
-    async fn run_job(
-        &self,
-        job_name: &str,
-        job: &Job,
-        workflow_env: &HashMap<String, String>,
-        parent_outputs: &HashMap<String, JobOutputs>,
-        matrix_values: &MatrixCombination,
-    ) -> JobResult {
-        let start = self.clock.now();
-        let matrix_suffix = format_matrix_suffix(matrix_values);
+fn warning_lines(warnings: &[String]) -> Vec<String> {
+    warnings
+        .iter()
+        .map(|warning| format!("    {} {}", "⚠".yellow(), warning))
+        .collect()
+}
 
-        let mut world = match W::new().await {
-            Ok(w) => w,
-            Err(e) => {
-                println!(
-                    "  {} {}{} (world init failed: {})",
-                    "✗".red(),
-                    job_name,
-                    matrix_suffix,
-                    e
-                );
-                return JobResult {
-                    name: job_name.to_string(),
-                    matrix_suffix,
-                    steps: vec![],
-                    outputs: JobOutputs::new(),
-                    duration: self.clock.elapsed_since(start),
-                };
-            }
-        };
+fn missing_key_warnings(ctx: &ExprContext) -> Vec<String> {
+    ctx.missing_keys.as_ref().map(|log| log.take()).unwrap_or_default()
+}
 
-        self.hooks.run_before_scenario(&mut world).await;
+/// How many reusable workflow calls may be nested unless `max_call_depth` says otherwise.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 8;
 
-        let mut ctx = ExprContext::new();
-        ctx.env = workflow_env.clone();
-        ctx.env.extend(job.env.clone());
-        ctx.matrix = matrix_values.clone();
+/// Why `fail_fast` left a job or workflow out of the run.
+const NOT_RUN: &str = "not run: fail-fast";
+/// Why a cancelled run left a job or workflow out.
+const CANCELLED: &str = "not run: cancelled";
 
-        for need in job.needs.as_vec() {
-            if let Some(outputs) = parent_outputs.get(&need) {
-                ctx.needs.insert(need.clone(), outputs.clone());
-            }
-        }
+pub(crate) fn job_key(workflow: &str, job: &str) -> String {
+    format!("{} / {}", workflow, job)
+}
 
-        let mut step_results = Vec::new();
-        let mut should_skip = false;
+fn print_summary(summary: &SuiteSummary) {
+    println!();
+    let jobs_passed = summary.jobs.passed + summary.jobs.warnings;
+    let total_jobs = jobs_passed + summary.jobs.failed + summary.jobs.quarantined;
+    let quarantined = match summary.jobs.quarantined {
+        0 => String::new(),
+        n => format!(", {} quarantined", n),
+    };
+    if summary.jobs.failed == 0 {
+        println!(
+            "{} {} ({} passed{})",
+            format!("{} jobs", total_jobs).green(),
+            "✓".green(),
+            jobs_passed,
+            quarantined
+        );
+    } else {
+        println!(
+            "{} ({} passed, {} failed{})",
+            format!("{} jobs", total_jobs).yellow(),
+            jobs_passed,
+            summary.jobs.failed,
+            quarantined
+        );
+    }
+    let steps_passed = summary.steps.passed + summary.steps.tolerated;
+    println!(
+        "{} steps ({} passed, {} failed)",
+        steps_passed + summary.steps.failed,
+        steps_passed,
+        summary.steps.failed
+    );
+    if !summary.quarantined.is_empty() {
+        println!("{}", "Quarantined failures:".yellow().bold());
+        for group in &summary.quarantined {
+            println!("  {}x {} {}", group.count, group.message, format!("({})", group.jobs.join(", ")).dimmed());
+        }
+    }
+}
 
-        for step in &job.steps {
-            let step_name = step.name.clone().unwrap_or_else(|| step.uses.clone());
+/// `1.2s`, or `35s simulated, 2ms wall` once paused time has pulled the two apart.
+fn format_durations(simulated: Duration, wall: Duration) -> String {
+    if simulated.abs_diff(wall) < Duration::from_millis(1) {
+        format!("{:?}", simulated)
+    } else {
+        format!("{:?} simulated, {:?} wall", simulated, wall)
+    }
+}
 
-            if should_skip {
-                step_results.push((step_name, StepResult::Skipped, false));
-                continue;
+/// Cleanup or compensating steps under `label`.
+fn deferred_lines(label: &str, results: &[(String, StepResult)]) -> Vec<String> {
+    if results.is_empty() {
+        return vec![];
+    }
+    let mut lines = vec![format!("    {}", label.dimmed())];
+    for (uses, result) in results {
+        match result {
+            StepResult::Failed(_, msg) => {
+                lines.push(format!("      {} {}", "✗".yellow(), uses));
+                lines.push(format!("        {}: {}", "Error".yellow(), msg));
             }
+            _ => lines.push(format!("      {} {}", "✓".green(), uses)),
+        }
+    }
+    lines
+}
 
-            self.hooks.run_before_step(&mut world, step).await;
+/// Key under which a workflow's job outputs are exposed as `workflows.<key>`.
+fn workflow_key(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
 
-            let result = self.run_step(&mut world, step, &mut ctx).await;
+/// Workflows in run order, each with what `execute` needs to know before running it.
+type PlannedWorkflows = Vec<(PathBuf, WorkflowPlan, Workflow)>;
 
-            self.hooks.run_after_step(&mut world, step, &result).await;
+/// A reusable workflow call in progress: nested calls evaluate their `with:` against its
+/// inputs, and repeating it with the same inputs is a cycle.
+#[derive(Debug, Clone)]
+struct CallFrame {
+    file: String,
+    inputs: HashMap<String, Value>,
+}
 
-            if result.is_failed() && !step.continue_on_error {
-                should_skip = true;
-            }
+/// What a workflow's `setup:` and `teardown:` steps share: a world of their own, kept while
+/// the jobs run.
+struct SetupPhase<W> {
+    world: W,
+    temp_dir: TempDir,
+    step_ctx: Option<StepContext>,
+}
 
-            step_results.push((step_name, result, step.continue_on_error));
-        }
+/// What `execute` needs to know about a workflow before running it.
+#[derive(Debug, Clone)]
+struct WorkflowPlan {
+    name: String,
+    ignored: bool,
+    needs: Vec<String>,
+    jobs: Vec<String>,
+    /// Jobs times matrix combinations, for the progress bar
+    job_count: usize,
+}
 
-        self.hooks.run_after_scenario(&mut world).await;
+impl WorkflowPlan {
+    fn new(path: &Path, workflow: &Workflow, registry: &WorkflowRegistry) -> Self {
+        let listing = WorkflowListing::build(path, workflow, Some(registry));
+        Self {
+            name: workflow.name.clone(),
+            ignored: workflow.ignore.is_ignored(),
+            needs: workflow.needs_workflow.as_vec(),
+            jobs: workflow.jobs.keys().cloned().collect(),
+            job_count: listing.jobs.iter().map(|j| j.matrix.len().max(1)).sum(),
+        }
+    }
+}
 
-        let duration = self.clock.elapsed_since(start);
-        let all_passed = step_results
-            .iter()
-            .all(|(_, r, continue_on_error)| r.is_passed() || *continue_on_error);
+/// Orders workflows so `needs-workflow` dependencies run first. Dependencies are resolved from
+/// the registry (and added if they weren't selected); each workflow appears once.
+fn order_workflows(
+    workflows: Vec<(PathBuf, Workflow)>,
+    registry: &WorkflowRegistry,
+) -> Result<Vec<(PathBuf, Workflow)>> {
+    order_by_needs(
+        workflows,
+        |workflow| workflow.needs_workflow.as_vec(),
+        |path| registry.get(path).cloned(),
+    )
+}
 
-        if all_passed {
-            println!(
-                "  {} {}{} ({:?})",
-                "✓".green(),
-                job_name,
-                matrix_suffix,
-                duration
-            );
-        } else {
-            println!(
-                "  {} {}{} ({:?})",
-                "✗".red(),
-                job_name,
-                matrix_suffix,
-                duration
-            );
+/// `workflows` with each one's `needs-workflow:` dependencies, looked up by path, placed
+/// before it.
+fn order_by_needs<T>(
+    workflows: Vec<(PathBuf, T)>,
+    needs: impl Fn(&T) -> Vec<String>,
+    lookup: impl Fn(&Path) -> Option<T>,
+) -> Result<Vec<(PathBuf, T)>> {
+    fn visit<T>(
+        path: PathBuf,
+        workflow: T,
+        deps: &dyn Fn(&T) -> Vec<String>,
+        lookup: &dyn Fn(&Path) -> Option<T>,
+        chain: &mut Vec<PathBuf>,
+        done: &mut HashSet<PathBuf>,
+        ordered: &mut Vec<(PathBuf, T)>,
+    ) -> Result<()> {
+        if done.contains(&path) {
+            return Ok(());
         }
-
-        for (name, result, continue_on_error) in &step_results {
-            match result {
-                StepResult::Passed(_) => {
-                    println!("    {} {}", "✓".green(), name);
-                }
-                StepResult::Failed(_, msg) => {
-                    if *continue_on_error {
-                        println!("    {} {} (expected error)", "○".yellow(), name);
-                        println!("      {}: {}", "Error".dimmed(), msg);
-                    } else {
-                        println!("    {} {}", "✗".red(), name);
-                        println!("      {}: {}", "Error".red(), msg);
-                    }
-                }
-                StepResult::Skipped => {
-                    println!("    {} {} (skipped)", "○".dimmed(), name);
-                }
-            }
+        if chain.contains(&path) {
+            chain.push(path);
+            return Err(Error::CircularDependency {
+                chain: chain
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> "),
+            });
         }
 
-        let mut outputs = JobOutputs::new();
-        for (key, expr) in &job.outputs {
-            if let Ok(value) = evaluate_value(&Value::String(expr.clone()), &ctx) {
-                outputs.insert(key.clone(), value);
-            }
+        chain.push(path.clone());
+        for dep in deps(&workflow) {
+            let dep_path = PathBuf::from(&dep);
+            let dep_workflow = lookup(&dep_path).ok_or(Error::WorkflowNotFound { path: dep })?;
+            visit(dep_path, dep_workflow, deps, lookup, chain, done, ordered)?;
         }
+        chain.pop();
 
-        JobResult {
-            name: job_name.to_string(),
-            matrix_suffix,
-            steps: step_results,
-            outputs,
-            duration,
-        }
+        done.insert(path.clone());
+        ordered.push((path, workflow));
+        Ok(())
     }
 
-    async fn run_step(&self, world: &mut W, step: &Step, ctx: &mut ExprContext) -> StepResult {
-        let start = self.clock.now();
-
-        for assertion in &step.pre_assert {
-            match evaluate_assertion(assertion, ctx) {
-                Ok(true) => {}
-                Ok(false) => {
-                    return StepResult::Failed(
-                        self.clock.elapsed_since(start),
-                        format!("Pre-assertion failed: {}", assertion),
-                    );
-                }
-                Err(e) => {
-                    return StepResult::Failed(
-                        self.clock.elapsed_since(start),
-                        format!("Pre-assertion error: {}", e),
-                    );
-                }
-            }
-        }
+    let mut ordered = Vec::new();
+    let mut done = HashSet::new();
+    for (path, workflow) in workflows {
+        visit(path, workflow, &needs, &lookup, &mut Vec::new(), &mut done, &mut ordered)?;
+    }
+    Ok(ordered)
+}
 
-        let step_fn = match self.steps.get(&step.uses) {
-            Some(f) => f,
-            None => {
-                return StepResult::Failed(
-                    self.clock.elapsed_since(start),
-                    format!("Step not found: {}", step.uses),
-                );
-            }
-        };
+/// Execution order of a job's steps, as (step index, indices of the steps it waits on).
+///
+/// Without any `depends-on:`, steps run in declaration order and each waits on the one
+/// before it. Otherwise a step waits only on the steps it names by `id`, and steps that are
+/// ready at the same time keep declaration order.
+pub(crate) fn plan_steps(steps: &[Step]) -> Result<Vec<(usize, Vec<usize>)>> {
+    if steps.iter().all(|s| s.depends_on.is_empty()) {
+        return Ok((0..steps.len())
+            .map(|i| (i, i.checked_sub(1).into_iter().collect()))
+            .collect());
+    }
 
-        let evaluated_args = match step
-            .with
-            .iter()
-            .map(|(k, v)| evaluate_value(v, ctx).map(|ev| (k.clone(), ev)))
-            .collect::<Result<HashMap<_, _>>>()
-        {
-            Ok(args) => args,
-            Err(e) => {
-                return StepResult::Failed(
-                    self.clock.elapsed_since(start),
-                    format!("Args evaluation failed: {}", e),
-                );
+    let ids: HashMap<&str, usize> = steps
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| s.id.as_deref().map(|id| (id, i)))
+        .collect();
+    let deps = steps
+        .iter()
+        .map(|step| {
+            step.depends_on
+                .as_vec()
+                .into_iter()
+                .map(|dep| {
+                    ids.get(dep.as_str()).copied().ok_or_else(|| Error::StepDependencyNotFound {
+                        step: step.label().to_string(),
+                        dependency: dep.clone(),
+                    })
+                })
+                .collect::<Result<Vec<usize>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut done = vec![false; steps.len()];
+    let mut order = Vec::with_capacity(steps.len());
+    while order.len() < steps.len() {
+        let ready = (0..steps.len()).find(|&i| !done[i] && deps[i].iter().all(|&d| done[d]));
+        let Some(i) = ready else {
+            // Every remaining step waits on another remaining step; follow them to a cycle
+            let mut path: Vec<usize> = Vec::new();
+            let mut current = (0..steps.len()).find(|&i| !done[i]);
+            while let Some(i) = current.filter(|i| !path.contains(i)) {
+                path.push(i);
+                current = deps[i].iter().copied().find(|&d| !done[d]);
             }
+            let start = current.and_then(|c| path.iter().position(|&p| p == c)).unwrap_or(0);
+            let chain: Vec<&str> = path[start..]
+                .iter()
+                .chain(current.as_ref())
+                .map(|&i| steps[i].label())
+                .collect();
+            return Err(Error::CircularDependency {
+                chain: chain.join(" -> "),
+            });
         };
+        done[i] = true;
+        order.push((i, deps[i].clone()));
+    }
+    Ok(order)
+}
 
-        let world_any: &mut dyn Any = world;
-        let outputs = match step_fn(world_any, evaluated_args).await {
-            Ok(outputs) => outputs,
-            Err(e) => return StepResult::Failed(self.clock.elapsed_since(start), e.to_string()),
-        };
+/// Each workflow narrowed to the `names` it has and the jobs they transitively need; workflows
+/// with none of them are dropped. No selection keeps everything.
+fn select_jobs(workflows: Vec<(PathBuf, Workflow)>, names: &[String]) -> Result<Vec<(PathBuf, Workflow)>> {
+    if names.is_empty() {
+        return Ok(workflows);
+    }
+    if let Some(missing) = names
+        .iter()
+        .find(|name| !workflows.iter().any(|(_, w)| w.jobs.contains_key(*name)))
+    {
+        let searched: Vec<&str> = workflows.iter().map(|(_, w)| w.name.as_str()).collect();
+        return Err(Error::JobNotFound {
+            workflow: searched.join(", "),
+            job: missing.clone(),
+        });
+    }
 
-        if let Some(id) = &step.id {
-            ctx.steps.insert(id.clone(), outputs.clone());
+    let mut selected = Vec::new();
+    for (path, mut workflow) in workflows {
+        let mut keep = HashSet::new();
+        let mut pending: Vec<String> = names.iter().filter(|n| workflow.jobs.contains_key(*n)).cloned().collect();
+        if pending.is_empty() {
+            continue;
         }
-
-        if !step.post_assert.is_empty() {
-            let assert_ctx = ctx.with_outputs(outputs);
-
-            for assertion in &step.post_assert {
-                match evaluate_assertion(assertion, &assert_ctx) {
-                    Ok(true) => {}
-                    Ok(false) => {
-                        return StepResult::Failed(
-                            self.clock.elapsed_since(start),
-                            format!("Post-assertion failed: {}", assertion),
-                        );
-                    }
-                    Err(e) => {
-                        return StepResult::Failed(
-                            self.clock.elapsed_since(start),
-                            format!("Post-assertion error: {}", e),
-                        );
-                    }
-                }
+        while let Some(name) = pending.pop() {
+            if !keep.insert(name.clone()) {
+                continue;
+            }
+            if let Some(job) = workflow.jobs.get(&name) {
+                pending.extend(job.needs.as_vec().into_iter().filter(|dep| workflow.jobs.contains_key(dep)));
             }
         }
-
-        StepResult::Passed(self.clock.elapsed_since(start))
-    }
-}
-
-impl<W: World + 'static> Default for RustActions<W> {
-    fn default() -> Self {
-        Self::new()
+        if keep.len() < workflow.jobs.len() {
+            workflow.jobs.retain(|name, _| keep.contains(name));
+            // They may read the outputs of jobs that no longer run
+            workflow.post_assert.clear();
+        }
+        selected.push((path, workflow));
     }
+    Ok(selected)
 }
 
-fn toposort_jobs(jobs: &HashMap<String, Job>) -> Result<Vec<String>> {
+/// Jobs with their `needs` first; independent jobs keep their declaration order.
+pub(crate) fn toposort_jobs(jobs: &IndexMap<String, Job>) -> Result<Vec<String>> {
     let mut result = Vec::new();
     let mut visited = HashSet::new();
     let mut temp_visited = HashSet::new();
 
     fn visit(
         name: &str,
-        jobs: &HashMap<String, Job>,
+        jobs: &IndexMap<String, Job>,
         visited: &mut HashSet<String>,
         temp_visited: &mut HashSet<String>,
         result: &mut Vec<String>,
@@ -725,6 +3431,9 @@ fn toposort_jobs(jobs: &HashMap<String, Job>) -> Result<Vec<String>> {
         if let Some(job) = jobs.get(name) {
             for dep in job.needs.as_vec() {
                 if !jobs.contains_key(&dep) {
+                    if job.needs.is_optional(&dep) {
+                        continue;
+                    }
                     return Err(Error::JobDependencyNotFound {
                         job: name.to_string(),
                         dependency: dep.clone(),
@@ -750,3 +3459,420 @@ fn toposort_jobs(jobs: &HashMap<String, Job>) -> Result<Vec<String>> {
 
     Ok(result)
 }
+
+/// Reorders `order`, which respects `needs`, so that of the jobs whose needs have run the one
+/// with the highest `priority:` goes next, then the longest by `duration`. Ties keep `order`.
+fn prioritize(
+    mut pending: Vec<String>,
+    jobs: &IndexMap<String, Job>,
+    duration: impl Fn(&str) -> Duration,
+) -> Vec<String> {
+    let mut order: Vec<String> = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let next = pending
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| {
+                let needs = jobs[*name].needs.as_vec();
+                needs.iter().all(|dep| order.contains(dep) || !jobs.contains_key(dep))
+            })
+            .max_by_key(|(i, name)| (jobs[*name].priority, duration(name), Reverse(*i)))
+            .map_or(0, |(i, _)| i);
+        order.push(pending.remove(next));
+    }
+    order
+}
+
+/// `order`, which respects `needs`, reordered so the next job is picked by `rng` from those
+/// whose needs have run.
+fn shuffle_ready(mut pending: Vec<String>, jobs: &IndexMap<String, Job>, rng: &mut SeededRng) -> Vec<String> {
+    let mut order: Vec<String> = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let ready: Vec<usize> = (0..pending.len())
+            .filter(|i| {
+                let needs = jobs[&pending[*i]].needs.as_vec();
+                needs.iter().all(|dep| order.contains(dep) || !jobs.contains_key(dep))
+            })
+            .collect();
+        let next = rng.choose(&ready).copied().unwrap_or(0);
+        order.push(pending.remove(next));
+    }
+    order
+}
+
+/// Jobs in run order: stage by stage once any job has a `stage:`, and by `needs` and
+/// `priority:` within each stage. A job can't need one from a later stage.
+pub(crate) fn order_jobs(workflow: &Workflow) -> Result<Vec<String>> {
+    order_jobs_by(workflow, |_| Duration::ZERO)
+}
+
+/// `order_jobs`, with jobs of equal priority started longest `duration` first.
+pub(crate) fn order_jobs_by(
+    workflow: &Workflow,
+    duration: impl Fn(&str) -> Duration,
+) -> Result<Vec<String>> {
+    let order = prioritize(toposort_jobs(&workflow.jobs)?, &workflow.jobs, duration);
+    order_stages(workflow, order)
+}
+
+/// `order_jobs`, ignoring `priority:` and picking each next job at random. The order depends
+/// only on `seed` and the workflow's name and jobs.
+pub(crate) fn order_jobs_shuffled(workflow: &Workflow, seed: u64) -> Result<Vec<String>> {
+    let mut rng = SeededRng::from_scenario_name(&format!("{}/{}", seed, workflow.name));
+    let order = shuffle_ready(toposort_jobs(&workflow.jobs)?, &workflow.jobs, &mut rng);
+    order_stages(workflow, order)
+}
+
+/// `order` sorted stage by stage, once any job has a `stage:`.
+fn order_stages(workflow: &Workflow, mut order: Vec<String>) -> Result<Vec<String>> {
+    if workflow.jobs.values().all(|job| job.stage.is_none()) {
+        return Ok(order);
+    }
+
+    let stages: Vec<&str> = if workflow.stages.is_empty() {
+        DEFAULT_STAGES.to_vec()
+    } else {
+        workflow.stages.iter().map(String::as_str).collect()
+    };
+    let stage_of = |name: &str| -> Result<(usize, &str)> {
+        let stage = workflow.jobs[name].stage.as_deref().unwrap_or(DEFAULT_STAGE);
+        match stages.iter().position(|s| *s == stage) {
+            Some(index) => Ok((index, stage)),
+            None => Err(Error::Stage(format!(
+                "Job '{}' is in stage '{}', which isn't one of {:?}",
+                name, stage, stages
+            ))),
+        }
+    };
+
+    let mut index = HashMap::new();
+    for name in &order {
+        let (job_index, job_stage) = stage_of(name)?;
+        for dep in workflow.jobs[name].needs.as_vec() {
+            if !workflow.jobs.contains_key(&dep) {
+                continue;
+            }
+            let (dep_index, dep_stage) = stage_of(&dep)?;
+            if dep_index > job_index {
+                return Err(Error::Stage(format!(
+                    "Job '{}' in stage '{}' needs '{}' from the later stage '{}'",
+                    name, job_stage, dep, dep_stage
+                )));
+            }
+        }
+        index.insert(name.clone(), job_index);
+    }
+
+    // Stable, so `needs` order within a stage is kept
+    order.sort_by_key(|name| index[name]);
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow_builder::{JobBuilder, WorkflowBuilder};
+    use serde_json::json;
+
+    #[test]
+    fn test_setup_cache_key_is_order_independent() {
+        let mut a = HashMap::new();
+        a.insert("name".to_string(), json!("acme"));
+        a.insert("plan".to_string(), json!({ "tier": "pro", "seats": 5 }));
+
+        let mut b = HashMap::new();
+        b.insert("plan".to_string(), json!({ "seats": 5, "tier": "pro" }));
+        b.insert("name".to_string(), json!("acme"));
+
+        assert_eq!(
+            setup_cache_key("tenant/create", &a),
+            setup_cache_key("tenant/create", &b)
+        );
+        assert_ne!(
+            setup_cache_key("tenant/create", &a),
+            setup_cache_key("tenant/delete", &a)
+        );
+    }
+
+    #[test]
+    fn test_deep_merge_overrides_nested_keys_only() {
+        let mut config = json!({"db": {"host": "localhost", "port": 5432}, "tags": ["a", "b"]});
+        deep_merge(&mut config, json!({"db": {"port": 6543}, "tags": ["c"], "debug": true}));
+        assert_eq!(
+            config,
+            json!({"db": {"host": "localhost", "port": 6543}, "tags": ["c"], "debug": true})
+        );
+
+        let mut scalar = json!({"a": 1});
+        deep_merge(&mut scalar, json!("replaced"));
+        assert_eq!(scalar, json!("replaced"));
+    }
+
+    #[test]
+    fn test_evaluate_output_names_with_matrix_values() {
+        let mut ctx = ExprContext::new();
+        ctx.matrix = Arc::new([("region".to_string(), json!("eu"))].into_iter().collect());
+
+        assert_eq!(
+            evaluate_output("token_${{ matrix.region }}", "${{ matrix.region }}-token", &ctx),
+            Some(("token_eu".to_string(), json!("eu-token")))
+        );
+        assert_eq!(evaluate_output("token_${{ matrix.zone }}", "x", &ctx), None);
+    }
+
+    #[test]
+    fn test_plan_steps() {
+        let steps = |f: fn(JobBuilder) -> JobBuilder| {
+            WorkflowBuilder::new("w").job("j", f).build().jobs["j"].steps.clone()
+        };
+
+        let sequential = steps(|j| j.step("a").step("b"));
+        assert_eq!(plan_steps(&sequential).unwrap(), vec![(0, vec![]), (1, vec![0])]);
+
+        let dag = steps(|j| {
+            j.step("a").id("a").depends_on("b")
+                .step("b").id("b")
+                .step("c").id("c")
+        });
+        assert_eq!(
+            plan_steps(&dag).unwrap(),
+            vec![(1, vec![]), (0, vec![1]), (2, vec![])]
+        );
+
+        let cycle = steps(|j| j.step("a").id("a").depends_on("b").step("b").id("b").depends_on("a"));
+        assert!(matches!(
+            plan_steps(&cycle),
+            Err(Error::CircularDependency { chain }) if chain == "a -> b -> a"
+        ));
+
+        let unknown = steps(|j| j.step("a").id("a").depends_on("missing"));
+        assert!(matches!(
+            plan_steps(&unknown),
+            Err(Error::StepDependencyNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_order_jobs_by_stage() {
+        let workflow = WorkflowBuilder::new("w")
+            .job("cleanup", |j| j.stage("teardown").step("db/drop"))
+            .job("migrate", |j| j.stage("setup").step("db/migrate"))
+            .job("seed", |j| j.stage("setup").needs("migrate").step("db/seed"))
+            .job("login", |j| j.step("user/login"))
+            .build();
+        assert_eq!(order_jobs(&workflow).unwrap(), vec!["migrate", "seed", "login", "cleanup"]);
+
+        let backwards = WorkflowBuilder::new("w")
+            .job("seed", |j| j.stage("setup").needs("login").step("db/seed"))
+            .job("login", |j| j.step("user/login"))
+            .build();
+        assert!(matches!(
+            order_jobs(&backwards),
+            Err(Error::Stage(msg)) if msg == "Job 'seed' in stage 'setup' needs 'login' from the later stage 'test'"
+        ));
+
+        let custom = WorkflowBuilder::new("w")
+            .stages(["build", "verify"])
+            .job("check", |j| j.stage("verify").step("a"))
+            .job("login", |j| j.step("user/login"))
+            .build();
+        assert!(matches!(order_jobs(&custom), Err(Error::Stage(msg)) if msg.contains("'login' is in stage 'test'")));
+    }
+
+    #[test]
+    fn test_order_jobs_keeps_declaration_order() {
+        let workflow = Workflow::from_yaml(
+            r#"
+name: w
+jobs:
+  zeta: { steps: [{ uses: a }] }
+  beta: { needs: gamma, steps: [{ uses: a }] }
+  alpha: { steps: [{ uses: a }] }
+  gamma: { steps: [{ uses: a }] }
+"#,
+        )
+        .unwrap();
+        for _ in 0..5 {
+            assert_eq!(order_jobs(&workflow).unwrap(), vec!["zeta", "gamma", "beta", "alpha"]);
+        }
+    }
+
+    #[test]
+    fn test_order_jobs_by_priority() {
+        let workflow = Workflow::from_yaml(
+            r#"
+name: w
+jobs:
+  lint: { steps: [{ uses: a }] }
+  build: { priority: 5, steps: [{ uses: a }] }
+  e2e: { needs: build, priority: 10, steps: [{ uses: a }] }
+  unit: { steps: [{ uses: a }] }
+"#,
+        )
+        .unwrap();
+        assert_eq!(order_jobs(&workflow).unwrap(), vec!["build", "e2e", "lint", "unit"]);
+
+        let slow = |job: &str| if job == "unit" { Duration::from_secs(60) } else { Duration::ZERO };
+        assert_eq!(order_jobs_by(&workflow, slow).unwrap(), vec!["build", "e2e", "unit", "lint"]);
+    }
+
+    #[test]
+    fn test_order_jobs_shuffled() {
+        let workflow = Workflow::from_yaml(
+            r#"
+name: w
+jobs:
+  a: { steps: [{ uses: a }] }
+  b: { steps: [{ uses: a }] }
+  c: { needs: a, steps: [{ uses: a }] }
+  d: { steps: [{ uses: a }] }
+  e: { needs: [c, d], steps: [{ uses: a }] }
+"#,
+        )
+        .unwrap();
+        let orders: Vec<Vec<String>> = (0..20).map(|seed| order_jobs_shuffled(&workflow, seed).unwrap()).collect();
+        for order in &orders {
+            let at = |job: &str| order.iter().position(|j| j == job).unwrap();
+            assert!(at("a") < at("c") && at("c") < at("e") && at("d") < at("e"));
+        }
+        assert!(orders.iter().any(|order| order != &orders[0]));
+        assert_eq!(order_jobs_shuffled(&workflow, 7).unwrap(), orders[7]);
+    }
+
+    #[test]
+    fn test_verify_needs() {
+        let workflow = Workflow::from_yaml(
+            r#"
+name: w
+jobs:
+  checkout:
+    needs: [create]
+    verify-needs:
+      create: { user_id: number, email: string, tags: any }
+      billing: { account: string }
+    steps: [{ uses: a }]
+"#,
+        )
+        .unwrap();
+        let mut create = JobOutputs::new();
+        create.insert("user_id", serde_json::json!("u-1"));
+        create.insert("email", serde_json::json!("a@example.com"));
+        let outputs = HashMap::from([("create".to_string(), create)]);
+
+        assert_eq!(
+            verify_needs(&workflow.jobs["checkout"], &outputs),
+            vec![
+                "needs.create.outputs.user_id should be number, got \"u-1\"",
+                "needs.create.outputs.tags is missing",
+                "'billing' isn't one of the job's needs",
+            ]
+        );
+        assert!(Workflow::from_yaml("name: w\njobs:\n  a:\n    verify-needs: { b: { id: uuid } }\n").is_err());
+    }
+
+    #[test]
+    fn test_optional_and_aliased_needs() {
+        let workflow = Workflow::from_yaml(
+            r#"
+name: w
+jobs:
+  seed: { steps: [{ uses: a }] }
+  checkout:
+    needs:
+      seed: { alias: base }
+      migrate: { optional: true }
+    steps: [{ uses: a }]
+"#,
+        )
+        .unwrap();
+        assert_eq!(order_jobs(&workflow).unwrap(), vec!["seed", "checkout"]);
+
+        let mut seed = JobOutputs::new();
+        seed.insert("tenant", serde_json::json!("acme"));
+        let outputs = HashMap::from([("seed".to_string(), seed)]);
+        let needs = aliased_needs(&workflow.jobs["checkout"], &outputs);
+        assert_eq!(needs.keys().collect::<Vec<_>>(), vec!["base"]);
+
+        let required = Workflow::from_yaml("name: w\njobs:\n  a:\n    needs: { gone: {} }\n").unwrap();
+        assert!(matches!(order_jobs(&required), Err(Error::JobDependencyNotFound { .. })));
+    }
+
+    fn registry(files: &[(&str, &str)]) -> (tempfile::TempDir, WorkflowRegistry) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, yaml) in files {
+            std::fs::write(dir.path().join(name), yaml).unwrap();
+        }
+        let registry = WorkflowRegistry::build(dir.path(), true).unwrap();
+        (dir, registry)
+    }
+
+    #[test]
+    fn test_select_jobs_keeps_transitive_needs() {
+        let checkout = Workflow::from_yaml(
+            r#"
+name: Checkout
+assert-after: ["${{ jobs.audit.outputs.ok == true }}"]
+jobs:
+  login: { steps: [{ uses: a }] }
+  cart: { needs: login, steps: [{ uses: a }] }
+  place-order: { needs: [cart], steps: [{ uses: a }] }
+  audit: { steps: [{ uses: a }] }
+"#,
+        )
+        .unwrap();
+        let search = Workflow::from_yaml("name: Search\njobs:\n  query: { steps: [{ uses: a }] }\n").unwrap();
+        let workflows = || {
+            vec![
+                (PathBuf::from("checkout.yaml"), checkout.clone()),
+                (PathBuf::from("search.yaml"), search.clone()),
+            ]
+        };
+
+        let selected = select_jobs(workflows(), &["place-order".to_string()]).unwrap();
+        assert_eq!(selected.len(), 1);
+        let mut jobs: Vec<&str> = selected[0].1.jobs.keys().map(String::as_str).collect();
+        jobs.sort();
+        assert_eq!(jobs, vec!["cart", "login", "place-order"]);
+        assert!(selected[0].1.post_assert.is_empty());
+
+        assert_eq!(select_jobs(workflows(), &[]).unwrap().len(), 2);
+        let result = select_jobs(workflows(), &["refund".to_string()]);
+        assert!(matches!(result, Err(Error::JobNotFound { job, .. }) if job == "refund"));
+    }
+
+    #[test]
+    fn test_order_workflows_runs_dependencies_once() {
+        let (_dir, registry) = registry(&[
+            ("smoke.yaml", "name: Smoke\njobs: {}\n"),
+            ("e2e.yaml", "name: E2E\nneeds-workflow: smoke.yaml\njobs: {}\n"),
+        ]);
+        let selected = |names: &[&str]| -> Vec<(PathBuf, Workflow)> {
+            names
+                .iter()
+                .map(|n| (PathBuf::from(n), registry.get_by_str(n).unwrap().clone()))
+                .collect()
+        };
+
+        let names = |ordered: Vec<(PathBuf, Workflow)>| -> Vec<String> {
+            ordered.into_iter().map(|(_, w)| w.name).collect()
+        };
+
+        let ordered = order_workflows(selected(&["e2e.yaml"]), &registry).unwrap();
+        assert_eq!(names(ordered), vec!["Smoke", "E2E"]);
+
+        let ordered = order_workflows(selected(&["e2e.yaml", "smoke.yaml"]), &registry).unwrap();
+        assert_eq!(names(ordered), vec!["Smoke", "E2E"]);
+    }
+
+    #[test]
+    fn test_order_workflows_detects_cycles() {
+        let (_dir, registry) = registry(&[
+            ("a.yaml", "name: A\nneeds-workflow: b.yaml\njobs: {}\n"),
+            ("b.yaml", "name: B\nneeds-workflow: [a.yaml]\njobs: {}\n"),
+        ]);
+        let a = registry.get_by_str("a.yaml").unwrap().clone();
+
+        let result = order_workflows(vec![(PathBuf::from("a.yaml"), a)], &registry);
+        assert!(matches!(result, Err(Error::CircularDependency { .. })));
+    }
+}
@@ -0,0 +1,266 @@
+use crate::args::ArgField;
+use crate::parser::Workflow;
+use crate::registry::StepRegistry;
+use crate::{Error, Result};
+use std::io::{BufRead, Write};
+
+/// What to scaffold: a workflow name and its jobs, in order, each a list of step names as
+/// they would appear under `uses:`.
+///
+/// ```ignore
+/// let spec = ScaffoldSpec::new("Signup")
+///     .job("create", ["user/create", "user/get"]);
+/// let yaml = RustActions::<TestWorld>::new().scaffold(&spec)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScaffoldSpec {
+    pub name: String,
+    pub jobs: Vec<(String, Vec<String>)>,
+}
+
+impl ScaffoldSpec {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            jobs: Vec::new(),
+        }
+    }
+
+    pub fn job(
+        mut self,
+        name: impl Into<String>,
+        steps: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.jobs
+            .push((name.into(), steps.into_iter().map(Into::into).collect()));
+        self
+    }
+}
+
+/// Renders `spec` as workflow YAML. Every step gets an id unique within its job and a
+/// `with:` block holding a placeholder per required arg; optional args are left commented
+/// out. Step names not in `steps` are rejected with the closest registered names.
+pub fn scaffold(spec: &ScaffoldSpec, steps: &StepRegistry) -> Result<String> {
+    let mut yaml = format!("name: {}\n\njobs:\n", quote(&spec.name));
+
+    for (job, uses_list) in &spec.jobs {
+        if uses_list.is_empty() {
+            return Err(Error::Custom(format!("Job '{}' has no steps", job)));
+        }
+        yaml.push_str(&format!("  {}:\n    steps:\n", slug(job)));
+
+        let mut ids: Vec<String> = Vec::new();
+        for uses in uses_list {
+            let fields = steps.arg_fields(uses)?;
+            let id = unique_id(&step_id(uses), &ids);
+            yaml.push_str(&format!("      - id: {}\n        uses: {}\n", id, uses));
+            yaml.push_str(&with_block(fields));
+            ids.push(id);
+        }
+    }
+
+    // The output is meant to load as-is, placeholders included
+    Workflow::from_yaml(&yaml)?;
+    Ok(yaml)
+}
+
+/// Builds a `ScaffoldSpec` by prompting on `output` and reading answers from `input`, then
+/// renders it with `scaffold`. Lists the registered steps first; a blank line ends a job's
+/// steps, and a blank job name (or end of input) ends the workflow.
+pub fn scaffold_interactive(
+    steps: &StepRegistry,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<String> {
+    writeln!(output, "Registered steps:")?;
+    for name in steps.names() {
+        let fields = steps.arg_fields(&name).unwrap_or(&[]);
+        let args: Vec<&str> = fields.iter().map(|f| f.name).collect();
+        if args.is_empty() {
+            writeln!(output, "  {}", name)?;
+        } else {
+            writeln!(output, "  {} ({})", name, args.join(", "))?;
+        }
+    }
+    writeln!(output)?;
+
+    let name = prompt(&mut input, &mut output, "Workflow name: ")?;
+    let mut spec = ScaffoldSpec::new(if name.is_empty() { "New Workflow" } else { &name });
+
+    loop {
+        let job = prompt(&mut input, &mut output, "Job name (blank to finish): ")?;
+        if job.is_empty() {
+            break;
+        }
+
+        let mut uses_list = Vec::new();
+        loop {
+            let uses = prompt(&mut input, &mut output, "  Step (blank to end job): ")?;
+            if uses.is_empty() {
+                break;
+            }
+            match steps.arg_fields(&uses) {
+                Ok(_) => uses_list.push(uses),
+                Err(e) => writeln!(output, "  {}", e)?,
+            }
+        }
+        if !uses_list.is_empty() {
+            spec = spec.job(job, uses_list);
+        }
+    }
+
+    scaffold(&spec, steps)
+}
+
+/// Reads one trimmed line; end of input reads as blank.
+fn prompt(input: &mut impl BufRead, output: &mut impl Write, message: &str) -> Result<String> {
+    write!(output, "{}", message)?;
+    output.flush()?;
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+fn with_block(fields: &[ArgField]) -> String {
+    if fields.is_empty() {
+        return String::new();
+    }
+    // A `with:` holding only comments would load as null
+    let mut block = if fields.iter().any(|f| !f.optional) {
+        "        with:\n".to_string()
+    } else {
+        "        # with:\n".to_string()
+    };
+    for field in fields {
+        let comment = if field.optional { "# " } else { "" };
+        block.push_str(&format!(
+            "          {}{}: {}  # {}\n",
+            comment,
+            field.name,
+            placeholder(field.ty),
+            field.ty
+        ));
+    }
+    block
+}
+
+/// A YAML value of roughly the right shape for a Rust type name.
+fn placeholder(ty: &str) -> &'static str {
+    let ty = ty
+        .strip_prefix("Option<")
+        .and_then(|t| t.strip_suffix('>'))
+        .unwrap_or(ty);
+    let base = ty.split('<').next().unwrap_or(ty);
+    let base = base.rsplit("::").next().unwrap_or(base);
+    match base {
+        "String" | "&str" | "str" | "PathBuf" | "Uuid" => "\"\"",
+        "bool" => "false",
+        "f32" | "f64" => "0.0",
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+        | "u128" | "usize" => "0",
+        "Vec" | "HashSet" | "BTreeSet" => "[]",
+        "Value" => "null",
+        _ => "{}",
+    }
+}
+
+/// `user/create@1` → `create`
+fn step_id(uses: &str) -> String {
+    let name = uses.split('@').next().unwrap_or(uses);
+    slug(name.rsplit('/').next().unwrap_or(name))
+}
+
+fn unique_id(id: &str, taken: &[String]) -> String {
+    let mut candidate = id.to_string();
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{}-{}", id, n);
+        n += 1;
+    }
+    candidate
+}
+
+fn slug(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "step".to_string()
+    } else {
+        slug
+    }
+}
+
+/// JSON string literals are valid YAML scalars.
+fn quote(s: &str) -> String {
+    serde_json::Value::String(s.to_string()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::RawArgs;
+    use crate::context::StepContext;
+    use crate::outputs::StepOutputs;
+    use std::any::Any;
+    use std::future::Future;
+    use std::pin::Pin;
+
+    fn step_fn<'a>(
+        _world: &'a mut dyn Any,
+        _args: RawArgs,
+        _ctx: &'a StepContext,
+    ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>> {
+        Box::pin(async { Ok(StepOutputs::new()) })
+    }
+
+    const CREATE_ARGS: &[ArgField] = &[
+        ArgField { name: "username", ty: "String", optional: false },
+        ArgField { name: "age", ty: "Option<u32>", optional: true },
+    ];
+
+    #[test]
+    fn test_scaffold_from_spec_and_prompts() {
+        let mut steps = StepRegistry::new();
+        steps.register_with_args("user/create", step_fn, CREATE_ARGS);
+        steps.register("user/list", step_fn);
+
+        let spec = ScaffoldSpec::new("Sign Up").job("Create Users", ["user/create", "user/create", "user/list"]);
+        let yaml = scaffold(&spec, &steps).unwrap();
+        assert_eq!(
+            yaml,
+            r#"name: "Sign Up"
+
+jobs:
+  create-users:
+    steps:
+      - id: create
+        uses: user/create
+        with:
+          username: ""  # String
+          # age: 0  # Option<u32>
+      - id: create-2
+        uses: user/create
+        with:
+          username: ""  # String
+          # age: 0  # Option<u32>
+      - id: list
+        uses: user/list
+"#
+        );
+
+        let err = scaffold(&ScaffoldSpec::new("Typo").job("a", ["user/craete"]), &steps).unwrap_err();
+        assert_eq!(err.to_string(), "Step not found: user/craete (did you mean user/create? 2 steps registered)");
+
+        let answers = "Prompted\nsetup\nuser/craete\nuser/create\n\n\n";
+        let mut transcript = Vec::new();
+        let yaml = scaffold_interactive(&steps, answers.as_bytes(), &mut transcript).unwrap();
+        let transcript = String::from_utf8(transcript).unwrap();
+        assert!(transcript.contains("  user/create (username, age)\n"));
+        assert!(transcript.contains("did you mean user/create?"));
+        assert!(yaml.starts_with("name: \"Prompted\"\n\njobs:\n  setup:\n    steps:\n      - id: create\n"));
+    }
+}
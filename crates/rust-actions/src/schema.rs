@@ -0,0 +1,259 @@
+use serde_yaml::Value;
+
+const WORKFLOW_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "extends",
+    "on",
+    "ignore",
+    "quarantined",
+    "needs-workflow",
+    "env",
+    "env-file",
+    "services",
+    "stages",
+    "setup",
+    "teardown",
+    "jobs",
+    "assert-after",
+    "post-assert",
+];
+const SERVICE_KEYS: &[&str] = &["image", "port", "env", "scheme"];
+const TRIGGER_KEYS: &[&str] = &["workflow_call"];
+const WORKFLOW_CALL_KEYS: &[&str] = &["inputs", "outputs", "memoize"];
+const INPUT_KEYS: &[&str] = &["description", "required", "default", "type"];
+const OUTPUT_KEYS: &[&str] = &["description", "value"];
+const JOB_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "needs",
+    "stage",
+    "quarantined",
+    "priority",
+    "as",
+    "uses",
+    "with",
+    "strategy",
+    "outputs",
+    "env",
+    "env-file",
+    "steps",
+    "export-steps",
+    "assert-after",
+    "post-assert",
+    "verify-needs",
+    "assert-duration",
+];
+const NEED_KEYS: &[&str] = &["optional", "alias"];
+const STRATEGY_KEYS: &[&str] = &["matrix", "fail-fast", "max-parallel", "sample"];
+const STEP_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "id",
+    "uses",
+    "as",
+    "with",
+    "continue-on-error",
+    "setup",
+    "mask-outputs",
+    "assert-before",
+    "pre-assert",
+    "assert-after",
+    "post-assert",
+    "assert-mode",
+    "expect",
+    "cleanup",
+    "compensate",
+    "capture",
+    "delay",
+    "rate-limit",
+    "depends-on",
+    "assert-duration",
+];
+const CLEANUP_KEYS: &[&str] = &["uses", "with"];
+
+/// Finds keys in a workflow document that the parser would silently ignore, one message per
+/// key with its location and the closest known key. Free-form maps (`env`, `with`, `matrix`,
+/// `expect`, job `outputs`) are not checked.
+pub(crate) fn unknown_fields(workflow: &Value) -> Vec<String> {
+    let mut found = Vec::new();
+    check_keys(workflow, "", WORKFLOW_KEYS, &mut found);
+
+    if let Some(on) = workflow.get("on") {
+        check_keys(on, "on", TRIGGER_KEYS, &mut found);
+        if let Some(call) = on.get("workflow_call") {
+            let path = "on.workflow_call";
+            check_keys(call, path, WORKFLOW_CALL_KEYS, &mut found);
+            for (name, input) in entries(call.get("inputs")) {
+                check_keys(input, &format!("{}.inputs.{}", path, name), INPUT_KEYS, &mut found);
+            }
+            for (name, output) in entries(call.get("outputs")) {
+                check_keys(output, &format!("{}.outputs.{}", path, name), OUTPUT_KEYS, &mut found);
+            }
+        }
+    }
+
+    for (name, service) in entries(workflow.get("services")) {
+        check_keys(service, &format!("services.{}", name), SERVICE_KEYS, &mut found);
+    }
+
+    for key in ["setup", "teardown"] {
+        for (i, step) in items(workflow.get(key)) {
+            check_keys(step, &format!("{}[{}]", key, i), STEP_KEYS, &mut found);
+        }
+    }
+
+    for (name, job) in entries(workflow.get("jobs")) {
+        let path = format!("jobs.{}", name);
+        check_keys(job, &path, JOB_KEYS, &mut found);
+        if let Some(strategy) = job.get("strategy") {
+            check_keys(strategy, &format!("{}.strategy", path), STRATEGY_KEYS, &mut found);
+        }
+        for (dep, need) in entries(job.get("needs")) {
+            check_keys(need, &format!("{}.needs.{}", path, dep), NEED_KEYS, &mut found);
+        }
+        for (i, step) in items(job.get("steps")) {
+            let path = format!("{}.steps[{}]", path, i);
+            check_keys(step, &path, STEP_KEYS, &mut found);
+            for (j, cleanup) in items(step.get("cleanup")) {
+                check_keys(cleanup, &format!("{}.cleanup[{}]", path, j), CLEANUP_KEYS, &mut found);
+            }
+            if let Some(compensate) = step.get("compensate") {
+                check_keys(compensate, &format!("{}.compensate", path), CLEANUP_KEYS, &mut found);
+            }
+        }
+    }
+
+    found
+}
+
+fn check_keys(value: &Value, path: &str, known: &[&str], found: &mut Vec<String>) {
+    let Some(map) = value.as_mapping() else {
+        return;
+    };
+    for key in map.keys().filter_map(Value::as_str) {
+        if known.contains(&key) {
+            continue;
+        }
+        let location = if path.is_empty() {
+            String::new()
+        } else {
+            format!("{}: ", path)
+        };
+        found.push(match suggest(key, known) {
+            Some(suggestion) => format!(
+                "{}unknown field `{}`, did you mean `{}`?",
+                location, key, suggestion
+            ),
+            None => format!(
+                "{}unknown field `{}`, expected one of {}",
+                location,
+                key,
+                known
+                    .iter()
+                    .map(|k| format!("`{}`", k))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+    }
+}
+
+/// The known key equal to `key` up to case and `_`/`-`, else the most similar one.
+pub(crate) fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    let normalized = key.to_lowercase().replace('_', "-");
+    if let Some(exact) = known.iter().find(|k| k.replace('_', "-") == normalized) {
+        return Some(exact);
+    }
+    known
+        .iter()
+        .map(|k| (*k, strsim::jaro_winkler(&normalized, k)))
+        .filter(|(_, score)| *score > 0.8)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(k, _)| k)
+}
+
+fn entries(value: Option<&Value>) -> impl Iterator<Item = (&str, &Value)> {
+    value
+        .and_then(Value::as_mapping)
+        .into_iter()
+        .flat_map(|m| m.iter())
+        .filter_map(|(k, v)| Some((k.as_str()?, v)))
+}
+
+fn items(value: Option<&Value>) -> impl Iterator<Item = (usize, &Value)> {
+    value
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flat_map(|s| s.iter().enumerate())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Workflow;
+
+    #[test]
+    fn test_unknown_fields_suggest_closest_key() {
+        let yaml = r#"
+name: Typos
+jobs:
+  create:
+    stratgy:
+      fail-fast: false
+    steps:
+      - uses: user/create
+        continue_on_error: true
+        post_assert:
+          - ${{ outputs.id != "" }}
+        cleanup:
+          - uses: user/delete
+            wiht: { id: 1 }
+      - uses: user/get
+        frobnicate: true
+"#;
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        let mut found = unknown_fields(&value);
+        found.sort();
+
+        assert_eq!(found.len(), 5);
+        assert_eq!(found[0], "jobs.create.steps[0].cleanup[0]: unknown field `wiht`, did you mean `with`?");
+        assert_eq!(found[1], "jobs.create.steps[0]: unknown field `continue_on_error`, did you mean `continue-on-error`?");
+        assert_eq!(found[2], "jobs.create.steps[0]: unknown field `post_assert`, did you mean `post-assert`?");
+        assert!(found[3].starts_with("jobs.create.steps[1]: unknown field `frobnicate`, expected one of `name`, `description`, `id`"));
+        assert_eq!(found[4], "jobs.create: unknown field `stratgy`, did you mean `strategy`?");
+    }
+
+    #[test]
+    fn test_known_keys_cover_parser_fields() {
+        let yaml = r#"
+name: Everything
+on:
+  workflow_call:
+    inputs:
+      username: { type: string }
+    outputs:
+      id: { value: "${{ jobs.create.outputs.id }}" }
+setup:
+  - uses: db/migrate
+teardown:
+  - uses: db/drop
+jobs:
+  create:
+    strategy:
+      matrix: { region: [eu] }
+    steps:
+      - uses: user/create
+        cleanup:
+          - uses: user/delete
+  verify:
+    needs: { create: { optional: true, alias: base } }
+    steps:
+      - uses: user/get
+"#;
+        // Serializing writes every field under its canonical name
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let value = serde_yaml::to_value(&workflow).unwrap();
+        assert_eq!(unknown_fields(&value), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,111 @@
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// Describes what changed between two `WorldSnapshot`s, as exposed to `${{ diff.* }}`:
+///
+/// - `created`, `deleted`, `updated`: records per collection
+/// - `count.created`, `count.deleted`, `count.updated`: number of records per collection
+/// - `changed`: sorted names of collections with any change
+///
+/// Every collection in either snapshot appears in each map, so `diff.count.created.users == 0`
+/// holds when nothing was created.
+pub fn diff_snapshots(before: &Value, after: &Value) -> Value {
+    let names: BTreeSet<&String> = collections(before).chain(collections(after)).collect();
+
+    let mut created = Map::new();
+    let mut deleted = Map::new();
+    let mut updated = Map::new();
+    let mut changed = Vec::new();
+
+    for name in names {
+        let old = records(before, name);
+        let new = records(after, name);
+
+        let added: Vec<Value> = new.iter().filter(|r| !contains(old, r)).cloned().collect();
+        let removed: Vec<Value> = old.iter().filter(|r| !contains(new, r)).cloned().collect();
+        let modified: Vec<Value> = new
+            .iter()
+            .filter(|r| {
+                r.get("id").is_some_and(|id| {
+                    old.iter().any(|o| o.get("id") == Some(id) && o != *r)
+                })
+            })
+            .cloned()
+            .collect();
+
+        if !(added.is_empty() && removed.is_empty() && modified.is_empty()) {
+            changed.push(Value::String(name.clone()));
+        }
+        created.insert(name.clone(), Value::Array(added));
+        deleted.insert(name.clone(), Value::Array(removed));
+        updated.insert(name.clone(), Value::Array(modified));
+    }
+
+    let count = |map: &Map<String, Value>| -> Value {
+        map.iter()
+            .map(|(k, v)| (k.clone(), Value::from(v.as_array().map_or(0, Vec::len))))
+            .collect::<Map<_, _>>()
+            .into()
+    };
+
+    let mut diff = Map::new();
+    diff.insert(
+        "count".to_string(),
+        serde_json::json!({
+            "created": count(&created),
+            "deleted": count(&deleted),
+            "updated": count(&updated),
+        }),
+    );
+    diff.insert("created".to_string(), Value::Object(created));
+    diff.insert("deleted".to_string(), Value::Object(deleted));
+    diff.insert("updated".to_string(), Value::Object(updated));
+    diff.insert("changed".to_string(), Value::Array(changed));
+    Value::Object(diff)
+}
+
+fn collections(snapshot: &Value) -> impl Iterator<Item = &String> {
+    snapshot.as_object().into_iter().flat_map(|m| m.keys())
+}
+
+fn records<'a>(snapshot: &'a Value, name: &str) -> &'a [Value] {
+    snapshot
+        .get(name)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Records with an `id` match by id; others must be equal.
+fn contains(records: &[Value], record: &Value) -> bool {
+    match record.get("id") {
+        Some(id) => records.iter().any(|r| r.get("id") == Some(id)),
+        None => records.contains(record),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_snapshots() {
+        let before = json!({
+            "users": [{ "id": 1, "name": "alice" }, { "id": 2, "name": "bob" }],
+            "tags": ["a"]
+        });
+        let after = json!({
+            "users": [{ "id": 1, "name": "alice" }, { "id": 2, "name": "robert" }, { "id": 3, "name": "carol" }],
+            "tags": ["a"],
+            "orders": []
+        });
+
+        let diff = diff_snapshots(&before, &after);
+        assert_eq!(diff["created"]["users"], json!([{ "id": 3, "name": "carol" }]));
+        assert_eq!(diff["updated"]["users"], json!([{ "id": 2, "name": "robert" }]));
+        assert_eq!(diff["deleted"]["users"], json!([]));
+        assert_eq!(diff["count"]["created"]["users"], json!(1));
+        assert_eq!(diff["count"]["created"]["orders"], json!(0));
+        assert_eq!(diff["changed"], json!(["users"]));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::runner::{job_key, JobResult, StepResult, WorkflowResult};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::Duration;
+
+/// Failure messages at least this similar, once numbers and ids are masked, share a group.
+const SIMILARITY: f64 = 0.85;
+
+/// Numbers, hex ids and quoted values: the parts of a failure message that vary between runs
+static VARIABLE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"'[^']*'|"[^"]*"|`[^`]*`|\b[0-9a-fA-F-]*[0-9][0-9a-fA-F-]*\b"#).unwrap()
+});
+
+/// How a job or workflow went, worst last so roll-ups can take the max.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Passed,
+    /// Passed, but a `continue-on-error` step or a cleanup failed, or a missing key was
+    /// resolved under `MissingKeyPolicy::Lenient`
+    Warning,
+    /// Failed under `quarantined:` or `allow_failure`, which doesn't fail the run
+    Quarantined,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StatusCounts {
+    pub passed: usize,
+    pub warnings: usize,
+    pub failed: usize,
+    pub quarantined: usize,
+    /// Workflows only: ignored or not run
+    pub ignored: usize,
+}
+
+impl StatusCounts {
+    fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Passed => self.passed += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Quarantined => self.quarantined += 1,
+            Severity::Failed => self.failed += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.passed + self.warnings + self.failed + self.quarantined + self.ignored
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StepCounts {
+    pub passed: usize,
+    pub failed: usize,
+    /// Failed under `continue-on-error`
+    pub tolerated: usize,
+    pub skipped: usize,
+}
+
+impl StepCounts {
+    pub fn total(&self) -> usize {
+        self.passed + self.failed + self.tolerated + self.skipped
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobTiming {
+    /// `<workflow> / <job>`, with the matrix suffix
+    pub job: String,
+    pub duration: Duration,
+}
+
+/// Failures whose messages differ only in numbers, ids or quoted values.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureGroup {
+    /// The first message seen
+    pub message: String,
+    pub count: usize,
+    pub jobs: Vec<String>,
+    #[serde(skip)]
+    pattern: String,
+}
+
+/// Roll-up of one or more runs' results, for a suite summary or downstream tooling.
+#[derive(Debug, Clone, Serialize)]
+pub struct SuiteSummary {
+    pub severity: Severity,
+    pub workflows: StatusCounts,
+    pub jobs: StatusCounts,
+    pub steps: StepCounts,
+    pub duration: Duration,
+    pub wall_duration: Duration,
+    /// The slowest jobs, slowest first
+    pub slowest: Vec<JobTiming>,
+    /// Largest group first
+    pub failures: Vec<FailureGroup>,
+    /// Failures of quarantined jobs and workflows, kept out of `failures`
+    pub quarantined: Vec<FailureGroup>,
+    /// Jobs that both passed and failed across the results, e.g. in several saved runs
+    pub flaky: Vec<String>,
+}
+
+impl SuiteSummary {
+    /// Summarizes `results`, listing the `slowest` slowest jobs.
+    pub fn new(results: &[WorkflowResult], slowest: usize) -> Self {
+        let mut summary = Self {
+            severity: Severity::Passed,
+            workflows: StatusCounts::default(),
+            jobs: StatusCounts::default(),
+            steps: StepCounts::default(),
+            duration: Duration::ZERO,
+            wall_duration: Duration::ZERO,
+            slowest: Vec::new(),
+            failures: Vec::new(),
+            quarantined: Vec::new(),
+            flaky: Vec::new(),
+        };
+        let mut timings = Vec::new();
+        let mut outcomes: BTreeMap<String, BTreeSet<bool>> = BTreeMap::new();
+
+        for workflow in results {
+            summary.duration += workflow.duration;
+            summary.wall_duration += workflow.wall_duration;
+            if workflow.is_ignored() {
+                summary.workflows.ignored += 1;
+                continue;
+            }
+
+            let mut worst = match (workflow.failed_assertions.is_empty(), workflow.quarantined) {
+                (true, _) => Severity::Passed,
+                (false, true) => Severity::Quarantined,
+                (false, false) => Severity::Failed,
+            };
+            for assertion in &workflow.failed_assertions {
+                summary.add_failure(workflow.quarantined, &workflow.name, assertion);
+            }
+
+            for job in &workflow.jobs {
+                let name = job_key(&workflow.name, &format!("{}{}", job.name, job.matrix_suffix));
+                let severity = job_severity(job);
+                summary.jobs.add(severity);
+                worst = worst.max(severity);
+                outcomes.entry(name.clone()).or_default().insert(job.passed());
+                timings.push(JobTiming {
+                    job: name.clone(),
+                    duration: job.duration,
+                });
+
+                for step in &job.steps {
+                    match &step.result {
+                        StepResult::Passed(_) => summary.steps.passed += 1,
+                        StepResult::Skipped => summary.steps.skipped += 1,
+                        StepResult::Failed(..) if step.continue_on_error => summary.steps.tolerated += 1,
+                        StepResult::Failed(_, msg) => {
+                            summary.steps.failed += 1;
+                            summary.add_failure(job.quarantined, &name, msg);
+                        }
+                    }
+                }
+                // A setup phase that couldn't start already reports the error as a failed step
+                if let Some(error) = job.infra_error.as_ref().filter(|_| job.steps_failed() == 0) {
+                    summary.add_failure(job.quarantined, &name, error);
+                }
+            }
+
+            summary.workflows.add(worst);
+            summary.severity = summary.severity.max(worst);
+        }
+
+        timings.sort_by_key(|t| Reverse(t.duration));
+        timings.truncate(slowest);
+        summary.slowest = timings;
+        summary.failures.sort_by_key(|g| Reverse(g.count));
+        summary.quarantined.sort_by_key(|g| Reverse(g.count));
+        summary.flaky = outcomes
+            .into_iter()
+            .filter(|(_, seen)| seen.len() > 1)
+            .map(|(job, _)| job)
+            .collect();
+        summary
+    }
+
+    pub fn passed(&self) -> bool {
+        self.severity != Severity::Failed
+    }
+
+    fn add_failure(&mut self, quarantined: bool, job: &str, message: &str) {
+        let groups = if quarantined { &mut self.quarantined } else { &mut self.failures };
+        let pattern = failure_pattern(message);
+        let group = groups
+            .iter_mut()
+            .find(|g| strsim::normalized_levenshtein(&g.pattern, &pattern) >= SIMILARITY);
+        match group {
+            Some(group) => {
+                group.count += 1;
+                if !group.jobs.iter().any(|j| j == job) {
+                    group.jobs.push(job.to_string());
+                }
+            }
+            None => groups.push(FailureGroup {
+                message: message.to_string(),
+                count: 1,
+                jobs: vec![job.to_string()],
+                pattern,
+            }),
+        }
+    }
+}
+
+fn job_severity(job: &JobResult) -> Severity {
+    let tolerated = job.steps.iter().any(|step| step.result.is_failed() && step.continue_on_error);
+    let cleanup_failed = job.cleanup.iter().any(|(_, r)| r.is_failed());
+    if !job.passed() {
+        if job.quarantined {
+            Severity::Quarantined
+        } else {
+            Severity::Failed
+        }
+    } else if tolerated || cleanup_failed || !job.warnings.is_empty() {
+        Severity::Warning
+    } else {
+        Severity::Passed
+    }
+}
+
+fn failure_pattern(message: &str) -> String {
+    VARIABLE_RE.replace_all(message, "_").into_owned()
+}
+
+impl fmt::Display for SuiteSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |c: &StatusCounts| {
+            let mut parts = vec![format!("{} passed", c.passed)];
+            if c.warnings > 0 {
+                parts.push(format!("{} with warnings", c.warnings));
+            }
+            parts.push(format!("{} failed", c.failed));
+            if c.quarantined > 0 {
+                parts.push(format!("{} quarantined", c.quarantined));
+            }
+            if c.ignored > 0 {
+                parts.push(format!("{} ignored", c.ignored));
+            }
+            parts.join(", ")
+        };
+        writeln!(f, "{} workflows ({})", self.workflows.total(), counts(&self.workflows))?;
+        writeln!(f, "{} jobs ({})", self.jobs.total(), counts(&self.jobs))?;
+        write!(
+            f,
+            "{} steps ({} passed, {} failed, {} tolerated, {} skipped)",
+            self.steps.total(),
+            self.steps.passed,
+            self.steps.failed,
+            self.steps.tolerated,
+            self.steps.skipped
+        )?;
+
+        if !self.slowest.is_empty() {
+            write!(f, "\nslowest:")?;
+            for timing in &self.slowest {
+                write!(f, "\n  {} ({:?})", timing.job, timing.duration)?;
+            }
+        }
+        if !self.failures.is_empty() {
+            write!(f, "\nfailures ({} groups):", self.failures.len())?;
+            for group in &self.failures {
+                write!(f, "\n  {}x {}\n    in {}", group.count, group.message, group.jobs.join(", "))?;
+            }
+        }
+        if !self.quarantined.is_empty() {
+            write!(f, "\nquarantined failures ({} groups):", self.quarantined.len())?;
+            for group in &self.quarantined {
+                write!(f, "\n  {}x {}\n    in {}", group.count, group.message, group.jobs.join(", "))?;
+            }
+        }
+        if !self.flaky.is_empty() {
+            write!(f, "\nflaky ({}):", self.flaky.len())?;
+            for job in &self.flaky {
+                write!(f, "\n  {}", job)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::StepRecord;
+
+    fn job(name: &str, millis: u64, steps: Vec<(StepResult, bool)>) -> JobResult {
+        JobResult {
+            name: name.to_string(),
+            steps: steps
+                .into_iter()
+                .map(|(r, c)| StepRecord { continue_on_error: c, ..StepRecord::new("step", r) })
+                .collect(),
+            duration: Duration::from_millis(millis),
+            ..Default::default()
+        }
+    }
+
+    fn workflow(name: &str, jobs: Vec<JobResult>) -> WorkflowResult {
+        WorkflowResult {
+            name: name.to_string(),
+            jobs,
+            duration: Duration::from_millis(10),
+            ignored: None,
+            failed_assertions: vec![],
+            wall_duration: Duration::ZERO,
+            quarantined: false,
+            description: None,
+        }
+    }
+
+    fn passed() -> StepResult {
+        StepResult::Passed(Duration::ZERO)
+    }
+
+    fn failed(msg: &str) -> StepResult {
+        StepResult::Failed(Duration::ZERO, msg.to_string())
+    }
+
+    #[test]
+    fn test_summary_rolls_up_severity_and_groups_failures() {
+        let results = vec![
+            workflow(
+                "Users",
+                vec![
+                    job("create", 30, vec![(passed(), false), (failed("timeout"), true)]),
+                    job("login", 50, vec![(failed("user 1234 not found"), false)]),
+                    job("delete", 10, vec![(failed("user 98 not found"), false), (StepResult::Skipped, false)]),
+                ],
+            ),
+            workflow("Orders", vec![job("list", 5, vec![(passed(), false)])]),
+            workflow("Users", vec![job("login", 40, vec![(passed(), false)])]),
+            WorkflowResult {
+                ignored: Some("wip".to_string()),
+                ..workflow("Draft", vec![])
+            },
+        ];
+
+        let summary = SuiteSummary::new(&results, 2);
+
+        assert_eq!(summary.severity, Severity::Failed);
+        assert_eq!(
+            summary.workflows,
+            StatusCounts { passed: 2, warnings: 0, failed: 1, quarantined: 0, ignored: 1 }
+        );
+        assert_eq!(
+            summary.jobs,
+            StatusCounts { passed: 2, warnings: 1, failed: 2, quarantined: 0, ignored: 0 }
+        );
+        assert_eq!(summary.steps, StepCounts { passed: 3, failed: 2, tolerated: 1, skipped: 1 });
+
+        let slowest: Vec<&str> = summary.slowest.iter().map(|t| t.job.as_str()).collect();
+        assert_eq!(slowest, vec!["Users / login", "Users / login"]);
+
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].count, 2);
+        assert_eq!(summary.failures[0].message, "user 1234 not found");
+        assert_eq!(summary.failures[0].jobs, vec!["Users / login", "Users / delete"]);
+
+        assert_eq!(summary.flaky, vec!["Users / login"]);
+        assert!(summary.to_string().starts_with(
+            "4 workflows (2 passed, 1 failed, 1 ignored)\n5 jobs (2 passed, 1 with warnings, 2 failed)\n"
+        ));
+    }
+
+    #[test]
+    fn test_quarantined_failures_dont_fail_the_suite() {
+        let flaky = JobResult {
+            quarantined: true,
+            ..job("import", 20, vec![(failed("upstream returned 502"), false)])
+        };
+        let results = vec![
+            workflow("Nightly", vec![job("export", 10, vec![(passed(), false)]), flaky]),
+            WorkflowResult {
+                quarantined: true,
+                failed_assertions: vec!["jobs.sync.outputs.count == 3".to_string()],
+                ..workflow("Sync", vec![])
+            },
+        ];
+
+        let summary = SuiteSummary::new(&results, 0);
+
+        assert!(summary.passed());
+        assert_eq!(summary.severity, Severity::Quarantined);
+        assert_eq!(
+            summary.workflows,
+            StatusCounts { passed: 0, warnings: 0, failed: 0, quarantined: 2, ignored: 0 }
+        );
+        assert_eq!(summary.jobs.quarantined, 1);
+        assert!(summary.failures.is_empty());
+        let quarantined: Vec<&str> = summary.quarantined.iter().map(|g| g.jobs[0].as_str()).collect();
+        assert_eq!(quarantined, vec!["Nightly / import", "Sync"]);
+        assert!(summary.to_string().contains("quarantined failures (2 groups):"));
+    }
+}
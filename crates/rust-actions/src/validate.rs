@@ -1,8 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::parser::JobNeeds;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::parser::{Job, JobNeeds, Workflow, WorkflowCallConfig};
+use crate::policy::StepPolicy;
+use crate::registry::StepRegistry;
+use crate::runner::{order_jobs, plan_steps};
+use crate::Error;
 use crate::workflow_registry::{is_file_ref, parse_file_ref, WorkflowRegistry};
 
 #[derive(Debug, Clone)]
@@ -84,6 +92,54 @@ pub enum ValidationError {
         job: String,
         file_ref: String,
     },
+    DeniedStep {
+        workflow: PathBuf,
+        job: String,
+        step: String,
+    },
+    AmbiguousStep {
+        workflow: PathBuf,
+        job: String,
+        reason: String,
+    },
+    InvalidStepDependency {
+        workflow: PathBuf,
+        job: String,
+        reason: String,
+    },
+    ExportedStepNotFound {
+        workflow: PathBuf,
+        job: String,
+        step_id: String,
+    },
+    InvalidStage {
+        workflow: PathBuf,
+        reason: String,
+    },
+    UnknownInput {
+        workflow: PathBuf,
+        job: String,
+        file_ref: String,
+        input: String,
+    },
+    MissingRequiredInput {
+        workflow: PathBuf,
+        job: String,
+        file_ref: String,
+        input: String,
+    },
+    UndeclaredOutput {
+        workflow: PathBuf,
+        job: String,
+        dependency: String,
+        file_ref: String,
+        output: String,
+    },
+    UndeclaredIdentity {
+        workflow: PathBuf,
+        job: String,
+        identity: String,
+    },
 }
 
 impl fmt::Display for ValidationError {
@@ -165,6 +221,94 @@ impl fmt::Display for ValidationError {
                 job,
                 file_ref
             ),
+            ValidationError::DeniedStep {
+                workflow,
+                job,
+                step,
+            } => write!(
+                f,
+                "[{}] Job '{}' uses step '{}' which is denied by the step policy",
+                workflow.display(),
+                job,
+                step
+            ),
+            ValidationError::AmbiguousStep {
+                workflow,
+                job,
+                reason,
+            }
+            | ValidationError::InvalidStepDependency {
+                workflow,
+                job,
+                reason,
+            } => write!(f, "[{}] Job '{}': {}", workflow.display(), job, reason),
+            ValidationError::ExportedStepNotFound {
+                workflow,
+                job,
+                step_id,
+            } => write!(
+                f,
+                "[{}] Job '{}' exports non-existent step id '{}'",
+                workflow.display(),
+                job,
+                step_id
+            ),
+            ValidationError::InvalidStage { workflow, reason } => {
+                write!(f, "[{}] {}", workflow.display(), reason)
+            }
+            ValidationError::UnknownInput {
+                workflow,
+                job,
+                file_ref,
+                input,
+            } => write!(
+                f,
+                "[{}] Job '{}' passes input '{}' which '{}' doesn't declare",
+                workflow.display(),
+                job,
+                input,
+                file_ref
+            ),
+            ValidationError::MissingRequiredInput {
+                workflow,
+                job,
+                file_ref,
+                input,
+            } => write!(
+                f,
+                "[{}] Job '{}' doesn't pass input '{}' which '{}' requires",
+                workflow.display(),
+                job,
+                input,
+                file_ref
+            ),
+            ValidationError::UndeclaredOutput {
+                workflow,
+                job,
+                dependency,
+                file_ref,
+                output,
+            } => write!(
+                f,
+                "[{}] Job '{}' uses needs.{}.outputs.{} but '{}' declares no output '{}'",
+                workflow.display(),
+                job,
+                dependency,
+                output,
+                file_ref,
+                output
+            ),
+            ValidationError::UndeclaredIdentity {
+                workflow,
+                job,
+                identity,
+            } => write!(
+                f,
+                "[{}] Job '{}' acts as '{}' which isn't a declared identity (see `RustActions::identities`)",
+                workflow.display(),
+                job,
+                identity
+            ),
         }
     }
 }
@@ -187,6 +331,28 @@ pub enum ValidationWarning {
         step_index: usize,
         step_uses: String,
     },
+    UnknownStep {
+        workflow: PathBuf,
+        job: String,
+        reason: String,
+    },
+    /// A `continue-on-error` step nothing reads, so its failure only shows in the summary
+    UnreadContinueOnError {
+        workflow: PathBuf,
+        job: String,
+        step: String,
+    },
+    /// A `post-assert` on `outputs` of a step whose function returns `()`
+    AssertOnMissingOutputs {
+        workflow: PathBuf,
+        job: String,
+        step: String,
+    },
+    UnusedNeed {
+        workflow: PathBuf,
+        job: String,
+        need: String,
+    },
 }
 
 impl fmt::Display for ValidationWarning {
@@ -219,6 +385,33 @@ impl fmt::Display for ValidationWarning {
                 step_index,
                 step_uses
             ),
+            ValidationWarning::UnknownStep {
+                workflow,
+                job,
+                reason,
+            } => write!(f, "[{}] Job '{}': {}", workflow.display(), job, reason),
+            ValidationWarning::UnreadContinueOnError { workflow, job, step } => write!(
+                f,
+                "[{}] Job '{}' step '{}' has continue-on-error but nothing reads its outputs",
+                workflow.display(),
+                job,
+                step
+            ),
+            ValidationWarning::AssertOnMissingOutputs { workflow, job, step } => write!(
+                f,
+                "[{}] Job '{}' step '{}' asserts on outputs but the step returns none",
+                workflow.display(),
+                job,
+                step
+            ),
+            ValidationWarning::UnusedNeed { workflow, job, need } => write!(
+                f,
+                "[{}] Job '{}' needs '{}' but never reads needs.{} (use `stage:` to only order jobs)",
+                workflow.display(),
+                job,
+                need,
+                need
+            ),
         }
     }
 }
@@ -242,7 +435,7 @@ pub fn validate_registry(registry: &WorkflowRegistry) -> ValidationReport {
             validate_job_dependencies(path, job_name, &job.needs, &job_names, &mut report);
 
             if let Some(ref uses) = job.uses {
-                validate_job_uses(path, job_name, uses, registry, &mut report, &mut referenced_reusables);
+                validate_job_uses(path, job_name, job, uses, registry, &mut report, &mut referenced_reusables);
             } else if job.steps.is_empty() {
                 report.add_warning(ValidationWarning::JobWithNoSteps {
                     workflow: path.clone(),
@@ -252,10 +445,29 @@ pub fn validate_registry(registry: &WorkflowRegistry) -> ValidationReport {
 
             validate_step_ids(path, job_name, &job.steps, &mut report);
 
+            if let Err(e) = plan_steps(&job.steps) {
+                report.add_error(ValidationError::InvalidStepDependency {
+                    workflow: path.clone(),
+                    job: job_name.clone(),
+                    reason: e.to_string(),
+                });
+            }
+
             validate_job_outputs(path, job_name, &job.outputs, &job.steps, &mut report);
+            validate_exported_steps(path, job_name, job, &mut report);
         }
 
+        lint_workflow(path, workflow, &mut report);
+
+        validate_needs_outputs(path, workflow, registry, &mut report);
         validate_circular_dependencies(path, workflow, &mut report);
+
+        if let Err(Error::Stage(reason)) = order_jobs(workflow) {
+            report.add_error(ValidationError::InvalidStage {
+                workflow: path.clone(),
+                reason,
+            });
+        }
     }
 
     for (path, _workflow) in registry.reusable_workflows() {
@@ -269,6 +481,150 @@ pub fn validate_registry(registry: &WorkflowRegistry) -> ValidationReport {
     report
 }
 
+/// Reports every step in the registry that `policy` doesn't allow.
+pub fn validate_step_policy(registry: &WorkflowRegistry, policy: &StepPolicy) -> ValidationReport {
+    let mut report = ValidationReport::new();
+    for (path, workflow) in registry.all_workflows() {
+        check_step_policy(path, workflow, policy, &mut report);
+    }
+    report
+}
+
+pub fn check_step_policy(
+    workflow_path: &Path,
+    workflow: &Workflow,
+    policy: &StepPolicy,
+    report: &mut ValidationReport,
+) {
+    for (job_name, steps) in workflow.step_lists() {
+        for uses in steps.iter().flat_map(|s| s.all_uses()) {
+            if !policy.is_allowed(uses) {
+                report.add_error(ValidationError::DeniedStep {
+                    workflow: workflow_path.to_path_buf(),
+                    job: job_name.to_string(),
+                    step: uses.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Reports steps whose `uses:` matches several registered versions, or has an invalid
+/// version requirement. Unknown steps are only warned about, with the closest registered
+/// names; they fail when they run.
+pub fn check_step_resolution(
+    workflow_path: &Path,
+    workflow: &Workflow,
+    steps: &StepRegistry,
+    remote: &dyn Fn(&str) -> bool,
+    report: &mut ValidationReport,
+) {
+    for (job_name, job_steps) in workflow.step_lists() {
+        for uses in job_steps.iter().flat_map(|s| s.all_uses()).filter(|uses| !remote(uses)) {
+            match steps.callable(uses) {
+                Ok(_) => {}
+                Err(e @ Error::StepNotFound(_)) => report.add_warning(ValidationWarning::UnknownStep {
+                    workflow: workflow_path.to_path_buf(),
+                    job: job_name.to_string(),
+                    reason: e.to_string(),
+                }),
+                Err(e) => report.add_error(ValidationError::AmbiguousStep {
+                    workflow: workflow_path.to_path_buf(),
+                    job: job_name.to_string(),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+    }
+}
+
+/// Reports `as:` identities of jobs and steps that aren't among `declared`.
+pub fn check_identities(
+    workflow_path: &Path,
+    workflow: &Workflow,
+    declared: &BTreeSet<String>,
+    report: &mut ValidationReport,
+) {
+    let job_identities = workflow
+        .jobs
+        .iter()
+        .filter_map(|(name, job)| Some((name.as_str(), job.identity.as_deref()?)));
+    let step_identities = workflow.step_lists().flat_map(|(name, steps)| {
+        steps.iter().filter_map(move |s| Some((name, s.identity.as_deref()?)))
+    });
+    for (job_name, identity) in job_identities.chain(step_identities) {
+        if !declared.contains(identity) {
+            report.add_error(ValidationError::UndeclaredIdentity {
+                workflow: workflow_path.to_path_buf(),
+                job: job_name.to_string(),
+                identity: identity.to_string(),
+            });
+        }
+    }
+}
+
+/// Warns about patterns that are valid but almost always a mistake: `continue-on-error`
+/// steps whose outputs nothing reads, and `needs:` a job never reads from.
+pub fn lint_workflow(workflow_path: &Path, workflow: &Workflow, report: &mut ValidationReport) {
+    for (job_name, job) in &workflow.jobs {
+        let value = serde_json::to_value(job).unwrap_or_default();
+        let mut strings = Vec::new();
+        collect_strings(&value, &mut strings);
+        let reads = |prefix: String| strings.iter().any(|s| s.contains(&prefix));
+
+        for step in job.steps.iter().filter(|s| s.continue_on_error) {
+            let read = step.id.as_ref().is_some_and(|id| {
+                reads(format!("steps.{}.", id))
+                    || job.export_steps.contains(id)
+                    || job.steps.iter().any(|s| s.depends_on.as_vec().contains(id))
+            });
+            if !read {
+                report.add_warning(ValidationWarning::UnreadContinueOnError {
+                    workflow: workflow_path.to_path_buf(),
+                    job: job_name.clone(),
+                    step: step.label().to_string(),
+                });
+            }
+        }
+
+        for need in job.needs.as_vec() {
+            let alias = job.needs.alias(&need);
+            if !reads(format!("needs.{}.", alias)) && !job.verify_needs.contains_key(&need) {
+                report.add_warning(ValidationWarning::UnusedNeed {
+                    workflow: workflow_path.to_path_buf(),
+                    job: job_name.clone(),
+                    need,
+                });
+            }
+        }
+    }
+}
+
+/// `outputs.` not preceded by `steps.<id>.` or `needs.<job>.`
+static BARE_OUTPUTS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|[^\w.-])outputs\.").unwrap());
+
+/// Warns about `post-assert`s on `outputs` of steps registered as returning `()`.
+pub fn check_step_outputs(
+    workflow_path: &Path,
+    workflow: &Workflow,
+    steps: &StepRegistry,
+    report: &mut ValidationReport,
+) {
+    for (job_name, job_steps) in workflow.step_lists() {
+        for step in job_steps {
+            if steps.returns_no_outputs(&step.uses)
+                && step.post_assert.iter().any(|a| BARE_OUTPUTS_RE.is_match(a))
+            {
+                report.add_warning(ValidationWarning::AssertOnMissingOutputs {
+                    workflow: workflow_path.to_path_buf(),
+                    job: job_name.to_string(),
+                    step: step.label().to_string(),
+                });
+            }
+        }
+    }
+}
+
 fn validate_job_dependencies(
     workflow_path: &PathBuf,
     job_name: &str,
@@ -277,7 +633,7 @@ fn validate_job_dependencies(
     report: &mut ValidationReport,
 ) {
     for dep in needs.as_vec() {
-        if !all_jobs.contains(&dep) {
+        if !all_jobs.contains(&dep) && !needs.is_optional(&dep) {
             report.add_error(ValidationError::JobDependencyNotFound {
                 workflow: workflow_path.clone(),
                 job: job_name.to_string(),
@@ -290,6 +646,7 @@ fn validate_job_dependencies(
 fn validate_job_uses(
     workflow_path: &PathBuf,
     job_name: &str,
+    job: &Job,
     uses: &str,
     registry: &WorkflowRegistry,
     report: &mut ValidationReport,
@@ -307,16 +664,8 @@ fn validate_job_uses(
                 } else {
                     referenced_reusables.insert(PathBuf::from(file_path));
 
-                    if let Some(reusable) = registry.get_by_str(file_path) {
-                        let has_outputs = reusable
-                            .on
-                            .as_ref()
-                            .and_then(|t| t.workflow_call.as_ref())
-                            .map(|wc| !wc.outputs.is_empty())
-                            .unwrap_or(false);
-
-                        if !has_outputs && !reusable.is_reusable() {
-                        }
+                    if let Some(call) = registry.get_by_str(file_path).and_then(workflow_call) {
+                        validate_call_inputs(workflow_path, job_name, &job.with, file_path, call, report);
                     }
                 }
             }
@@ -331,6 +680,131 @@ fn validate_job_uses(
     }
 }
 
+/// The reusable workflow's declared contract; without `on.workflow_call` there is none to check.
+fn workflow_call(workflow: &Workflow) -> Option<&WorkflowCallConfig> {
+    workflow.on.as_ref().and_then(|t| t.workflow_call.as_ref())
+}
+
+fn validate_call_inputs(
+    workflow_path: &Path,
+    job_name: &str,
+    with: &HashMap<String, Value>,
+    file_ref: &str,
+    call: &WorkflowCallConfig,
+    report: &mut ValidationReport,
+) {
+    let mut unknown: Vec<&String> = with.keys().filter(|k| !call.inputs.contains_key(*k)).collect();
+    unknown.sort();
+    for input in unknown {
+        report.add_error(ValidationError::UnknownInput {
+            workflow: workflow_path.to_path_buf(),
+            job: job_name.to_string(),
+            file_ref: file_ref.to_string(),
+            input: input.clone(),
+        });
+    }
+
+    let mut missing: Vec<&String> = call
+        .inputs
+        .iter()
+        .filter(|(name, def)| def.required && def.default.is_none() && !with.contains_key(*name))
+        .map(|(name, _)| name)
+        .collect();
+    missing.sort();
+    for input in missing {
+        report.add_error(ValidationError::MissingRequiredInput {
+            workflow: workflow_path.to_path_buf(),
+            job: job_name.to_string(),
+            file_ref: file_ref.to_string(),
+            input: input.clone(),
+        });
+    }
+}
+
+/// `needs.<job>.outputs.<key>`, with the key after it for a matrix job's combination
+static NEEDS_OUTPUT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"needs\.([\w-]+)\.outputs\.([\w-]+)(?:\.([\w-]+))?").unwrap());
+
+/// Checks every `needs.<job>.outputs.<key>` a job uses against the outputs declared by the
+/// reusable workflow that `<job>` calls.
+fn validate_needs_outputs(
+    workflow_path: &Path,
+    workflow: &Workflow,
+    registry: &WorkflowRegistry,
+    report: &mut ValidationReport,
+) {
+    let mut jobs: Vec<(&String, &Job)> = workflow.jobs.iter().collect();
+    jobs.sort_by_key(|(name, _)| *name);
+
+    for (job_name, job) in jobs {
+        let needs = job.needs.as_vec();
+        let value = serde_json::to_value(job).unwrap_or_default();
+        let mut strings = Vec::new();
+        collect_strings(&value, &mut strings);
+
+        let mut seen = HashSet::new();
+        for cap in strings.iter().flat_map(|s| NEEDS_OUTPUT_RE.captures_iter(s)) {
+            // Expressions name a need by its alias
+            let Some(dependency) = needs.iter().find(|n| job.needs.alias(n) == &cap[1]) else {
+                continue;
+            };
+            let Some(dep_job) = workflow.jobs.get(dependency) else {
+                continue;
+            };
+            let Some(file_ref) = dep_job.uses.as_deref().filter(|u| is_file_ref(u)) else {
+                continue;
+            };
+            let Some(file_path) = parse_file_ref(file_ref).ok() else {
+                continue;
+            };
+            let Some(call) = registry.get_by_str(file_path).and_then(workflow_call) else {
+                continue;
+            };
+
+            // A matrix job's outputs are keyed by combination first
+            let output = match dep_job.strategy.is_some() {
+                true => cap.get(3),
+                false => cap.get(2),
+            };
+            let Some(output) = output.map(|m| m.as_str()) else {
+                continue;
+            };
+            if !declares_output(call, output) && seen.insert((dependency.to_string(), output.to_string())) {
+                report.add_error(ValidationError::UndeclaredOutput {
+                    workflow: workflow_path.to_path_buf(),
+                    job: job_name.clone(),
+                    dependency: dependency.to_string(),
+                    file_ref: file_path.to_string(),
+                    output: output.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// A name with a `${{ }}` in it (e.g. `auditor_${{ matrix.region }}`) matches on its fixed prefix.
+fn declares_output(call: &WorkflowCallConfig, output: &str) -> bool {
+    call.outputs.keys().any(|name| match name.split_once("${{") {
+        Some((prefix, _)) => output.starts_with(prefix),
+        None => name == output,
+    })
+}
+
+fn collect_strings<'a>(value: &'a Value, strings: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) => strings.push(s),
+        Value::Array(values) => values.iter().for_each(|v| collect_strings(v, strings)),
+        Value::Object(map) => {
+            // Keys too: job output names can hold expressions
+            for (key, v) in map {
+                strings.push(key);
+                collect_strings(v, strings);
+            }
+        }
+        _ => {}
+    }
+}
+
 fn validate_step_ids(
     workflow_path: &PathBuf,
     job_name: &str,
@@ -378,6 +852,23 @@ fn validate_job_outputs(
     }
 }
 
+fn validate_exported_steps(
+    workflow_path: &Path,
+    job_name: &str,
+    job: &crate::parser::Job,
+    report: &mut ValidationReport,
+) {
+    for id in &job.export_steps {
+        if !job.steps.iter().any(|s| s.id.as_ref() == Some(id)) {
+            report.add_error(ValidationError::ExportedStepNotFound {
+                workflow: workflow_path.to_path_buf(),
+                job: job_name.to_string(),
+                step_id: id.clone(),
+            });
+        }
+    }
+}
+
 fn extract_step_reference(expression: &str) -> Option<String> {
     let trimmed = expression.trim();
     if !trimmed.starts_with("${{") || !trimmed.ends_with("}}") {
@@ -401,8 +892,6 @@ fn validate_circular_dependencies(
     workflow: &crate::parser::Workflow,
     report: &mut ValidationReport,
 ) {
-    use std::collections::HashMap;
-
     let mut in_degree: HashMap<&String, usize> = HashMap::new();
     let mut dependents: HashMap<&String, Vec<&String>> = HashMap::new();
 
@@ -413,6 +902,9 @@ fn validate_circular_dependencies(
 
     for (job_name, job) in &workflow.jobs {
         for dep in job.needs.as_vec() {
+            if job.needs.is_optional(&dep) && !workflow.jobs.contains_key(&dep) {
+                continue;
+            }
             if let Some(deg) = in_degree.get_mut(&job_name) {
                 *deg += 1;
             }
@@ -477,7 +969,7 @@ mod tests {
             fs::write(&path, content).unwrap();
         }
 
-        WorkflowRegistry::build(dir.path()).unwrap()
+        WorkflowRegistry::build(dir.path(), true).unwrap()
     }
 
     #[test]
@@ -565,6 +1057,27 @@ jobs:
         ));
     }
 
+    #[test]
+    fn test_validate_exported_step_not_found() {
+        let yaml = r#"
+name: Test
+jobs:
+  job1:
+    export-steps: [real_step, missing]
+    steps:
+      - uses: test/step
+        id: real_step
+"#;
+        let registry = create_test_registry(vec![("test.yaml", yaml)]);
+        let report = validate_registry(&registry);
+
+        assert_eq!(report.error_count(), 1);
+        assert!(matches!(
+            &report.errors[0],
+            ValidationError::ExportedStepNotFound { step_id, .. } if step_id == "missing"
+        ));
+    }
+
     #[test]
     fn test_validate_missing_file_reference() {
         let yaml = r#"
@@ -623,6 +1136,140 @@ jobs:
         assert!(report.is_valid(), "Errors: {:?}", report.errors);
     }
 
+    #[test]
+    fn test_validate_call_contract() {
+        let reusable = r#"
+name: Setup
+on:
+  workflow_call:
+    inputs:
+      username:
+        required: true
+      tier:
+        required: true
+        default: free
+    outputs:
+      user_id:
+        value: ${{ jobs.setup.outputs.user_id }}
+      auditor_${{ matrix.region }}:
+        value: ${{ jobs.setup.outputs.user_id }}
+
+jobs:
+  setup:
+    outputs:
+      user_id: ${{ steps.create.outputs.id }}
+    steps:
+      - uses: user/create
+        id: create
+"#;
+
+        let main = r#"
+name: Main
+jobs:
+  setup:
+    uses: "@file:setup.yaml"
+    with:
+      user_name: alice
+  regional:
+    strategy:
+      matrix:
+        region: [eu]
+    uses: "@file:setup.yaml"
+    with:
+      username: admin
+  test:
+    needs: [setup, regional]
+    steps:
+      - uses: test/run
+        with:
+          id: ${{ needs.setup.outputs.user_id }}
+          email: ${{ needs.setup.outputs.email }}
+          regional: ${{ needs.regional.outputs.eu.user_id }}
+          auditor: ${{ needs.regional.outputs.eu.auditor_eu }}
+        assert-before:
+          - ${{ needs.regional.outputs.eu.username != "" }}
+"#;
+
+        let registry = create_test_registry(vec![("setup.yaml", reusable), ("main.yaml", main)]);
+        let report = validate_registry(&registry);
+
+        let errors: Vec<String> = report.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "[main.yaml] Job 'setup' passes input 'user_name' which 'setup.yaml' doesn't declare",
+                "[main.yaml] Job 'setup' doesn't pass input 'username' which 'setup.yaml' requires",
+                "[main.yaml] Job 'test' uses needs.regional.outputs.username but 'setup.yaml' declares no output 'username'",
+                "[main.yaml] Job 'test' uses needs.setup.outputs.email but 'setup.yaml' declares no output 'email'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_step_policy() {
+        let registry = create_test_registry(vec![(
+            "main.yaml",
+            r#"
+name: Main
+jobs:
+  cleanup:
+    steps:
+      - uses: user/create
+      - uses: admin/reset
+"#,
+        )]);
+
+        let report = validate_step_policy(&registry, &StepPolicy::new().deny("admin/*"));
+        assert_eq!(report.error_count(), 1);
+        assert!(matches!(
+            &report.errors[0],
+            ValidationError::DeniedStep { step, .. } if step == "admin/reset"
+        ));
+
+        let report = validate_step_policy(&registry, &StepPolicy::new().deny("db/*"));
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_lint_workflow() {
+        let yaml = r#"
+name: Test
+jobs:
+  setup:
+    steps:
+      - uses: test/step
+        id: seed
+  check:
+    needs: [setup]
+    steps:
+      - uses: test/flaky
+        continue-on-error: true
+      - uses: test/flaky
+        id: probe
+        continue-on-error: true
+      - uses: test/step
+        with:
+          found: ${{ steps.probe.outputs.found }}
+  report:
+    needs: setup
+    steps:
+      - uses: test/step
+        with:
+          seed: ${{ needs.setup.outputs.seed }}
+"#;
+        let registry = create_test_registry(vec![("test.yaml", yaml)]);
+        let report = validate_registry(&registry);
+
+        let warnings: Vec<String> = report.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "[test.yaml] Job 'check' step 'test/flaky' has continue-on-error but nothing reads its outputs",
+                "[test.yaml] Job 'check' needs 'setup' but never reads needs.setup (use `stage:` to only order jobs)",
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_step_reference() {
         assert_eq!(
@@ -0,0 +1,26 @@
+//! Built-in `vars/set` step: stores each `with:` key as `${{ vars.<key> }}` for the rest of the
+//! job, for intermediate values that aren't any step's output.
+
+use crate::args::RawArgs;
+use crate::context::StepContext;
+use crate::outputs::StepOutputs;
+use crate::registry::StepRegistry;
+use crate::Result;
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+
+type StepFuture<'a> = Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>>;
+
+pub(crate) fn register(registry: &mut StepRegistry) {
+    registry.register("vars/set", set);
+}
+
+fn set<'a>(_world: &'a mut dyn Any, args: RawArgs, ctx: &'a StepContext) -> StepFuture<'a> {
+    Box::pin(async move {
+        for (name, value) in args {
+            ctx.set_var(name, value);
+        }
+        Ok(StepOutputs::new())
+    })
+}
@@ -0,0 +1,402 @@
+use crate::parser::{AssertMode, CleanupStep, Delay, DurationAssertion, Ignore, Job, JobNeeds, Need, ServiceSpec, Step, ValueType, Workflow};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Builds a `Workflow` in Rust code, for one-off scenarios that don't warrant a YAML file.
+///
+/// ```ignore
+/// let workflow = WorkflowBuilder::new("smoke")
+///     .job("create", |j| {
+///         j.step("user/create")
+///             .id("alice")
+///             .with("username", "alice")
+///             .post_assert("${{ outputs.id != \"\" }}")
+///     })
+///     .build();
+/// ```
+pub struct WorkflowBuilder {
+    workflow: Workflow,
+}
+
+impl WorkflowBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            workflow: Workflow {
+                name: name.into(),
+                description: None,
+                extends: None,
+                on: None,
+                ignore: Ignore::No,
+                quarantined: false,
+                needs_workflow: JobNeeds::None,
+                env: HashMap::new(),
+                env_file: None,
+                services: IndexMap::new(),
+                stages: Vec::new(),
+                setup: Vec::new(),
+                teardown: Vec::new(),
+                jobs: IndexMap::new(),
+                post_assert: Vec::new(),
+            },
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.workflow.description = Some(description.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.workflow.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn needs_workflow(mut self, path: impl Into<String>) -> Self {
+        let mut needs = self.workflow.needs_workflow.as_vec();
+        needs.push(path.into());
+        self.workflow.needs_workflow = JobNeeds::Multiple(needs);
+        self
+    }
+
+    /// Declares a service the jobs reach as `${{ containers.<name>.* }}`.
+    pub fn service(mut self, name: impl Into<String>, spec: ServiceSpec) -> Self {
+        self.workflow.services.insert(name.into(), spec);
+        self
+    }
+
+    /// Orders job stages, replacing the default `setup`, `test`, `teardown`.
+    pub fn stages(mut self, stages: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.workflow.stages = stages.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Reports failures without failing the run; see `RustActions::allow_failure`.
+    pub fn quarantined(mut self) -> Self {
+        self.workflow.quarantined = true;
+        self
+    }
+
+    /// Steps to run once before the jobs, built like a job's: `setup(|s| s.step("db/migrate"))`.
+    pub fn setup(mut self, f: impl FnOnce(JobBuilder) -> JobBuilder) -> Self {
+        self.workflow.setup = f(JobBuilder::new()).job.steps;
+        self
+    }
+
+    /// Steps to run after the jobs, whatever happened; built like `setup`.
+    pub fn teardown(mut self, f: impl FnOnce(JobBuilder) -> JobBuilder) -> Self {
+        self.workflow.teardown = f(JobBuilder::new()).job.steps;
+        self
+    }
+
+    pub fn job(mut self, name: impl Into<String>, f: impl FnOnce(JobBuilder) -> JobBuilder) -> Self {
+        let job = f(JobBuilder::new()).job;
+        self.workflow.jobs.insert(name.into(), job);
+        self
+    }
+
+    pub fn post_assert(mut self, expr: impl Into<String>) -> Self {
+        self.workflow.post_assert.push(expr.into());
+        self
+    }
+
+    pub fn build(self) -> Workflow {
+        self.workflow
+    }
+}
+
+impl From<WorkflowBuilder> for Workflow {
+    fn from(builder: WorkflowBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Step modifiers (`with`, `id`, `post_assert`, ...) apply to the most recently added step.
+pub struct JobBuilder {
+    job: Job,
+}
+
+impl JobBuilder {
+    fn new() -> Self {
+        Self {
+            job: Job {
+                name: None,
+                description: None,
+                needs: JobNeeds::None,
+                stage: None,
+                quarantined: false,
+                priority: 0,
+                identity: None,
+                uses: None,
+                with: HashMap::new(),
+                strategy: None,
+                outputs: HashMap::new(),
+                env: HashMap::new(),
+                env_file: None,
+                steps: Vec::new(),
+                export_steps: Vec::new(),
+                post_assert: Vec::new(),
+                verify_needs: IndexMap::new(),
+                assert_duration: None,
+            },
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.job.name = Some(name.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.job.description = Some(description.into());
+        self
+    }
+
+    pub fn needs(mut self, job: impl Into<String>) -> Self {
+        self.job.needs.push(job, Need::default());
+        self
+    }
+
+    /// Needs `job` with options, e.g. `Need::optional().alias("base")`.
+    pub fn needs_with(mut self, job: impl Into<String>, need: Need) -> Self {
+        self.job.needs.push(job, need);
+        self
+    }
+
+    /// Requires `job`, one of this job's needs, to have produced `output` of type `ty`.
+    pub fn verify_need(mut self, job: impl Into<String>, output: impl Into<String>, ty: ValueType) -> Self {
+        self.job.verify_needs.entry(job.into()).or_default().insert(output.into(), ty);
+        self
+    }
+
+    pub fn stage(mut self, stage: impl Into<String>) -> Self {
+        self.job.stage = Some(stage.into());
+        self
+    }
+
+    pub fn quarantined(mut self) -> Self {
+        self.job.quarantined = true;
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.job.priority = priority;
+        self
+    }
+
+    /// The job's `as:`.
+    pub fn acting_as(mut self, identity: impl Into<String>) -> Self {
+        self.job.identity = Some(identity.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.job.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn output(mut self, name: impl Into<String>, expr: impl Into<String>) -> Self {
+        self.job.outputs.insert(name.into(), expr.into());
+        self
+    }
+
+    /// Makes step `id`'s outputs available to dependents as `needs.<job>.steps.<id>.outputs.*`.
+    pub fn export_step(mut self, id: impl Into<String>) -> Self {
+        self.job.export_steps.push(id.into());
+        self
+    }
+
+    /// Job-level assertion, checked after all steps.
+    pub fn job_post_assert(mut self, expr: impl Into<String>) -> Self {
+        self.job.post_assert.push(expr.into());
+        self
+    }
+
+    /// Fails the job when its duration doesn't satisfy `assertion`.
+    pub fn job_assert_duration(mut self, assertion: DurationAssertion) -> Self {
+        self.job.assert_duration = Some(assertion);
+        self
+    }
+
+    pub fn step(mut self, uses: impl Into<String>) -> Self {
+        self.job.steps.push(Step {
+            name: None,
+            description: None,
+            id: None,
+            uses: uses.into(),
+            identity: None,
+            with: HashMap::new(),
+            continue_on_error: false,
+            setup: false,
+            mask_outputs: Vec::new(),
+            pre_assert: Vec::new(),
+            post_assert: Vec::new(),
+            assert_mode: AssertMode::First,
+            expect: None,
+            cleanup: Vec::new(),
+            compensate: None,
+            capture: IndexMap::new(),
+            delay: None,
+            rate_limit: None,
+            depends_on: JobNeeds::None,
+            assert_duration: None,
+        });
+        self
+    }
+
+    pub fn step_name(mut self, name: impl Into<String>) -> Self {
+        self.last_step().name = Some(name.into());
+        self
+    }
+
+    pub fn step_description(mut self, description: impl Into<String>) -> Self {
+        self.last_step().description = Some(description.into());
+        self
+    }
+
+    /// The step's `as:`.
+    pub fn step_as(mut self, identity: impl Into<String>) -> Self {
+        self.last_step().identity = Some(identity.into());
+        self
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.last_step().id = Some(id.into());
+        self
+    }
+
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.last_step().with.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn continue_on_error(mut self) -> Self {
+        self.last_step().continue_on_error = true;
+        self
+    }
+
+    pub fn setup(mut self) -> Self {
+        self.last_step().setup = true;
+        self
+    }
+
+    pub fn mask_output(mut self, field: impl Into<String>) -> Self {
+        self.last_step().mask_outputs.push(field.into());
+        self
+    }
+
+    pub fn pre_assert(mut self, expr: impl Into<String>) -> Self {
+        self.last_step().pre_assert.push(expr.into());
+        self
+    }
+
+    pub fn post_assert(mut self, expr: impl Into<String>) -> Self {
+        self.last_step().post_assert.push(expr.into());
+        self
+    }
+
+    /// `assert-mode: all`: checks every `post_assert` of the step instead of stopping at the
+    /// first that fails.
+    pub fn assert_all(mut self) -> Self {
+        self.last_step().assert_mode = AssertMode::All;
+        self
+    }
+
+    pub fn expect(mut self, expected: impl Into<Value>) -> Self {
+        self.last_step().expect = Some(expected.into());
+        self
+    }
+
+    /// Defers `uses` to the end of the job once the current step has run.
+    pub fn cleanup(mut self, uses: impl Into<String>, with: HashMap<String, Value>) -> Self {
+        self.last_step().cleanup.push(CleanupStep {
+            uses: uses.into(),
+            with,
+        });
+        self
+    }
+
+    /// Runs `uses` to undo the step if a later step of the job fails.
+    pub fn compensate(mut self, uses: impl Into<String>, with: HashMap<String, Value>) -> Self {
+        self.last_step().compensate = Some(CleanupStep {
+            uses: uses.into(),
+            with,
+        });
+        self
+    }
+
+    /// Stores `expr` (e.g. `outputs.body.data.id`) as `vars.<name>` once the step passes.
+    pub fn capture(mut self, name: impl Into<String>, expr: impl Into<String>) -> Self {
+        self.last_step().capture.insert(name.into(), expr.into());
+        self
+    }
+
+    pub fn delay(mut self, delay: Delay) -> Self {
+        self.last_step().delay = Some(delay);
+        self
+    }
+
+    pub fn depends_on(mut self, step_id: impl Into<String>) -> Self {
+        let step = self.last_step();
+        let mut depends_on = step.depends_on.as_vec();
+        depends_on.push(step_id.into());
+        step.depends_on = JobNeeds::Multiple(depends_on);
+        self
+    }
+
+    pub fn assert_duration(mut self, assertion: DurationAssertion) -> Self {
+        self.last_step().assert_duration = Some(assertion);
+        self
+    }
+
+    pub fn rate_limit(mut self, per_second: f64) -> Self {
+        self.last_step().rate_limit = Some(per_second);
+        self
+    }
+
+    fn last_step(&mut self) -> &mut Step {
+        self.job
+            .steps
+            .last_mut()
+            .expect("JobBuilder: call step() before configuring a step")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_build_workflow() {
+        let workflow = WorkflowBuilder::new("smoke")
+            .env("REGION", "eu")
+            .job("create", |j| {
+                j.output("user_id", "${{ steps.alice.outputs.id }}")
+                    .step("user/create")
+                    .id("alice")
+                    .with("username", "alice")
+                    .with("age", 30)
+                    .post_assert("${{ outputs.id != \"\" }}")
+            })
+            .job("verify", |j| j.needs("create").step("user/get"))
+            .build();
+
+        assert_eq!(workflow.name, "smoke");
+        assert_eq!(workflow.env["REGION"], "eu");
+
+        let create = &workflow.jobs["create"];
+        assert_eq!(create.steps.len(), 1);
+        assert_eq!(create.steps[0].id.as_deref(), Some("alice"));
+        assert_eq!(create.steps[0].with["age"], json!(30));
+        assert_eq!(create.steps[0].post_assert.len(), 1);
+
+        assert_eq!(workflow.jobs["verify"].needs.as_vec(), vec!["create"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "call step()")]
+    fn test_step_modifier_without_step() {
+        WorkflowBuilder::new("bad").job("j", |j| j.with("a", 1));
+    }
+}
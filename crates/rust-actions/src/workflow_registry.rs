@@ -1,20 +1,31 @@
-use crate::parser::{parse_workflows, Workflow};
+use crate::parser::{parse_workflows_filtered, Workflow, WorkflowFilter};
 use crate::{Error, Result};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 const FILE_REF_PREFIX: &str = "@file:";
 
 pub struct WorkflowRegistry {
     base_path: PathBuf,
-    workflows: HashMap<PathBuf, Workflow>,
+    /// Sorted by path, so iteration is the same every run
+    workflows: BTreeMap<PathBuf, Workflow>,
 }
 
 impl WorkflowRegistry {
-    pub fn build(workflows_path: impl AsRef<Path>) -> Result<Self> {
+    /// Loads every workflow under `workflows_path`; see `Workflow::from_yaml_with` for `strict`.
+    pub fn build(workflows_path: impl AsRef<Path>, strict: bool) -> Result<Self> {
+        Self::build_filtered(workflows_path, strict, &WorkflowFilter::default())
+    }
+
+    /// Loads the workflows under `workflows_path` that `filter` matches.
+    pub fn build_filtered(
+        workflows_path: impl AsRef<Path>,
+        strict: bool,
+        filter: &WorkflowFilter,
+    ) -> Result<Self> {
         let base_path = workflows_path.as_ref().to_path_buf();
-        let parsed = parse_workflows(&base_path)?;
-        let workflows: HashMap<PathBuf, Workflow> = parsed.into_iter().collect();
+        let parsed = parse_workflows_filtered(&base_path, strict, filter)?;
+        let workflows: BTreeMap<PathBuf, Workflow> = parsed.into_iter().collect();
 
         Ok(Self {
             base_path,
@@ -22,6 +33,18 @@ impl WorkflowRegistry {
         })
     }
 
+    /// A registry holding only the workflows later `insert`ed, e.g. just the reusable ones.
+    pub fn empty(workflows_path: impl AsRef<Path>) -> Self {
+        Self {
+            base_path: workflows_path.as_ref().to_path_buf(),
+            workflows: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, workflow: Workflow) {
+        self.workflows.insert(path.into(), workflow);
+    }
+
     pub fn get(&self, path: &Path) -> Option<&Workflow> {
         self.workflows.get(path)
     }
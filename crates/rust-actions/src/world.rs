@@ -1,6 +1,64 @@
 use crate::Result;
+use serde_json::Value;
 use std::future::Future;
 
 pub trait World: Sized + Send + Sync + 'static {
+    /// Whether a world can be returned to the pool and reused via `reset` instead of being
+    /// rebuilt with `new` for every job.
+    const RESETTABLE: bool = false;
+
     fn new() -> impl Future<Output = Result<Self>> + Send;
+
+    /// Returns a pooled world to a clean state before its next job.
+    fn reset(&mut self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Closes what the world owns (connections, containers) before it's dropped. The runner
+    /// awaits it after the world's last job: after every job, or at the end of the run for a
+    /// pooled world. Cancelled runs still tear down.
+    fn teardown(self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Switches who the following step acts as, e.g. the token the world's client sends. Called
+    /// before each step whose job or step has `as: <name>`, with names checked against
+    /// `RustActions::identities` before the run starts.
+    fn assume_identity(&mut self, name: &str) -> impl Future<Output = Result<()>> + Send {
+        let _ = name;
+        async { Ok(()) }
+    }
+
+    /// State for `${{ diff.* }}`, or `None` when the world doesn't implement `WorldSnapshot`.
+    fn snapshot(&self) -> impl Future<Output = Result<Option<Value>>> + Send {
+        async { Ok(None) }
+    }
+}
+
+/// Captures world state before and after each job that has `assert-after`, so those
+/// assertions can check what the job changed through `${{ diff.* }}`. Enabled with
+/// `#[world(snapshot)]`.
+pub trait WorldSnapshot {
+    /// Named collections of records, e.g. `{"users": [...]}`. Records with an `id` field are
+    /// matched by it, so a modified record shows up as updated rather than deleted and created.
+    fn snapshot(&self) -> impl Future<Output = Result<Value>> + Send;
+}
+
+/// Constructor named by `#[world(init = ...)]`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a valid World constructor",
+    label = "expected an `async fn() -> Result<{W}>`"
+)]
+pub trait WorldInit<W> {
+    fn init(self) -> impl Future<Output = Result<W>> + Send;
+}
+
+impl<W, F, Fut> WorldInit<W> for F
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<W>> + Send,
+{
+    fn init(self) -> impl Future<Output = Result<W>> + Send {
+        self()
+    }
 }
@@ -0,0 +1,21 @@
+//! Scaffolds a workflow from the example's registered steps:
+//!
+//! ```sh
+//! cargo run -p rust-actions-example --bin scaffold > tests/workflows/new.yaml
+//! ```
+//!
+//! Prompts go to stderr so stdout holds only the YAML.
+
+use rust_actions::prelude::*;
+use rust_actions_example::TestWorld;
+
+fn main() {
+    let stdin = std::io::stdin();
+    match RustActions::<TestWorld>::new().scaffold_interactive(stdin.lock(), std::io::stderr()) {
+        Ok(yaml) => print!("{}", yaml),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! A step library written against a trait rather than `TestWorld`, so any world that
+//! declares `#[world(implements(Directory))]` can use it.
+use rust_actions::prelude::*;
+
+pub trait Directory: Send {
+    fn usernames(&self) -> Vec<String>;
+}
+
+#[derive(Deserialize, Args)]
+pub struct LookupArgs {
+    pub username: String,
+}
+
+#[derive(Serialize, Outputs)]
+pub struct LookupOutput {
+    pub found: bool,
+    pub total: usize,
+}
+
+#[step("directory/lookup")]
+pub async fn lookup(directory: &mut dyn Directory, args: LookupArgs) -> Result<LookupOutput> {
+    let usernames = directory.usernames();
+    Ok(LookupOutput {
+        found: usernames.contains(&args.username),
+        total: usernames.len(),
+    })
+}
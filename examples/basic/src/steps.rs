@@ -1,6 +1,7 @@
 use rust_actions::prelude::*;
 use crate::world::{TestWorld, User};
 use serde::Deserialize;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 #[derive(Deserialize, Args)]
 pub struct CreateUserArgs {
@@ -14,7 +15,8 @@ pub struct UserOutput {
     pub username: String,
 }
 
-#[step("user/create")]
+/// Adds a user to the world and outputs its generated id.
+#[step("user/create", ensures = "outputs.id != ''", ensures = "outputs.username != ''")]
 pub async fn create_user(world: &mut TestWorld, args: CreateUserArgs) -> Result<UserOutput> {
     let id = world.rng.next_uuid().to_string();
 
@@ -31,3 +33,190 @@ pub async fn create_user(world: &mut TestWorld, args: CreateUserArgs) -> Result<
         username: args.username,
     })
 }
+
+#[derive(Deserialize, Args)]
+pub struct DeleteUserArgs {
+    pub id: String,
+}
+
+#[step("user/delete")]
+pub async fn delete_user(world: &mut TestWorld, args: DeleteUserArgs) -> Result<()> {
+    let before = world.users.len();
+    world.users.retain(|u| u.id != args.id);
+    if world.users.len() == before {
+        return Err(Error::Custom(format!("no user with id {}", args.id)));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+}
+
+#[derive(Deserialize, Args)]
+pub struct CreateUsersArgs {
+    #[arg(rows)]
+    pub users: Vec<NewUser>,
+}
+
+#[derive(Serialize, Outputs)]
+pub struct UsersOutput {
+    pub count: usize,
+    pub usernames: Vec<String>,
+}
+
+#[step("user/create-many")]
+pub async fn create_users(world: &mut TestWorld, args: CreateUsersArgs) -> Result<UsersOutput> {
+    let mut usernames = Vec::new();
+
+    for new_user in args.users {
+        usernames.push(new_user.username.clone());
+        world.users.push(User {
+            id: world.rng.next_uuid().to_string(),
+            username: new_user.username,
+            email: new_user.email,
+        });
+    }
+
+    Ok(UsersOutput {
+        count: usernames.len(),
+        usernames,
+    })
+}
+
+#[derive(Serialize, Outputs)]
+pub struct UserCountOutput {
+    pub count: usize,
+}
+
+#[step("user/count", version = "1.0.0", deprecated = "use user/count@2")]
+pub async fn count_users_v1(world: &mut TestWorld) -> Result<UserCountOutput> {
+    Ok(UserCountOutput {
+        count: world.users.len(),
+    })
+}
+
+#[derive(Serialize, Outputs)]
+pub struct UserStatsOutput {
+    pub count: usize,
+    pub usernames: Vec<String>,
+}
+
+#[step("user/count", version = "2.1.0")]
+pub async fn count_users_v2(world: &mut TestWorld) -> Result<UserStatsOutput> {
+    Ok(UserStatsOutput {
+        count: world.users.len(),
+        usernames: world.users.iter().map(|u| u.username.clone()).collect(),
+    })
+}
+
+#[derive(Serialize, Outputs)]
+pub struct ContextOutput {
+    pub workflow: String,
+    pub job: String,
+    pub region: Option<String>,
+}
+
+#[step("context/describe")]
+pub async fn describe_context(_world: &mut TestWorld, ctx: &StepContext) -> Result<ContextOutput> {
+    Ok(ContextOutput {
+        workflow: ctx.workflow().to_string(),
+        job: ctx.job().to_string(),
+        region: ctx
+            .matrix("region")
+            .and_then(|v| v.as_str())
+            .map(String::from),
+    })
+}
+
+#[derive(Deserialize, Args)]
+pub struct FindUserArgs {
+    pub username: String,
+    #[serde(default)]
+    pub indexed_after: u32,
+}
+
+/// Simulates an eventually consistent search index: the first `indexed_after` lookups fail
+/// with a retryable error.
+#[step("user/find")]
+pub async fn find_user(world: &mut TestWorld, args: FindUserArgs) -> Result<UserOutput> {
+    world.lookups += 1;
+    if world.lookups <= args.indexed_after {
+        return Err(StepError::retryable(format!("{} is not indexed yet", args.username)).into());
+    }
+
+    let user = world
+        .users
+        .iter()
+        .find(|u| u.username == args.username)
+        .ok_or_else(|| Error::Custom(format!("no user named {}", args.username)))?;
+    Ok(UserOutput {
+        id: user.id.clone(),
+        username: user.username.clone(),
+    })
+}
+
+/// Times `price/quote` actually ran, across all runners in the process
+pub static QUOTES_COMPUTED: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Deserialize, Args)]
+pub struct QuoteArgs {
+    pub plan: String,
+    pub seats: u32,
+}
+
+#[derive(Serialize, Outputs)]
+pub struct QuoteOutput {
+    pub total: u32,
+}
+
+/// Depends only on its args, so the runner reuses its outputs for the rest of the session.
+#[step("price/quote", pure)]
+pub async fn quote_price(_world: &mut TestWorld, args: QuoteArgs) -> Result<QuoteOutput> {
+    QUOTES_COMPUTED.fetch_add(1, Ordering::SeqCst);
+    let per_seat = if args.plan == "pro" { 20 } else { 5 };
+    Ok(QuoteOutput {
+        total: per_seat * args.seats,
+    })
+}
+
+/// `via:` picks the channel, and with it the other keys `user/notify` takes.
+#[derive(Deserialize, Args)]
+#[serde(tag = "via", rename_all = "snake_case")]
+pub enum NotifyArgs {
+    Email { address: String, subject: Option<String> },
+    Sms { number: String },
+}
+
+#[derive(Serialize, Outputs)]
+pub struct NotifyOutput {
+    pub channel: String,
+    pub to: String,
+}
+
+#[step("user/notify")]
+pub async fn notify_user(_world: &mut TestWorld, args: NotifyArgs) -> Result<NotifyOutput> {
+    let (channel, to) = match args {
+        NotifyArgs::Email { address, .. } => ("email", address),
+        NotifyArgs::Sms { number } => ("sms", number),
+    };
+    Ok(NotifyOutput {
+        channel: channel.to_string(),
+        to,
+    })
+}
+
+#[derive(Serialize, Outputs)]
+pub struct WhoamiOutput {
+    pub identity: Option<String>,
+}
+
+/// Outputs who the step runs as, from the job's or step's `as:`.
+#[step("user/whoami")]
+pub async fn whoami(world: &mut TestWorld) -> Result<WhoamiOutput> {
+    Ok(WhoamiOutput {
+        identity: world.identity.clone(),
+    })
+}
@@ -1,11 +1,23 @@
+use crate::directory::Directory;
 use rust_actions::prelude::*;
+use rust_actions::serde_json::{self, Value};
 use serde::{Deserialize, Serialize};
 
 #[derive(World)]
-#[world(init = Self::setup)]
+#[world(
+    init = Self::setup,
+    reset = Self::reset,
+    assume_identity = Self::act_as,
+    snapshot,
+    implements(Directory)
+)]
 pub struct TestWorld {
     pub rng: SeededRng,
     pub users: Vec<User>,
+    /// Calls to `user/find` so far, see `steps::find_user`
+    pub lookups: u32,
+    /// Set by `as:`, see `act_as`
+    pub identity: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +32,35 @@ impl TestWorld {
         Ok(Self {
             rng: SeededRng::new(),
             users: Vec::new(),
+            lookups: 0,
+            identity: None,
         })
     }
+
+    /// Lets the runner reuse this world across jobs instead of calling `setup` again.
+    pub async fn reset(&mut self) -> Result<()> {
+        self.rng = SeededRng::new();
+        self.users.clear();
+        self.lookups = 0;
+        self.identity = None;
+        Ok(())
+    }
+
+    /// A real world would switch its client's credentials here.
+    pub async fn act_as(&mut self, name: &str) -> Result<()> {
+        self.identity = Some(name.to_string());
+        Ok(())
+    }
+}
+
+impl WorldSnapshot for TestWorld {
+    async fn snapshot(&self) -> Result<Value> {
+        Ok(serde_json::json!({ "users": self.users }))
+    }
+}
+
+impl Directory for TestWorld {
+    fn usernames(&self) -> Vec<String> {
+        self.users.iter().map(|u| u.username.clone()).collect()
+    }
 }
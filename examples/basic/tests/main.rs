@@ -1,5 +1,11 @@
 use rust_actions::prelude::*;
 use rust_actions_example::TestWorld;
+use rust_actions::serde_json;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::test(flavor = "current_thread", start_paused = true)]
 async fn run_workflows() {
@@ -8,3 +14,1378 @@ async fn run_workflows() {
         .run()
         .await;
 }
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn streaming_runs_the_same_workflows() {
+    let run = |streaming: bool| async move {
+        let runner = RustActions::<TestWorld>::new().workflows("tests/workflows");
+        let runner = if streaming { runner.streaming() } else { runner };
+        runner.execute().await.unwrap()
+    };
+    let summary = |results: Vec<WorkflowResult>| -> Vec<(String, bool, usize)> {
+        results.into_iter().map(|w| (w.name.clone(), w.passed(), w.jobs.len())).collect()
+    };
+
+    let held = summary(run(false).await);
+    assert!(!held.is_empty());
+    assert_eq!(summary(run(true).await), held);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn run_inline_workflow() {
+    let workflow = WorkflowBuilder::new("Inline smoke").job("create", |j| {
+        j.step("user/create")
+            .with("username", "bob")
+            .with("email", "bob@example.com")
+            .post_assert("${{ outputs.username == \"bob\" }}")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].passed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn run_workflow_from_yaml_string() {
+    let team = r#"
+name: Generated team
+jobs:
+  team:
+    uses: "@file:generated/member.yaml"
+    with:
+      team: core
+  check:
+    needs: [team]
+    steps:
+      - uses: user/create
+        with:
+          username: ${{ needs.team.outputs.username | upper }}
+          email: check@example.com
+        assert-after:
+          - ${{ outputs.username == "CORE-LEAD" }}
+"#;
+    let member = r#"
+name: Member
+on:
+  workflow_call:
+    inputs:
+      team:
+        required: true
+    outputs:
+      username:
+        value: ${{ jobs.join.outputs.username }}
+jobs:
+  join:
+    outputs:
+      username: ${{ steps.user.outputs.username }}
+    steps:
+      - id: user
+        uses: user/create
+        with:
+          username: ${{ inputs.team }}-lead
+          email: lead@example.com
+"#;
+
+    let results = RustActions::<TestWorld>::new()
+        .workflows("does/not/exist")
+        .workflow_yaml("generated/team.yaml", team)
+        .workflow_files([("generated/member.yaml", member)])
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "Generated team");
+    assert!(results[0].passed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn steps_act_as_their_declared_identity() {
+    let workflow = r#"
+name: Refund approval
+jobs:
+  refund:
+    as: customer
+    steps:
+      - id: request
+        uses: user/whoami
+        assert-after:
+          - ${{ outputs.identity == "customer" }}
+      - id: approve
+        uses: user/whoami
+        as: admin
+        assert-after:
+          - ${{ outputs.identity == "admin" }}
+"#;
+
+    let results = RustActions::<TestWorld>::new()
+        .identities(["customer", "admin"])
+        .workflow_yaml("refund.yaml", workflow)
+        .execute()
+        .await
+        .unwrap();
+    assert!(results[0].passed());
+
+    let undeclared = RustActions::<TestWorld>::new()
+        .identities(["customer"])
+        .workflow_yaml("refund.yaml", workflow)
+        .execute()
+        .await
+        .unwrap_err();
+    assert!(undeclared.to_string().contains("acts as 'admin'"), "{}", undeclared);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn assert_mode_all_reports_every_failed_assertion() {
+    let workflow = |all: bool| {
+        WorkflowBuilder::new("Assertions").job("create", |j| {
+            let j = j
+                .step("user/create")
+                .with("username", "erin")
+                .with("email", "erin@example.com")
+                .post_assert("${{ outputs.username == \"frank\" }}")
+                .post_assert("${{ outputs.id != \"\" }}")
+                .post_assert("${{ outputs.username | length > 10 }}");
+            if all { j.assert_all() } else { j }
+        })
+    };
+    let failure = |all: bool| async move {
+        let results = RustActions::<TestWorld>::new()
+            .add_workflow(workflow(all))
+            .execute()
+            .await
+            .unwrap();
+        match &results[0].jobs[0].steps[0].result {
+            StepResult::Failed(_, msg) => msg.clone(),
+            other => panic!("expected a failed step, got {:?}", other),
+        }
+    };
+
+    assert_eq!(failure(false).await, "Post-assertion failed: ${{ outputs.username == \"frank\" }}");
+    let all = failure(true).await;
+    assert!(all.starts_with("2 of 3 assertions failed: "), "{}", all);
+    assert!(all.contains("outputs.username | length > 10"), "{}", all);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn globals_are_scoped_to_their_runner() {
+    let run = |tenant: &'static str| async move {
+        let workflow = WorkflowBuilder::new("Tenant admin").job("create", |j| {
+            j.step("user/create")
+                .with("username", "${{ globals.tenant.name }}-admin")
+                .with("email", "admin@example.com")
+                .post_assert("${{ outputs.username == globals.expected }}")
+        });
+        RustActions::<TestWorld>::new()
+            .global("tenant", rust_actions::serde_json::json!({ "name": tenant }))
+            .globals([("expected", format!("{}-admin", tenant))])
+            .add_workflow(workflow)
+            .execute()
+            .await
+    };
+
+    let (acme, globex) = tokio::join!(run("acme"), run("globex"));
+    assert!(acme.unwrap()[0].passed());
+    assert!(globex.unwrap()[0].passed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn fail_fast_stops_after_first_failed_job() {
+    let broken = WorkflowBuilder::new("Broken")
+        .job("first", |j| j.step("missing/step"))
+        .job("second", |j| j.needs("first").step("user/count@1"));
+    let later = WorkflowBuilder::new("Later").job("count", |j| j.step("user/count@1"));
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(broken)
+        .add_workflow(later)
+        .fail_fast()
+        .execute()
+        .await
+        .unwrap();
+
+    let jobs: Vec<&str> = results[0].jobs.iter().map(|j| j.name.as_str()).collect();
+    assert_eq!(jobs, vec!["first"]);
+    assert!(!results[0].passed());
+    assert_eq!(results[1].ignored.as_deref(), Some("not run: fail-fast"));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn quarantined_failures_dont_fail_the_run() {
+    let flaky = WorkflowBuilder::new("Flaky")
+        .job("import", |j| j.quarantined().step("missing/step"))
+        .job("export", |j| j.step("missing/step"));
+    let later = WorkflowBuilder::new("Later").job("count", |j| j.step("user/count@1"));
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(flaky)
+        .add_workflow(later)
+        .allow_failure("Flaky / exp*")
+        .fail_fast()
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(results[0].jobs.iter().all(|j| j.quarantined && !j.passed()));
+    assert!(!results[0].passed() && !results[0].fails_run());
+    assert!(results[1].passed() && !results[1].is_ignored());
+
+    let summary = SuiteSummary::new(&results, 0);
+    assert!(summary.passed());
+    assert_eq!((summary.jobs.failed, summary.jobs.quarantined), (0, 2));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn hooks_call_registered_steps() {
+    let workflow = WorkflowBuilder::new("Seeded").job("count", |j| {
+        j.step("user/count@1").post_assert("${{ outputs.count == 1 }}")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .hook(HookDef::before_scenario(|world, hooks| {
+            Box::pin(async move {
+                let args = rust_actions::serde_json::json!({ "username": "admin", "email": "admin@example.com" });
+                let outputs = hooks.call_step(world, "user/create", args).await.unwrap();
+                assert_eq!(outputs.get_string("username").unwrap(), "admin");
+            })
+        }))
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(results[0].passed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn denied_steps_fail_before_running() {
+    let result = RustActions::<TestWorld>::new()
+        .workflows("tests/workflows")
+        .step_policy(StepPolicy::new().deny("user/*"))
+        .execute()
+        .await;
+
+    assert!(matches!(result, Err(Error::StepPolicy(_))));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn resume_skips_checkpointed_jobs() {
+    use rust_actions::checkpoint::Checkpoint;
+    use std::path::Path;
+
+    let dir = std::env::temp_dir().join(format!("rust-actions-resume-{}", Uuid::new_v4()));
+    let mut checkpoint = Checkpoint::new("resumed");
+    let mut outputs = JobOutputs::new();
+    outputs.insert("user_id", rust_actions::serde_json::json!("from-checkpoint"));
+    checkpoint.record(Path::new("<Resume>"), "provision", outputs);
+    checkpoint.save(&dir).unwrap();
+
+    // `provision` would fail if it ran again
+    let workflow = WorkflowBuilder::new("Resume")
+        .job("provision", |j| j.step("missing/step"))
+        .job("verify", |j| {
+            j.needs("provision")
+                .step("user/create")
+                .with("username", "carol")
+                .with("email", "carol@example.com")
+                .pre_assert("${{ needs.provision.outputs.user_id == \"from-checkpoint\" }}")
+        });
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .checkpoint_dir(&dir)
+        .resume("resumed")
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(results[0].passed());
+    assert_eq!(results[0].jobs.len(), 1);
+    assert!(!Checkpoint::path(&dir, "resumed").exists());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn checkpoints_leave_out_jobs_with_secret_outputs() {
+    use rust_actions::checkpoint::Checkpoint;
+    use std::path::Path;
+
+    let dir = std::env::temp_dir().join(format!("rust-actions-checkpoint-{}", Uuid::new_v4()));
+    let signup = |job: JobBuilder, username: &str, secret: bool| {
+        let job = job
+            .step("user/create")
+            .id("user")
+            .with("username", username)
+            .with("email", format!("{}@example.com", username))
+            .output("username", "${{ steps.user.outputs.username }}");
+        if secret { job.mask_output("username") } else { job }
+    };
+    let workflow = WorkflowBuilder::new("Checkpoint")
+        .job("public", |j| signup(j, "olivia", false))
+        .job("private", |j| signup(j, "mallory-s3cret", true))
+        .job("broken", |j| j.needs("public").needs("private").step("missing/step"));
+
+    let runner = RustActions::<TestWorld>::new().add_workflow(workflow).checkpoint_dir(&dir);
+    let session_id = runner.session_id().to_string();
+    let results = runner.execute().await.unwrap();
+
+    assert!(!results[0].passed());
+    let checkpoint = Checkpoint::load(&dir, &session_id).unwrap();
+    let workflow = Path::new("<Checkpoint>");
+    assert!(checkpoint.get(workflow, "public").is_some());
+    assert!(checkpoint.get(workflow, "private").is_none());
+    let saved = std::fs::read_to_string(Checkpoint::path(&dir, &session_id)).unwrap();
+    assert!(!saved.contains("mallory-s3cret"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn saved_results_mask_secret_outputs() {
+    let dir = std::env::temp_dir().join(format!("rust-actions-results-{}", Uuid::new_v4()));
+    let path = dir.join("results.json");
+    let workflow = WorkflowBuilder::new("Secrets").job("signup", |j| {
+        j.step("user/create")
+            .id("user")
+            .with("username", "mallory-s3cret")
+            .with("email", "mallory@example.com")
+            .mask_output("username")
+            .output("username", "${{ steps.user.outputs.username }}")
+    });
+
+    RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .save_results(&path)
+        .run()
+        .await;
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(!saved.contains("mallory-s3cret"));
+    assert!(saved.contains(r#""username": "***""#));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn cleanup_runs_after_failed_job() {
+    let workflow = WorkflowBuilder::new("Cleanup").job("create", |j| {
+        j.step("user/create")
+            .with("username", "dave")
+            .with("email", "dave@example.com")
+            .cleanup(
+                "user/delete",
+                [("id".to_string(), "${{ outputs.id }}".into())].into(),
+            )
+            .step("missing/step")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let job = &results[0].jobs[0];
+    assert!(!job.passed());
+    assert_eq!(job.cleanup.len(), 1);
+    assert_eq!(job.cleanup[0].0, "user/delete");
+    assert!(job.cleanup[0].1.is_passed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn panicking_steps_fail_their_job_only() {
+    let workflow = WorkflowBuilder::new("Panics")
+        .job("broken", |j| {
+            j.step("user/create")
+                .with("username", "erin")
+                .with("email", "erin@example.com")
+                .cleanup(
+                    "user/delete",
+                    [("id".to_string(), "${{ outputs.id }}".into())].into(),
+                )
+                .step("test/panic")
+                .step("user/create")
+                .with("username", "frank")
+                .with("email", "frank@example.com")
+        })
+        .job("healthy", |j| {
+            j.step("user/create")
+                .with("username", "grace")
+                .with("email", "grace@example.com")
+        });
+
+    let results = RustActions::<TestWorld>::new()
+        .register_step("test/panic", |_, _, _| {
+            Box::pin(async {
+                let outputs: Vec<StepOutputs> = Vec::new();
+                Ok(outputs[0].clone())
+            })
+        })
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let broken = &results[0].jobs[0];
+    assert!(!broken.passed());
+    assert!(matches!(
+        &broken.steps[1].result,
+        StepResult::Failed(_, msg) if msg.starts_with("Step panicked: index out of bounds")
+    ));
+    assert!(matches!(broken.steps[2].result, StepResult::Skipped));
+    assert!(broken.cleanup[0].1.is_passed());
+    assert!(results[0].jobs[1].passed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn step_links_are_kept_with_the_result() {
+    let workflow = WorkflowBuilder::new("Links").job("charge", |j| {
+        j.step("payments/charge").with("order", "o-17")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .register_step("payments/charge", |_, args, ctx| {
+            ctx.link("trace", "https://tempo.local/trace/4bf92f");
+            ctx.link("payload", format!("target/payloads/{}.json", args["order"].as_str().unwrap()));
+            Box::pin(async { Err(Error::Custom("payment declined".to_string())) })
+        })
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let job = &results[0].jobs[0];
+    assert!(!job.passed());
+    let links: Vec<(&str, &str)> = job.steps[0]
+        .links
+        .iter()
+        .map(|link| (link.label.as_str(), link.target.as_str()))
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            ("trace", "https://tempo.local/trace/4bf92f"),
+            ("payload", "target/payloads/o-17.json"),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn compensate_runs_only_when_a_later_step_fails() {
+    let job = |fail: bool| {
+        move |j: JobBuilder| {
+            let j = j
+                .step("user/create")
+                .with("username", "frank")
+                .with("email", "frank@example.com")
+                .compensate(
+                    "user/delete",
+                    [("id".to_string(), "${{ outputs.id }}".into())].into(),
+                );
+            if fail { j.step("missing/step") } else { j.step("user/count@1") }
+        }
+    };
+    let workflow = WorkflowBuilder::new("Compensate")
+        .job("failing", job(true))
+        .job("passing", job(false));
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let (failing, passing) = (&results[0].jobs[0], &results[0].jobs[1]);
+    assert!(!failing.passed());
+    assert_eq!(failing.compensations.len(), 1);
+    assert_eq!(failing.compensations[0].0, "user/delete");
+    assert!(failing.compensations[0].1.is_passed());
+    assert!(passing.passed());
+    assert!(passing.compensations.is_empty());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn reusable_workflow_jobs_compensate_only_their_own_failures() {
+    let results = RustActions::<TestWorld>::new()
+        .workflow_yaml(
+            "Compensate",
+            r#"
+name: Compensate
+jobs:
+  call:
+    uses: "@file:reusable/compensate.yaml"
+"#,
+        )
+        .workflow_files([(
+            "reusable/compensate.yaml",
+            r#"
+name: Compensate
+on: { workflow_call: {} }
+jobs:
+  broken:
+    steps:
+      - uses: user/delete
+        with: { id: nobody }
+  signup:
+    steps:
+      - uses: user/create
+        with: { username: frank, email: frank@example.com }
+        compensate:
+          uses: user/delete
+          with: { id: "${{ outputs.id }}" }
+      - uses: user/count@1
+"#,
+        )])
+        .execute()
+        .await
+        .unwrap();
+
+    let job = &results[0].jobs[0];
+    assert!(!job.passed());
+    assert_eq!(job.steps.len(), 3);
+    assert!(job.steps[1].result.is_passed() && job.steps[2].result.is_passed());
+    assert!(job.compensations.is_empty());
+}
+
+/// Runs `user/create` on a worker with its own world, round-tripping each call through JSON
+/// as a real transport would.
+struct RemoteUsers {
+    worker: RustActions<TestWorld>,
+    world: Arc<tokio::sync::Mutex<TestWorld>>,
+}
+
+impl StepExecutor for RemoteUsers {
+    fn handles(&self, uses: &str) -> bool {
+        uses == "user/create"
+    }
+
+    fn execute<'a>(
+        &'a self,
+        call: StepCall,
+    ) -> Pin<Box<dyn Future<Output = Result<StepOutputs>> + Send + 'a>> {
+        Box::pin(async move {
+            let call = serde_json::from_value(serde_json::to_value(&call)?)?;
+            let mut world = self.world.lock().await;
+            self.worker.serve_call(&mut world, call).await
+        })
+    }
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn executor_runs_handled_steps_on_the_worker() {
+    let world = Arc::new(tokio::sync::Mutex::new(TestWorld::new().await.unwrap()));
+    let workflow = WorkflowBuilder::new("Remote").job("create", |j| {
+        j.step("user/create")
+            .with("username", "erin")
+            .with("email", "erin@example.com")
+            .post_assert("${{ outputs.username == \"erin\" }}")
+            .step("user/count@1")
+            .post_assert("${{ outputs.count == 0 }}")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .executor(RemoteUsers { worker: RustActions::new(), world: world.clone() })
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(results[0].passed());
+    assert_eq!(world.lock().await.users.len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn capture_stores_vars_for_later_steps() {
+    let workflow = WorkflowBuilder::new("Capture").job("create", |j| {
+        j.output("user_id", "${{ vars.user_id }}")
+            .step("user/create")
+            .with("username", "grace")
+            .with("email", "grace@example.com")
+            .capture("user_id", "outputs.id")
+            .step("user/find")
+            .with("username", "grace")
+            .post_assert("${{ outputs.id == vars.user_id }}")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let job = &results[0].jobs[0];
+    assert!(job.passed(), "{:?}", job.steps);
+    assert!(job.outputs.get_string("user_id").is_some_and(|id| !id.is_empty()));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn vars_set_assigns_job_vars() {
+    let workflow = WorkflowBuilder::new("Vars").job("create", |j| {
+        j.output("last", "${{ vars.username }}")
+            .step("vars/set")
+            .with("username", "heidi")
+            .with("domain", "example.com")
+            .step("user/create")
+            .with("username", "${{ vars.username }}")
+            .with("email", "${{ vars.username }}@${{ vars.domain }}")
+            .post_assert("${{ outputs.username == vars.username }}")
+            .step("vars/set")
+            .with("username", "ivan")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let job = &results[0].jobs[0];
+    assert!(job.passed(), "{:?}", job.steps);
+    assert_eq!(job.outputs.get_string("last").as_deref(), Some("ivan"));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn step_coverage_tracks_workflows_per_step() {
+    let workflow = WorkflowBuilder::new("Coverage").job("count", |j| {
+        j.step("user/create")
+            .with("username", "judy")
+            .with("email", "judy@example.com")
+            .step("user/count@1")
+            .step("vars/set")
+            .with("done", true)
+    });
+
+    let runner = RustActions::<TestWorld>::new().add_workflow(workflow);
+    runner.execute().await.unwrap();
+    let coverage = runner.step_coverage();
+
+    assert_eq!(coverage.steps["user/create"], ["Coverage".to_string()].into());
+    assert!(coverage.unexercised().contains(&"user/count@2.1.0"));
+    assert!(!coverage.steps.contains_key("vars/set"));
+    assert_eq!(coverage.deprecated["user/count@1.0.0"].note, "use user/count@2");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn delays_pause_before_steps() {
+    let run = || async {
+        let workflow = WorkflowBuilder::new("Paced").job("poll", |j| {
+            j.step("user/count@2")
+                .delay(Delay::Fixed(Duration::from_secs(2)))
+                .step("user/count@2")
+                .delay(Delay::Range(Duration::from_secs(1), Duration::from_secs(3)))
+        });
+        let start = tokio::time::Instant::now();
+        let results = RustActions::<TestWorld>::new()
+            .add_workflow(workflow)
+            .execute()
+            .await
+            .unwrap();
+        assert!(results[0].passed());
+        let steps = &results[0].jobs[0].steps;
+        assert!(steps
+            .iter()
+            .all(|step| matches!(step.result, StepResult::Passed(d) if d < Duration::from_secs(1))));
+        start.elapsed()
+    };
+
+    let paused = run().await;
+    assert!((Duration::from_secs(3)..=Duration::from_secs(5)).contains(&paused), "{:?}", paused);
+    assert_eq!(run().await, paused);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn step_families_build_steps_per_name() {
+    let workflow = WorkflowBuilder::new("Queues").job("publish", |j| {
+        j.step("queue/orders")
+            .id("orders")
+            .step("queue/payments")
+            .id("payments")
+            .job_post_assert("${{ steps.orders.outputs.topic == \"prod.orders\" }}")
+            .job_post_assert("${{ steps.payments.outputs.topic == \"prod.payments\" }}")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .step_family("queue/*", serde_json::json!({ "prefix": "prod" }), |queue, config| {
+            let topic = format!("{}.{}", config["prefix"].as_str().unwrap_or_default(), queue);
+            typed_step(move |world: &mut TestWorld, _args, _ctx| {
+                let topic = topic.clone();
+                world.lookups += 1;
+                Box::pin(async move {
+                    let mut outputs = StepOutputs::new();
+                    outputs.insert("topic", topic);
+                    Ok(outputs)
+                })
+            })
+        })
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+    assert!(results[0].passed(), "{:?}", results[0].jobs[0].steps);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn cancellation_stops_the_run_but_still_cleans_up() {
+    let workflow = WorkflowBuilder::new("Interrupted")
+        .job("create", |j| {
+            j.step("user/create")
+                .with("username", "erin")
+                .with("email", "erin@example.com")
+                .cleanup(
+                    "user/delete",
+                    [("id".to_string(), "${{ outputs.id }}".into())].into(),
+                )
+                .step("test/interrupt")
+                .step("user/count@1")
+        })
+        .job("count", |j| j.needs("create").step("user/count@1"));
+    let later = WorkflowBuilder::new("Later").job("count", |j| j.step("user/count@1"));
+
+    let results = RustActions::<TestWorld>::new()
+        .register_step("test/interrupt", |_, _, ctx| {
+            ctx.cancellation().cancel();
+            Box::pin(async { Ok(StepOutputs::default()) })
+        })
+        .cancel_on_ctrl_c(false)
+        .add_workflow(workflow)
+        .add_workflow(later)
+        .execute()
+        .await
+        .unwrap();
+
+    let job = &results[0].jobs[0];
+    assert!(!job.passed());
+    assert!(matches!(job.steps[2].result, StepResult::Skipped));
+    assert!(job.cleanup[0].1.is_passed());
+    assert_eq!(results[0].jobs.len(), 1);
+    assert_eq!(results[1].ignored.as_deref(), Some("not run: cancelled"));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn depends_on_skips_only_dependents() {
+    let workflow = WorkflowBuilder::new("Partial order").job("create", |j| {
+        j.step("missing/step")
+            .id("broken")
+            .step("user/count@1")
+            .id("count")
+            .depends_on("broken")
+            .step("user/create")
+            .id("erin")
+            .with("username", "erin")
+            .with("email", "erin@example.com")
+            .depends_on("erin-ready")
+            .step("user/count@1")
+            .id("erin-ready")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let steps: Vec<_> = results[0].jobs[0]
+        .steps
+        .iter()
+        .map(|step| (step.name.as_str(), step.result.is_passed(), step.result.is_failed()))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            ("missing/step", false, true),
+            ("user/count@1", false, false),
+            ("user/count@1", true, false),
+            ("user/create", true, false),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn reusable_workflow_steps_skip_failed_dependencies() {
+    let results = RustActions::<TestWorld>::new()
+        .workflow_yaml(
+            "Partial order",
+            r#"
+name: Partial order
+jobs:
+  call:
+    uses: "@file:reusable/partial.yaml"
+"#,
+        )
+        .workflow_files([(
+            "reusable/partial.yaml",
+            r#"
+name: Partial order
+on: { workflow_call: {} }
+jobs:
+  create:
+    steps:
+      - uses: user/delete
+        id: broken
+        with: { id: nobody }
+      - uses: user/count@1
+        depends-on: broken
+      - uses: user/count@1
+        id: ready
+      - uses: user/create
+        depends-on: ready
+        with: { username: erin, email: erin@example.com }
+"#,
+        )])
+        .execute()
+        .await
+        .unwrap();
+
+    let steps: Vec<_> = results[0].jobs[0]
+        .steps
+        .iter()
+        .map(|step| (step.name.as_str(), step.result.is_passed(), step.result.is_failed()))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            ("user/delete", false, true),
+            ("user/count@1", false, false),
+            ("user/count@1", true, false),
+            ("user/create", true, false),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn default_retry_retries_only_retryable_errors() {
+    let workflow = || {
+        WorkflowBuilder::new("Search").job("find", |j| {
+            j.step("user/create")
+                .with("username", "dave")
+                .with("email", "dave@example.com")
+                .step("user/find")
+                .with("username", "dave")
+                .with("indexed_after", 2)
+                .step("user/find")
+                .with("username", "nobody")
+        })
+    };
+    let steps = |results: &[WorkflowResult]| -> Vec<String> {
+        results[0].jobs[0]
+            .steps
+            .iter()
+            .map(|step| match &step.result {
+                StepResult::Failed(_, msg) => format!("{}: {}", step.name, msg),
+                _ => format!("{}: ok", step.name),
+            })
+            .collect()
+    };
+
+    let without_retry = RustActions::<TestWorld>::new()
+        .add_workflow(workflow())
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(steps(&without_retry)[1], "user/find: Step error: dave is not indexed yet");
+
+    let with_retry = RustActions::<TestWorld>::new()
+        .add_workflow(workflow())
+        .default_retry(RetryPolicy::new(3))
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(
+        steps(&with_retry)[1..],
+        ["user/find: ok", "user/find: no user named nobody"]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn pure_steps_run_once_per_args() {
+    use rust_actions_example::steps::QUOTES_COMPUTED;
+    use std::sync::atomic::Ordering;
+
+    let quote = |j: JobBuilder, plan: &str| {
+        j.step("price/quote")
+            .with("plan", plan)
+            .with("seats", 3)
+            .post_assert("${{ outputs.total > 0 }}")
+    };
+    let nightly = WorkflowBuilder::new("Nightly pricing")
+        .job("pro", |j| quote(j, "pro"))
+        .job("both", |j| quote(quote(j, "pro"), "basic"));
+    let weekly = WorkflowBuilder::new("Weekly pricing").job("pro", |j| quote(j, "pro"));
+
+    let before = QUOTES_COMPUTED.load(Ordering::SeqCst);
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(nightly)
+        .add_workflow(weekly)
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(results.iter().all(|r| r.passed()));
+    assert_eq!(QUOTES_COMPUTED.load(Ordering::SeqCst) - before, 2);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn step_contracts_hold_without_post_asserts() {
+    let workflow = WorkflowBuilder::new("Nameless").job("create", |j| {
+        j.step("user/create")
+            .with("username", "")
+            .with("email", "nobody@example.com")
+    });
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let result = &results[0].jobs[0].steps[0].result;
+    assert!(matches!(
+        result,
+        StepResult::Failed(_, msg)
+            if msg == "Contract of user/create failed: ${{ outputs.username != '' }}"
+    ));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn enum_args_report_the_step_and_key() {
+    let workflow = WorkflowBuilder::new("Notify")
+        .job("sms", |j| {
+            j.step("user/notify")
+                .with("via", "sms")
+                .with("number", "+15550100")
+                .post_assert("${{ outputs.channel == 'sms' }}")
+        })
+        .job("pager", |j| j.step("user/notify").with("via", "pager"))
+        .job("email", |j| j.step("user/notify").with("via", "email"));
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let error = |job: &str| {
+        let job = results[0].jobs.iter().find(|j| j.name == job).unwrap();
+        match &job.steps[0].result {
+            StepResult::Failed(_, msg) => msg.clone(),
+            other => format!("{:?}", other),
+        }
+    };
+    assert!(error("sms").starts_with("Passed"));
+    assert!(error("pager").starts_with(
+        "Invalid args for step 'user/notify': 'via': unknown variant `pager`, expected `email` or `sms`"
+    ));
+    assert_eq!(error("email"), "Invalid args for step 'user/notify': missing field `address`");
+}
+
+/// Connections held by `ConnectionWorld`s that haven't been torn down
+static OPEN_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(World)]
+#[world(init = Self::connect, reset = Self::reset, teardown = Self::close)]
+struct ConnectionWorld;
+
+impl ConnectionWorld {
+    async fn connect() -> Result<Self> {
+        OPEN_CONNECTIONS.fetch_add(1, Ordering::SeqCst);
+        Ok(Self)
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(self) -> Result<()> {
+        OPEN_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn worlds_are_torn_down_after_the_run() {
+    let workflow = WorkflowBuilder::new("Connections")
+        .job("first", |j| j.step("db/ping"))
+        .job("second", |j| j.needs("first").step("db/ping"));
+
+    let results = RustActions::<ConnectionWorld>::new()
+        .register_step("db/ping", |_, _, _| {
+            Box::pin(async {
+                assert_eq!(OPEN_CONNECTIONS.load(Ordering::SeqCst), 1);
+                Ok(StepOutputs::default())
+            })
+        })
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(results[0].passed());
+    assert_eq!(OPEN_CONNECTIONS.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn scaffold_uses_registered_arg_schemas() {
+    let spec = ScaffoldSpec::new("Lookup").job("find", ["user/create-many", "user/find", "user/count@2"]);
+    let yaml = RustActions::<TestWorld>::new().scaffold(&spec).unwrap();
+
+    assert!(yaml.contains("      - id: create-many\n        uses: user/create-many\n        with:\n          rows: []  # Vec<NewUser>\n"));
+    assert!(yaml.contains("          username: \"\"  # String\n          # indexed_after: 0  # u32\n"));
+    assert!(yaml.contains("      - id: count\n        uses: user/count@2\n"));
+    Workflow::from_yaml(&yaml).unwrap();
+
+    let spec = ScaffoldSpec::new("Notify").job("notify", ["user/notify"]);
+    let yaml = RustActions::<TestWorld>::new().scaffold(&spec).unwrap();
+    assert!(yaml.contains("          via: \"\"  # String\n          # address: \"\"  # String\n"));
+    assert!(yaml.contains("          # number: \"\"  # String\n"));
+}
+
+static SEEN_SESSIONS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn steps_get_the_session_id_from_their_context() {
+    let runners: Vec<_> = (0..2)
+        .map(|_| {
+            RustActions::<TestWorld>::new()
+                .register_step("session/read", |_, _, ctx| {
+                    SEEN_SESSIONS.lock().unwrap().push(ctx.session_id().to_string());
+                    Box::pin(async { Ok(StepOutputs::default()) })
+                })
+                .add_workflow(WorkflowBuilder::new("Session").job("read", |j| j.step("session/read")))
+        })
+        .collect();
+
+    for runner in &runners {
+        assert!(runner.execute().await.unwrap()[0].passed());
+    }
+
+    let ids: Vec<_> = runners.iter().map(|r| r.session_id().to_string()).collect();
+    assert_ne!(ids[0], ids[1]);
+    let seen = SEEN_SESSIONS.lock().unwrap();
+    assert!(ids.iter().all(|id| seen.contains(id)));
+    assert!(std::env::var("RUST_ACTIONS_SESSION_ID").is_err());
+}
+
+/// A runner with only the `user/create` steps, as for a deployment without the rest.
+fn create_only() -> RustActions<TestWorld> {
+    let mut steps = StepRegistry::new();
+    steps.collect_for_filtered::<TestWorld>(|name| name.starts_with("user/create"));
+    RustActions::<TestWorld>::new().with_steps(steps)
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn explicit_registries_leave_out_unlisted_steps() {
+    let churn = || {
+        WorkflowBuilder::new("Churn").job("churn", |j| {
+            j.step("user/create")
+                .id("alice")
+                .with("username", "alice")
+                .with("email", "alice@example.com")
+                .step("user/delete")
+                .with("id", "${{ steps.alice.outputs.id }}")
+        })
+    };
+    let passes = |results: Result<Vec<WorkflowResult>>| matches!(results, Ok(r) if r[0].passed());
+
+    assert!(passes(RustActions::<TestWorld>::new().add_workflow(churn()).execute().await));
+    assert!(!passes(create_only().add_workflow(churn()).execute().await));
+    assert!(!passes(
+        RustActions::<TestWorld>::new()
+            .without_auto_steps()
+            .add_workflow(churn())
+            .execute()
+            .await
+    ));
+}
+
+#[test]
+fn step_definitions_record_their_source() {
+    let mut steps = StepRegistry::new();
+    steps.collect_for::<TestWorld>();
+
+    let source = steps.source("user/create").unwrap();
+    assert!(source.file.ends_with("steps.rs"));
+    assert!(source.module.ends_with("::steps"));
+    assert_eq!(source.doc, Some("Adds a user to the world and outputs its generated id."));
+    assert_eq!(steps.source("user/delete").unwrap().doc, None);
+
+    let message = steps.resolve("user/craete").err().unwrap().to_string();
+    assert!(message.contains(&format!("did you mean user/create at {}", source)));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn nested_workflow_calls_stop_at_the_depth_limit() {
+    // Each call passes on a longer `trail`, so only the depth limit ends the recursion
+    let workflow = Workflow::from_yaml(
+        r#"
+name: Recursion
+jobs:
+  repeat:
+    uses: "@file:reusable/repeat.yaml"
+"#,
+    )
+    .unwrap();
+
+    let results = RustActions::<TestWorld>::new()
+        .workflows("tests/workflows")
+        .max_call_depth(3)
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let job = &results[0].jobs[0];
+    assert!(!job.passed());
+    assert!(matches!(
+        &job.steps[..],
+        [StepRecord { result: StepResult::Failed(_, msg), .. }] if msg == "Workflow calls nested deeper than 3: \
+            reusable/repeat.yaml -> reusable/repeat.yaml -> reusable/repeat.yaml -> reusable/repeat.yaml"
+    ));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn failed_workflow_calls_are_recorded_as_failed_jobs() {
+    let results = RustActions::<TestWorld>::new()
+        .workflow_yaml(
+            "Loop",
+            r#"
+name: Loop
+jobs:
+  call:
+    uses: "@file:reusable/loop.yaml"
+  after:
+    needs: call
+    steps:
+      - uses: user/create
+        with: { username: zoe, email: zoe@example.com }
+"#,
+        )
+        .workflow_files([(
+            "reusable/loop.yaml",
+            r#"
+name: Loop
+on: { workflow_call: {} }
+jobs:
+  first: { needs: second, steps: [{ uses: user/create }] }
+  second: { needs: first, steps: [{ uses: user/create }] }
+"#,
+        )])
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(!results[0].passed());
+    let job = &results[0].jobs[0];
+    assert_eq!(job.name, "call");
+    assert!(!job.passed());
+    assert!(matches!(
+        &job.steps[..],
+        [StepRecord { name, result: StepResult::Failed(_, msg), .. }]
+            if name == "@file:reusable/loop.yaml" && msg.contains("first")
+    ));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn workflow_setup_outputs_reach_every_job() {
+    let signup = |setup_user: &str| {
+        let setup_user = setup_user.to_string();
+        WorkflowBuilder::new("Signup")
+            .setup(move |s| {
+                s.step("user/create")
+                    .id("admin")
+                    .with("username", setup_user)
+                    .with("email", "admin@example.com")
+            })
+            .job("invite", |j| {
+                j.step("user/create")
+                    .with("username", "bob")
+                    .with("email", "bob@example.com")
+                    .post_assert("${{ setup.outputs.username == 'admin' }}")
+            })
+            .teardown(|t| t.step("user/delete").with("id", "${{ setup.outputs.id }}"))
+    };
+
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(signup("admin"))
+        .execute()
+        .await
+        .unwrap();
+    let names: Vec<&str> = results[0].jobs.iter().map(|j| j.name.as_str()).collect();
+    assert_eq!(names, vec!["setup:", "invite", "teardown:"]);
+    assert!(results[0].passed());
+
+    // A failed setup skips the jobs, but teardown still runs
+    let results = RustActions::<TestWorld>::new()
+        .add_workflow(signup(""))
+        .execute()
+        .await
+        .unwrap();
+    let names: Vec<&str> = results[0].jobs.iter().map(|j| j.name.as_str()).collect();
+    assert_eq!(names, vec!["setup:", "teardown:"]);
+    assert!(!results[0].passed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn duration_assertions_fail_slow_steps_and_jobs() {
+    let workflow = WorkflowBuilder::new("Latency")
+        .job("fast", |j| {
+            j.step("clock/wait")
+                .assert_duration(DurationAssertion::less_than(Duration::from_secs(2)))
+        })
+        .job("slow", |j| {
+            j.step("clock/wait")
+                .step("clock/wait")
+                .job_assert_duration("<= 5s".parse().unwrap())
+        });
+
+    let results = RustActions::<TestWorld>::new()
+        .register_step("clock/wait", |_, _, _| {
+            Box::pin(async {
+                tokio::time::sleep(Duration::from_secs(3)).await;
+                Ok(StepOutputs::default())
+            })
+        })
+        .add_workflow(workflow)
+        .execute()
+        .await
+        .unwrap();
+
+    let fast = &results[0].jobs[0].steps[0].result;
+    assert!(matches!(fast, StepResult::Failed(_, msg) if msg.contains("expected < 2000ms")));
+    let slow = results[0].jobs[1].steps.last().unwrap();
+    assert_eq!(slow.name, "assert-duration");
+    assert!(slow.result.is_failed());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn external_services_resolve_to_running_endpoints() {
+    let workflow = r#"
+name: Billing
+services:
+  postgres:
+    image: postgres:16
+    port: 5432
+    scheme: postgres
+  redis:
+    image: redis:7
+    port: 6379
+jobs:
+  invoice:
+    steps:
+      - uses: user/create
+        with:
+          username: ${{ containers.postgres.host }}
+          email: billing@example.com
+        assert-after:
+          - ${{ outputs.username == "ci-db" }}
+          - ${{ containers.postgres.url == "postgres://ci-db:6432" }}
+          - ${{ containers.redis.port == 6379 }}
+"#;
+
+    let results = RustActions::<TestWorld>::new()
+        .workflows("does/not/exist")
+        .workflow_yaml("billing.yaml", workflow)
+        .service_provider(
+            ExternalServices::new()
+                .endpoint("postgres", "ci-db:6432")
+                .endpoint("redis", "ci-cache"),
+        )
+        .execute()
+        .await
+        .unwrap();
+    assert!(results[0].passed());
+
+    // Without an endpoint the jobs don't run, and the run fails as an infrastructure failure
+    let results = RustActions::<TestWorld>::new()
+        .workflows("does/not/exist")
+        .workflow_yaml("billing.yaml", workflow)
+        .service_provider(ExternalServices::new().endpoint("postgres", "ci-db:6432"))
+        .execute()
+        .await
+        .unwrap();
+    let names: Vec<&str> = results[0].jobs.iter().map(|j| j.name.as_str()).collect();
+    assert_eq!(names, vec!["services:"]);
+    assert!(results[0].jobs[0].infra_error.as_deref().unwrap().contains("'redis'"));
+    let manifest = FailureManifest::from_results("s", &results, &ExitPolicy::new(), false);
+    assert_eq!(manifest.kind, Some(FailureKind::Infrastructure));
+}
+
+/// Runs of the `db/seed` step in `memoized_reusable_workflows_run_once_per_inputs`
+static SEEDED: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn memoized_reusable_workflows_run_once_per_inputs() {
+    let seed = r#"
+name: Seed tenant
+on:
+  workflow_call:
+    memoize: true
+    inputs:
+      tenant:
+        required: true
+    outputs:
+      admin:
+        value: ${{ jobs.seed.outputs.admin }}
+jobs:
+  seed:
+    outputs:
+      admin: ${{ steps.admin.outputs.username }}
+    steps:
+      - uses: db/seed
+      - id: admin
+        uses: user/create
+        with:
+          username: ${{ inputs.tenant }}-admin
+          email: admin@example.com
+"#;
+    let billing = r#"
+name: Billing
+jobs:
+  tenant:
+    uses: "@file:shared/seed.yaml"
+    with: { tenant: acme }
+"#;
+    let invoices = r#"
+name: Invoices
+jobs:
+  tenant:
+    uses: "@file:shared/seed.yaml"
+    with: { tenant: acme }
+  globex:
+    uses: "@file:shared/seed.yaml"
+    with: { tenant: globex }
+  check:
+    needs: [tenant, globex]
+    steps:
+      - uses: user/create
+        with:
+          username: ${{ needs.tenant.outputs.admin }}
+          email: check@example.com
+        assert-after:
+          - ${{ outputs.username == "acme-admin" }}
+          - ${{ needs.globex.outputs.admin == "globex-admin" }}
+"#;
+
+    let results = RustActions::<TestWorld>::new()
+        .workflows("does/not/exist")
+        .workflow_yaml("billing.yaml", billing)
+        .workflow_yaml("invoices.yaml", invoices)
+        .workflow_files([("shared/seed.yaml", seed)])
+        .register_step("db/seed", |_, _, _| {
+            Box::pin(async {
+                SEEDED.fetch_add(1, Ordering::SeqCst);
+                Ok(StepOutputs::default())
+            })
+        })
+        .execute()
+        .await
+        .unwrap();
+
+    assert!(results.iter().all(|w| w.passed()));
+    // acme was seeded by Billing; Invoices reused it and only seeded globex
+    assert_eq!(SEEDED.load(Ordering::SeqCst), 2);
+    let reused = results[1].jobs.iter().find(|j| j.name == "tenant").unwrap();
+    assert!(reused.steps.is_empty());
+}